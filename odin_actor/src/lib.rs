@@ -50,6 +50,8 @@ pub use errors::{OdinActorError,Result,OdinActorResult};
 mod msg_patterns;
 pub use msg_patterns::*;
 
+pub mod resource_watcher;
+
 extern crate odin_macro;
 #[doc(hidden)]
 pub use odin_macro::{
@@ -19,6 +19,7 @@ pub use crate::{
     MpscSender, MpscReceiver, create_mpsc_sender_receiver, send, recv,
     ActorReceiver, ReceiveAction, MsgReceiver, DynMsgReceiverTrait, DynMsgReceiver, into_dyn_msg_receiver, TryMsgReceiver, 
     MsgReceiverList, DynMsgReceiverList, msg_receiver_list,
+    resource_watcher::{ResourceWatcher, ResourceWatcherMsg, Reconfigure, AssetChanged},
     SysMsgReceiver, SysMsg, DefaultReceiveAction, FromSysMsg, Identifiable,
     _Start_, _Ping_, _Timer_, _Exec_, _Pause_, _Resume_, _Terminate_,
     OdinActorError, OdinActorResult,
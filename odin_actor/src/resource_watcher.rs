@@ -0,0 +1,203 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the resource_watcher module provides the `ResourceWatcher` actor, which periodically checks config and asset files
+//! for modifications so that changes can be applied without restarting the application.
+//!
+//! Modified configs are parsed and validated before they are sent as `Reconfigure<C>` messages to the actor that owns
+//! the config, i.e. a config file that is (temporarily) invalid does not affect the running system. Modified assets
+//! are removed from the asset cache of their resource crate and reported as `AssetChanged` messages, which is usually
+//! handled by a `SpaServer` notifying its clients to reload.
+//!
+//! File lookup uses the `config_path(..)` and `asset_path(..)` functions that are generated by the
+//! `odin_build::define_load_config!{}` and `odin_build::define_load_asset!{}` macros, i.e. we watch the same file that
+//! was used to load the resource. Embedded resources are not watched.
+
+use std::{fmt::Debug, fs, marker::PhantomData, path::{Path,PathBuf}, time::{Duration,SystemTime}};
+use serde::de::DeserializeOwned;
+
+use crate::prelude::*;
+use crate::errors::{op_failed, OdinActorError, Result};
+
+/// message that is sent to the owner of a config once the config file was modified and its new content was validated
+#[derive(Debug,Clone)]
+pub struct Reconfigure<C> {
+    pub filename: String,
+    pub config: C
+}
+
+/// message that is sent once an asset file was modified. The asset cache entry has already been invalidated at this point
+#[derive(Debug,Clone)]
+pub struct AssetChanged {
+    pub filename: String
+}
+
+/// fn type of the `config_path(..)` and `asset_path(..)` functions generated by odin_build
+pub type ResourcePathFp = fn(&str)->Option<PathBuf>;
+
+/// fn type of the `invalidate_asset(..)` functions generated by odin_build
+pub type InvalidateAssetFp = fn(&str);
+
+/// object safe interface for what we do when a watched file changed
+trait WatchHandler: Send {
+    fn changed (&self, filename: &str, path: &Path)->Result<()>;
+}
+
+struct ConfigHandler<C,R,V> {
+    receiver: R,
+    validate: V,
+    _phantom: PhantomData<fn()->C>
+}
+
+impl<C,R,V> WatchHandler for ConfigHandler<C,R,V>
+    where C: DeserializeOwned + Debug + Send + 'static,
+          R: TryMsgReceiver<Reconfigure<C>> + 'static,
+          V: Fn(&C)->std::result::Result<(),String> + Send + 'static
+{
+    fn changed (&self, filename: &str, path: &Path)->Result<()> {
+        let data = fs::read( path).map_err( |e| op_failed( format!("failed to read config {filename}: {e}")))?;
+        let config: C = ron::de::from_bytes( &data).map_err( |e| op_failed( format!("invalid config {filename}: {e}")))?;
+        (self.validate)( &config).map_err( |e| op_failed( format!("rejected config {filename}: {e}")))?;
+
+        self.receiver.try_send_msg( Reconfigure{ filename: filename.to_string(), config })
+    }
+}
+
+struct AssetHandler<R> {
+    invalidate: InvalidateAssetFp,
+    receiver: R
+}
+
+impl<R> WatchHandler for AssetHandler<R> where R: TryMsgReceiver<AssetChanged> + 'static {
+    fn changed (&self, filename: &str, path: &Path)->Result<()> {
+        (self.invalidate)( filename);
+        self.receiver.try_send_msg( AssetChanged{ filename: filename.to_string() })
+    }
+}
+
+struct WatchEntry {
+    filename: String,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    handler: Box<dyn WatchHandler>
+}
+
+fn last_modified (path: &Path)->Option<SystemTime> {
+    fs::metadata( path).and_then( |meta| meta.modified()).ok()
+}
+
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// actor state of the ResourceWatcher. Watched files have to be registered before the actor is spawned, e.g.
+/// ```ignore
+/// let mut watcher = ResourceWatcher::new( secs(2));
+/// watcher.watch_config::<SentinelAlarmMonitorConfig,_>( "sentinel_alarm.ron", odin_sentinel::config_path, hmonitor.clone());
+/// watcher.watch_asset( "odin_sentinel.js", odin_sentinel::asset_path, odin_sentinel::invalidate_asset, hserver.clone());
+/// spawn_actor!( actor_system, "watcher", watcher)?;
+/// ```
+pub struct ResourceWatcher {
+    interval: Duration,
+    entries: Vec<WatchEntry>,
+    timer: Option<AbortHandle>
+}
+
+impl ResourceWatcher {
+    pub fn new (interval: Duration)->Self {
+        ResourceWatcher { interval, entries: Vec::new(), timer: None }
+    }
+
+    /// watch a config file and send a `Reconfigure<C>` message to `receiver` if it changes and can be parsed
+    pub fn watch_config<C,R> (&mut self, filename: &str, config_path: ResourcePathFp, receiver: R)
+        where C: DeserializeOwned + Debug + Send + 'static, R: TryMsgReceiver<Reconfigure<C>> + 'static
+    {
+        self.watch_validated_config( filename, config_path, receiver, |_: &C| Ok(()))
+    }
+
+    /// watch a config file and send a `Reconfigure<C>` message to `receiver` if it changes, can be parsed and
+    /// passes the provided `validate` check
+    pub fn watch_validated_config<C,R,V> (&mut self, filename: &str, config_path: ResourcePathFp, receiver: R, validate: V)
+        where C: DeserializeOwned + Debug + Send + 'static,
+              R: TryMsgReceiver<Reconfigure<C>> + 'static,
+              V: Fn(&C)->std::result::Result<(),String> + Send + 'static
+    {
+        let handler = ConfigHandler { receiver, validate, _phantom: PhantomData };
+        self.add_entry( filename, config_path( filename), Box::new(handler))
+    }
+
+    /// watch an asset file, invalidate its cache entry and send an `AssetChanged` message to `receiver` if it changes
+    pub fn watch_asset<R> (&mut self, filename: &str, asset_path: ResourcePathFp, invalidate: InvalidateAssetFp, receiver: R)
+        where R: TryMsgReceiver<AssetChanged> + 'static
+    {
+        let handler = AssetHandler { invalidate, receiver };
+        self.add_entry( filename, asset_path( filename), Box::new(handler))
+    }
+
+    fn add_entry (&mut self, filename: &str, path: Option<PathBuf>, handler: Box<dyn WatchHandler>) {
+        if let Some(path) = path {
+            let modified = last_modified( &path);
+            self.entries.push( WatchEntry { filename: filename.to_string(), path, modified, handler })
+        } else {
+            warn!("no file for resource {filename}, not watched");
+        }
+    }
+
+    pub fn is_empty (&self)->bool {
+        self.entries.is_empty()
+    }
+
+    /// check all watched files for modification. Note that we don't notify for files that were removed, and that
+    /// we only report each modification once - if the new content is rejected we wait for the next modification.
+    /// Modifications that could not be sent because the receiver mailbox was full are retried on the next check
+    fn check_entries (&mut self) {
+        for e in &mut self.entries {
+            let modified = last_modified( &e.path);
+            if modified != e.modified {
+                if modified.is_some() {
+                    match e.handler.changed( &e.filename, &e.path) {
+                        Ok(()) => info!("reloaded modified resource {:?}", e.path),
+                        Err(OdinActorError::ReceiverFull) => {
+                            warn!("receiver of modified resource {:?} busy, retrying", e.path);
+                            continue
+                        }
+                        Err(err) => warn!("ignoring modified resource {:?}: {}", e.path, err)
+                    }
+                }
+                e.modified = modified;
+            }
+        }
+    }
+}
+
+define_actor_msg_set! { pub ResourceWatcherMsg }
+
+impl_actor! { match msg for Actor<ResourceWatcher,ResourceWatcherMsg> as
+    _Start_ => cont! {
+        if !self.is_empty() {
+            match self.start_repeat_timer( 1, self.interval, false) {
+                Ok(timer) => self.timer = Some(timer),
+                Err(e) => warn!("failed to start resource watcher timer: {e}")
+            }
+        }
+    }
+    _Timer_ => cont! {
+        self.check_entries()
+    }
+    _Terminate_ => stop! {
+        if let Some(timer) = &self.timer {
+            timer.abort();
+            self.timer = None;
+        }
+    }
+}
@@ -0,0 +1,116 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::{fs, path::PathBuf, time::Duration};
+use serde::Deserialize;
+use odin_actor::prelude::*;
+
+#[derive(Deserialize,Debug,Clone,PartialEq)]
+struct TestConfig {
+    value: u32
+}
+
+fn config_dir ()->PathBuf {
+    std::env::temp_dir().join( format!("odin_actor_watcher_{}", std::process::id()))
+}
+
+/// our stand-in for the `config_path(..)` function generated by odin_build
+fn config_path (filename: &str)->Option<PathBuf> {
+    Some( config_dir().join( filename))
+}
+
+fn write_config (filename: &str, content: &str) {
+    std::thread::sleep( Duration::from_millis(20)); // make sure the modification time changes
+    fs::write( config_path( filename).unwrap(), content).unwrap();
+}
+
+/// the owner of the config, which forwards all `Reconfigure` messages to the test
+struct ConfigOwner {
+    tx: MpscSender<Reconfigure<TestConfig>>
+}
+
+define_actor_msg_set! { ConfigOwnerMsg = Reconfigure<TestConfig> }
+
+impl_actor! { match msg for Actor<ConfigOwner,ConfigOwnerMsg> as
+    Reconfigure<TestConfig> => cont! {
+        send( &self.tx, msg).await;
+    }
+}
+
+async fn next_config (rx: &MpscReceiver<Reconfigure<TestConfig>>, to: Duration)->Option<TestConfig> {
+    timeout( to, recv( rx)).await.ok().map( |msg| msg.config)
+}
+
+fn watcher (filename: &str, owner: ActorHandle<ConfigOwnerMsg>)->ResourceWatcher {
+    let mut watcher = ResourceWatcher::new( millis(50));
+    watcher.watch_validated_config::<TestConfig,_,_>( filename, config_path, owner, |c: &TestConfig| {
+        if c.value <= 100 { Ok(()) } else { Err( format!("value out of range: {}", c.value)) }
+    });
+    watcher
+}
+
+// run with "cargo test test_reconfigure -- --nocapture"
+#[tokio::test]
+async fn test_reconfigure() {
+    fs::create_dir_all( config_dir()).unwrap();
+    let filename = "reconfigure.ron";
+    write_config( filename, "TestConfig( value: 1)");
+
+    let mut asys = ActorSystem::new( "test");
+    let (tx, rx) = create_mpsc_sender_receiver( 8);
+    let howner = spawn_actor!( asys, "owner", ConfigOwner{ tx }).unwrap();
+    spawn_actor!( asys, "watcher", watcher( filename, howner)).unwrap();
+    asys.start_all().await.unwrap();
+
+    write_config( filename, "TestConfig( value: 2)");
+    assert_eq!( next_config( &rx, secs(1)).await, Some( TestConfig{ value: 2 }));
+
+    write_config( filename, "TestConfig( value: "); // invalid RON is ignored
+    assert_eq!( next_config( &rx, millis(300)).await, None);
+
+    write_config( filename, "TestConfig( value: 200)"); // so are configs that don't pass validation
+    assert_eq!( next_config( &rx, millis(300)).await, None);
+
+    write_config( filename, "TestConfig( value: 3)");
+    assert_eq!( next_config( &rx, secs(1)).await, Some( TestConfig{ value: 3 }));
+
+    asys.terminate_and_wait( secs(2)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_full_receiver() {
+    fs::create_dir_all( config_dir()).unwrap();
+    let filename = "full_receiver.ron";
+    write_config( filename, "TestConfig( value: 1)");
+
+    let mut asys = ActorSystem::new( "test");
+    let (tx, rx) = create_mpsc_sender_receiver( 8);
+    let pre_owner = PreActorHandle::new( &asys, "owner", 1);
+    let howner = pre_owner.to_actor_handle();
+    spawn_actor!( asys, "watcher", watcher( filename, howner.clone())).unwrap();
+    asys.start_all().await.unwrap();
+
+    // the owner is not running yet and its mailbox is full when the config changes
+    howner.try_send_msg( Reconfigure{ filename: filename.to_string(), config: TestConfig{ value: 0 } }).unwrap();
+    write_config( filename, "TestConfig( value: 2)");
+    sleep( millis(200)).await;
+
+    // once the owner processes its mailbox the modification is sent again
+    spawn_pre_actor!( asys, pre_owner, ConfigOwner{ tx }).unwrap();
+    assert_eq!( next_config( &rx, secs(1)).await, Some( TestConfig{ value: 0 }));
+    assert_eq!( next_config( &rx, secs(1)).await, Some( TestConfig{ value: 2 }));
+
+    asys.terminate_and_wait( secs(2)).await.unwrap();
+}
@@ -180,6 +180,20 @@ macro_rules! define_load_asset {
                 }
                 Err( odin_build::OdinBuildError::ResourceNotFoundError(format!("{:?}",path)) )
             }
+
+            /// get the path of the file `load_asset(filename)` would read, or None if the asset is only embedded
+            pub fn asset_path (filename: &str) -> Option<std::path::PathBuf> {
+                if odin_build::is_env_enabled("ODIN_EMBEDDED_ONLY") { return None }
+                odin_build::find_asset_file( &odin_build::BIN_CONTEXT.get(), env!("CARGO_PKG_NAME"), filename)
+            }
+
+            /// remove a cached fs asset so that the next `load_asset(filename)` reads it again. This is called when
+            /// we detect a modified asset file at runtime
+            pub fn invalidate_asset (filename: &str) {
+                if let Ok(mut cache) = CACHED_FS_ASSETS.lock() {
+                    cache.remove( filename);
+                }
+            }
         }
         pub use assets::*;
     }
@@ -145,6 +145,13 @@ macro_rules! define_load_config {
 
                 Err( odin_build::OdinBuildError::ResourceNotFoundError(filename.to_string()) )
             }
+
+            /// get the path of the file `load_config(filename)` would read, or None if the config is only embedded.
+            /// This is used to watch configs for changes at runtime
+            pub fn config_path (filename: &str) -> Option<std::path::PathBuf> {
+                if odin_build::is_env_enabled("ODIN_EMBEDDED_ONLY") { return None }
+                odin_build::find_config_file( &odin_build::BIN_CONTEXT.get(), env!("CARGO_PKG_NAME"), filename)
+            }
         }
         pub use configs::*; // make load_config() visible at the crate level
     }
@@ -0,0 +1,88 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! tool to pre-fetch tiles of the imagery proxies used by odin_cesium into the local proxy cache, e.g. to prepare
+//! a server for deployment at an incident command post without internet access. Interrupted runs can be resumed by
+//! executing the same command again. Example:
+//! ```
+//!   seed_tiles --proxy globe-natgeo --tile-uri "tile/{z}/{y}/{x}" --bbox=-122.4,37.0,-122.0,37.3 --min-zoom 6 --max-zoom 14
+//! ```
+//! (note the '=' for the bbox argument, which is required for negative longitudes)
+
+use std::sync::Arc;
+use anyhow::{anyhow,Result};
+use odin_common::{define_cli, check_cli, geo::GeoBoundingBox};
+use odin_server::{prelude::*, ServerConfig, proxy_cache::ProxyCache, tile_seeder::{TileSeeder,SeedRequest}};
+use odin_cesium::ImgLayerService;
+
+define_cli! { ARGS [about="pre-fetch proxied map tiles into the local proxy cache"] =
+    config: String    [help="filename of SpaServer config with proxy cache settings", long, default_value="spa_server.ron"],
+    proxy: String     [help="symbolic name of proxy (e.g. globe-osm)", long],
+    tile_uri: String  [help="tile uri template relative to proxy", long, default_value="{z}/{x}/{y}.png"],
+    bbox: String      [help="bounding box as west,south,east,north degrees", long],
+    min_zoom: u32     [help="min zoom level", long, default_value="0"],
+    max_zoom: u32     [help="max zoom level", long],
+    concurrency: usize [help="max number of simultaneous requests", long, default_value="4"]
+}
+
+#[tokio::main]
+async fn main()->Result<()> {
+    check_cli!(ARGS);
+    odin_build::set_bin_context!();
+
+    let config: ServerConfig = odin_server::load_config( &ARGS.config)?;
+    let bbox = parse_bbox( &ARGS.bbox)?;
+
+    // we get the proxies from the same services we would use in the server
+    let svc_list = SpaServiceList::new().add( build_service!( => ImgLayerService::new()));
+    let comps = SpaComponents::from( &svc_list)?;
+    if !comps.proxies().contains_key( &ARGS.proxy) {
+        let known: Vec<&String> = comps.proxies().keys().collect();
+        return Err( anyhow!("unknown proxy {}, use one of {:?}", ARGS.proxy, known))
+    }
+
+    let cache = Arc::new( ProxyCache::new( config.proxy_cache));
+    let seeder = TileSeeder::new( cache, Arc::new( comps.proxies().clone()));
+
+    let request = SeedRequest {
+        proxy: ARGS.proxy.clone(),
+        tile_uri: ARGS.tile_uri.clone(),
+        bbox,
+        min_zoom: ARGS.min_zoom,
+        max_zoom: ARGS.max_zoom,
+        concurrency: ARGS.concurrency,
+    };
+
+    println!("seeding {} tiles of proxy {} into {:?}", request.total_tiles(), request.proxy, seeder.manifest_path( &request.id()));
+    let manifest = seeder.run( request, |m| {
+        println!("{:6.2}% ({} of {} tiles, {} fetched, {} already cached, {} not cacheable, {} failed)",
+                 m.percent_done(), m.next_index, m.total, m.fetched, m.skipped, m.uncacheable, m.pending.len());
+    }).await?;
+
+    for (z,x,y) in &manifest.pending {
+        println!("failed: {}", manifest.request.tile_uri( *z, *x, *y));
+    }
+
+    Ok(())
+}
+
+fn parse_bbox (s: &str)->Result<GeoBoundingBox> {
+    let v: Vec<f64> = s.split(',').map( |e| e.trim().parse::<f64>()).collect::<std::result::Result<Vec<f64>,_>>()?;
+    if v.len() == 4 {
+        Ok( GeoBoundingBox::from_wsen_degrees( &[v[0], v[1], v[2], v[3]]))
+    } else {
+        Err( anyhow!("bbox needs to have 4 elements (west,south,east,north)"))
+    }
+}
@@ -13,7 +13,7 @@
  */
 #![allow(unused)]
 
-use std::{net::SocketAddr,any::type_name};
+use std::{net::SocketAddr,any::type_name,time::Duration};
 use odin_common::{datetime::epoch_millis, strings::to_string_vec, collections::empty_vec};
 use async_trait::async_trait;
 
@@ -111,6 +111,9 @@ impl ImgLayerService {
 // note that requests will fail if we copy all headers
 const OSM_HDR: &[&str] = &["user-agent","referer","accept","accept-encoding"]; 
 
+// default cache TTL for globe tiles if the tile server does not specify one (can be overridden in the ServerConfig)
+const GLOBE_TILE_TTL: Duration = Duration::from_secs( 60*60*24*30);
+
 impl SpaService for ImgLayerService {
     fn add_dependencies (&self, spa_builder: SpaServiceList) -> SpaServiceList {
         spa_builder.add( build_service!( => CesiumService::new()))
@@ -126,6 +129,11 @@ impl SpaService for ImgLayerService {
         spa.add_proxy("globe-osm", "https://tile.openstreetmap.org", to_string_vec(OSM_HDR), empty_vec(), true, empty_vec());
         spa.add_proxy("globe-otm", "https://tile.opentopomap.org", to_string_vec(OSM_HDR), empty_vec(), true, empty_vec());
 
+        // globe tiles hardly ever change - keep them around for field deployments with intermittent connectivity
+        for proxy in ["globe-natgeo", "globe-osm", "globe-otm"] {
+            spa.set_proxy_ttl( proxy, GLOBE_TILE_TTL);
+        }
+
         Ok(())
     }
 }
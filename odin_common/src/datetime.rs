@@ -88,6 +88,23 @@ pub fn ser_epoch_millis_option<S: Serializer> (opt: &Option<DateTime<Utc>>, s: S
     }
 }
 
+/// accept either RFC3339 strings (as used by most external servers) or epoch millis (as we serialize for JS clients)
+pub fn deserialize_rfc3339_or_epoch_millis <'a,D>(deserializer: D) -> Result<DateTime<Utc>,D::Error>
+    where D: Deserializer<'a>
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DateRepr { Millis(i64), Rfc3339(String) }
+
+    match DateRepr::deserialize(deserializer)? {
+        DateRepr::Millis(millis) => DateTime::from_timestamp_millis(millis)
+            .ok_or_else( || serde::de::Error::custom(format!("epoch millis out of range: {millis}"))),
+        DateRepr::Rfc3339(s) => DateTime::parse_from_rfc3339(&s)
+            .map( |dt| dt.with_timezone(&Utc))
+            .map_err( |e| serde::de::Error::custom(format!("{:?}",e)))
+    }
+}
+
 pub fn deserialize_duration <'a,D>(deserializer: D) -> Result<Duration,D::Error>
     where D: Deserializer<'a>
 {
@@ -118,7 +135,7 @@ pub fn serialize_optional_duration<S>(dur: &Option<Duration>, s: S) -> Result<S:
     where S: Serializer,
 {
     if let Some(ref d) = *dur {
-        let dfm = format!("{:?}", dur);
+        let dfm = format!("{:?}", d);
         return s.serialize_str(&dfm);
     }
     s.serialize_none()
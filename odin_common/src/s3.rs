@@ -16,8 +16,9 @@
 
 use std::{path::{Path,PathBuf},fmt::{Debug,Display}, fs::File, io::{Write,Error}, ops::Deref};
 use thiserror::Error;
-use aws_sdk_s3::{Client, types::Object, operation::list_objects::builders::ListObjectsFluentBuilder};
+use aws_sdk_s3::{Client, types::Object, operation::list_objects::builders::ListObjectsFluentBuilder, config::Credentials};
 use aws_config::{Region,meta::region::RegionProviderChain};
+use serde::{Serialize,Deserialize};
 use aws_smithy_types_convert::date_time::DateTimeExt;
 use chrono::{DateTime,Utc};
 
@@ -88,11 +89,75 @@ impl Dated for S3Object {
     }
 }
 
+/// static credentials for S3 servers that require authentication
+#[derive(Serialize,Deserialize,Clone)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+// configs are logged, make sure this does not leak the secret
+impl Debug for S3Credentials {
+    fn fmt (&self, f: &mut std::fmt::Formatter<'_>)->std::fmt::Result {
+        f.debug_struct("S3Credentials")
+            .field( "access_key_id", &self.access_key_id)
+            .field( "secret_access_key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// S3 client configuration. If no `endpoint` is set we use the AWS endpoint for `region`, otherwise the given URL
+/// (e.g. "http://localhost:9000" for a local S3-compatible server) with path-style bucket access.
+/// Without `credentials` requests are not signed, which is what public buckets (e.g. NOAA data) and local servers need
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct S3ClientConfig {
+    pub region: String,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub credentials: Option<S3Credentials>,
+}
+
+impl S3ClientConfig {
+    pub fn new (region: impl ToString)->Self {
+        S3ClientConfig { region: region.to_string(), endpoint: None, credentials: None }
+    }
+
+    pub fn with_endpoint (mut self, endpoint: impl ToString)->Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    pub fn with_credentials (mut self, access_key_id: impl ToString, secret_access_key: impl ToString)->Self {
+        self.credentials = Some( S3Credentials { access_key_id: access_key_id.to_string(), secret_access_key: secret_access_key.to_string() });
+        self
+    }
+}
+
 /// create S3 Client for given region
 pub async fn create_s3_client (region: String) -> Result<Client> {
-    let region_provider = RegionProviderChain::first_try( Region::new( region));
-    let aws_config = aws_config::from_env().no_credentials().region(region_provider).load().await; // add anonymous creditials
-    Ok( Client::new(&aws_config) ) 
+    create_s3_client_from_config( &S3ClientConfig::new( region)).await
+}
+
+/// create S3 Client for given region, (optional) endpoint and (optional) credentials
+pub async fn create_s3_client_from_config (config: &S3ClientConfig) -> Result<Client> {
+    let region_provider = RegionProviderChain::first_try( Region::new( config.region.clone()));
+    let mut loader = aws_config::from_env().region(region_provider);
+
+    loader = if let Some(creds) = &config.credentials {
+        loader.credentials_provider( Credentials::new( &creds.access_key_id, &creds.secret_access_key, None, None, "odin"))
+    } else {
+        loader.no_credentials() // anonymous access
+    };
+    if let Some(endpoint) = &config.endpoint {
+        loader = loader.endpoint_url( endpoint);
+    }
+    let aws_config = loader.load().await;
+
+    let s3_config = aws_sdk_s3::config::Builder::from( &aws_config)
+        .force_path_style( config.endpoint.is_some()) // local servers normally don't support virtual-host style buckets
+        .build();
+    Ok( Client::from_conf( s3_config) ) 
 }
 
 /// retrieve all objects (from optional marker) for given bucket/prefix. If there is no error this always returns a `Vec<S3Object>`
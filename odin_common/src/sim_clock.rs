@@ -68,6 +68,7 @@ impl SettableSimClock {
     pub fn reset (&mut self, start_dt: DateTime<Utc>, timescale: u32)->Result<(),OdinClockError> {
         if self.is_resettable {
             self.start_dt = start_dt;
+            self.timescale = timescale;
             self.wall_start = Instant::now();
             Ok(())
         } else { Err( OdinClockError::ClockNotResettable) }
@@ -78,6 +79,10 @@ impl SettableSimClock {
         self.start_dt + (Instant::now() - self.wall_start) * self.timescale
     }
 
+    pub fn timescale (&self)->u32 {
+        self.timescale
+    }
+
     pub fn now_local (&self)->DateTime<Local> {
         self.now().with_timezone(&Local)
     }
@@ -179,6 +184,18 @@ pub fn epoch_millis()->Result<i64,OdinClockError> {
     }
 }
 
+/// the factor by which the clock advances faster than wall time (wall clocks always return 1)
+pub fn timescale()->Result<u32,OdinClockError> {
+    match SIM_CLOCK.get() {
+        Some(sim_clock) => 
+            match sim_clock {
+                SimClock::Settable(sim_clock) => Ok(sim_clock.lock()?.timescale()),
+                SimClock::Wall => Ok(1)
+            }        
+        None => Err( OdinClockError::ClockNotInitialized)
+    }
+}
+
 pub fn reset (start_dt: DateTime<Utc>, timescale: u32)->Result<(),OdinClockError> {
     match SIM_CLOCK.get() {
         Some(sim_clock) => {
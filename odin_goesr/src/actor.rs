@@ -30,7 +30,7 @@ pub struct GoesrImportActorConfig {
 #[derive(Debug)] pub struct Initialize(pub(crate) Vec<GoesrHotspotSet>);
 #[derive(Debug)] pub struct ImportError(pub(crate) OdinGoesrError);
 
-define_actor_msg_set! { pub GoesrHotspotImportActorMsg = ExecSnapshotAction | Initialize | Update | ImportError }
+define_actor_msg_set! { pub GoesrHotspotImportActorMsg = ExecSnapshotAction | Initialize | Update | ImportError | Reconfigure<GoesrImportActorConfig> }
 
 /// user part of the GoesR import actor
 /// this basically provides a message interface around an encapsulated, async updated HotspotStore
@@ -81,6 +81,8 @@ impl_actor! { match msg for Actor< GoesrHotspotActor<T,I,U>, GoesrHotspotImportA
     Update => cont! { self.update(msg.0).await; }
 
     ImportError => cont! { error!("{:?}", msg.0); }
+
+    Reconfigure<GoesrImportActorConfig> => cont! { self.hotspot_store.set_max_capacity( msg.config.max_records); }
     
     _Terminate_ => stop! { self.goesr_importer.terminate(); }
 }
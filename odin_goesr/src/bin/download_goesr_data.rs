@@ -26,9 +26,9 @@ use chrono::{DateTime,Utc};
 
 use odin_build;
 use odin_common::{define_cli,fs::ensure_writable_dir};
-use odin_common::s3::{S3Object,create_s3_client, get_s3_objects, get_last_s3_object};
+use odin_common::s3::{S3Object,create_s3_client_from_config, get_s3_objects, get_last_s3_object};
 use odin_common::schedule::{get_hourly_schedule,Compaction,get_next_hourly_event_dtg};
-use odin_goesr::{load_config,get_goesr_data, get_most_recent_objects, get_objects_since, no_object_error, OdinGoesrError, Result, LiveGoesrHotspotImporterConfig};
+use odin_goesr::{load_config,get_goesr_data, get_most_recent_objects, get_init_objects, get_objects_since, no_object_error, OdinGoesrError, Result, LiveGoesrHotspotImporterConfig};
 
 define_cli! { ARGS [about="GOES-R file download tool"] =
     config: String [help="pathname to LiveGoesrDataImporterConfig config"]
@@ -41,7 +41,7 @@ async fn main()->Result<()> {
     let cache_dir = odin_build::cache_dir().join("goesr");
     ensure_writable_dir(&cache_dir)?;
 
-    let client = create_s3_client( config.s3_region.clone()).await?;
+    let client = create_s3_client_from_config( &config.s3_client_config()).await?;
     let bucket = &config.bucket;
     let sat_id = config.sat_id;
     let source = Arc::new(config.source.clone());
@@ -56,7 +56,7 @@ async fn main()->Result<()> {
     if objs.len() < 12 { return Err(no_object_error("not enough initial objects")) }
 
     let hourly_schedule = get_hourly_schedule(&objs, Some(Compaction::BoundedRightEdge(3)));
-    let mut init_objs = if objs.len() > config.init_files { objs.split_off( objs.len()-config.init_files) } else { objs };
+    let mut init_objs = get_init_objects( objs, config.init_files);
 
     for obj in &init_objs {
         let gdata = get_goesr_data( &client, obj, &cache_dir, bucket, source.clone(), sat_id).await?;
@@ -21,7 +21,7 @@ use odin_build;
 use odin_actor::prelude::*;
 use odin_server::prelude::*;
 use odin_goesr::{
-    load_config, GoesrHotspotActor, GoesrHotspotImportActorMsg, GoesrHotspotSet, GoesrHotspotStore, GoesrSat, GoesrService, LiveGoesrHotspotImporter, LiveGoesrHotspotImporterConfig};
+    load_config, config_path, asset_path, invalidate_asset, GoesrImportActorConfig, GoesrHotspotActor, GoesrHotspotImportActorMsg, GoesrHotspotSet, GoesrHotspotStore, GoesrSat, GoesrService, LiveGoesrHotspotImporter, LiveGoesrHotspotImporterConfig};
 
  
 #[tokio::main]
@@ -43,8 +43,16 @@ async fn main()->Result<()> {
             .add( build_service!( => GoesrService::new( vec![goes18,goes16])) )
     ))?;
 
-    let _hgoes18 = spawn_goesr_updater( &mut actor_system, "goes18", hgoes18, load_config( "goes_18_fdcc.ron")?, &hserver)?;
-    let _hgoes16 = spawn_goesr_updater( &mut actor_system, "goes16", hgoes16, load_config( "goes_16_fdcc.ron")?, &hserver)?;
+    let hgoes18 = spawn_goesr_updater( &mut actor_system, "goes18", hgoes18, load_config( "goes_18_fdcc.ron")?, &hserver)?;
+    let hgoes16 = spawn_goesr_updater( &mut actor_system, "goes16", hgoes16, load_config( "goes_16_fdcc.ron")?, &hserver)?;
+
+    // apply config and asset modifications without restart
+    let mut watcher = ResourceWatcher::new( secs(2));
+    watcher.watch_config::<ServerConfig,_>( "spa_server.ron", odin_server::config_path, hserver.clone());
+    watcher.watch_config::<GoesrImportActorConfig,_>( "goesr.ron", config_path, hgoes18);
+    watcher.watch_config::<GoesrImportActorConfig,_>( "goesr.ron", config_path, hgoes16);
+    watcher.watch_asset( "odin_goesr.js", asset_path, invalidate_asset, hserver.clone());
+    spawn_actor!( actor_system, "watcher", watcher)?;
 
     actor_system.timeout_start_all(secs(2)).await?;
     actor_system.process_requests().await?;
@@ -109,17 +109,17 @@ impl SpaService for GoesrService {
         conn.send(msg).await;
 
         if is_data_available {
-            let remote_addr = conn.remote_addr;
+            let conn_id = conn.id;
             for sat in &self.satellites {
                 let action = dyn_dataref_action!{ 
                     let hself: ActorHandle<SpaServerMsg> = hself.clone(), 
-                    let remote_addr: SocketAddr = remote_addr => 
+                    let conn_id: ConnectionId = conn_id => 
                     |store: &GoesrHotspotStore| {
                         for hotspots in store.iter_old_to_new(){
-                            let remote_addr = remote_addr.clone();
+                            let conn_id = conn_id.clone();
                             //let data = ws_msg!( "odin_goesr/odin_goesr.js", hotspots).to_json()?;
                             let data = WsMsg::json( GoesrService::mod_path(), "hotspots", hotspots)?;
-                            hself.try_send_msg( SendWsMsg{remote_addr,data})?;
+                            hself.try_send_msg( SendWsMsg{conn_id,data})?;
                         }
                         Ok(())
                     }
@@ -47,6 +47,12 @@ pub use actor::*;
 pub mod live_importer;
 pub use live_importer::*;
 
+pub mod replay_importer;
+pub use replay_importer::*;
+
+#[cfg(feature="mock")] pub mod mock_s3;
+#[cfg(feature="mock")] pub use mock_s3::*;
+
 pub mod goesr_service;
 pub use goesr_service::*;
 
@@ -206,6 +212,12 @@ impl GoesrHotspotStore {
         }
     }
 
+    /// change the max number of stored hotspot sets, dropping the oldest ones if the new capacity is exceeded
+    pub fn set_max_capacity(&mut self, capacity: usize) -> () {
+        self.hotspots.truncate(capacity);
+        self.max_capacity = capacity;
+    }
+
     pub fn initialize_hotspots(&mut self, init_hotspots: Vec<GoesrHotspotSet>) -> () {
         for hs in init_hotspots {
             self.hotspots.push_front(hs);
@@ -360,6 +372,11 @@ pub async fn get_objects_since_last (client: &S3Client, bucket: &str, source: &s
     Ok(objects)
 }
 
+/// return the `init_files` most recent objects from a list that is in ascending time order (newest last)
+pub fn get_init_objects (mut objs: Vec<S3Object>, init_files: usize)->Vec<S3Object> {
+    if objs.len() > init_files { objs.split_off( objs.len() - init_files) } else { objs }
+}
+
 // get all S3Objects either from last downloaded one or as a fallback since the provided DateTime<Utc>
 pub async fn get_objects_since (client: &S3Client, bucket: &str, source: &str, last_obj: &Option<S3Object>, dt: DateTime<Utc>, now: DateTime<Utc>)->Result<Vec<S3Object>> {
     if let Some(last_obj) = last_obj {
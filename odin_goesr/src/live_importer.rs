@@ -15,7 +15,7 @@
 use crate::*;
 use odin_actor::ObjSafeFuture;
 use odin_common::fs::ensure_writable_dir;
-use odin_common::s3::{create_s3_client, get_s3_objects, get_last_s3_object};
+use odin_common::s3::{S3ClientConfig, S3Credentials, create_s3_client_from_config, get_s3_objects, get_last_s3_object};
 use odin_common::schedule::{get_hourly_schedule,Compaction,get_next_hourly_event_dtg};
 use std::{path::Path,time::Instant};
 
@@ -24,6 +24,10 @@ use std::{path::Path,time::Instant};
 pub struct LiveGoesrHotspotImporterConfig {
     pub sat_id: u32,  // SATCAT # (e.g. 51850 for GOES-18)
     pub s3_region: String, // e.g. "us-east-1"
+    #[serde(default)]
+    pub s3_endpoint: Option<String>, // only set for non-AWS (e.g. local) S3 servers such as "http://localhost:9000"
+    #[serde(default)]
+    pub s3_credentials: Option<S3Credentials>, // only set if the server requires authentication (NOAA buckets are public)
     pub bucket: String, // e.g. "noaa-goes18"
     pub source: String, // e.g. "ABI-L2-FDCC"
     pub keep_files: bool,
@@ -32,6 +36,12 @@ pub struct LiveGoesrHotspotImporterConfig {
     pub max_age: Duration,
 }
 
+impl LiveGoesrHotspotImporterConfig {
+    pub fn s3_client_config (&self)->S3ClientConfig {
+        S3ClientConfig { region: self.s3_region.clone(), endpoint: self.s3_endpoint.clone(), credentials: self.s3_credentials.clone() }
+    }
+}
+
 /// the structure representing objects to collect and announce availability of live GoesR FDCC fire product data (hotspots)
 /// 
 /// (REQ) instance should check availability of new data sets on a guaranteed time interval
@@ -57,7 +67,7 @@ impl LiveGoesrHotspotImporter {
     async fn initialize  (&mut self, hself: ActorHandle<GoesrHotspotImportActorMsg>) -> Result<()> { 
         let config = &self.config;
         let init_files = config.init_files;
-        let s3_client = create_s3_client( config.s3_region.clone()).await?;
+        let s3_client = create_s3_client_from_config( &config.s3_client_config()).await?;
 
         self.import_task = Some( self.spawn_import_task( s3_client, hself)? );
         self.file_cleanup_task = Some( self.spawn_file_cleanup_task()? );
@@ -110,7 +120,7 @@ async fn run_data_acquisition (hself: ActorHandle<GoesrHotspotImportActorMsg>, c
     if objs.len() < 12 { return Err(no_object_error("not enough initial objects")) }
 
     let hourly_schedule = get_hourly_schedule(&objs, Some(Compaction::BoundedRightEdge(3)));
-    let mut init_objs = if objs.len() > config.init_files { objs.split_off( objs.len()-config.init_files) } else { objs };
+    let mut init_objs = get_init_objects( objs, config.init_files);
 
     //--- now get the initial files and send an Initialize msg with the hotspots read from them
     let hotspots = download_and_read_objects( &client, bucket, &source, sat_id, &cache_dir, &init_objs).await?;
@@ -0,0 +1,200 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! a minimal local stand-in for an S3 compatible object store that can be used to run the GOES-R import functions
+//! (e.g. [`get_most_recent_objects`], [`get_objects_since`] and [`get_goesr_data`]) against fixture objects, without
+//! network access or AWS credentials.
+//!
+//! The server only supports what our S3 client functions use: path-style `ListObjects` (v1, with `prefix` and `marker`
+//! query parameters) and `GetObject` requests. Objects can be added while the server is running, which is how tests
+//! simulate new data sets becoming available over time. Use [`MockS3Server::s3_client_config`] to connect to it
+
+use std::{net::SocketAddr, collections::{BTreeMap,HashMap}, sync::{Arc,Mutex}};
+use axum::{
+    Router, routing::get,
+    extract::{State, Path as AxumPath, Query as AxumQuery},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tokio::net::TcpListener;
+use chrono::{DateTime,Utc};
+
+use odin_actor::prelude::*;
+use odin_common::s3::S3ClientConfig;
+
+use crate::errors::*;
+
+pub const MOCK_S3_REGION: &str = "us-east-1";
+
+/* #region server ****************************************************************************************************/
+
+#[derive(Debug,Clone)]
+struct MockS3Object {
+    last_modified: DateTime<Utc>,
+    data: Arc<Vec<u8>>,
+}
+
+/// bucket name -> (key -> object). We use BTreeMaps since S3 lists objects in ascending key order
+type MockBuckets = Mutex<HashMap<String,BTreeMap<String,MockS3Object>>>;
+
+/// handle for a running mock S3 server. The server is terminated when this object is dropped
+pub struct MockS3Server {
+    addr: SocketAddr,
+    buckets: Arc<MockBuckets>,
+    server_task: AbortHandle,
+}
+
+impl MockS3Server {
+
+    /// start server on the given address (use port 0 to get an ephemeral port)
+    pub async fn start (addr: SocketAddr)->Result<Self> {
+        let buckets = Arc::new( Mutex::new( HashMap::new()));
+
+        let listener = TcpListener::bind( addr).await?;
+        let addr = listener.local_addr()?;
+        let router = Self::router( buckets.clone());
+        let server_task = spawn( "mock-s3", async move {
+            if let Err(e) = axum::serve( listener, router).await { error!("mock S3 server terminated: {e}") }
+        })?.abort_handle();
+        info!("mock S3 server listening on {addr}");
+
+        Ok( MockS3Server { addr, buckets, server_task } )
+    }
+
+    /// start server on an ephemeral localhost port
+    pub async fn start_local ()->Result<Self> {
+        Self::start( SocketAddr::from( ([127,0,0,1], 0))).await
+    }
+
+    pub fn addr (&self)->SocketAddr { self.addr }
+
+    pub fn endpoint (&self)->String { format!("http://{}", self.addr) }
+
+    /// an [`S3ClientConfig`] that connects to this server (without credentials)
+    pub fn s3_client_config (&self)->S3ClientConfig {
+        S3ClientConfig::new( MOCK_S3_REGION).with_endpoint( self.endpoint())
+    }
+
+    pub fn create_bucket (&self, bucket: &str) {
+        self.buckets.lock().unwrap().entry( bucket.to_string()).or_default();
+    }
+
+    /// make an object available (this creates the bucket if it does not exist yet, and replaces existing objects)
+    pub fn put_object (&self, bucket: &str, key: &str, last_modified: DateTime<Utc>, data: Vec<u8>) {
+        let obj = MockS3Object { last_modified, data: Arc::new(data) };
+        self.buckets.lock().unwrap().entry( bucket.to_string()).or_default().insert( key.to_string(), obj);
+    }
+
+    pub fn remove_object (&self, bucket: &str, key: &str)->bool {
+        self.buckets.lock().unwrap().get_mut( bucket).and_then( |objs| objs.remove( key)).is_some()
+    }
+
+    pub fn keys (&self, bucket: &str)->Vec<String> {
+        self.buckets.lock().unwrap().get( bucket).map( |objs| objs.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn terminate (&mut self) {
+        self.server_task.abort();
+    }
+
+    fn router (buckets: Arc<MockBuckets>)->Router {
+        Router::new()
+            .route( "/:bucket", get( list_objects_handler))
+            .route( "/:bucket/*key", get( get_object_handler))
+            .with_state( buckets)
+    }
+}
+
+impl Drop for MockS3Server {
+    fn drop (&mut self) {
+        self.terminate()
+    }
+}
+
+/* #endregion server */
+
+/* #region http handlers *********************************************************************************************/
+
+/// ListObjects (v1) - we only support `prefix` and `marker`, and never truncate
+async fn list_objects_handler (
+    State(buckets): State<Arc<MockBuckets>>,
+    AxumPath(bucket): AxumPath<String>,
+    AxumQuery(params): AxumQuery<HashMap<String,String>>
+)->Response {
+    let prefix = params.get("prefix").map( |s| s.as_str()).unwrap_or("");
+    let marker = params.get("marker").map( |s| s.as_str()).unwrap_or("");
+
+    let buckets = buckets.lock().unwrap();
+    let Some(objs) = buckets.get( &bucket) else {
+        return error_response( StatusCode::NOT_FOUND, "NoSuchBucket", &bucket)
+    };
+
+    let mut xml = String::with_capacity( 1024);
+    xml.push_str( r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push_str( r#"<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">"#);
+    xml.push_str( &format!("<Name>{}</Name><Prefix>{}</Prefix><Marker>{}</Marker>", xml_escape(&bucket), xml_escape(prefix), xml_escape(marker)));
+    xml.push_str( "<MaxKeys>1000</MaxKeys><IsTruncated>false</IsTruncated>");
+
+    for (key,obj) in objs.iter().filter( |(k,_)| k.starts_with( prefix) && k.as_str() > marker) {
+        xml.push_str( &format!(
+            "<Contents><Key>{}</Key><LastModified>{}</LastModified><ETag>{}</ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+            xml_escape(key), s3_date(&obj.last_modified), etag(key, obj), obj.data.len()
+        ));
+    }
+    xml.push_str( "</ListBucketResult>");
+
+    ( [(header::CONTENT_TYPE, "application/xml")], xml ).into_response()
+}
+
+async fn get_object_handler (
+    State(buckets): State<Arc<MockBuckets>>,
+    AxumPath((bucket,key)): AxumPath<(String,String)>
+)->Response {
+    let obj = buckets.lock().unwrap().get( &bucket).and_then( |objs| objs.get( &key)).cloned();
+
+    if let Some(obj) = obj {
+        let last_modified = obj.last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        let etag = etag( &key, &obj);
+        (
+            [ (header::CONTENT_TYPE, "application/octet-stream".to_string()), (header::LAST_MODIFIED, last_modified), (header::ETAG, etag) ],
+            obj.data.as_ref().clone()
+        ).into_response()
+    } else {
+        error_response( StatusCode::NOT_FOUND, "NoSuchKey", &key)
+    }
+}
+
+fn error_response (status: StatusCode, code: &str, resource: &str)->Response {
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>{}</Code><Message>{} does not exist</Message><Resource>{}</Resource></Error>"#,
+        code, xml_escape(resource), xml_escape(resource)
+    );
+    ( status, [(header::CONTENT_TYPE, "application/xml")], xml ).into_response()
+}
+
+fn s3_date (date: &DateTime<Utc>)->String {
+    date.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+/// not a real MD5 but stable for a given object version, which is all our clients need
+fn etag (key: &str, obj: &MockS3Object)->String {
+    format!("\"{:x}-{:x}\"", obj.last_modified.timestamp_millis(), key.len() + obj.data.len())
+}
+
+fn xml_escape (s: &str)->String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+/* #endregion http handlers */
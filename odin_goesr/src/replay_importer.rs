@@ -0,0 +1,174 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use crate::*;
+use odin_common::sim_clock;
+use std::fs;
+
+/// configuration for GoesR FDCC hotspot replay from a local directory of NetCDF files
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct ReplayGoesrHotspotImporterConfig {
+    pub sat_id: u32,  // SATCAT # (e.g. 51850 for GOES-18)
+    pub source: String, // e.g. "ABI-L2-FDCC" - only files for this product are replayed
+    pub data_dir: PathBuf, // directory with OR_ABI-L2-FDCC-* files (e.g. populated by download_goesr_data)
+    pub init_files: usize, // number of data files to read on initialization
+
+    #[serde(default)]
+    pub start: Option<DateTime<Utc>>, // optional scan start of the first update, init files are the ones before
+    #[serde(default)]
+    pub end: Option<DateTime<Utc>>, // optional scan start after which we stop the replay
+
+    #[serde(default)]
+    pub rebase_times: bool, // shift data set dates so that the first update corresponds to the current (sim) time
+}
+
+/// a data file to replay, ordered by scan start time
+#[derive(Debug,Clone,PartialEq)]
+pub struct GoesrReplayFile {
+    pub path: PathBuf,
+    pub start_time: DateTime<Utc>,
+}
+
+/// replay of archived GoesR FDCC fire product data (hotspots), e.g. for incident reconstructions and offline demos
+///
+/// The replay files are ordered by the scan start time (`_s` field) of their filenames. The first `init_files` files 
+/// (or the last `init_files` before the configured `start`) are sent as an `Initialize` message, all subsequent files as
+/// `Update` messages paced by the differences of their scan start times. If there is a settable [`sim_clock`] we scale
+/// this timing by its timescale, otherwise updates are paced by wall time
+#[derive(Debug)]
+pub struct ReplayGoesrHotspotImporter {
+    config: ReplayGoesrHotspotImporterConfig,
+
+    /// values set during initialization
+    import_task: Option<AbortHandle>,
+}
+
+impl ReplayGoesrHotspotImporter {
+    pub fn new (config: ReplayGoesrHotspotImporterConfig) -> Self {
+        ReplayGoesrHotspotImporter{ config, import_task:None }
+    }
+
+    async fn initialize  (&mut self, hself: ActorHandle<GoesrHotspotImportActorMsg>) -> Result<()> { 
+        let files = get_replay_files( &self.config.data_dir, &self.config.source)?;
+        let (init_files, update_files) = split_replay_files( files, self.config.init_files, self.config.start, self.config.end);
+        if init_files.is_empty() && update_files.is_empty() {
+            return Err(no_object_error(format!("no {} files in {:?}", self.config.source, self.config.data_dir)))
+        }
+
+        let config = self.config.clone();
+        self.import_task = Some( spawn( &format!("goes-{}-data-replay", self.config.sat_id), async move {
+                run_data_replay( hself, config, init_files, update_files).await
+            })?.abort_handle()
+        );
+        Ok(())
+    }
+}
+
+impl GoesrHotspotImporter for ReplayGoesrHotspotImporter {
+    async fn start (&mut self, hself: ActorHandle<GoesrHotspotImportActorMsg>) -> Result<()> {
+        self.initialize(hself).await?;
+        Ok(())
+    }
+
+    fn terminate (&mut self) {
+        if let Some(task) = &self.import_task { task.abort() }
+    }
+}
+
+/// all files in `dir` (including sub-directories) that have a valid GOES-R filename for the given `source` product,
+/// in ascending order of scan start time
+pub fn get_replay_files (dir: impl AsRef<Path>, source: &str) -> Result<Vec<GoesrReplayFile>> {
+    fn collect (dir: &Path, source: &str, files: &mut Vec<GoesrReplayFile>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                collect( &path, source, files)?
+            } else if let Some(info) = parse_filename( &path) {
+                if format!("{}-{}-{}", info.instrument, info.level, info.product) == source {
+                    files.push( GoesrReplayFile{ path, start_time: info.start_time })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    collect( dir.as_ref(), source, &mut files)?;
+    files.sort_by( |a,b| a.start_time.cmp( &b.start_time));
+    Ok(files)
+}
+
+/// split ordered replay files into init and update files. If `start` is set the init files are the last `init_files`
+/// files before it, otherwise the first `init_files` files. Files with a scan start after `end` are dropped
+pub fn split_replay_files (mut files: Vec<GoesrReplayFile>, init_files: usize, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>)
+    -> (Vec<GoesrReplayFile>, Vec<GoesrReplayFile>)
+{
+    if let Some(end) = end { files.retain( |f| f.start_time <= end) }
+
+    let n_before = if let Some(start) = start {
+        files.iter().take_while( |f| f.start_time < start).count()
+    } else {
+        init_files.min( files.len())
+    };
+
+    let updates = files.split_off( n_before);
+    let init = if files.len() > init_files { files.split_off( files.len() - init_files) } else { files };
+    (init, updates)
+}
+
+async fn run_data_replay (hself: ActorHandle<GoesrHotspotImportActorMsg>, config: ReplayGoesrHotspotImporterConfig,
+                          init_files: Vec<GoesrReplayFile>, update_files: Vec<GoesrReplayFile>)->Result<()> 
+{
+    let source = Arc::new( config.source); // no need to keep gazillions of copies
+    let sat_id = config.sat_id;
+
+    let timescale = if sim_clock::is_settable().unwrap_or(false) { sim_clock::timescale().unwrap_or(1).max(1) } else { 1 };
+    let time_offset = if config.rebase_times {
+        let now = sim_clock::now().unwrap_or_else( |_| Utc::now());
+        update_files.first().or( init_files.last()).map( |f| now - f.start_time).unwrap_or( TimeDelta::zero())
+    } else {
+        TimeDelta::zero()
+    };
+
+    //--- send the Initialize msg with the hotspots read from the init files
+    let hotspots: Vec<GoesrHotspotSet> = init_files.iter()
+        .filter_map( |f| read_replay_file( f, sat_id, &source, time_offset))
+        .collect();
+    let mut last_start = init_files.last().map( |f| f.start_time);
+    hself.send_msg( Initialize(hotspots) ).await?;
+
+    //--- run update loop
+    for f in &update_files {
+        if let Some(last) = last_start {
+            let dt = ((f.start_time - last) / timescale as i32).to_std().unwrap_or( Duration::ZERO);
+            if !dt.is_zero() { sleep( dt).await }
+        }
+        last_start = Some(f.start_time);
+
+        if let Some(hs) = read_replay_file( f, sat_id, &source, time_offset) {
+            hself.send_msg( Update(hs)).await?;
+        }
+    }
+
+    info!("GOES-{} replay of {:?} done", sat_id, config.data_dir);
+    Ok(())
+}
+
+fn read_replay_file (f: &GoesrReplayFile, sat_id: u32, source: &Arc<String>, time_offset: TimeDelta) -> Option<GoesrHotspotSet> {
+    let data = GoesrData{ sat_id, file: f.path.clone(), source: source.clone(), date: f.start_time + time_offset };
+    match read_goesr_data( &data) {
+        Ok(hs) => Some(hs),
+        Err(e) => { warn!("error parsing GOES-R data {:?}: {e:?}", f.path); None }
+    }
+}
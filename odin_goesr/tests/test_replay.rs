@@ -0,0 +1,75 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::{fs, path::PathBuf};
+use chrono::{DateTime,Utc};
+use odin_goesr::{get_replay_files, split_replay_files, parse_goesr_dtg, GoesrReplayFile, Result};
+
+const FILES: [&str;6] = [
+    "OR_ABI-L2-FDCC-M6_G18_s20241381011170_e20241381013543_c20241381014128.nc",
+    "OR_ABI-L2-FDCC-M6_G18_s20241381001170_e20241381003543_c20241381004119.nc",
+    "OR_ABI-L2-FDCC-M6_G18_s20241381006170_e20241381008543_c20241381009121.nc",
+    "OR_ABI-L2-FDCC-M6_G18_s20241380956170_e20241380958543_c20241380959130.nc",
+    "OR_ABI-L2-ACMC-M6_G18_s20241381001170_e20241381003543_c20241381004020.nc", // different product
+    "README.txt"
+];
+
+fn create_dir (name: &str)->Result<PathBuf> {
+    let dir = std::env::temp_dir().join( format!("odin_goesr_replay_{}_{}", name, std::process::id()));
+    if dir.is_dir() { fs::remove_dir_all( &dir)?; }
+    fs::create_dir_all( dir.join("sub"))?;
+
+    for (i,f) in FILES.iter().enumerate() {
+        let path = if i == 0 { dir.join("sub").join(f) } else { dir.join(f) }; // we also replay from sub-dirs
+        fs::write( path, b"")?;
+    }
+    Ok(dir)
+}
+
+fn dtg (s: &str)->DateTime<Utc> { parse_goesr_dtg(s).unwrap() }
+
+#[test]
+fn test_replay_order ()->Result<()> {
+    let dir = create_dir( "order")?;
+    let files = get_replay_files( &dir, "ABI-L2-FDCC")?;
+    for f in &files { println!("{}: {:?}", f.start_time, f.path.file_name().unwrap()) }
+
+    let start_times: Vec<DateTime<Utc>> = files.iter().map( |f| f.start_time).collect();
+    assert_eq!( start_times, vec![ dtg("20241380956170"), dtg("20241381001170"), dtg("20241381006170"), dtg("20241381011170") ]);
+
+    fs::remove_dir_all( &dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_replay_split ()->Result<()> {
+    let dir = create_dir( "split")?;
+    let files = get_replay_files( &dir, "ABI-L2-FDCC")?;
+
+    let (init, updates) = split_replay_files( files.clone(), 2, None, None);
+    assert_eq!( (init.len(), updates.len()), (2,2));
+    assert_eq!( init[1].start_time, dtg("20241381001170"));
+
+    // init files are the last ones before start
+    let (init, updates) = split_replay_files( files.clone(), 1, Some( dtg("20241381006000")), None);
+    assert_eq!( init.iter().map( |f| f.start_time).collect::<Vec<_>>(), vec![ dtg("20241381001170") ]);
+    assert_eq!( updates.len(), 2);
+
+    let (init, updates) = split_replay_files( files.clone(), 1, None, Some( dtg("20241381006170")));
+    assert_eq!( (init.len(), updates.len()), (1,2));
+
+    fs::remove_dir_all( &dir)?;
+    Ok(())
+}
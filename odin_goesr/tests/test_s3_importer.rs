@@ -0,0 +1,174 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+#![feature(duration_constructors)]
+
+//! integration tests for the S3 functions used by the `LiveGoesrHotspotImporter`, run against a local
+//! [`MockS3Server`] that is populated with GOES-R fixture objects following the NOAA bucket layout. Data sets
+//! are produced every 5min and become available at their create time, i.e. before their `last_modified` time
+
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+use chrono::{DateTime,Utc,TimeDelta,Datelike,Timelike};
+use odin_common::s3::{S3Client,S3Object,create_s3_client_from_config};
+use odin_goesr::{MockS3Server, get_most_recent_objects, get_objects_since, get_init_objects, get_goesr_data, parse_goesr_create_dtg, Result};
+
+const BUCKET: &str = "noaa-goes18";
+const SOURCE: &str = "ABI-L2-FDCC";
+const SAT_ID: u32 = 51850;
+
+struct Fixture {
+    key: String,
+    created: DateTime<Utc>,
+    last_modified: DateTime<Utc>,
+}
+
+fn dtg (s: &str)->DateTime<Utc> { s.parse().unwrap() }
+
+fn goesr_dtg (dt: &DateTime<Utc>)->String {
+    format!("{}{}", dt.format("%Y%j%H%M%S"), dt.timestamp_subsec_millis() / 100)
+}
+
+/// `n` data sets with scan start times every 5min from `t0` (+1:17min)
+fn fixtures (t0: DateTime<Utc>, n: i64)->Vec<Fixture> {
+    (0..n).map( |i| {
+        let start = t0 + TimeDelta::seconds( 77 + i*300);
+        let end = start + TimeDelta::milliseconds( 157_300);
+        let created = start + TimeDelta::milliseconds( 180_500);
+        let key = format!("{}/{}/{:03}/{:02}/OR_{}-M6_G18_s{}_e{}_c{}.nc", 
+            SOURCE, start.year(), start.ordinal(), start.hour(), SOURCE, goesr_dtg(&start), goesr_dtg(&end), goesr_dtg(&created));
+        Fixture { key, created, last_modified: created + TimeDelta::seconds(10) }
+    }).collect()
+}
+
+/// put all fixtures that have been created at `now` into the server
+fn release (server: &MockS3Server, fixtures: &[Fixture], now: DateTime<Utc>) {
+    for f in fixtures.iter().filter( |f| f.created <= now) {
+        server.put_object( BUCKET, &f.key, f.last_modified, f.key.as_bytes().to_vec());
+    }
+}
+
+fn keys (objs: &[S3Object])->Vec<String> {
+    objs.iter().map( |o| o.key().unwrap().to_string()).collect()
+}
+
+async fn setup (fixtures: &[Fixture], now: DateTime<Utc>)->Result<(MockS3Server,S3Client)> {
+    let server = MockS3Server::start_local().await?;
+    server.create_bucket( BUCKET);
+    release( &server, fixtures, now);
+    let client = create_s3_client_from_config( &server.s3_client_config()).await?;
+    Ok( (server, client) )
+}
+
+#[tokio::test]
+async fn test_initial_load ()->Result<()> {
+    let fixtures = fixtures( dtg("2024-05-17T06:00:00Z"), 60);
+    let now = dtg("2024-05-17T10:02:00Z");
+    let (server, client) = setup( &fixtures, now).await?;
+
+    let objs = get_most_recent_objects( &client, BUCKET, SOURCE, Duration::from_hours(3), now).await?;
+    let dt_start = now - TimeDelta::hours(3);
+    let expected: Vec<String> = fixtures.iter()
+        .filter( |f| f.created <= now && f.last_modified > dt_start)
+        .map( |f| f.key.clone()).collect();
+    assert_eq!( keys(&objs), expected);
+    assert!( objs.len() >= 12); // enough to compute a schedule
+
+    let init_objs = get_init_objects( objs, 3);
+    assert_eq!( keys(&init_objs), expected[expected.len()-3..].to_vec());
+
+    let dir = std::env::temp_dir().join( format!("odin_goesr_s3_init_{}", std::process::id()));
+    fs::create_dir_all( &dir)?;
+    for obj in &init_objs {
+        let gdata = get_goesr_data( &client, obj, &dir, BUCKET, Arc::new(SOURCE.to_string()), SAT_ID).await?;
+        println!("downloaded {:?}", gdata.file);
+        assert_eq!( fs::read( &gdata.file)?, obj.key().unwrap().as_bytes());
+    }
+    fs::remove_dir_all( &dir)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_gap_recovery ()->Result<()> {
+    let fixtures = fixtures( dtg("2024-05-17T06:00:00Z"), 90);
+    let now = dtg("2024-05-17T10:02:00Z");
+    let (server, client) = setup( &fixtures, now).await?;
+
+    let objs = get_most_recent_objects( &client, BUCKET, SOURCE, Duration::from_hours(3), now).await?;
+    let last_obj = get_init_objects( objs, 3).pop();
+    let last_key = last_obj.as_ref().unwrap().key().unwrap().to_string();
+
+    //--- we are down for 2.5h (spanning several hour prefixes) while new data keeps coming in
+    let dt_cycle = dtg("2024-05-17T12:31:00Z");
+    let now = dt_cycle + TimeDelta::seconds(1);
+    release( &server, &fixtures, now);
+
+    let objs = get_objects_since( &client, BUCKET, SOURCE, &last_obj, dt_cycle, now).await?;
+    let expected: Vec<String> = fixtures.iter()
+        .filter( |f| f.key > last_key && f.last_modified <= now)
+        .map( |f| f.key.clone()).collect();
+    assert_eq!( keys(&objs), expected);
+    assert_eq!( expected.len(), 30);
+
+    //--- without a last object we fall back to everything since the cycle start
+    let dt = dtg("2024-05-17T12:00:00Z");
+    let objs = get_objects_since( &client, BUCKET, SOURCE, &None, dt, now).await?;
+    let expected: Vec<String> = fixtures.iter()
+        .filter( |f| f.last_modified > dt && f.created <= now)
+        .map( |f| f.key.clone()).collect();
+    assert_eq!( keys(&objs), expected);
+
+    Ok(())
+}
+
+/// (REQ) instance should not miss any available data set once initialized
+/// we simulate the update loop with irregular cycles (including downtimes), data sets that are already listed but
+/// not yet within the `last_modified` window of a cycle, and an empty bucket prefix for a new hour
+#[tokio::test]
+async fn test_no_missed_data ()->Result<()> {
+    let fixtures = fixtures( dtg("2024-05-17T06:00:00Z"), 120);
+    let now = dtg("2024-05-17T10:02:00Z");
+    let (server, client) = setup( &fixtures, now).await?;
+
+    let objs = get_most_recent_objects( &client, BUCKET, SOURCE, Duration::from_hours(3), now).await?;
+    let mut last_obj = get_init_objects( objs, 3).pop();
+    let init_key = last_obj.as_ref().unwrap().key().unwrap().to_string();
+
+    let mut cycles: Vec<DateTime<Utc>> = Vec::new();
+    let mut dt = dtg("2024-05-17T10:04:00Z");
+    while dt < dtg("2024-05-17T15:59:00Z") {
+        if dt < dtg("2024-05-17T11:10:00Z") || dt > dtg("2024-05-17T12:20:00Z") { // downtime
+            cycles.push( dt);
+        }
+        dt += TimeDelta::seconds( if cycles.len() % 3 == 0 { 290 } else { 305 }); // some jitter
+    }
+    cycles.push( dtg("2024-05-17T13:04:20Z")); // 13:01:17 data set is listed but its last_modified is 7sec in the future
+    cycles.sort();
+    cycles.push( dtg("2024-05-17T17:00:00Z")); // make sure we eventually see everything
+
+    let mut received: Vec<String> = Vec::new();
+    for dt_cycle in cycles {
+        let now = dt_cycle + TimeDelta::milliseconds(500);
+        release( &server, &fixtures, now);
+
+        let mut update_objs = get_objects_since( &client, BUCKET, SOURCE, &last_obj, dt_cycle, now).await?;
+        received.extend( keys(&update_objs));
+        last_obj = update_objs.pop().or( last_obj);
+    }
+
+    let expected: Vec<String> = fixtures.iter().filter( |f| f.key > init_key).map( |f| f.key.clone()).collect();
+    assert_eq!( received, expected); // all of them, each one once and in order
+
+    Ok(())
+}
@@ -49,9 +49,16 @@ var sentinelGpsView = undefined;
 var sentinelOrientationView = undefined;
 var sentinelCloudCoverView = undefined;
 var sentinelPowerView = undefined;
+var sentinelHealthView = undefined;
 
 var sentinelNameLabel = undefined;
 
+var alarmStatusView = undefined;
+var alarmStatusList = []; // lifecycle states of reported alarms (only if server has an alarm monitor), most recent first
+
+var cmdQueueView = undefined;
+var cmdQueue = []; // status of scheduled and sent commands, most recent first
+
 var maxHistory = config.maxHistory;
 
 class SentinelAssets {
@@ -79,6 +86,7 @@ class SentinelEntry {
         this.displayId = util.maxString(sentinel.deviceId, 4);
 
         this.sentinel = sentinel;
+        this.health = undefined; // set by "health" messages
 
         this.setAlarmList();
         this.setPos();
@@ -116,6 +124,14 @@ class SentinelEntry {
         return (smoke && smoke.length > 0) ? smoke[0].smoke.smokeProb.toFixed(2) : "-";
     }
 
+    healthStatus() {
+        return this.health ? this.health.score : "-";
+    }
+
+    healthWarnings() {
+        return this.health ? this.health.warnings : [];
+    }
+
     imageStatus() {
         let images = this.sentinel.image;
         return (images && images.length > 0) ? images.length : "-";
@@ -212,7 +228,10 @@ sentinelGpsView = initSentinelGpsView();
 sentinelOrientationView = initSentinelOrientationView();
 sentinelCloudCoverView = initSentinelCloudCoverView();
 sentinelPowerView = initSentinelPowerView();
+sentinelHealthView = initSentinelHealthView();
 sentinelNameLabel = ui.getText("sentinel.name");
+alarmStatusView = initAlarmStatusView();
+cmdQueueView = initCmdQueueView();
 
 initSentinelCmdList();
 
@@ -231,6 +250,22 @@ function createWindow() {
     return ui.Window("Sentinels", "sentinel", "./asset/odin_sentinel/sentinel.svg")(
         ui.LayerPanel("sentinel", toggleShowSentinels),
         ui.List("sentinel.list", 10, selectSentinel,null,null,zoomToSentinel),
+        ui.Panel("alarm status", false)(
+            ui.List("sentinel.alarmStatus.list", 5),
+            ui.RowContainer()(
+                ui.Button("ack", ackSelectedAlarm),
+                ui.Button("resolve", resolveSelectedAlarm)
+            )
+        ),
+        ui.Panel("commands", false)(
+            ui.List("sentinel.cmdQueue.list", 5),
+            ui.RowContainer()(
+                ui.Button("cancel schedule", cancelSelectedCmdSchedule),
+                ui.TextInput("flag", "sentinel.cmdFlag", "8rem"),
+                ui.Button("set", setCmdFlag),
+                ui.Button("clear", clearCmdFlag)
+            )
+        ),
 
         ui.Text("sentinel.name"),
         ui.Panel("data", true)(
@@ -245,7 +280,8 @@ function createWindow() {
                 ui.Tab("accel", false)( ui.List("sentinel.accel.list", maxDataRows)),
                 ui.Tab("gps", false)( ui.List("sentinel.gps.list", maxDataRows)),
                 ui.Tab("att", false)( ui.List("sentinel.orientation.list", maxDataRows)),
-                ui.Tab("power", false)( ui.List("sentinel.power.list", maxDataRows))
+                ui.Tab("power", false)( ui.List("sentinel.power.list", maxDataRows)),
+                ui.Tab("health", false)( ui.List("sentinel.health.list", maxDataRows))
             )
         )
     );
@@ -270,6 +306,7 @@ function initSentinelView() {
             { name: "fire", tip: "fire probability [0..1]", width: "4rem", attrs: ["fixed", "alignRight"], map: e => e.fireStatus() },
             { name: "smoke", tip: "smoke probability [0..1]", width: "4rem", attrs: ["fixed", "alignRight"], map: e => e.smokeStatus() },
             { name: "img", tip: "number of available images", width: "4rem", attrs: ["fixed", "alignRight"], map: e => e.imageStatus() },
+            { name: "hlth", tip: "health score [0..100]", width: "3rem", attrs: ["fixed", "alignRight"], map: e => e.healthStatus() },
             ui.listItemSpacerColumn(),
             { name: "stat", tip: "inactive alert", width: "2rem", attrs:["alignRight"], map: e => e.inactive ? "⚠︎" : "" },
             { name: "last report", width: "9rem", attrs: ["fixed", "alignRight"], map: e => util.toLocalMDHMSString(e.sentinel.timeRecorded) }
@@ -296,6 +333,32 @@ function initSentinelAlarmView() {
     ]);
 }
 
+function initAlarmStatusView() {
+    return initListView( "sentinel.alarmStatus.list", [
+        { name: "no", tip: "alarm number (use in replies)", width: "3rem", attrs: ["fixed", "alignRight"], map: e => e.no },
+        { name: "state", tip: "alarm state", width: "6rem", attrs: [], map: e => e.state },
+        { name: "type", tip: "alarm type", width: "4rem", attrs: [], map: e => e.alarmType },
+        { name: "dev", tip: "device id", width: "4rem", attrs: [], map: e => util.maxString(e.deviceId, 4) },
+        { name: "tier", tip: "escalation tier", width: "2rem", attrs: ["fixed", "alignRight"], map: e => e.tier },
+        { name: "by", tip: "acknowledged/resolved by", width: "5rem", attrs: [], map: e => e.changedBy ? e.changedBy : "" },
+        ui.listItemSpacerColumn(),
+        { name: "date", width: "9rem", attrs: ["fixed", "alignRight"], map: e => util.toLocalMDHMSString(e.timeRecorded) }
+    ]);
+}
+
+function initCmdQueueView() {
+    return initListView( "sentinel.cmdQueue.list", [
+        { name: "id", tip: "command message id", width: "5rem", attrs: [], map: e => util.maxString(e.id, 9) },
+        { name: "cmd", tip: "command", width: "6rem", attrs: [], map: e => e.cmd },
+        { name: "dev", tip: "number of target devices", width: "2rem", attrs: ["fixed", "alignRight"], map: e => e.deviceIds.length },
+        { name: "state", tip: "command state", width: "5rem", attrs: [], map: e => e.state },
+        { name: "try", tip: "send attempts", width: "2rem", attrs: ["fixed", "alignRight"], map: e => e.attempts },
+        { name: "sched", tip: "command schedule", width: "5rem", attrs: [], map: e => e.schedule ? e.schedule : "" },
+        ui.listItemSpacerColumn(),
+        { name: "date", width: "9rem", attrs: ["fixed", "alignRight"], map: e => util.toLocalMDHMSString(e.created) }
+    ]);
+}
+
 function initSentinelGasView() {
     return initListView( "sentinel.gas.list", [
         { name: "sen", tip: "sensor number", width: "2rem", attrs: [], map: e => e.sensorNo },
@@ -396,6 +459,23 @@ function initSentinelPowerView() {
     ]); 
 }
 
+function initSentinelHealthView() {
+    return initListView( "sentinel.health.list", [
+        { name: "sev", tip: "severity", width: "2rem", attrs: [], map: e => healthSeveritySymbol(e.severity) },
+        { name: "issue", tip: "health issue", width: "8rem", attrs: [], map: e => e.issue },
+        ui.listItemSpacerColumn(),
+        { name: "details", width: "14rem", attrs: ["alignRight"], map: e => e.message }
+    ]);
+}
+
+function healthSeveritySymbol (severity) {
+    switch (severity) {
+        case "Critical": return "⛔";
+        case "Warning": return "⚠︎";
+        default: return "ℹ";
+    }
+}
+
 function initSentinelImagesView() {
     return initListView( "sentinel.image.list", [
         { name: "show", tip: "show image", width: "3rem", attrs: [], map: e => ui.createCheckBox(e.window, toggleShowImage, null) },
@@ -501,9 +581,71 @@ function handleWsMessages(msgType, msg) {
         case "update": handleSentinelUpdateMessage(msg); break;
         case "alert": handleSentinelAlertMessage(msg); break;
         case "cmdResponse": logResponse(msg); break;
+        case "alarms": handleAlarmsMessage(msg); break;
+        case "alarmStatus": handleAlarmStatusMessage(msg); break;
+        case "health": handleHealthMessage(msg); break;
+        case "cmdQueue": handleCmdQueueMessage(msg); break;
     }
 }
 
+function handleAlarmsMessage(alarms) {
+    alarmStatusList = alarms;
+    ui.setListItems(alarmStatusView, alarmStatusList);
+}
+
+function handleAlarmStatusMessage(status) {
+    let idx = alarmStatusList.findIndex( a=> a.no == status.no);
+    if (idx >= 0) {
+        alarmStatusList[idx] = status;
+    } else {
+        alarmStatusList.unshift(status);
+    }
+    ui.setListItems(alarmStatusView, alarmStatusList);
+}
+
+function ackSelectedAlarm(event) {
+    let alarm = ui.getSelectedListItem(alarmStatusView);
+    if (alarm) ws.sendWsMessage( MOD_PATH, "ackAlarm", {no: alarm.no});
+}
+
+function resolveSelectedAlarm(event) {
+    let alarm = ui.getSelectedListItem(alarmStatusView);
+    if (alarm) ws.sendWsMessage( MOD_PATH, "resolveAlarm", {no: alarm.no});
+}
+
+// the server sends the whole queue whenever a command changes its state
+function handleCmdQueueMessage(cmds) {
+    cmdQueue = cmds;
+    ui.setListItems(cmdQueueView, cmdQueue);
+}
+
+function cancelSelectedCmdSchedule(event) {
+    let cmd = ui.getSelectedListItem(cmdQueueView);
+    if (cmd && cmd.schedule) ws.sendWsMessage( MOD_PATH, "cancelCmd", {name: cmd.schedule});
+}
+
+function setCmdFlag(event) {
+    let name = ui.getFieldValue("sentinel.cmdFlag");
+    if (name) ws.sendWsMessage( MOD_PATH, "setCmdFlag", {name: name, active: true});
+}
+
+function clearCmdFlag(event) {
+    let name = ui.getFieldValue("sentinel.cmdFlag");
+    if (name) ws.sendWsMessage( MOD_PATH, "setCmdFlag", {name: name, active: false});
+}
+
+// the health report is computed on the server whenever it checks for inactive devices
+function handleHealthMessage(report) {
+    report.forEach( health=> {
+        let e = sentinelEntries.get(health.deviceId);
+        if (e) {
+            e.health = health;
+            ui.updateListItem(sentinelView, e);
+            if (e === selectedSentinelEntry) ui.setListItems(sentinelHealthView, e.healthWarnings());
+        }
+    });
+}
+
 function handleDeviceInfoMessage(deviceInfos) {
     sentinelInfos = deviceInfos;
 }
@@ -922,6 +1064,7 @@ function setDataViews(sentinelEntry) {
     ui.setListItems(sentinelGpsView, sentinel.gps);
     ui.setListItems(sentinelOrientationView, sentinel.orientation);
     ui.setListItems(sentinelPowerView, sentinel.power);
+    ui.setListItems(sentinelHealthView, sentinelEntry.healthWarnings());
 }
 
 function clearDataViews() {
@@ -935,6 +1078,7 @@ function clearDataViews() {
     ui.clearList(sentinelGpsView);
     ui.clearList(sentinelOrientationView);
     ui.clearList(sentinelPowerView);
+    ui.clearList(sentinelHealthView);
 }
 
 function selectImage(event) {
@@ -0,0 +1,97 @@
+/**
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+// window to query and show the alarm audit trail (decisions made by the SentinelAlarmMonitor)
+
+import * as util from "../odin_server/ui_util.js";
+import * as ui from "../odin_server/ui.js";
+
+const AUDIT_URI = "./sentinel-alarm-audit";
+const MAX_ENTRIES = 500;
+
+var auditView = undefined;
+
+createIcon();
+createWindow();
+auditView = initAuditView();
+
+console.log("odin_sentinel_audit initialized");
+
+
+function createIcon() {
+    return ui.Icon("./asset/odin_sentinel/sentinel.svg", (e)=> ui.toggleWindow(e,'sentinel.audit'));
+}
+
+function createWindow() {
+    return ui.Window("Sentinel Alarm History", "sentinel.audit", "./asset/odin_sentinel/sentinel.svg")(
+        ui.RowContainer()(
+            ui.TextInput("device", "sentinel.audit.device", "8rem", {placeHolder: "id prefix"}),
+            ui.TextInput("type", "sentinel.audit.type", "5rem", {placeHolder: "fire,smoke"}),
+            ui.TextInput("since", "sentinel.audit.since", "10rem", {placeHolder: "2024-06-01T00:00:00Z"}),
+            ui.TextInput("until", "sentinel.audit.until", "10rem", {placeHolder: "now"}),
+            ui.Button("query", queryAudit)
+        ),
+        ui.List("sentinel.audit.list", 15, null, null, null, showDetails),
+        ui.Text("sentinel.audit.details")
+    );
+}
+
+function initAuditView() {
+    let view = ui.getList("sentinel.audit.list");
+    if (view) {
+        ui.setListItemDisplayColumns(view, ["fit", "header"], [
+            { name: "date", width: "9rem", attrs: ["fixed"], map: e => util.toLocalMDHMSString(e.time) },
+            { name: "action", tip: "alarm decision", width: "10rem", attrs: [], map: e => e.action },
+            { name: "type", tip: "alarm type", width: "4rem", attrs: [], map: e => e.alarmType },
+            { name: "dev", tip: "device id", width: "8rem", attrs: [], map: e => e.deviceId },
+            { name: "no", tip: "alarm number", width: "3rem", attrs: ["fixed", "alignRight"], map: e => e.alarmNo ? e.alarmNo : "" },
+            { name: "conf", tip: "confidence [0..1]", width: "4rem", attrs: ["fixed", "alignRight"], map: e => (e.confidence != null) ? e.confidence.toFixed(2) : "" },
+            { name: "ev", tip: "number of evidences", width: "2rem", attrs: ["fixed", "alignRight"], map: e => e.evidences.length }
+        ]);
+    }
+    return view;
+}
+
+function queryAudit() {
+    let params = new URLSearchParams();
+    addParam( params, "device", "sentinel.audit.device");
+    addParam( params, "alarm_type", "sentinel.audit.type");
+    addParam( params, "since", "sentinel.audit.since");
+    addParam( params, "until", "sentinel.audit.until");
+    params.set("limit", MAX_ENTRIES);
+
+    fetch( AUDIT_URI + "?" + params.toString())
+        .then( response => response.ok ? response.json() : Promise.reject(response.statusText))
+        .then( entries => ui.setListItems( auditView, entries))
+        .catch( err => console.log("alarm audit query failed: ", err));
+}
+
+function addParam (params, name, fieldId) {
+    let v = ui.getFieldValue(fieldId);
+    if (v && v.trim().length > 0) params.set( name, v.trim());
+}
+
+function showDetails(event) {
+    let e = ui.getSelectedListItem(auditView);
+    if (e) {
+        let lines = [];
+        if (e.alarmKey) lines.push("alarm: " + e.alarmKey);
+        if (e.recordId) lines.push("record: " + e.recordId);
+        if (e.threshold != null) lines.push("threshold: " + e.threshold);
+        if (e.messenger) lines.push("messenger: " + e.messenger);
+        if (e.detail) lines.push(e.detail);
+        e.evidences.forEach( ev=> lines.push("evidence: " + ev));
+        ui.setTextContent("sentinel.audit.details", lines.join("\n"));
+    }
+}
@@ -0,0 +1,85 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use structopt::StructOpt;
+use chrono::Utc;
+use odin_build;
+use odin_actor::prelude::*;
+use odin_common::sim_clock;
+use odin_sentinel::{load_config, ReplaySentinelConnector, SentinelActor, SentinelInactiveAlert, SentinelStore, SentinelUpdate};
+
+/// replay a Sentinel archive that was recorded by a LiveSentinelConnector with a configured `record_dir`
+#[derive(StructOpt)]
+struct CliOpts {
+    /// replay speedup factor
+    #[structopt(long,default_value="1")]
+    timescale: u32,
+}
+
+/* #region monitor actor *****************************************************************/
+
+#[derive(Debug)] pub struct Snapshot(String);
+#[derive(Debug)] pub struct Update(String);
+#[derive(Debug)] pub struct Inactive(String);
+
+define_actor_msg_set! { SentinelMonitorMsg = Snapshot | Update | Inactive }
+
+struct SentinelMonitor {}
+
+impl_actor! { match msg for Actor<SentinelMonitor,SentinelMonitorMsg> as
+    Snapshot => cont! {
+        println!("------------------------------ snapshot");
+        println!("{}", msg.0);
+    }
+    Update => cont! { 
+        println!("------------------------------ replayed update");
+        println!("{}", msg.0) 
+    }
+    Inactive => cont! {
+        println!("------------------------------ inactive");
+        println!("{}", msg.0)  
+    }
+}
+
+/* #endregion monitor actor */
+
+
+run_async_main!({
+    odin_build::set_bin_context!();
+    let opts = CliOpts::from_args();
+    sim_clock::initialize( Utc::now(), opts.timescale, false, false).expect("failed to initialize sim clock");
+
+    let mut actor_system = ActorSystem::with_env_tracing("main");
+
+    let hmonitor = spawn_actor!( actor_system, "monitor", SentinelMonitor{})?;
+
+    let _hsentinel = spawn_actor!( actor_system, "sentinel", SentinelActor::new(
+        ReplaySentinelConnector::new( load_config( "sentinel_replay.ron")?), 
+        dataref_action!( let hmonitor: ActorHandle<SentinelMonitorMsg> = hmonitor.clone() => |data:&SentinelStore| {
+            let msg = Snapshot(data.to_json_pretty().unwrap());
+            Ok( hmonitor.try_send_msg( msg)? )
+        }),
+        data_action!( let hmonitor: ActorHandle<SentinelMonitorMsg> = hmonitor.clone() => |update:SentinelUpdate| {
+            let msg = Update(update.description());
+            Ok( hmonitor.try_send_msg( msg)? )
+        }),
+        data_action!( let hmonitor: ActorHandle<SentinelMonitorMsg> = hmonitor => |alert: SentinelInactiveAlert| {
+            let msg = Inactive( serde_json::to_string(&alert)?);
+            Ok( hmonitor.try_send_msg( msg)? )
+        })
+    ))?;
+
+    actor_system.timeout_start_all(secs(2)).await?;
+    actor_system.process_requests().await
+});
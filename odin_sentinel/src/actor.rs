@@ -19,7 +19,7 @@ use odin_actor::prelude::*;
 use odin_actor::{error,debug,warn,info};
 use odin_common::{geo::LatLon, datetime::duration_since};
 use crate::*;
-use crate::ws::WsCmd;
+use crate::ws::{WsCmd,WsMsg};
 
 /// message object to indicate a device hasn't reported within a configured amount of time
 #[derive(Debug,Clone,Serialize)]
@@ -30,6 +30,7 @@ pub struct SentinelInactiveAlert {
 }
 
 const INACTIVE_TIMER: i64 = 1;
+const CMD_TIMER: i64 = 2;
 
 //-- external messages (from other actors)
 
@@ -43,14 +44,36 @@ const INACTIVE_TIMER: i64 = 1;
 
 // the answer for a GetSentinelPosition query
 
+/// health analysis for a single device or (if device_id is None) all devices
+#[derive(Debug)] pub struct GetSentinelHealth { pub device_id: Option<String> }
 
-/// send a command to Sentinel devices
-#[derive(Debug)] pub struct SendSentinelCmd { sentinel_cmd: WsCmd }
+/// register an action that is executed with the health report of all devices each time we check for inactive devices
+#[derive(Debug)] pub struct AddHealthAction( pub DynDataAction<Vec<SentinelHealth>> );
+
+/// send a command to Sentinel devices (tracked by our command manager, i.e. the message_id of the command is replaced).
+/// Pings are sent as-is
+#[derive(Debug)] pub struct SendSentinelCmd { pub sentinel_cmd: WsCmd }
+
+/// add (or replace) a command schedule
+#[derive(Debug)] pub struct ScheduleSentinelCmd( pub ScheduledCmd );
+
+/// remove a command schedule and cancel its unfinished commands
+#[derive(Debug)] pub struct CancelSentinelCmd { pub name: String }
+
+/// set or clear a condition flag for scheduled commands (e.g. "red_flag")
+#[derive(Debug)] pub struct SetSentinelCmdFlag { pub name: String, pub active: bool }
+
+/// query the current command queue (most recent first)
+#[derive(Debug)] pub struct GetSentinelCmdQueue;
+
+/// register an action that is executed with the command queue whenever it changes
+#[derive(Debug)] pub struct AddCmdQueueAction( pub DynDataAction<Vec<SentinelCmdStatus>> );
 
 //-- internal messages. Note these are not public since we should only get them from our connector
 #[derive(Debug)] pub(crate) struct InitializeStore (pub(crate) SentinelStore);  // set initial store contents
 #[derive(Debug)] pub(crate) struct UpdateStore (pub(crate) SentinelUpdate); // single record update (triggered by websocket notification)
 #[derive(Debug)] pub(crate) struct ConnectorError (pub(crate) OdinSentinelError);
+#[derive(Debug)] pub(crate) struct CmdResponse (pub(crate) WsMsg); // websocket response to a command
 
 define_actor_msg_set! { pub SentinelActorMsg = 
     //-- messages we get from other actors
@@ -58,33 +81,60 @@ define_actor_msg_set! { pub SentinelActorMsg =
     Query<GetSentinelUpdate,Result<SentinelUpdate>> |
     Query<GetSentinelFile,Result<SentinelFile>> |
     Query<GetSentinelPosition,Option<DatedGeoPos>> |
+    Query<GetSentinelHealth,Vec<SentinelHealth>> |
+    AddHealthAction |
+    SendSentinelCmd |
+    ScheduleSentinelCmd |
+    CancelSentinelCmd |
+    SetSentinelCmdFlag |
+    Query<GetSentinelCmdQueue,Vec<SentinelCmdStatus>> |
+    AddCmdQueueAction |
 
     //-- messages we get from our connector
     InitializeStore |
     UpdateStore |
-    ConnectorError
+    ConnectorError |
+    CmdResponse
 }
 
 pub struct SentinelActor <C,I,U,IA> 
     where C: SentinelConnector + Send,  I: DataRefAction<SentinelStore>,  U: DataAction<SentinelUpdate>, IA: DataAction<SentinelInactiveAlert>
 {
-    connector: C,               // where we get the external data from
+    connector: C,               // where we get the external data from (also provides the inactive, health and command config)
+    networks: Vec<SensorNetworkConnector>, // additional sensor networks that feed into the same store
     sentinels: SentinelStore,   // our internal store
 
     init_action: I,             // initialized interaction (triggered by self)
     update_action: U,           // update interactions (triggered by self)
     inactive_action: IA,        // inactive device alert interactions
+    health_actions: DynDataActionList<Vec<SentinelHealth>>, // dynamically registered health report interactions
+
+    commands: SentinelCmdManager, // (re-)initialized from connector config when we start
+    cmd_actions: DynDataActionList<Vec<SentinelCmdStatus>>, // dynamically registered command queue interactions
 }
 
 impl<C,I,U,IA> SentinelActor <C,I,U,IA>
     where C: SentinelConnector + Send, I: DataRefAction<SentinelStore>, U: DataAction<SentinelUpdate>, IA: DataAction<SentinelInactiveAlert>
 {
     pub fn new (connector: C, init_action: I, update_action: U, inactive_action: IA)->Self {
-        SentinelActor { connector, sentinels: SentinelStore::new(), init_action, update_action, inactive_action }
+        SentinelActor { connector, networks: Vec::new(), sentinels: SentinelStore::new(), init_action, update_action, inactive_action, health_actions: DynDataActionList::new(),
+                        commands: SentinelCmdManager::new( SentinelCmdConfig::default()), cmd_actions: DynDataActionList::new() }
+    }
+
+    /// add a sensor network whose devices are imported into the same store as the ones of our main connector
+    /// (e.g. weather stations next to Delphire Sentinels). Each connector only replaces the devices of its own network
+    /// when it (re-)initializes
+    pub fn with_network (mut self, network: SensorNetworkConnector)->Self {
+        self.networks.push( network);
+        self
     }
 
     async fn init_store (&mut self, sentinels: SentinelStore)->Result<()> {
-        self.sentinels = sentinels;
+        if self.networks.is_empty() {
+            self.sentinels = sentinels;
+        } else {
+            self.sentinels.merge( sentinels);
+        }
         self.init_action.execute(&self.sentinels).await;
         Ok(())
     }
@@ -107,6 +157,18 @@ impl<C,I,U,IA> SentinelActor <C,I,U,IA>
         record_query.respond( res).await.map_err(|_| op_failed("receiver closed"))
     }
 
+    /// file queries are handled by the connector of the network the record belongs to
+    async fn handle_file_query (&self, query: Query<GetSentinelFile,Result<SentinelFile>>)->Result<()> {
+        let network = self.sentinels.get_update( &query.question.record_id)
+            .and_then( |update| self.sentinels.get( update.device_id()))
+            .and_then( |sentinel| sentinel.network.as_deref());
+
+        match network.and_then( |network| self.networks.iter().find( |c| c.network() == network)) {
+            Some(connector) => connector.handle_sentinel_file_query( query).await,
+            None => self.connector.handle_sentinel_file_query( query).await // might be in-flight, hand over to connector
+        }
+    }
+
     async fn handle_position_query( &self, query: Query<GetSentinelPosition,Option<DatedGeoPos>>)->Result<()> {
         if let Some(sentinel) = self.sentinels.get( &query.question.device_id) {
             query.respond( sentinel.get_position_at( query.question.date)).await.map_err(|_| op_failed("receiver closed"))
@@ -115,6 +177,52 @@ impl<C,I,U,IA> SentinelActor <C,I,U,IA>
         }
     }
 
+    async fn handle_health_query( &self, query: Query<GetSentinelHealth,Vec<SentinelHealth>>)->Result<()> {
+        let now = Utc::now();
+        let config = self.connector.health_config();
+        let report = if let Some(device_id) = &query.question.device_id {
+            self.sentinels.device_health( device_id, config, now).into_iter().collect()
+        } else {
+            self.sentinels.health_report( config, now)
+        };
+        query.respond( report).await.map_err(|_| op_failed("receiver closed"))
+    }
+
+    // this is our early warning - devices usually show battery, cadence or sensor problems before they go inactive
+    async fn check_health (&self) {
+        let report = self.sentinels.health_report( self.connector.health_config(), Utc::now());
+        for health in &report {
+            if let Some(HealthSeverity::Critical) = health.max_severity() {
+                warn!("device {} health critical: {:?}", health.device_id, health.warnings)
+            }
+        }
+        self.health_actions.execute( report, true).await;
+    }
+
+    /// send all commands that are due (including retries), and publish the command queue if it changed
+    async fn process_commands (&mut self) {
+        let now = Utc::now();
+        let devices: Vec<(DeviceId,Option<(f64,f64)>)> = self.sentinels.values_iter()
+            .map( |s| (s.device_id.clone(), s.gps.front().map( |r| (r.data.latitude.degrees(), r.data.longitude.degrees()))))
+            .collect();
+
+        self.commands.run_schedules( now, &devices);
+        for cmd in self.commands.next_messages( now) {
+            let id = cmd.message_id().to_string();
+            if let Err(e) = self.connector.send_cmd( cmd).await {
+                warn!("failed to send command {id}: {e}");
+                self.commands.send_failed( &id, e.to_string());
+            }
+        }
+        self.publish_commands().await;
+    }
+
+    async fn publish_commands (&mut self) {
+        if self.commands.take_changed() {
+            self.cmd_actions.execute( self.commands.status(), true).await;
+        }
+    }
+
     // note this is a server-side inactive check, i.e. new client connections won't see a status change until the
     // next server check runs. If we want this instantly we should transmit the inactive_duration through the websocket
     // during the init_action and then perform the check when receiving the sentinels on the client. Alternatively the SentinelService 
@@ -149,11 +257,45 @@ impl_actor! { match msg for Actor< SentinelActor<C,I,U,IA>, SentinelActorMsg>
         self.handle_record_query(msg).await;
     }
     Query<GetSentinelFile,Result<SentinelFile>> => cont! { 
-        self.connector.handle_sentinel_file_query(msg).await; // might be in-flight, hand over to connector
+        self.handle_file_query(msg).await;
     }
     Query<GetSentinelPosition,Option<DatedGeoPos>> => cont! {
         self.handle_position_query(msg).await;
     }
+    Query<GetSentinelHealth,Vec<SentinelHealth>> => cont! {
+        self.handle_health_query(msg).await;
+    }
+    AddHealthAction => cont! {
+        self.health_actions.push( msg.0)
+    }
+    SendSentinelCmd => cont! {
+        if let WsCmd::Ping{..} = msg.sentinel_cmd { // not a device command, i.e. it is not queued or tracked
+            if let Err(e) = self.connector.send_cmd( msg.sentinel_cmd).await { warn!("failed to send ping: {e}") }
+        } else {
+            match self.commands.enqueue_ws_cmd( &msg.sentinel_cmd, Utc::now()) {
+                Ok(_) => self.process_commands().await, // don't wait for the next timer tick
+                Err(e) => warn!("rejected command {:?}: {e}", msg.sentinel_cmd)
+            }
+        }
+    }
+    ScheduleSentinelCmd => cont! {
+        self.commands.schedule( msg.0);
+        self.process_commands().await;
+    }
+    CancelSentinelCmd => cont! {
+        if !self.commands.cancel( &msg.name) { warn!("no command schedule {}", msg.name) }
+        self.publish_commands().await;
+    }
+    SetSentinelCmdFlag => cont! {
+        self.commands.set_flag( &msg.name, msg.active);
+        self.process_commands().await;
+    }
+    Query<GetSentinelCmdQueue,Vec<SentinelCmdStatus>> => cont! {
+        if msg.respond( self.commands.status()).await.is_err() { warn!("command queue query receiver closed") }
+    }
+    AddCmdQueueAction => cont! {
+        self.cmd_actions.push( msg.0)
+    }
 
     //--- connector messages
     InitializeStore => cont! { 
@@ -165,23 +307,46 @@ impl_actor! { match msg for Actor< SentinelActor<C,I,U,IA>, SentinelActorMsg>
     ConnectorError => cont! { 
         error!("connector error: {:?}", msg) // TODO - this needs to be handled
     }
+    CmdResponse => cont! {
+        if self.commands.handle_response( &msg.0) {
+            self.publish_commands().await;
+        } else {
+            debug!("ignoring websocket message {:?}", msg.0)
+        }
+    }
 
     _Start_ => cont! {
         let hself = self.hself.clone();
-        if let Err(e) = self.connector.start( hself).await {  // this should eventually lead to an InitializeStore
+        if let Err(e) = self.connector.start( hself.clone()).await {  // this should eventually lead to an InitializeStore
             error!("failed to start connector: {:?}", e)
         }
+        for network in &mut self.networks {
+            if let Err(e) = network.start( hself.clone()).await {
+                error!("failed to start {} connector: {:?}", network.network(), e)
+            }
+        }
         if let Err(e) = self.start_repeat_timer( INACTIVE_TIMER, self.connector.inactive_interval(), false) {
             error!("failed to start inactive timer")
         } 
+
+        let cmd_config = self.connector.cmd_config().clone();
+        let check_interval = cmd_config.check_interval;
+        self.commands = SentinelCmdManager::new( cmd_config);
+        if let Err(e) = self.start_repeat_timer( CMD_TIMER, check_interval, false) {
+            error!("failed to start command timer")
+        }
     }
     _Timer_ => cont! {
         if msg.id == INACTIVE_TIMER {
             self.check_inactive().await;
+            if !self.sentinels.is_empty() { self.check_health().await }
+        } else if msg.id == CMD_TIMER {
+            self.process_commands().await;
         }
     }
     _Terminate_ => stop! { 
-        self.connector.terminate(); 
+        self.connector.terminate();
+        for network in &mut self.networks { network.terminate() }
     }
 }
 
@@ -20,21 +20,26 @@ use odin_common::geo::DatedGeoPos;
 use odin_common::sim_clock;
 use odin_common::{datetime::Dated,sim_clock::now,fs::{append_open,append_to_file,append_line_to_file}};
 use serde::{Deserialize,Serialize,Serializer};
-use serde_json;
+use serde_json::{self,json,Map,Value};
 use chrono::{DateTime, Local, TimeDelta, Utc};
 use async_trait::async_trait;
 use odin_actor::prelude::*;
 use odin_macro::{match_algebraic_type, define_struct};
 use uom::si::f32::Time;
 
-use crate::{op_failed, sentinel_cache_dir, ExternalImage, FireData, GetSentinelFile, GetSentinelPosition, RecordDataBounds, RecordRef, SensorRecord, SentinelDeviceInfo, SentinelDeviceInfos, SentinelFile, SentinelInactiveAlert, SentinelStore, SentinelUpdate, SmokeData
+use crate::{op_failed, sentinel_cache_dir, sentinel_data_dir, AlarmAuditEntry, AlarmAuditStore, AuditAction, AlarmContact, AlarmState, AlarmCommand, AlarmNo, AlarmReply, AlarmStatus, AlarmTracker, OnCallSchedule, parse_alarm_reply, AlarmRuleSet, AlarmRuleEngine, AlarmRuleMatch, AlarmRuleSkipReason, ExternalImage, FireData, GetSentinelFile, GetSentinelPosition, RecordDataBounds, RecordRef, SensorRecord, SentinelDeviceInfo, SentinelDeviceInfos, SentinelFile, SentinelInactiveAlert, SentinelStore, SentinelUpdate, SmokeData
 };
 use crate::actor::{SentinelActorMsg,GetSentinelUpdate};
 use crate::errors::{OdinSentinelError, Result};
 
 /// abstract alarm data
-#[derive(Debug)]
+#[derive(Debug,Clone)]
 pub struct Alarm {
+    pub id: String, // unique key, e.g. "fire(<device>,<time>)"
+    pub record_id: String, // the record that caused this alarm (empty for status alarms)
+    pub no: AlarmNo, // short number to refer to this alarm in acknowledgements (0 if not tracked yet)
+    pub tier: usize, // escalation tier this notification is for
+    pub contacts: Vec<AlarmContact>, // on-call recipients - if empty messengers use their configured recipients
     pub device_id: String,
     pub description: String,
     pub time_recorded: DateTime<Utc>,
@@ -44,6 +49,40 @@ pub struct Alarm {
     pub evidence_info: Vec<EvidenceInfo>,
 }
 
+impl Alarm {
+    /// the messenger specific addresses of the on-call contacts, e.g. `alarm.contact_addresses( |c| c.email.as_ref())`
+    pub fn contact_addresses<'a> (&'a self, get_addr: impl Fn(&'a AlarmContact)->Option<&'a String>)->Vec<&'a String> {
+        self.contacts.iter().filter_map( get_addr).collect()
+    }
+
+    /// generic JSON representation for messengers that push alarms into other systems (webhooks, MQTT etc.).
+    /// The top level field names are the variables that can be used in [`expand_json_template`]
+    pub fn to_json (&self)->Map<String,Value> {
+        let images: Vec<String> = self.evidence_info.iter()
+            .filter_map( |e| e.img.as_ref().and_then( |f| f.pathname.file_name()).map( |n| n.to_string_lossy().to_string()))
+            .collect();
+        let contacts: Vec<&String> = self.contacts.iter().map( |c| &c.name).collect();
+
+        let v = json!({
+            "id": self.id,
+            "recordId": self.record_id,
+            "no": self.no,
+            "tier": self.tier,
+            "deviceId": self.device_id,
+            "alarmType": self.alarm_type,
+            "confidence": self.confidence,
+            "description": self.description,
+            "timeRecorded": self.time_recorded.to_rfc3339(),
+            "timeRecordedMillis": self.time_recorded.timestamp_millis(),
+            "lat": self.pos.map( |p| p.lat.degrees()),
+            "lon": self.pos.map( |p| p.lon.degrees()),
+            "contacts": contacts,
+            "images": images,
+        });
+        if let Value::Object(map) = v { map } else { Map::new() } // can't fail
+    }
+}
+
 /// abstract data to describe an evidence record
 #[derive(Debug,Clone)]
 pub struct EvidenceInfo {
@@ -73,6 +112,15 @@ fn same_evidence_sensors (ev1: &Vec<EvidenceInfo>, ev2: &Vec<EvidenceInfo>)->boo
 pub trait AlarmMessenger: Send + Sync {
     /// impls have to make sure this is guaranteed to return in bounded time so that we know if notifications were sent out
     async fn send_alarm (&self, alarm: &Alarm)->Result<()>;
+
+    /// messengers that can receive messages return the replies they got since the last call (which are parsed as
+    /// acknowledgement commands). Same return time constraints as for `send_alarm`
+    async fn receive_replies (&self)->Result<Vec<AlarmReply>> { Ok(Vec::new()) }
+
+    /// used in logs and audit entries
+    fn name (&self)->&'static str {
+        std::any::type_name::<Self>().rsplit("::").next().unwrap_or("?")
+    }
 }
 
 #[macro_export]
@@ -96,6 +144,18 @@ pub struct SentinelAlarmMonitorConfig {
     pub image_timeout: Duration,
     pub fire_prob: f64,
     pub smoke_prob: f64,
+
+    /// if set we use these rules instead of the fire_prob and smoke_prob thresholds
+    pub rules: Option<AlarmRuleSet>,
+
+    pub ack_timeout: Duration, // after which unacknowledged alarms are escalated to the next tier. Zero disables escalation
+    pub ack_exempt: Vec<String>, // alarm types that don't need to be acknowledged
+    pub check_interval: Duration, // for escalations and received replies
+    pub on_call: Option<OnCallSchedule>, // if not set alarms go to the configured recipients of each messenger
+
+    pub audit_dir: Option<PathBuf>, // where to store the alarm audit trail, defaults to sentinel_data_dir()
+    pub audit_retention: Duration, // after which daily audit files are removed. Zero keeps all files
+    pub audit_below_threshold: f64, // fraction of fire_prob/smoke_prob from which lower probabilities are audited
 }
 
 impl Default for SentinelAlarmMonitorConfig {
@@ -107,10 +167,48 @@ impl Default for SentinelAlarmMonitorConfig {
             image_timeout: Duration::from_secs(20),
             fire_prob: 0.7,
             smoke_prob: 0.7,
+            rules: None,
+            ack_timeout: minutes(10),
+            ack_exempt: vec![ "status".to_string() ],
+            check_interval: secs(30),
+            on_call: None,
+            audit_dir: None,
+            audit_retention: Duration::from_secs( 90*24*3600),
+            audit_below_threshold: 0.5,
         }
     }
 }
 
+impl SentinelAlarmMonitorConfig {
+    /// check for consistency - this is used to reject invalid configs that are modified at runtime
+    pub fn check (&self)->std::result::Result<(),String> {
+        if !(0.0..=1.0).contains( &self.fire_prob) { return Err( format!("fire_prob not in [0.0..1.0]: {}", self.fire_prob)) }
+        if !(0.0..=1.0).contains( &self.smoke_prob) { return Err( format!("smoke_prob not in [0.0..1.0]: {}", self.smoke_prob)) }
+        if !(0.0..=1.0).contains( &self.audit_below_threshold) { return Err( format!("audit_below_threshold not in [0.0..1.0]: {}", self.audit_below_threshold)) }
+        if self.old_alarm_duration <= self.new_alarm_duration { return Err( "old_alarm_duration has to exceed new_alarm_duration".to_string()) }
+        if let Some(rules) = &self.rules { rules.check()? }
+        if let Some(on_call) = &self.on_call { on_call.check()? }
+        if self.check_interval.is_zero() { return Err( "check_interval has to be > 0".to_string()) }
+        Ok(())
+    }
+
+    /// without an on-call schedule there is nobody to escalate to and no replies are accepted, i.e. alarms can only be
+    /// acknowledged through the web UI and are never re-sent
+    pub fn escalation_timeout (&self)->Duration {
+        if self.on_call.is_some() { self.ack_timeout } else { Duration::ZERO }
+    }
+
+    /// do we accept acknowledge/resolve replies through messengers (which requires on-call contacts to identify senders)
+    pub fn accepts_replies (&self)->bool {
+        self.on_call.is_some()
+    }
+
+    /// is a probability below `threshold` close enough to be recorded in the audit trail
+    pub fn is_near_miss (&self, prob: f64, threshold: f64)->bool {
+        prob > 0.0 && prob < threshold && prob >= threshold * self.audit_below_threshold
+    }
+}
+
 /// for now this is just a cache so that we don't have to retrieve EvidenceInfos on each check
 /// but we could add more context info here
 struct ReportedAlarm<T> where T: RecordDataBounds{
@@ -120,7 +218,21 @@ struct ReportedAlarm<T> where T: RecordDataBounds{
 
 const ALARM_HISTORY: usize = 10;
 
-define_actor_msg_set! { pub SentinelAlarmMonitorMsg = SentinelUpdate | SentinelInactiveAlert | Alarm }
+/// acknowledge alarm `no` (or the most recent pending alarm if `None`)
+#[derive(Debug)] pub struct AcknowledgeAlarm { pub no: Option<AlarmNo>, pub by: String, pub via: String }
+
+/// resolve alarm `no` (or the most recent open alarm if `None`)
+#[derive(Debug)] pub struct ResolveAlarm { pub no: Option<AlarmNo>, pub by: String, pub via: String }
+
+/// query for the lifecycle states of all tracked alarms
+#[derive(Debug)] pub struct GetAlarmStatus;
+
+/// register an action that is executed for each alarm lifecycle change (e.g. to update web UIs)
+#[derive(Debug)] pub struct AddAlarmStatusAction(pub DynDataAction<AlarmStatus>);
+
+define_actor_msg_set! { pub SentinelAlarmMonitorMsg = SentinelUpdate | SentinelInactiveAlert | Alarm | Reconfigure<SentinelAlarmMonitorConfig> |
+    AcknowledgeAlarm | ResolveAlarm | Query<GetAlarmStatus,Vec<AlarmStatus>> | AddAlarmStatusAction
+}
 
 /// the Sentinel Alarm Actor state
 define_struct! { pub SentinelAlarmMonitor =
@@ -131,7 +243,14 @@ define_struct! { pub SentinelAlarmMonitor =
 
     reported_fire_alarms: VecDeque<ReportedAlarm<FireData>> = VecDeque::with_capacity( ALARM_HISTORY),
     reported_smoke_alarms: VecDeque<ReportedAlarm<SmokeData>> = VecDeque::with_capacity( ALARM_HISTORY),
-    inactive_alerts: Vec<SentinelInactiveAlert> = Vec::new()
+    inactive_alerts: Vec<SentinelInactiveAlert> = Vec::new(),
+    rule_engine: Option<AlarmRuleEngine> = None, // only used if config has rules
+
+    tracker: AlarmTracker = AlarmTracker::new(),
+    pending_alarms: HashMap<AlarmNo,Alarm> = HashMap::new(), // what we have to re-send on escalation
+    status_actions: DynDataActionList<AlarmStatus> = DynDataActionList::new(),
+    audit: Option<AlarmAuditStore> = None, // opened when the actor starts
+    timer: Option<AbortHandle> = None
 }
 
 impl SentinelAlarmMonitor {
@@ -148,7 +267,11 @@ impl SentinelAlarmMonitor {
                 let alarm_type = rec.capability().property_name().to_string();
                 let confidence = rec.data.fire_prob;
                 self.process_alarm( hself, &alarm_id, &rec.id, rec.device_id.clone(), descr, rec.time_recorded, alarm_type, confidence, evidence_info).await;
+            } else {
+                self.audit_record( AuditAction::SuppressedDuplicate, &rec, rec.data.fire_prob, self.config.fire_prob);
             }
+        } else if self.config.is_near_miss( rec.data.fire_prob, self.config.fire_prob) { // we don't record each low probability update
+            self.audit_record( AuditAction::BelowThreshold, &rec, rec.data.fire_prob, self.config.fire_prob);
         }
     }
 
@@ -164,10 +287,50 @@ impl SentinelAlarmMonitor {
                 let alarm_type = rec.capability().property_name().to_string();
                 let confidence = rec.data.smoke_prob;
                 self.process_alarm( hself, &alarm_id, &rec.id, rec.device_id.clone(), descr, rec.time_recorded, alarm_type, confidence, evidence_info).await;
+            } else {
+                self.audit_record( AuditAction::SuppressedDuplicate, &rec, rec.data.smoke_prob, self.config.smoke_prob);
             }
+        } else if self.config.is_near_miss( rec.data.smoke_prob, self.config.smoke_prob) { // we don't record each low probability update
+            self.audit_record( AuditAction::BelowThreshold, &rec, rec.data.smoke_prob, self.config.smoke_prob);
         }   
     }
 
+    async fn process_rule_alarms (&mut self, hself: ActorHandle<SentinelAlarmMonitorMsg>, update: &SentinelUpdate) {
+        if self.rule_engine.is_none() {
+            self.rule_engine = self.config.rules.clone().map( AlarmRuleEngine::new);
+        }
+
+        let (matches, skips) = if let Some(engine) = &mut self.rule_engine { engine.process_with_skips( update) } else { (Vec::new(), Vec::new()) };
+        for skip in skips {
+            let (action, detail) = match skip.reason {
+                AlarmRuleSkipReason::Suppressed => (AuditAction::Suppressed, format!("rule {}", skip.rule)),
+                AlarmRuleSkipReason::Duplicate => (AuditAction::SuppressedDuplicate, format!("rule {}", skip.rule)),
+                AlarmRuleSkipReason::Uncorroborated{ devices, min_devices } => {
+                    (AuditAction::Uncorroborated, format!("rule {}: {devices} of {min_devices} devices", skip.rule))
+                }
+            };
+            self.audit( AlarmAuditEntry::new( action, &skip.device_id, &skip.alarm_type)
+                .with_time( skip.time)
+                .with_record( &skip.record_id)
+                .with_detail( detail));
+        }
+
+        for m in matches {
+            let evidence_info = self.retrieve_evidence( &self.hupdater, m.trigger.evidences(), self.config.image_timeout).await;
+
+            let info: &str = self.device_infos.get(&m.device_id).map(|s|s.name.as_str()).unwrap_or("");
+            let mut descr = format!("🔥 {}\ndevice: {} {}\nrule: {} (confidence: {})", 
+                m.time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S %Z"), m.device_id, info, m.rule, m.confidence);
+            if !m.corroborating_devices.is_empty() {
+                write!( descr, "\ncorroborated by: {}", m.corroborating_devices.join(", "));
+            }
+            let alarm_id = format!("{}({},{})", m.rule, m.device_id, m.time.format("%Y-%m-%dT%H:%M:%S%Z"));
+            let record_id = m.trigger.record_id().clone();
+
+            self.process_alarm( hself.clone(), &alarm_id, &record_id, m.device_id, descr, m.time, m.alarm_type, m.confidence, evidence_info).await;
+        }
+    }
+
     fn check_new_alarm<T> (rec: &Arc<SensorRecord<T>>, evidence: &Vec<EvidenceInfo>, reported_alarms: &mut VecDeque<ReportedAlarm<T>>, config: &SentinelAlarmMonitorConfig) -> Option<String> 
         where T: RecordDataBounds 
     {
@@ -219,8 +382,10 @@ impl SentinelAlarmMonitor {
             write!( description, "\nhttps://wildfireai.com/odin-fire/live?view={:.4},{:.4},{:.0}", p.lat.degrees(), p.lon.degrees(), alt);
         }
 
+        let id = alarm_id.to_string();
         if !self.config.attach_image {  // we don't want images - send right away
-            hself.send_msg( Alarm { device_id, description, time_recorded, pos, alarm_type, confidence, evidence_info: Vec::with_capacity(0) }).await;
+            hself.send_msg( Alarm { id, record_id: record_id.to_string(), no: 0, tier: 0, contacts: Vec::new(), 
+                device_id, description, time_recorded, pos, alarm_type, confidence, evidence_info: Vec::with_capacity(0) }).await;
 
         } else { // we have to dig up the evidence image(s)
             let timeout = self.config.image_timeout;
@@ -229,7 +394,8 @@ impl SentinelAlarmMonitor {
                 self.add_external_evidence( &mut evidence_info, device_info, hupdater, record_id, time_recorded, timeout).await;
             }
 
-            hself.send_msg( Alarm { device_id, description, time_recorded, pos, alarm_type, confidence, evidence_info }).await;
+            hself.send_msg( Alarm { id, record_id: record_id.to_string(), no: 0, tier: 0, contacts: Vec::new(), 
+                device_id, description, time_recorded, pos, alarm_type, confidence, evidence_info }).await;
         }
     }
 
@@ -347,14 +513,225 @@ impl SentinelAlarmMonitor {
             self.process_alarm( hself, &alarm_id, "", device_id.clone(), description, time_recorded, alarm_type, 1.0, evidences).await
         }
     }
+
+    //--- alarm lifecycle
+
+    /// start tracking a new alarm and notify tier 0 recipients
+    async fn dispatch_alarm (&mut self, mut alarm: Alarm) {
+        let now = Utc::now();
+        let requires_ack = !self.config.ack_exempt.contains( &alarm.alarm_type);
+        let status = self.tracker.add( alarm.id.clone(), alarm.device_id.clone(), alarm.alarm_type.clone(), alarm.time_recorded, requires_ack, now).clone();
+
+        alarm.no = status.no;
+        alarm.contacts = self.on_call_recipients( &alarm.device_id, now, 0);
+        if requires_ack && self.config.accepts_replies() {
+            write!( alarm.description, "\nalarm #{}: reply \"ack {}\" to acknowledge", status.no, status.no);
+        }
+
+        let (sent, failed) = self.send_notifications( &alarm).await;
+        let action = if sent.is_empty() { AuditAction::SendFailed } else { AuditAction::Sent };
+        self.audit( self.alarm_audit_entry( action, &alarm)
+            .with_detail( format!("sent by [{}], failed: [{}]", sent.join(", "), failed.join(", "))));
+        self.publish_status( status).await;
+        if requires_ack { self.pending_alarms.insert( alarm.no, alarm); }
+    }
+
+    /// re-send pending alarms that were not acknowledged in time to the next on-call tier
+    async fn check_escalations (&mut self) {
+        let now = Utc::now();
+
+        for no in self.tracker.due_escalations( now, self.config.escalation_timeout()) {
+            let Some(alarm) = self.pending_alarms.get( &no) else { continue };
+            let max_tier = self.config.on_call.as_ref().map( |s| s.num_tiers( &alarm.device_id, &now)).unwrap_or(0).saturating_sub(1);
+
+            if let Some(status) = self.tracker.escalate( no, max_tier, now).cloned() {
+                let mut escalated = alarm.clone();
+                escalated.tier = status.tier;
+                escalated.contacts = self.on_call_recipients( &alarm.device_id, now, status.tier);
+                escalated.description = format!("⚠️ ESCALATED (tier {}) - not acknowledged after {} min\n{}", 
+                    status.tier, self.config.ack_timeout.as_secs() / 60, alarm.description);
+
+                warn!("escalating unacknowledged alarm {} to tier {}", status.key, status.tier);
+                self.send_notifications( &escalated).await;
+                self.audit( self.alarm_audit_entry( AuditAction::Escalated, &escalated).with_detail( format!("tier {}", status.tier)));
+                self.publish_status( status).await;
+            }
+        }
+
+        self.tracker.purge( now, self.config.old_alarm_duration);
+        self.pending_alarms.retain( |no,_| self.tracker.get(*no).map( |s| s.state.is_pending()).unwrap_or(false));
+    }
+
+    /// poll all messengers for replies and execute the contained acknowledge/resolve commands
+    async fn check_replies (&mut self) {
+        let mut replies: Vec<AlarmReply> = Vec::new();
+        for msgr in &self.messengers {
+            match msgr.receive_replies().await {
+                Ok(mut r) => replies.append( &mut r),
+                Err(e) => warn!("failed to receive alarm replies: {e}")
+            }
+        }
+
+        for reply in replies {
+            let accepted = match &self.config.on_call {
+                Some(schedule) => schedule.accept_reply( &reply).map( |(cmd,no,contact)| (cmd, no, contact.name.clone())),
+                None => Err( "no on-call contacts configured".to_string())
+            };
+
+            match accepted {
+                Ok((cmd,no,by)) => self.execute_command( cmd, Some(no), by, reply.via).await,
+                Err(msg) => {
+                    warn!("rejected alarm reply from {} via {}: {msg}", reply.from, reply.via);
+                    self.audit( self.reply_audit_entry( &reply).with_detail( format!("from {} via {}: {msg}", reply.from, reply.via)));
+                }
+            }
+        }
+    }
+
+    /// audit entry for a rejected reply, which refers to the alarm if the reply contains a known alarm number
+    fn reply_audit_entry (&self, reply: &AlarmReply)->AlarmAuditEntry {
+        let status = parse_alarm_reply( &reply.text).and_then( |(_,no)| self.tracker.get(no));
+        let entry = match status {
+            Some(status) => AlarmAuditEntry::new( AuditAction::RejectedReply, &status.device_id, &status.alarm_type).with_alarm( &status.key, status.no),
+            None => AlarmAuditEntry::new( AuditAction::RejectedReply, "", "")
+        };
+        entry.with_messenger( &reply.via)
+    }
+
+    async fn execute_command (&mut self, cmd: AlarmCommand, no: Option<AlarmNo>, by: String, via: String) {
+        let now = Utc::now();
+        let res = match cmd {
+            AlarmCommand::Acknowledge => self.tracker.acknowledge( no, &by, &via, now),
+            AlarmCommand::Resolve => self.tracker.resolve( no, &by, &via, now),
+        };
+
+        match res {
+            Ok(status) => {
+                let status = status.clone();
+                self.pending_alarms.remove( &status.no);
+
+                // let everybody who got this alarm know that it is taken care of
+                let confirmation = Alarm {
+                    id: status.key.clone(),
+                    record_id: String::new(),
+                    no: status.no,
+                    tier: status.tier,
+                    contacts: (0..=status.tier).flat_map( |t| self.on_call_recipients( &status.device_id, now, t)).collect(),
+                    device_id: status.device_id.clone(),
+                    description: format!("✅ alarm #{} {:?} by {} (via {})", status.no, status.state, by, via),
+                    time_recorded: now,
+                    pos: None,
+                    alarm_type: "status".to_string(),
+                    confidence: 1.0,
+                    evidence_info: Vec::with_capacity(0),
+                };
+                self.send_notifications( &confirmation).await;
+
+                let action = if status.state == AlarmState::Resolved { AuditAction::Resolved } else { AuditAction::Acknowledged };
+                self.audit( AlarmAuditEntry::new( action, &status.device_id, &status.alarm_type)
+                    .with_alarm( &status.key, status.no)
+                    .with_detail( format!("by {by} via {via}")));
+                self.publish_status( status).await;
+            }
+            Err(msg) => warn!("rejected alarm {cmd:?} by {by} via {via}: {msg}")
+        }
+    }
+
+    fn on_call_recipients (&self, device_id: &str, date: DateTime<Utc>, tier: usize)->Vec<AlarmContact> {
+        self.config.on_call.as_ref().map( |s| s.recipients( device_id, &date, tier)).unwrap_or_default()
+    }
+
+    /// send alarm through all messengers and return the names of the ones that succeeded and failed (failures are audited)
+    async fn send_notifications (&self, alarm: &Alarm)->(Vec<&'static str>,Vec<&'static str>) {
+        let mut sent = Vec::new();
+        let mut failed = Vec::new();
+        for msgr in &self.messengers {
+            match msgr.send_alarm( alarm).await {
+                Ok(()) => sent.push( msgr.name()),
+                Err(e) => {
+                    warn!("failed to send alarm notification: {e}");
+                    self.audit( self.alarm_audit_entry( AuditAction::MessengerFailure, alarm).with_messenger( msgr.name()).with_detail( e));
+                    failed.push( msgr.name());
+                }
+            }
+        }
+        (sent, failed)
+    }
+
+    //--- audit trail
+
+    fn audit (&self, entry: AlarmAuditEntry) {
+        if let Some(store) = &self.audit {
+            if let Err(e) = store.append( &entry) {
+                error!("failed to append alarm audit entry: {e}");
+            }
+        }
+    }
+
+    fn audit_record<T> (&self, action: AuditAction, rec: &SensorRecord<T>, confidence: f64, threshold: f64) where T: RecordDataBounds {
+        let evidences: Vec<String> = rec.evidences.iter().map( |r| r.id.clone()).collect();
+        self.audit( AlarmAuditEntry::new( action, &rec.device_id, rec.capability().property_name())
+            .with_record( &rec.id)
+            .with_confidence( confidence, Some(threshold))
+            .with_evidences( evidences));
+    }
+
+    fn alarm_audit_entry (&self, action: AuditAction, alarm: &Alarm)->AlarmAuditEntry {
+        let evidences: Vec<String> = alarm.evidence_info.iter()
+            .filter_map( |e| e.img.as_ref().map( |f| f.pathname.to_string_lossy().to_string()))
+            .collect();
+
+        let mut entry = AlarmAuditEntry::new( action, &alarm.device_id, &alarm.alarm_type)
+            .with_alarm( &alarm.id, alarm.no)
+            .with_confidence( alarm.confidence, None)
+            .with_evidences( evidences);
+        if !alarm.record_id.is_empty() { entry = entry.with_record( &alarm.record_id) }
+        entry
+    }
+
+    fn open_audit_store (&mut self) {
+        let dir = self.config.audit_dir.clone().unwrap_or_else( sentinel_data_dir);
+        match AlarmAuditStore::open( &dir) {
+            Ok(store) => self.audit = Some( store.with_retention( self.config.audit_retention)),
+            Err(e) => error!("failed to open alarm audit store in {dir:?}: {e}")
+        }
+    }
+
+    async fn publish_status (&self, status: AlarmStatus) {
+        self.log_status( &status);
+        self.status_actions.execute( status, true).await;
+    }
+
+    fn log_status (&self, status: &AlarmStatus) {
+        let path = sentinel_cache_dir().join("alarm.log");
+        match append_open(path) {
+            Ok(mut file) => { 
+                writeln!(file, "{}: #{} {} {:?} tier {} {} {}", Local::now(), status.no, status.key, status.state, status.tier,
+                    status.changed_by.as_deref().unwrap_or(""), status.changed_via.as_deref().unwrap_or("")); 
+            }
+            Err(e) => { error!("failed to append to alarm.log: {:?}", e) }
+        };
+    }
+
+    fn start_check_timer (&mut self, hself: &ActorHandle<SentinelAlarmMonitorMsg>) {
+        if let Some(timer) = self.timer.take() { timer.abort() }
+        match hself.start_repeat_timer( 1, self.config.check_interval, false) {
+            Ok(timer) => self.timer = Some(timer),
+            Err(e) => error!("failed to start alarm check timer: {e}")
+        }
+    }
 }
 
 impl_actor! { match msg for Actor<SentinelAlarmMonitor,SentinelAlarmMonitorMsg> as
     SentinelUpdate => cont! { // external - update notification
         let hself = self.hself.clone();
+        let use_rules = self.config.rules.is_some();
+        if use_rules {
+            self.process_rule_alarms( hself.clone(), &msg).await;
+        }
         match_algebraic_type! { msg: SentinelUpdate as 
-            Arc<SensorRecord<FireData>> => self.process_fire_alarm( hself, msg).await,
-            Arc<SensorRecord<SmokeData>> => self.process_smoke_alarm( hself, msg).await,
+            Arc<SensorRecord<FireData>> => if !use_rules { self.process_fire_alarm( hself, msg).await },
+            Arc<SensorRecord<SmokeData>> => if !use_rules { self.process_smoke_alarm( hself, msg).await },
             Arc<SensorRecord<ImageData>> => self.check_inactive_alerts( hself, &msg.device_id, &msg.time_recorded).await,
             // TODO - we should add a couple other SensorRecords here that are frequently updated
             _ => {} // the rest we ignore
@@ -364,20 +741,92 @@ impl_actor! { match msg for Actor<SentinelAlarmMonitor,SentinelAlarmMonitorMsg>
         let hself = self.hself();
         self.process_inactive_alert( hself, msg).await
     }
-    Alarm => cont! { // internal message that we have to send out notifications  
-        for msgr in &self.messengers {
-            if let Err(e) = msgr.send_alarm( &msg).await {
-                warn!("failed to send alarm notification: {e}");
-            }
+    Reconfigure<SentinelAlarmMonitorConfig> => cont! { // external - the config file was modified (already checked)
+        info!("new alarm thresholds: fire_prob={}, smoke_prob={}", msg.config.fire_prob, msg.config.smoke_prob);
+        match (&mut self.rule_engine, &msg.config.rules) {
+            (Some(engine), Some(rules)) => { info!("new alarm rules: {} rules", rules.rules.len()); engine.set_rules( rules.clone()) }
+            (_, None) => self.rule_engine = None,
+            _ => {} // engine is created on demand
         }
+        let restart_timer = msg.config.check_interval != self.config.check_interval;
+        self.config = msg.config;
+        if restart_timer { 
+            let hself = self.hself.clone();
+            self.start_check_timer( &hself) 
+        }
+    }
+    Alarm => cont! { // internal message that we have to send out notifications  
+        self.dispatch_alarm( msg).await
+    }
+    AcknowledgeAlarm => cont! { // external - from web UI
+        self.execute_command( AlarmCommand::Acknowledge, msg.no, msg.by, msg.via).await
+    }
+    ResolveAlarm => cont! { // external - from web UI
+        self.execute_command( AlarmCommand::Resolve, msg.no, msg.by, msg.via).await
+    }
+    Query<GetAlarmStatus,Vec<AlarmStatus>> => cont! {
+        let alarms: Vec<AlarmStatus> = self.tracker.alarms().cloned().collect();
+        if msg.respond( alarms).await.is_err() { warn!("alarm status query receiver closed") }
+    }
+    AddAlarmStatusAction => cont! {
+        self.status_actions.push( msg.0)
+    }
+    _Start_ => cont! {
+        self.open_audit_store();
+        let hself = self.hself.clone();
+        self.start_check_timer( &hself)
+    }
+    _Timer_ => cont! {
+        self.check_replies().await;
+        self.check_escalations().await;
+    }
+    _Terminate_ => stop! {
+        if let Some(timer) = self.timer.take() { timer.abort() }
     }
-    
 }
 
 /* #endregion SentinelAlarm */
 
 /* #region Messenger *****************************************************************************************/
 
+/// replace `${name}` placeholders in all strings of a JSON template with the respective `vars` values. String values that
+/// consist only of a placeholder are replaced with the (typed) variable value, e.g. with alarm vars (see [`Alarm::to_json`])
+/// ```json
+/// { "event": "wildfire-${alarmType}", "unit": "${deviceId}", "confidence": "${confidence}", "at": ["${lon}","${lat}"] }
+/// ```
+/// expands into `{"event":"wildfire-smoke","unit":"roo7gd1dldn3","confidence":0.87,"at":[-121.96,37.17]}`
+pub fn expand_json_template (template: &Value, vars: &Map<String,Value>)->Value {
+    match template {
+        Value::String(s) => {
+            if let Some(v) = s.strip_prefix("${").and_then( |s| s.strip_suffix('}')).and_then( |name| vars.get(name)) {
+                v.clone()
+            } else {
+                Value::String( expand_text_template( s, vars))
+            }
+        }
+        Value::Array(a) => Value::Array( a.iter().map( |v| expand_json_template( v, vars)).collect()),
+        Value::Object(o) => Value::Object( o.iter().map( |(k,v)| (k.clone(), expand_json_template( v, vars))).collect()),
+        other => other.clone()
+    }
+}
+
+/// replace `${name}` placeholders in a text (e.g. an MQTT topic) with the respective `vars` values
+pub fn expand_text_template (template: &str, vars: &Map<String,Value>)->String {
+    let mut s = template.to_string();
+    for (k,v) in vars {
+        let placeholder = format!("${{{k}}}");
+        if s.contains( &placeholder) {
+            let text = match v {
+                Value::String(v) => v.clone(),
+                Value::Null => String::new(),
+                other => other.to_string()
+            };
+            s = s.replace( &placeholder, &text);
+        }
+    }
+    s
+}
+
 /// this is just a dummy Messenger that prints out alarms to the console (used for testing)
 pub struct ConsoleAlarmMessenger {}
 
@@ -0,0 +1,216 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! durable audit trail of alarm decisions made by the [`crate::SentinelAlarmMonitor`]. Entries are appended as
+//! NDJSON to daily files `<dir>/alarm_audit-<yyyy-mm-dd>.ndjson` (by entry time) and are never modified, i.e. this can
+//! be used for after-action reviews. Files older than the (optional) retention period are removed when a new file is started
+
+use std::{fs::{self,File}, io::{BufRead,BufReader}, path::{Path,PathBuf}, time::Duration};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Serialize,Deserialize};
+
+use odin_actor::warn;
+use odin_common::{datetime::{ser_epoch_millis,deserialize_rfc3339_or_epoch_millis}, fs::{append_line_to_file,ensure_writable_dir}};
+use crate::{AlarmNo, DeviceId, RecordId};
+use crate::errors::Result;
+
+pub const AUDIT_FILE: &str = "alarm_audit.ndjson"; // pre-rotation audit file, still included in queries
+const AUDIT_FILE_PREFIX: &str = "alarm_audit-";
+const AUDIT_FILE_EXT: &str = ".ndjson";
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub enum AuditAction {
+    Sent,                // notification was sent by at least one messenger
+    SendFailed,          // notification could not be sent by any messenger
+    SuppressedDuplicate, // already reported
+    Suppressed,          // rule alarm in a suppression window
+    BelowThreshold,      // record had a probability below but close to the configured threshold
+    Uncorroborated,      // rule alarm without enough corroborating devices
+    MessengerFailure,    // one of the messengers failed to send the notification
+    Escalated,
+    Acknowledged,
+    Resolved,
+    RejectedReply,       // reply from an unknown sender or without a valid alarm command
+}
+
+/// a single alarm decision
+#[derive(Serialize,Deserialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct AlarmAuditEntry {
+    #[serde(serialize_with="ser_epoch_millis", deserialize_with="deserialize_rfc3339_or_epoch_millis")]
+    pub time: DateTime<Utc>, // when the decision was made
+    pub action: AuditAction,
+    pub device_id: DeviceId,
+    pub alarm_type: String,
+
+    #[serde(default)] pub record_id: Option<RecordId>, // the record that caused this decision
+    #[serde(default)] pub alarm_key: Option<String>,
+    #[serde(default)] pub alarm_no: Option<AlarmNo>,
+    #[serde(default)] pub confidence: Option<f64>,
+    #[serde(default)] pub threshold: Option<f64>,
+    #[serde(default)] pub evidences: Vec<String>, // evidence record ids or image files
+    #[serde(default)] pub messenger: Option<String>,
+    #[serde(default)] pub detail: Option<String>, // e.g. error message or who acknowledged
+}
+
+impl AlarmAuditEntry {
+    pub fn new (action: AuditAction, device_id: impl ToString, alarm_type: impl ToString)->Self {
+        AlarmAuditEntry {
+            time: Utc::now(), action, device_id: device_id.to_string(), alarm_type: alarm_type.to_string(),
+            record_id: None, alarm_key: None, alarm_no: None, confidence: None, threshold: None,
+            evidences: Vec::new(), messenger: None, detail: None
+        }
+    }
+
+    pub fn with_time (mut self, time: DateTime<Utc>)->Self { self.time = time; self }
+
+    pub fn with_record (mut self, record_id: impl ToString)->Self { self.record_id = Some(record_id.to_string()); self }
+
+    pub fn with_alarm (mut self, key: impl ToString, no: AlarmNo)->Self {
+        self.alarm_key = Some(key.to_string());
+        if no > 0 { self.alarm_no = Some(no) }
+        self
+    }
+
+    pub fn with_confidence (mut self, confidence: f64, threshold: Option<f64>)->Self {
+        self.confidence = Some(confidence);
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_evidences (mut self, evidences: Vec<String>)->Self { self.evidences = evidences; self }
+
+    pub fn with_messenger (mut self, messenger: impl ToString)->Self { self.messenger = Some(messenger.to_string()); self }
+
+    pub fn with_detail (mut self, detail: impl ToString)->Self { self.detail = Some(detail.to_string()); self }
+}
+
+/// filter for audit queries. All fields are optional, i.e. the default query returns everything (up to `limit`)
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+#[serde(default)]
+pub struct AlarmAuditQuery {
+    pub device: Option<String>, // device id prefix
+    pub alarm_type: Option<String>, // comma separated list, e.g. "fire,smoke"
+    pub action: Option<AuditAction>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>, // most recent entries
+}
+
+impl AlarmAuditQuery {
+    pub fn matches (&self, e: &AlarmAuditEntry)->bool {
+        self.device.as_ref().map( |d| e.device_id.starts_with( d.as_str())).unwrap_or(true)
+        && self.alarm_type.as_ref().map( |types| types.split(',').any( |t| t.trim() == e.alarm_type)).unwrap_or(true)
+        && self.action.map( |a| e.action == a).unwrap_or(true)
+        && self.since.map( |t| e.time >= t).unwrap_or(true)
+        && self.until.map( |t| e.time < t).unwrap_or(true)
+    }
+}
+
+/// append-only file store for [`AlarmAuditEntry`] records. This only holds the directory so that readers (e.g. web services)
+/// can use their own instance
+#[derive(Debug,Clone)]
+pub struct AlarmAuditStore {
+    dir: PathBuf,
+    retention: Option<Duration>,
+}
+
+impl AlarmAuditStore {
+    pub fn open (dir: impl AsRef<Path>)->Result<Self> {
+        let dir = dir.as_ref();
+        ensure_writable_dir( dir)?;
+        Ok( AlarmAuditStore { dir: dir.to_path_buf(), retention: None } )
+    }
+
+    /// remove daily files that are older than `retention` (zero keeps all files)
+    pub fn with_retention (mut self, retention: Duration)->Self {
+        self.retention = if retention.is_zero() { None } else { Some(retention) };
+        self
+    }
+
+    pub fn dir (&self)->&Path { &self.dir }
+
+    /// the file that holds entries of `date`
+    pub fn path (&self, date: NaiveDate)->PathBuf {
+        self.dir.join( format!("{AUDIT_FILE_PREFIX}{}{AUDIT_FILE_EXT}", date.format("%Y-%m-%d")))
+    }
+
+    pub fn append (&self, entry: &AlarmAuditEntry)->Result<()> {
+        let path = self.path( entry.time.date_naive());
+        if !path.exists() { self.purge( Utc::now()) } // new day - check for expired files
+
+        let line = serde_json::to_string( entry)?;
+        Ok( append_line_to_file( &path, &line)? )
+    }
+
+    /// remove daily files that are past the retention period at `now`
+    pub fn purge (&self, now: DateTime<Utc>) {
+        let Some(retention) = self.retention else { return };
+        let Ok(retention) = TimeDelta::from_std( retention) else { return };
+        let cutoff = (now - retention).date_naive();
+
+        for (date,path) in self.daily_files() {
+            if date < cutoff {
+                if let Err(e) = fs::remove_file( &path) { warn!("failed to remove expired audit file {path:?}: {e}") }
+            }
+        }
+    }
+
+    /// (date,path) of daily audit files, most recent first
+    fn daily_files (&self)->Vec<(NaiveDate,PathBuf)> {
+        let mut files: Vec<(NaiveDate,PathBuf)> = fs::read_dir( &self.dir).into_iter().flatten().flatten()
+            .filter_map( |e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let date = name.strip_prefix( AUDIT_FILE_PREFIX)?.strip_suffix( AUDIT_FILE_EXT)?;
+                NaiveDate::parse_from_str( date, "%Y-%m-%d").ok().map( |d| (d, e.path()))
+            })
+            .collect();
+        files.sort_by( |a,b| b.0.cmp( &a.0));
+        files
+    }
+
+    /// return matching entries, most recent first. Only files within the query time range are read and reading stops
+    /// once `limit` entries were found. Unreadable or unparsable lines are skipped
+    pub fn query (&self, query: &AlarmAuditQuery)->Result<Vec<AlarmAuditEntry>> {
+        let since = query.since.map( |t| t.date_naive());
+        let until = query.until.map( |t| t.date_naive());
+        let limit = query.limit.unwrap_or( usize::MAX);
+
+        let mut paths: Vec<PathBuf> = self.daily_files().into_iter()
+            .filter( |(d,_)| since.map_or( true, |s| *d >= s) && until.map_or( true, |u| *d <= u))
+            .map( |(_,p)| p)
+            .collect();
+        let legacy = self.dir.join( AUDIT_FILE);
+        if legacy.is_file() { paths.push( legacy) }
+
+        let mut entries: Vec<AlarmAuditEntry> = Vec::new();
+        for path in &paths {
+            if entries.len() >= limit { break }
+
+            let reader = BufReader::new( File::open( path)?);
+            let lines: Vec<String> = reader.lines().filter_map( |l| l.ok()).collect();
+            for line in lines.iter().rev() {
+                if let Ok(entry) = serde_json::from_str::<AlarmAuditEntry>( line) {
+                    if query.matches( &entry) {
+                        entries.push( entry);
+                        if entries.len() >= limit { break }
+                    }
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,331 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! alarm lifecycle support for the [`crate::SentinelAlarmMonitor`]: alarm states, acknowledgement, escalation tiers
+//! and on-call schedules. Schedules are part of the `SentinelAlarmMonitorConfig`, e.g.
+//! ```ron
+//! on_call: Some( OnCallSchedule(
+//!     contacts: [
+//!         AlarmContact( name: "alice", signal: Some("+15551234567"), email: Some("alice@example.com")),
+//!         AlarmContact( name: "bob", signal: Some("+15557654321")),
+//!         AlarmContact( name: "dispatch", email: Some("dispatch@example.com"), slack: Some("C0123456")),
+//!     ],
+//!     regions: [
+//!         OnCallRegion(
+//!             name: "north",
+//!             devices: ["roo7gd1dldn3", "roo7"],
+//!             shifts: [
+//!                 OnCallShift( days: ["Sat","Sun"], hours: TimeWindow( start: "00:00:00", end: "23:59:59"), tiers: [["dispatch"]]),
+//!                 OnCallShift( hours: TimeWindow( start: "22:00:00", end: "07:00:00"), tiers: [["bob"], ["dispatch"]]),
+//!             ],
+//!             tiers: [["alice"], ["bob","dispatch"]],
+//!         ),
+//!     ],
+//! ))
+//! ```
+//! Tier 0 contacts get the initial notification. Each time an alarm that requires acknowledgement is not acknowledged
+//! within the configured `ack_timeout` it is escalated to the next tier (repeating the last one).
+//!
+//! This module does not depend on actors or messengers so that lifecycle and schedule logic can be tested in isolation.
+
+use std::{collections::VecDeque, time::Duration};
+use chrono::{DateTime, Datelike, Local, TimeDelta, Utc, Weekday};
+use serde::{Serialize,Deserialize};
+
+use odin_common::datetime::{ser_epoch_millis,ser_epoch_millis_option};
+use crate::{DeviceId, TimeWindow};
+
+/// the short alarm number that is used to refer to alarms in replies (e.g. "ack 42")
+pub type AlarmNo = u32;
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub enum AlarmState { New, Acknowledged, Escalated, Resolved }
+
+impl AlarmState {
+    /// is this alarm still waiting for somebody to react
+    pub fn is_pending (&self)->bool {
+        matches!( self, AlarmState::New | AlarmState::Escalated)
+    }
+}
+
+/* #region on-call schedule *****************************************************************************************/
+
+/// a person or group that can receive alarm notifications. Each messenger uses the address it understands -
+/// contacts without an address for a given messenger are not notified through it
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct AlarmContact {
+    pub name: String,
+    #[serde(default)] pub signal: Option<String>, // phone number or username
+    #[serde(default)] pub email: Option<String>,
+    #[serde(default)] pub slack: Option<String>, // channel or user id
+}
+
+impl AlarmContact {
+    pub fn has_address (&self, addr: &str)->bool {
+        [&self.signal, &self.email, &self.slack].iter().any( |a| a.as_ref().map( |a| a.eq_ignore_ascii_case(addr)).unwrap_or(false))
+    }
+}
+
+/// the tiers of a region that apply on the given days within the given hours. Overnight hours (start > end) count
+/// for the weekday on which the alarm time falls
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct OnCallShift {
+    #[serde(default)] pub days: Vec<Weekday>, // empty means all days
+    pub hours: TimeWindow,
+    pub tiers: Vec<Vec<String>>, // contact names per escalation tier
+}
+
+impl OnCallShift {
+    pub fn contains (&self, date: &DateTime<Utc>)->bool {
+        let weekday = if self.hours.utc { date.weekday() } else { date.with_timezone(&Local).weekday() };
+        (self.days.is_empty() || self.days.contains(&weekday)) && self.hours.contains( date)
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct OnCallRegion {
+    pub name: String,
+    pub devices: Vec<String>, // device id prefixes, "*" matches all devices
+    #[serde(default)] pub shifts: Vec<OnCallShift>, // first matching shift wins
+    #[serde(default)] pub tiers: Vec<Vec<String>>, // used outside of shifts
+}
+
+impl OnCallRegion {
+    pub fn matches (&self, device_id: &str)->bool {
+        self.devices.iter().any( |d| d == "*" || device_id.starts_with( d.as_str()))
+    }
+
+    pub fn tiers_at (&self, date: &DateTime<Utc>)->&Vec<Vec<String>> {
+        self.shifts.iter().find( |s| s.contains( date)).map( |s| &s.tiers).unwrap_or( &self.tiers)
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+#[serde(default)]
+pub struct OnCallSchedule {
+    pub contacts: Vec<AlarmContact>,
+    pub regions: Vec<OnCallRegion>, // first matching region wins
+}
+
+impl OnCallSchedule {
+    pub fn check (&self)->std::result::Result<(),String> {
+        for (i,c) in self.contacts.iter().enumerate() {
+            if self.contacts[..i].iter().any( |other| other.name == c.name) { return Err( format!("duplicate on-call contact {}", c.name)) }
+        }
+        for r in &self.regions {
+            let all_tiers = r.shifts.iter().map( |s| &s.tiers).chain( std::iter::once( &r.tiers));
+            for tiers in all_tiers {
+                for name in tiers.iter().flatten() {
+                    if self.contact(name).is_none() { return Err( format!("unknown contact {name} in on-call region {}", r.name)) }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contact (&self, name: &str)->Option<&AlarmContact> {
+        self.contacts.iter().find( |c| c.name == name)
+    }
+
+    /// find the contact that uses the given (messenger) address, which is how we identify reply senders
+    pub fn contact_for_address (&self, addr: &str)->Option<&AlarmContact> {
+        self.contacts.iter().find( |c| c.has_address( addr))
+    }
+
+    /// check if a reply comes from a known contact and contains a valid command. Replies are only accepted from
+    /// configured contacts since messenger addresses can be spoofed or re-used
+    pub fn accept_reply (&self, reply: &AlarmReply)->std::result::Result<(AlarmCommand,AlarmNo,&AlarmContact),String> {
+        let contact = self.contact_for_address( &reply.from).ok_or_else( || format!("unknown sender {}", reply.from))?;
+        let (cmd,no) = parse_alarm_reply( &reply.text).ok_or_else( || format!("no alarm command with number in {:?}", reply.text))?;
+        Ok( (cmd, no, contact))
+    }
+
+    pub fn region_for (&self, device_id: &str)->Option<&OnCallRegion> {
+        self.regions.iter().find( |r| r.matches( device_id))
+    }
+
+    pub fn num_tiers (&self, device_id: &str, date: &DateTime<Utc>)->usize {
+        self.region_for( device_id).map( |r| r.tiers_at(date).len()).unwrap_or(0)
+    }
+
+    /// the contacts of the given escalation tier that are on call for device_id at the given date. Returns an empty
+    /// Vec if there is no matching region or tier
+    pub fn recipients (&self, device_id: &str, date: &DateTime<Utc>, tier: usize)->Vec<AlarmContact> {
+        self.region_for( device_id)
+            .and_then( |r| r.tiers_at( date).get( tier))
+            .map( |names| names.iter().filter_map( |n| self.contact(n)).cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/* #endregion on-call schedule */
+
+/* #region alarm tracker ********************************************************************************************/
+
+/// the lifecycle state of a reported alarm. This is what we send to the web UI
+#[derive(Serialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct AlarmStatus {
+    pub no: AlarmNo,
+    pub key: String, // the (unique) alarm id we use in logs
+    pub device_id: DeviceId,
+    pub alarm_type: String,
+    #[serde(serialize_with="ser_epoch_millis")] pub time_recorded: DateTime<Utc>,
+    pub requires_ack: bool,
+    pub state: AlarmState,
+    pub tier: usize,
+    #[serde(serialize_with="ser_epoch_millis")] pub notified: DateTime<Utc>, // last time we sent out notifications
+    pub changed_by: Option<String>,
+    pub changed_via: Option<String>,
+    #[serde(serialize_with="ser_epoch_millis_option")] pub change_time: Option<DateTime<Utc>>,
+}
+
+/// the store for the lifecycle states of reported alarms
+pub struct AlarmTracker {
+    alarms: VecDeque<AlarmStatus>, // most recent first
+    next_no: AlarmNo,
+}
+
+impl AlarmTracker {
+    pub fn new ()->Self {
+        AlarmTracker { alarms: VecDeque::new(), next_no: 1 }
+    }
+
+    pub fn add (&mut self, key: String, device_id: DeviceId, alarm_type: String, time_recorded: DateTime<Utc>, 
+                requires_ack: bool, now: DateTime<Utc>)->&AlarmStatus 
+    {
+        let no = self.next_no;
+        self.next_no = self.next_no.wrapping_add(1).max(1);
+
+        let state = if requires_ack { AlarmState::New } else { AlarmState::Resolved }; // nothing to wait for
+        let status = AlarmStatus { 
+            no, key, device_id, alarm_type, time_recorded, requires_ack, state, tier: 0, notified: now, 
+            changed_by: None, changed_via: None, change_time: None 
+        };
+        self.alarms.push_front( status);
+        &self.alarms[0]
+    }
+
+    pub fn get (&self, no: AlarmNo)->Option<&AlarmStatus> {
+        self.alarms.iter().find( |a| a.no == no)
+    }
+
+    pub fn alarms (&self)->impl Iterator<Item=&AlarmStatus> {
+        self.alarms.iter()
+    }
+
+    pub fn len (&self)->usize { self.alarms.len() }
+
+    /// acknowledge the given alarm or - if no alarm number is provided - the most recent pending one
+    pub fn acknowledge (&mut self, no: Option<AlarmNo>, by: &str, via: &str, now: DateTime<Utc>)->std::result::Result<&AlarmStatus,String> {
+        let alarm = self.find_mut( no, |s| s.is_pending())?;
+        match alarm.state {
+            AlarmState::Acknowledged => Err( format!("alarm {} already acknowledged by {}", alarm.no, alarm.changed_by.as_deref().unwrap_or("?"))),
+            AlarmState::Resolved => Err( format!("alarm {} already resolved", alarm.no)),
+            _ => {
+                Self::set_state( alarm, AlarmState::Acknowledged, by, via, now);
+                Ok(alarm)
+            }
+        }
+    }
+
+    /// resolve the given alarm or - if no alarm number is provided - the most recent one that is not resolved yet
+    pub fn resolve (&mut self, no: Option<AlarmNo>, by: &str, via: &str, now: DateTime<Utc>)->std::result::Result<&AlarmStatus,String> {
+        let alarm = self.find_mut( no, |s| *s != AlarmState::Resolved)?;
+        if alarm.state == AlarmState::Resolved {
+            Err( format!("alarm {} already resolved", alarm.no))
+        } else {
+            Self::set_state( alarm, AlarmState::Resolved, by, via, now);
+            Ok(alarm)
+        }
+    }
+
+    fn find_mut (&mut self, no: Option<AlarmNo>, is_candidate: impl Fn(&AlarmState)->bool)->std::result::Result<&mut AlarmStatus,String> {
+        if let Some(no) = no {
+            self.alarms.iter_mut().find( |a| a.no == no).ok_or( format!("unknown alarm {no}"))
+        } else {
+            self.alarms.iter_mut().find( |a| is_candidate(&a.state)).ok_or( "no open alarm".to_string())
+        }
+    }
+
+    fn set_state (alarm: &mut AlarmStatus, state: AlarmState, by: &str, via: &str, now: DateTime<Utc>) {
+        alarm.state = state;
+        alarm.changed_by = Some(by.to_string());
+        alarm.changed_via = Some(via.to_string());
+        alarm.change_time = Some(now);
+    }
+
+    /// the numbers of pending alarms that were not acknowledged within ack_timeout since their last notification
+    pub fn due_escalations (&self, now: DateTime<Utc>, ack_timeout: Duration)->Vec<AlarmNo> {
+        let Ok(timeout) = TimeDelta::from_std( ack_timeout) else { return Vec::new() };
+        if timeout.is_zero() { return Vec::new() } // escalation disabled
+
+        self.alarms.iter()
+            .filter( |a| a.requires_ack && a.state.is_pending() && now - a.notified >= timeout)
+            .map( |a| a.no)
+            .collect()
+    }
+
+    /// move alarm to the next tier (not exceeding max_tier) and reset its notification time
+    pub fn escalate (&mut self, no: AlarmNo, max_tier: usize, now: DateTime<Utc>)->Option<&AlarmStatus> {
+        let alarm = self.alarms.iter_mut().find( |a| a.no == no && a.state.is_pending())?;
+        alarm.state = AlarmState::Escalated;
+        alarm.tier = (alarm.tier + 1).min( max_tier);
+        alarm.notified = now;
+        Some(alarm)
+    }
+
+    /// remove all alarms that are not pending anymore and were created before max_age
+    pub fn purge (&mut self, now: DateTime<Utc>, max_age: Duration) {
+        if let Ok(max_age) = TimeDelta::from_std( max_age) {
+            self.alarms.retain( |a| a.state.is_pending() || now - a.time_recorded < max_age);
+        }
+    }
+}
+
+/* #endregion alarm tracker */
+
+/* #region replies **************************************************************************************************/
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum AlarmCommand { Acknowledge, Resolve }
+
+/// a text message that was received by an [`crate::AlarmMessenger`] in response to an alarm notification
+#[derive(Debug,Clone)]
+pub struct AlarmReply {
+    pub from: String, // the sender address (phone number, email etc)
+    pub via: String,  // the messenger it was received by
+    pub text: String,
+}
+
+/// parse the command word of a reply ("ack", "acknowledge", "resolve" or "resolved", case insensitive)
+pub fn parse_alarm_command (word: &str)->Option<AlarmCommand> {
+    match word.trim_end_matches( |c: char| !c.is_alphanumeric()).to_lowercase().as_str() {
+        "ack" | "acknowledge" => Some(AlarmCommand::Acknowledge),
+        "resolve" | "resolved" => Some(AlarmCommand::Resolve),
+        _ => None
+    }
+}
+
+/// parse reply commands of the form "ack [#]<no>" or "resolve [#]<no>". The alarm number is mandatory so that
+/// stray messages cannot acknowledge whatever alarm happens to be pending
+pub fn parse_alarm_reply (text: &str)->Option<(AlarmCommand,AlarmNo)> {
+    let mut words = text.split_whitespace();
+    let cmd = parse_alarm_command( words.next()?)?;
+    let no = words.next()?.trim_start_matches('#').parse::<AlarmNo>().ok()?;
+    Some( (cmd, no))
+}
+
+/* #endregion replies */
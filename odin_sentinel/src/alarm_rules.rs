@@ -0,0 +1,436 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! a configurable rule engine for Sentinel alarms. Rules are specified in RON (as part of the [`SentinelAlarmMonitorConfig`])
+//! and combine conditions on the (time windowed) record history of a device, e.g.
+//! ```ron
+//! rules: Some( AlarmRuleSet(
+//!     params: { "smoke_prob": 0.7 },
+//!     rules: [
+//!         AlarmRule(
+//!             name: "smoke_and_rising_temp",
+//!             alarm_type: "smoke",
+//!             trigger: [smoke],
+//!             condition: All([
+//!                 Threshold( capability: smoke, field: "smokeProb", op: Ge, value: 0.7, param: Some("smoke_prob"), within: "1m"),
+//!                 Rising( capability: thermometer, field: "temperature", min_delta: 2.0, within: "5m"),
+//!             ]),
+//!             confidence: Some( FieldRef( capability: smoke, field: "smokeProb")),
+//!             corroboration: Some( Corroboration( min_devices: 2, radius: 5000.0, within: "10m")),
+//!             suppress: [ TimeWindow( start: "22:00:00", end: "06:00:00", utc: false) ],
+//!         ),
+//!     ],
+//!     overrides: { "roo7gd1dldn3": DeviceOverride( params: { "smoke_prob": 0.9 }) },
+//! ))
+//! ```
+//! Field names refer to the serialized (camelCase) record data, e.g. `fireProb`, `smokeProb`, `temperature`, `gas`, `TVOC`.
+//!
+//! Rule evaluation is purely based on record times (not wall clock), i.e. [`AlarmRuleEngine`] can be tested against
+//! recorded `SensorRecord` sequences
+
+use std::{collections::{HashMap,VecDeque}, time::Duration, sync::Arc};
+use chrono::{DateTime, Utc, Local, NaiveTime, TimeDelta};
+use serde::{Serialize,Deserialize};
+use serde_json::Value;
+
+use odin_common::datetime::{deserialize_duration,serialize_duration};
+use odin_macro::match_algebraic_type;
+
+use crate::{DeviceId, RecordId, SensorCapability, SensorRecord, SentinelUpdate, GpsData};
+
+/* #region rule specification ****************************************************************************************/
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub enum CmpOp { Gt, Ge, Lt, Le, Eq }
+
+impl CmpOp {
+    pub fn apply (&self, a: f64, b: f64)->bool {
+        match self {
+            CmpOp::Gt => a > b,
+            CmpOp::Ge => a >= b,
+            CmpOp::Lt => a < b,
+            CmpOp::Le => a <= b,
+            CmpOp::Eq => a == b,
+        }
+    }
+}
+
+/// a numeric field of a capability record
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct FieldRef {
+    pub capability: SensorCapability,
+    pub field: String,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub enum AlarmCondition {
+    All(Vec<AlarmCondition>),
+    Any(Vec<AlarmCondition>),
+    Not(Box<AlarmCondition>),
+
+    /// the latest `field` value of `capability` records within the `within` window satisfies `op value`.
+    /// If `param` is set and defined (per device or globally) it replaces `value`
+    Threshold { 
+        capability: SensorCapability, 
+        field: String, 
+        op: CmpOp, 
+        value: f64, 
+        #[serde(default)] param: Option<String>,
+        #[serde(deserialize_with="deserialize_duration", serialize_with="serialize_duration")] within: Duration 
+    },
+
+    /// the `field` value of `capability` records increased by at least `min_delta` within the `within` window
+    Rising { 
+        capability: SensorCapability, 
+        field: String, 
+        min_delta: f64, 
+        #[serde(default)] param: Option<String>,
+        #[serde(deserialize_with="deserialize_duration", serialize_with="serialize_duration")] within: Duration 
+    },
+}
+
+impl AlarmCondition {
+    fn max_window (&self)->Duration {
+        match self {
+            AlarmCondition::All(conds) | AlarmCondition::Any(conds) => conds.iter().map( |c| c.max_window()).max().unwrap_or(Duration::ZERO),
+            AlarmCondition::Not(cond) => cond.max_window(),
+            AlarmCondition::Threshold{within,..} | AlarmCondition::Rising{within,..} => *within
+        }
+    }
+}
+
+/// require at least `min_devices` (including the triggering one) within `radius` meters for which the rule condition
+/// was satisfied within the `within` window
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct Corroboration {
+    pub min_devices: usize,
+    pub radius: f64,
+    #[serde(deserialize_with="deserialize_duration", serialize_with="serialize_duration")] 
+    pub within: Duration,
+}
+
+/// time-of-day window (can wrap around midnight)
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    #[serde(default)] pub utc: bool, // otherwise local time
+}
+
+impl TimeWindow {
+    pub fn contains (&self, date: &DateTime<Utc>)->bool {
+        let t = if self.utc { date.time() } else { date.with_timezone(&Local).time() };
+        if self.start <= self.end {
+            t >= self.start && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+}
+
+fn default_dedup()->Duration { Duration::from_secs(600) }
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct AlarmRule {
+    pub name: String,
+    pub alarm_type: String,
+
+    #[serde(default)] pub trigger: Vec<SensorCapability>, // capabilities of updates that cause evaluation (empty means any)
+    pub condition: AlarmCondition,
+    #[serde(default)] pub confidence: Option<FieldRef>, // latest value of this field is the alarm confidence (default is 1.0)
+
+    #[serde(default)] pub corroboration: Option<Corroboration>,
+    #[serde(default)] pub suppress: Vec<TimeWindow>,
+    #[serde(default)] pub devices: Vec<DeviceId>, // devices this rule applies to (empty means all)
+
+    /// matches for the same rule and device within this duration are not reported again
+    #[serde(default="default_dedup", deserialize_with="deserialize_duration", serialize_with="serialize_duration")] 
+    pub dedup: Duration,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+#[serde(default)]
+pub struct DeviceOverride {
+    pub disabled: Vec<String>, // names of rules that do not apply to this device
+    pub params: HashMap<String,f64>, // device specific parameter values
+    pub suppress: Vec<TimeWindow>, // additional suppression windows for all rules of this device
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+#[serde(default)]
+pub struct AlarmRuleSet {
+    pub rules: Vec<AlarmRule>,
+    pub params: HashMap<String,f64>, // global parameter values
+    pub overrides: HashMap<DeviceId,DeviceOverride>,
+}
+
+impl AlarmRuleSet {
+    pub fn check (&self)->std::result::Result<(),String> {
+        for (i,rule) in self.rules.iter().enumerate() {
+            if self.rules[..i].iter().any( |r| r.name == rule.name) { return Err( format!("duplicated rule name: {}", rule.name)) }
+            if let Some(corr) = &rule.corroboration {
+                if corr.min_devices == 0 { return Err( format!("min_devices of rule {} has to be > 0", rule.name)) }
+                if corr.radius <= 0.0 { return Err( format!("corroboration radius of rule {} has to be > 0", rule.name)) }
+            }
+        }
+        Ok(())
+    }
+
+    /// the maximum time window we have to keep records for
+    pub fn history_window (&self)->Duration {
+        self.rules.iter().map( |r| {
+            let corr = r.corroboration.as_ref().map( |c| c.within).unwrap_or(Duration::ZERO);
+            r.condition.max_window().max( corr)
+        }).max().unwrap_or(Duration::ZERO)
+    }
+
+    fn param (&self, device_id: &str, name: &str)->Option<f64> {
+        self.overrides.get( device_id).and_then( |o| o.params.get(name)).or_else( || self.params.get(name)).copied()
+    }
+
+    fn is_disabled (&self, rule: &AlarmRule, device_id: &str)->bool {
+        if !rule.devices.is_empty() && !rule.devices.iter().any( |d| d == device_id) { return true }
+        self.overrides.get( device_id).map( |o| o.disabled.contains( &rule.name)).unwrap_or(false)
+    }
+
+    fn is_suppressed (&self, rule: &AlarmRule, device_id: &str, date: &DateTime<Utc>)->bool {
+        rule.suppress.iter().any( |w| w.contains(date)) ||
+            self.overrides.get( device_id).map( |o| o.suppress.iter().any( |w| w.contains(date))).unwrap_or(false)
+    }
+}
+
+/* #endregion rule specification */
+
+/* #region rule engine ***********************************************************************************************/
+
+/// a rule that fired
+#[derive(Debug,Clone)]
+pub struct AlarmRuleMatch {
+    pub rule: String,
+    pub alarm_type: String,
+    pub device_id: DeviceId,
+    pub time: DateTime<Utc>,
+    pub confidence: f64,
+    pub trigger: SentinelUpdate,
+    pub corroborating_devices: Vec<DeviceId>, // other devices that satisfied the rule condition
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum AlarmRuleSkipReason {
+    Suppressed,
+    Duplicate,
+    Uncorroborated { devices: usize, min_devices: usize }, // devices includes the triggering one
+}
+
+/// a rule that matched but did not fire, which is reported so that callers can audit it
+#[derive(Debug,Clone)]
+pub struct AlarmRuleSkip {
+    pub rule: String,
+    pub alarm_type: String,
+    pub device_id: DeviceId,
+    pub time: DateTime<Utc>,
+    pub record_id: RecordId,
+    pub reason: AlarmRuleSkipReason,
+}
+
+/// the time windowed record data of a device
+#[derive(Debug,Default)]
+struct DeviceHistory {
+    records: HashMap<&'static str, VecDeque<(DateTime<Utc>,Value)>>, // capability property name -> (time_recorded, record data), newest last
+    position: Option<(f64,f64)>, // latest (lat,lon) degrees
+}
+
+impl DeviceHistory {
+    fn add (&mut self, update: &SentinelUpdate, window: Duration) {
+        let capability = update.capability();
+        let property = capability.property_name();
+        let date = update.time_recorded();
+
+        if let Ok(Value::Object(mut rec)) = serde_json::to_value( update) {
+            if let Some(data) = rec.remove( property) {
+                let list = self.records.entry( property).or_insert_with( VecDeque::new);
+                let idx = list.iter().rposition( |(d,_)| *d <= date).map( |i| i+1).unwrap_or(0); // mostly appends
+                list.insert( idx, (date, data));
+
+                if let Some(newest) = list.back().map( |(d,_)| *d) {
+                    let cutoff = newest - TimeDelta::from_std( window).unwrap_or( TimeDelta::zero());
+                    while list.front().map( |(d,_)| *d < cutoff).unwrap_or(false) { list.pop_front(); }
+                }
+            }
+        }
+
+        match_algebraic_type! { update: SentinelUpdate as
+            Arc<SensorRecord<GpsData>> => { self.position = Some( (update.data.latitude.degrees(), update.data.longitude.degrees())) }
+            _ => {}
+        }
+    }
+
+    /// field values within [date-within, date], oldest first
+    fn values (&self, capability: SensorCapability, field: &str, date: &DateTime<Utc>, within: Duration)->Vec<f64> {
+        let start = *date - TimeDelta::from_std( within).unwrap_or( TimeDelta::zero());
+        self.records.get( capability.property_name()).map( |list| {
+            list.iter()
+                .filter( |(d,_)| *d >= start && *d <= *date)
+                .filter_map( |(_,v)| v.get( field).and_then( |f| f.as_f64()))
+                .collect()
+        }).unwrap_or_default()
+    }
+}
+
+/// the stateful evaluator for an [`AlarmRuleSet`]. Feed it with all [`SentinelUpdate`]s in order of arrival
+pub struct AlarmRuleEngine {
+    rules: AlarmRuleSet,
+    window: Duration,
+    history: HashMap<DeviceId,DeviceHistory>,
+    last_true: HashMap<(String,DeviceId),DateTime<Utc>>, // last time a rule condition was satisfied for a device
+    last_fired: HashMap<(String,DeviceId),DateTime<Utc>>,
+}
+
+impl AlarmRuleEngine {
+    pub fn new (rules: AlarmRuleSet)->Self {
+        let window = rules.history_window();
+        AlarmRuleEngine { rules, window, history: HashMap::new(), last_true: HashMap::new(), last_fired: HashMap::new() }
+    }
+
+    pub fn rules (&self)->&AlarmRuleSet { &self.rules }
+
+    /// replace rules (e.g. after a config change). This keeps the record history
+    pub fn set_rules (&mut self, rules: AlarmRuleSet) {
+        self.window = rules.history_window();
+        self.rules = rules;
+        self.last_true.clear();
+        self.last_fired.clear();
+    }
+
+    pub fn process (&mut self, update: &SentinelUpdate)->Vec<AlarmRuleMatch> {
+        self.process_with_skips( update).0
+    }
+
+    /// process update and return both the rules that fired and the ones that matched but were suppressed, deduplicated
+    /// or not corroborated by enough devices
+    pub fn process_with_skips (&mut self, update: &SentinelUpdate)->(Vec<AlarmRuleMatch>,Vec<AlarmRuleSkip>) {
+        let device_id = update.device_id().clone();
+        let date = update.time_recorded();
+        let capability = update.capability();
+
+        self.history.entry( device_id.clone()).or_default().add( update, self.window);
+
+        let mut matches = Vec::new();
+        let mut skips = Vec::new();
+        let skip = |rule: &AlarmRule, reason| AlarmRuleSkip {
+            rule: rule.name.clone(), alarm_type: rule.alarm_type.clone(), device_id: device_id.clone(), time: date,
+            record_id: update.record_id().clone(), reason
+        };
+
+        for rule in &self.rules.rules {
+            if self.rules.is_disabled( rule, &device_id) { continue }
+            if !rule.trigger.is_empty() && !rule.trigger.contains( &capability) { continue }
+
+            let history = &self.history[&device_id];
+            if !self.eval( &rule.condition, &device_id, history, &date) { continue }
+
+            let key = (rule.name.clone(), device_id.clone());
+            let last_true = self.last_true.entry( key.clone()).or_insert( date);
+            if date > *last_true { *last_true = date }
+
+            if self.rules.is_suppressed( rule, &device_id, &date) {
+                skips.push( skip( rule, AlarmRuleSkipReason::Suppressed));
+                continue
+            }
+
+            let corroborating_devices = self.corroborating_devices( rule, &device_id, &date);
+            if let Some(corr) = &rule.corroboration {
+                let devices = corroborating_devices.len() + 1;
+                if devices < corr.min_devices {
+                    skips.push( skip( rule, AlarmRuleSkipReason::Uncorroborated{ devices, min_devices: corr.min_devices }));
+                    continue
+                }
+            }
+
+            if let Some(last) = self.last_fired.get( &key) {
+                // late (out-of-order) records that predate the last alarm must not re-fire it
+                if date <= *last || (date - *last).to_std().map( |d| d < rule.dedup).unwrap_or(true) {
+                    skips.push( skip( rule, AlarmRuleSkipReason::Duplicate));
+                    continue
+                }
+            }
+            self.last_fired.insert( key, date);
+
+            let confidence = rule.confidence.as_ref()
+                .and_then( |f| history.values( f.capability, &f.field, &date, self.window.max( Duration::from_secs(1))).last().copied())
+                .unwrap_or(1.0);
+
+            matches.push( AlarmRuleMatch {
+                rule: rule.name.clone(),
+                alarm_type: rule.alarm_type.clone(),
+                device_id: device_id.clone(),
+                time: date,
+                confidence,
+                trigger: update.clone(),
+                corroborating_devices
+            });
+        }
+
+        (matches, skips)
+    }
+
+    fn eval (&self, cond: &AlarmCondition, device_id: &str, history: &DeviceHistory, date: &DateTime<Utc>)->bool {
+        match cond {
+            AlarmCondition::All(conds) => conds.iter().all( |c| self.eval( c, device_id, history, date)),
+            AlarmCondition::Any(conds) => conds.iter().any( |c| self.eval( c, device_id, history, date)),
+            AlarmCondition::Not(c) => !self.eval( c, device_id, history, date),
+
+            AlarmCondition::Threshold { capability, field, op, value, param, within } => {
+                let threshold = param.as_ref().and_then( |p| self.rules.param( device_id, p)).unwrap_or(*value);
+                history.values( *capability, field, date, *within).last().map( |v| op.apply( *v, threshold)).unwrap_or(false)
+            }
+            AlarmCondition::Rising { capability, field, min_delta, param, within } => {
+                let min_delta = param.as_ref().and_then( |p| self.rules.param( device_id, p)).unwrap_or(*min_delta);
+                let values = history.values( *capability, field, date, *within);
+                if values.len() < 2 { return false }
+                let latest = values[values.len()-1];
+                let min = values[..values.len()-1].iter().copied().fold( f64::INFINITY, f64::min);
+                latest - min >= min_delta
+            }
+        }
+    }
+
+    fn corroborating_devices (&self, rule: &AlarmRule, device_id: &str, date: &DateTime<Utc>)->Vec<DeviceId> {
+        let Some(corr) = &rule.corroboration else { return Vec::new() };
+        let Some(pos) = self.history.get( device_id).and_then( |h| h.position) else { return Vec::new() };
+        let within = TimeDelta::from_std( corr.within).unwrap_or( TimeDelta::zero());
+
+        self.last_true.iter()
+            .filter( |((name,other),_)| *name == rule.name && other != device_id)
+            .filter( |(_,t)| (*date - **t).abs() <= within)
+            .filter( |((_,other),_)| {
+                self.history.get( other).and_then( |h| h.position)
+                    .map( |other_pos| distance_meters( pos, other_pos) <= corr.radius).unwrap_or(false)
+            })
+            .map( |((_,other),_)| other.clone())
+            .collect()
+    }
+}
+
+/// great circle distance between two (lat,lon) degree positions
+pub(crate) fn distance_meters (a: (f64,f64), b: (f64,f64))->f64 {
+    const EARTH_RADIUS: f64 = 6_371_000.0;
+    let (lat1, lon1) = (a.0.to_radians(), a.1.to_radians());
+    let (lat2, lon2) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat2 - lat1)/2.0).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1)/2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/* #endregion rule engine */
@@ -0,0 +1,316 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! support for recording and reading archives of Delphire data. An archive is a directory with the following contents:
+//!   - `devices.json` - the [`DeviceList`] of recorded devices (using the sentinel name as device info)
+//!   - `records.ndjson` - one [`ArchivedUpdate`] per line, in the order in which records were received
+//!   - `files/` - the (image) files referenced by records, stored under their local (odin) filename
+//!
+//! Archives are written by the [`LiveSentinelConnector`] if its [`SentinelConfig`] has a `record_dir` set, and are
+//! read by the [`ReplaySentinelConnector`]. Restarted recordings continue existing archives, i.e. an archive can contain
+//! several recording sessions with downtime gaps in between
+
+use std::{fs, path::{Path,PathBuf}, io::{BufRead,BufReader}, sync::Arc, collections::HashMap, time::Duration};
+use chrono::{DateTime,Utc,TimeDelta};
+use serde::{Serialize,Deserialize};
+use serde_json::Value;
+
+use odin_actor::warn;
+use odin_macro::match_algebraic_type;
+use odin_common::{datetime::{ser_epoch_millis,deserialize_rfc3339_or_epoch_millis,duration_since}, fs::{ensure_writable_dir,append_line_to_file}};
+
+use crate::*;
+use crate::errors::*;
+
+pub const DEVICES_FILE: &str = "devices.json";
+pub const RECORDS_FILE: &str = "records.ndjson";
+pub const FILES_DIR: &str = "files";
+
+/// the default for the longest pause between replayed records. Longer pauses are usually recorder downtime
+pub const DEFAULT_MAX_REPLAY_GAP: Duration = Duration::from_secs( 300);
+
+/// the archive representation of a single received [`SentinelUpdate`]. We keep the record itself as a generic JSON
+/// value since its type depends on the `capability` field
+#[derive(Serialize,Deserialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct ArchivedUpdate {
+    #[serde(serialize_with="ser_epoch_millis", deserialize_with="deserialize_rfc3339_or_epoch_millis")]
+    pub received: DateTime<Utc>, // when we got it from the server (replay timing is based on this, not on time_recorded)
+
+    #[serde(default)]
+    pub initial: bool, // part of the initial store retrieval
+
+    pub capability: SensorCapability,
+    pub record: Value
+}
+
+impl ArchivedUpdate {
+    pub fn new (received: DateTime<Utc>, initial: bool, update: &SentinelUpdate)->Result<Self> {
+        let capability = update.capability();
+        let record = serde_json::to_value( update)?;
+        Ok( ArchivedUpdate { received, initial, capability, record } )
+    }
+
+    /// turn this back into a [`SentinelUpdate`], shifting its time_recorded by `time_offset`.
+    /// Note that image records keep their original local filename since this is how the file is stored in the archive
+    pub fn to_update (&self, time_offset: TimeDelta)->Result<SentinelUpdate> {
+        use SensorCapability::*;
+        let v = &self.record;
+
+        match self.capability {
+            Accelerometer => to_update::<AccelerometerData>( v, time_offset),
+            Anemometer    => to_update::<AnemometerData>( v, time_offset),
+            Cloudcover    => to_update::<CloudcoverData>( v, time_offset),
+            Event         => to_update::<EventData>( v, time_offset),
+            Fire          => to_update::<FireData>( v, time_offset),
+            Gas           => to_update::<GasData>( v, time_offset),
+            Gps           => to_update::<GpsData>( v, time_offset),
+            Gyroscope     => to_update::<GyroscopeData>( v, time_offset),
+            Magnetometer  => to_update::<MagnetometerData>( v, time_offset),
+            Orientation   => to_update::<OrientationData>( v, time_offset),
+            Person        => to_update::<PersonData>( v, time_offset),
+            Power         => to_update::<PowerData>( v, time_offset),
+            Smoke         => to_update::<SmokeData>( v, time_offset),
+            Thermometer   => to_update::<ThermometerData>( v, time_offset),
+            Valve         => to_update::<ValveData>( v, time_offset),
+            Voc           => to_update::<VocData>( v, time_offset),
+
+            Image => {
+                let mut rec: SensorRecord<ImageData> = serde_json::from_value( v.clone())?;
+                rec.set_local_filename(); // before we shift the time so that it matches the archived file
+                rec.time_recorded = rec.time_recorded + time_offset;
+                Ok( SentinelUpdate::from( Arc::new(rec)) )
+            }
+        }
+    }
+}
+
+fn to_update<T> (v: &Value, time_offset: TimeDelta)->Result<SentinelUpdate>
+    where T: RecordDataBounds, SentinelUpdate: From<Arc<SensorRecord<T>>>
+{
+    let mut rec: SensorRecord<T> = serde_json::from_value( v.clone())?;
+    rec.time_recorded = rec.time_recorded + time_offset;
+    Ok( SentinelUpdate::from( Arc::new(rec)) )
+}
+
+/* #region SentinelRecorder ******************************************************************************************/
+
+/// the writer side of an archive. This only appends, i.e. recording into an existing archive dir continues it
+#[derive(Debug,Clone)]
+pub struct SentinelRecorder {
+    dir: PathBuf
+}
+
+impl SentinelRecorder {
+    pub fn new (dir: impl AsRef<Path>)->Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        ensure_writable_dir( &dir)?;
+        ensure_writable_dir( dir.join(FILES_DIR))?;
+        Ok( SentinelRecorder { dir } )
+    }
+
+    /// get the recorder for a configured `record_dir` without checking the dir. This is used for individual updates once
+    /// the recorder was created with [`SentinelRecorder::new`]
+    pub(crate) fn from_config (config: &SentinelConfig)->Option<Self> {
+        config.record_dir.as_ref().map( |dir| SentinelRecorder { dir: dir.clone() })
+    }
+
+    /// is this a new recording, i.e. we don't have any records yet
+    pub fn is_new (&self)->bool {
+        fs::metadata( self.dir.join(RECORDS_FILE)).map( |m| m.len() == 0).unwrap_or(true)
+    }
+
+    /// this is called once we have the initial store contents, which also determine the recorded devices.
+    /// The initial records are only written if this is a new recording. If we continue an existing recording (e.g. after
+    /// a restart of the connection) we only add devices we did not record before
+    pub fn record_store (&self, store: &SentinelStore)->Result<()> {
+        let path = self.dir.join(DEVICES_FILE);
+        let is_new = self.is_new();
+
+        let mut data: Vec<Device> = if is_new || !path.is_file() { Vec::new() } else {
+            serde_json::from_str::<DeviceList>( &fs::read_to_string( &path)?)?.data
+        };
+        let n_recorded = data.len();
+        for s in store.values_iter() {
+            if !data.iter().any( |d| d.id == s.device_id) {
+                data.push( Device { id: s.device_id.clone(), info: Some(s.device_name.clone()) });
+            }
+        }
+        if is_new || data.len() > n_recorded {
+            fs::write( &path, serde_json::to_string_pretty( &DeviceList{data})?)?;
+        }
+
+        if !is_new { return Ok(()) }
+
+        let now = Utc::now();
+        let mut updates: Vec<&SentinelUpdate> = store.updates_iter().collect();
+        updates.sort_by_key( |u| u.time_recorded());
+        for update in updates {
+            self.append( &ArchivedUpdate::new( now, true, update)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn record_update (&self, update: &SentinelUpdate)->Result<()> {
+        self.append( &ArchivedUpdate::new( Utc::now(), false, update)?)
+    }
+
+    /// copy a downloaded file into the archive (if we don't have it yet)
+    pub fn record_file (&self, file: &SentinelFile)->Result<()> {
+        if let Some(filename) = file.pathname.file_name() {
+            let path = self.dir.join(FILES_DIR).join(filename);
+            if !path.is_file() {
+                fs::copy( &file.pathname, path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn append (&self, archived: &ArchivedUpdate)->Result<()> {
+        let line = serde_json::to_string( archived)?;
+        Ok( append_line_to_file( self.dir.join(RECORDS_FILE), &line)? )
+    }
+}
+
+/* #endregion SentinelRecorder */
+
+/* #region SentinelArchive *******************************************************************************************/
+
+/// the reader side of an archive
+#[derive(Debug)]
+pub struct SentinelArchive {
+    pub dir: PathBuf,
+    pub devices: DeviceList,
+    pub updates: Vec<ArchivedUpdate> // ordered by received time
+}
+
+impl SentinelArchive {
+    pub fn open (dir: impl AsRef<Path>)->Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        if !dir.is_dir() { return Err( OdinSentinelError::ConfigError( format!("no archive dir {dir:?}"))) }
+
+        let devices: DeviceList = serde_json::from_str( &fs::read_to_string( dir.join(DEVICES_FILE))?)?;
+
+        let mut updates: Vec<ArchivedUpdate> = Vec::new();
+        let reader = BufReader::new( fs::File::open( dir.join(RECORDS_FILE))?);
+        for (i,line) in reader.lines().enumerate() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                match serde_json::from_str::<ArchivedUpdate>(&line) {
+                    Ok(archived) => updates.push(archived),
+                    Err(e) => warn!("ignoring malformed archive record in line {}: {}", i+1, e)
+                }
+            }
+        }
+        updates.sort_by_key( |a| a.received); // stable, so we keep the recorded order of initial records
+
+        Ok( SentinelArchive { dir, devices, updates } )
+    }
+
+    pub fn file_path (&self, filename: &str)->PathBuf {
+        self.dir.join(FILES_DIR).join(filename)
+    }
+
+    /// the received time of the first non-initial update, which is where replay starts
+    pub fn replay_start (&self)->Option<DateTime<Utc>> {
+        self.updates.iter().find( |a| !a.initial).map( |a| a.received)
+    }
+
+    /// the received time of the last update in this archive
+    pub fn replay_end (&self)->Option<DateTime<Utc>> {
+        self.updates.last().map( |a| a.received)
+    }
+
+    /// build the store we get from the initial records (if any) of this archive. Only the initial records of the first
+    /// recording session are used - older archives can contain initial records of subsequent sessions
+    pub fn initial_store (&self, max_len: usize, time_offset: TimeDelta)->Result<SentinelStore> {
+        let mut store = SentinelStore::new();
+        for device in &self.devices.data {
+            let device_name = if let Some(info) = &device.info { info.clone() } else { "?".to_string() };
+            store.insert( device.id.clone(), Sentinel::new( device.id.clone(), device_name, max_len));
+        }
+
+        for archived in self.updates.iter().take_while( |a| a.initial) {
+            store.update_with( archived.to_update( time_offset)?, max_len);
+        }
+
+        for device in &self.devices.data {
+            if let Some(sentinel) = store.get_mut( &device.id) { sentinel.set_time_recorded() }
+        }
+
+        Ok(store)
+    }
+
+    /// map of image record ids to the filenames under which their files are stored in the archive
+    pub fn image_files (&self)->HashMap<RecordId,String> {
+        let mut map = HashMap::new();
+        for archived in self.updates.iter().filter( |a| a.capability == SensorCapability::Image) {
+            if let Ok(update) = archived.to_update( TimeDelta::zero()) {
+                match_algebraic_type! { update: SentinelUpdate as
+                    Arc<SensorRecord<ImageData>> => {
+                        if let Some(filename) = &update.data.local_filename { map.insert( update.id.clone(), filename.clone()); }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        map
+    }
+
+    /// the non-initial updates we have to replay
+    pub fn replay_updates (&self)->impl Iterator<Item = &ArchivedUpdate> {
+        self.updates.iter().filter( |a| !a.initial)
+    }
+
+    /// the replay schedule of this archive. Pauses between updates are clamped to `max_gap`, and pauses at the start
+    /// of a new recording session (marked by its initial records) are skipped. Each step includes the total time
+    /// skipped so far, which has to be subtracted from the record time offset so that record times stay consistent
+    /// with replay times
+    pub fn replay_steps (&self, max_gap: Duration)->Vec<ReplayStep<'_>> {
+        let mut steps = Vec::new();
+        let mut last_received: Option<DateTime<Utc>> = None;
+        let mut skipped = TimeDelta::zero();
+        let mut is_new_session = false;
+
+        for archived in self.updates.iter().skip_while( |a| a.initial) {
+            if archived.initial { // snapshot of a later session - we already have these records
+                is_new_session = true;
+                continue
+            }
+
+            let mut delay = Duration::ZERO;
+            if let Some(last) = &last_received {
+                let gap = duration_since( &archived.received, last);
+                delay = if is_new_session { Duration::ZERO } else { gap.min( max_gap) };
+                skipped = skipped + TimeDelta::from_std( gap - delay).unwrap_or( TimeDelta::zero());
+            }
+            last_received = Some(archived.received);
+            is_new_session = false;
+
+            steps.push( ReplayStep { delay, skipped, update: archived });
+        }
+        steps
+    }
+}
+
+/// a single update of the replay schedule (see [`SentinelArchive::replay_steps`])
+#[derive(Debug)]
+pub struct ReplayStep<'a> {
+    pub delay: Duration, // since the previous step
+    pub skipped: TimeDelta, // total time of clamped or skipped pauses up to this step
+    pub update: &'a ArchivedUpdate
+}
+
+/* #endregion SentinelArchive */
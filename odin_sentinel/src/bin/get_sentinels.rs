@@ -21,8 +21,9 @@ use displaydoc::Display;
 use tokio;
 use reqwest;
 use strum::EnumString;
+use chrono::{DateTime,Utc};
 
-use odin_sentinel::{SentinelStore,SentinelConfig,load_config};
+use odin_sentinel::{SentinelStore,SentinelConfig,SensorCapability,SentinelTimeSeries,TimeSeriesQuery,load_config,sentinel_timeseries_dir};
 use odin_build;
 
 #[derive(Debug,EnumString)]
@@ -45,11 +46,37 @@ struct CliOpts {
     #[structopt(short,long,default_value="rust")]
     format: OutputFormat,
 
-    /// optional path where to store output
+    /// optional path where to store output (directory for time-series exports)
     #[structopt(short,long)]
     output: Option<PathBuf>,
 
-    //.. and more to follow
+    /// export archived time-series records instead of retrieving current data from the server
+    #[structopt(short,long)]
+    export: bool,
+
+    /// time-series archive dir to export from (default is configured timeseries_dir or <data_dir>/sentinel/timeseries)
+    #[structopt(long)]
+    timeseries_dir: Option<PathBuf>,
+
+    /// start of export time range (RFC3339, e.g. 2024-06-01T00:00:00Z)
+    #[structopt(long)]
+    start: Option<DateTime<Utc>>,
+
+    /// end of export time range (RFC3339, default is now)
+    #[structopt(long)]
+    end: Option<DateTime<Utc>>,
+
+    /// device id to export (can be repeated, default is all devices)
+    #[structopt(long="device")]
+    devices: Vec<String>,
+
+    /// capability to export, e.g. thermometer (can be repeated, default is all capabilities)
+    #[structopt(long="capability", parse(try_from_str=parse_capability))]
+    capabilities: Vec<SensorCapability>,
+}
+
+fn parse_capability (s: &str)->Result<SensorCapability> {
+    SensorCapability::capability_of(s).ok_or( anyhow::anyhow!("unknown capability {s}"))
 }
 
 lazy_static! {
@@ -61,6 +88,10 @@ async fn main()->Result<()> {
     odin_build::set_bin_context!();
 
     let sentinel_config: SentinelConfig = load_config( "sentinel.ron")?;
+    if ARGS.export {
+        return export_timeseries( &sentinel_config)
+    }
+
     let http_client = reqwest::Client::new();
 
     let mut sentinel_store = SentinelStore::new();
@@ -85,6 +116,29 @@ async fn main()->Result<()> {
     Ok(())
 }
 
+fn export_timeseries (config: &SentinelConfig)->Result<()> {
+    let dir = ARGS.timeseries_dir.clone().or( config.timeseries_dir.clone()).unwrap_or_else( sentinel_timeseries_dir);
+    let timeseries = SentinelTimeSeries::open( &dir)?;
+
+    let start = ARGS.start.ok_or( anyhow::anyhow!("export requires --start"))?;
+    let end = ARGS.end.unwrap_or_else( Utc::now);
+    let query = TimeSeriesQuery::new( start, end)
+        .with_devices( ARGS.devices.clone())
+        .with_capabilities( ARGS.capabilities.clone());
+
+    if let Some(output) = &ARGS.output {
+        for (path,n) in timeseries.export_to_dir( &query, output)? {
+            if ARGS.verbose { println!("exported {n} records to {path:?}") }
+        }
+    } else if ARGS.capabilities.len() == 1 { // single capability can go to stdout
+        let n = timeseries.export( ARGS.capabilities[0], &query, &mut std::io::stdout())?;
+        if ARGS.verbose { eprintln!("exported {n} records") }
+    } else {
+        return Err( anyhow::anyhow!("export of multiple capabilities requires --output dir"))
+    }
+    Ok(())
+}
+
 fn produce_output (s: String)->Result<()> {
     if let Some(path) = &ARGS.output {
         let mut file = File::create(path)?;
@@ -0,0 +1,88 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::{path::PathBuf, net::SocketAddr, time::Duration};
+use anyhow::Result;
+use structopt::StructOpt;
+use tokio;
+
+use odin_sentinel::{MockDelphireServer, MockDelphireConfig, MockFaults};
+use odin_build;
+
+#[derive(StructOpt)]
+#[structopt(about = "local Delphire-compatible mock server that serves recorded Sentinel archives")]
+struct CliOpts {
+    /// address to listen on
+    #[structopt(long,default_value="127.0.0.1:9099")]
+    addr: SocketAddr,
+
+    /// bearer token clients have to use
+    #[structopt(long,default_value="mock-token")]
+    access_token: String,
+
+    /// release recorded records with their original inter-arrival timing
+    #[structopt(long)]
+    autoplay: bool,
+
+    /// autoplay speedup factor
+    #[structopt(long,default_value="1")]
+    timescale: u32,
+
+    /// delay in milliseconds for each http response
+    #[structopt(long)]
+    response_delay: Option<u64>,
+
+    /// http path prefix for which to return malformed JSON (can be repeated)
+    #[structopt(long)]
+    malformed_path: Vec<String>,
+
+    /// number of websocket connection attempts to reject
+    #[structopt(long,default_value="0")]
+    reject_ws: usize,
+
+    /// drop websocket connections after sending N record notifications
+    #[structopt(long)]
+    drop_socket_after: Option<usize>,
+
+    /// fixture (recorded archive) directory
+    fixture_dir: PathBuf,
+}
+
+#[tokio::main]
+async fn main()->Result<()> {
+    odin_build::set_bin_context!();
+    let opts = CliOpts::from_args();
+
+    let faults = MockFaults {
+        response_delay: opts.response_delay.map( Duration::from_millis),
+        malformed_paths: opts.malformed_path,
+        reject_ws: opts.reject_ws,
+        drop_socket_after: opts.drop_socket_after
+    };
+    let config = MockDelphireConfig {
+        addr: opts.addr,
+        fixture_dir: opts.fixture_dir,
+        access_token: opts.access_token,
+        autoplay: opts.autoplay,
+        timescale: opts.timescale,
+        faults
+    };
+
+    let server = MockDelphireServer::start( config).await?;
+    println!("mock Delphire server running on {} (websocket {}), terminate with ctrl-C", server.base_uri(), server.ws_uri());
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
@@ -19,7 +19,7 @@ use odin_build::define_load_config;
 use odin_actor::prelude::*;
 use odin_common::{define_cli,check_cli, admin, heap};
 use odin_sentinel::{
-    load_config, AlarmMessenger, ConsoleAlarmMessenger, LiveSentinelConnector, SentinelActor, SentinelAlarmMonitor, SentinelAlarmMonitorConfig, SentinelAlarmMonitorMsg, SentinelInactiveAlert, SentinelUpdate, SignalCmdAlarmMessenger, SlackAlarmMessenger, SmtpAlarmMessenger
+    load_config, config_path, AlarmMessenger, ConsoleAlarmMessenger, LiveSentinelConnector, SentinelActor, SentinelAlarmMonitor, SentinelAlarmMonitorConfig, SentinelAlarmMonitorMsg, SentinelInactiveAlert, SentinelUpdate, SignalCmdAlarmMessenger, SlackAlarmMessenger, SmtpAlarmMessenger, WebhookAlarmMessenger
 };
 
 #[cfg(feature="dhat")] heap::use_dhat!{} 
@@ -31,7 +31,10 @@ define_cli! { ARGS [about="Delphire Sentinel Alarm Server"] =
     slack: bool       [help="enable slack messenger", long],
     smtp: bool        [help="enable smtp messenger", long],
     signal_cli: bool  [help="enable signal-cli messenger (requires signal-cli installation)", long],
-    console: bool     [help="enable console messenger",long]
+    webhook: bool     [help="enable http webhook messenger", long],
+    mqtt: bool        [help="enable MQTT messenger (requires 'mqtt' feature)", long],
+    console: bool     [help="enable console messenger",long],
+    watch: bool       [help="apply modifications of sentinel_alarm.ron at runtime (ignores threshold overrides)", long]
 }
 
 #[tokio::main]
@@ -54,6 +57,12 @@ async fn main ()->Result<()> {
         create_messengers()?
     ))?;
 
+    if ARGS.watch {
+        let mut watcher = ResourceWatcher::new( secs(2));
+        watcher.watch_validated_config::<SentinelAlarmMonitorConfig,_,_>( "sentinel_alarm.ron", config_path, hmonitor.clone(), SentinelAlarmMonitorConfig::check);
+        spawn_actor!( actor_system, "watcher", watcher)?;
+    }
+
     let hsentinel = spawn_pre_actor!( actor_system, hsentinel, SentinelActor::new(
         LiveSentinelConnector::new( load_config( "sentinel.ron")?), 
         no_dataref_action(),
@@ -91,6 +100,16 @@ fn create_messengers()->Result<Vec<Box<dyn AlarmMessenger>>> {
     if ARGS.signal_cli { 
         messengers.push( Box::new( SignalCmdAlarmMessenger::new( load_config("signal_cmd.ron")?))) 
     }
+    if ARGS.webhook {
+        messengers.push( Box::new( WebhookAlarmMessenger::new( load_config("webhook.ron")?)))
+    }
+    if ARGS.mqtt {
+        #[cfg(feature="mqtt")]
+        messengers.push( Box::new( odin_sentinel::MqttAlarmMessenger::new( load_config("mqtt.ron")?)));
+
+        #[cfg(not(feature="mqtt"))]
+        warn!("ignoring --mqtt option, odin_sentinel was built without 'mqtt' feature");
+    }
 
     Ok(messengers)
 }
\ No newline at end of file
@@ -58,7 +58,7 @@ async fn main()->Result<()> {
         evidence_info.push(ei);
     }
 
-    let alarm = Alarm { device_id, description, time_recorded, pos, alarm_type, confidence, evidence_info };
+    let alarm = Alarm { id: "test".to_string(), record_id: String::new(), no: 0, tier: 0, contacts: Vec::new(), device_id, description, time_recorded, pos, alarm_type, confidence, evidence_info };
 
     let messengers = create_messengers()?;
     
@@ -0,0 +1,443 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! scheduling, batching and acknowledgement tracking for Sentinel device commands. Commands are either sent on demand
+//! (`SendSentinelCmd` messages to the [`crate::SentinelActor`]) or by [`ScheduledCmd`]s, which are part of the `commands`
+//! field of the [`SentinelConfig`] or added at runtime, e.g.
+//! ```ron
+//! commands: SentinelCmdConfig(
+//!     schedule: [
+//!         ScheduledCmd(
+//!             name: "red-flag-lights",
+//!             action: SwitchLights( light_type: "infrared", state: "on"),
+//!             targets: CmdTarget( area: [(37.5,-122.4), (37.5,-121.9), (37.2,-121.9), (37.2,-122.4)]),
+//!             interval: Some( (secs: 300, nanos: 0)),
+//!             flag: Some("red_flag"),
+//!         ),
+//!     ],
+//! )
+//! ```
+//! Scheduled commands are only executed while their (optional) `hours` window is open and their (optional) condition
+//! `flag` is set (see `SetSentinelCmdFlag`). Targets are resolved to devices (by id prefix and/or position within an
+//! area polygon) each time a command is due, and the resulting devices are batched into commands of at most
+//! `batch_size` devices. Commands for which the server sends responses (such as `trigger-alert`) are tracked per device
+//! and re-sent to devices that did not acknowledge within `ack_timeout`, up to `max_retries` times. Server error messages
+//! fail the pending command they refer to (or the oldest pending command) right away.
+//!
+//! This module does not depend on actors or connectors so that command logic can be tested in isolation.
+
+use std::{collections::{HashSet,VecDeque}, time::Duration};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Serialize,Deserialize,Deserializer,de};
+
+use odin_common::datetime::{duration_since,ser_epoch_millis,ser_epoch_millis_option};
+use crate::{get_next_msg_id, DeviceId, TimeWindow, ws::{WsCmd,WsMsg}};
+
+/* #region config ***************************************************************************************************/
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+#[serde(default)]
+pub struct SentinelCmdConfig {
+    pub schedule: Vec<ScheduledCmd>,
+    #[serde(deserialize_with="deserialize_check_interval")]
+    pub check_interval: Duration, // how often we check for due commands and acknowledgement timeouts
+    pub ack_timeout: Duration,    // after which we re-send commands to devices that did not respond
+    pub max_retries: u32,         // number of re-sends before a command is considered to have failed
+    pub batch_size: usize,        // max number of devices per command message
+    pub max_history: usize,       // number of finished commands we keep for display
+}
+
+impl Default for SentinelCmdConfig {
+    fn default()->Self {
+        SentinelCmdConfig {
+            schedule: Vec::new(),
+            check_interval: Duration::from_secs(5),
+            ack_timeout: Duration::from_secs(30),
+            max_retries: 3,
+            batch_size: 10,
+            max_history: 50,
+        }
+    }
+}
+
+// this is used as a repeat timer interval, i.e. we have to reject zero durations when loading the config
+fn deserialize_check_interval<'de,D> (deserializer: D)->std::result::Result<Duration,D::Error> where D: Deserializer<'de> {
+    let interval = Duration::deserialize( deserializer)?;
+    if interval.is_zero() { Err( de::Error::custom("check_interval has to be > 0")) } else { Ok(interval) }
+}
+
+/// the device independent part of a [`WsCmd`]
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub enum CmdAction {
+    TriggerAlert,
+    SwitchLights { light_type: String, state: String },
+    SwitchValve { state: String },
+}
+
+impl CmdAction {
+    /// Ping is not a device command (it goes to the server and has no device ids), i.e. it cannot be queued
+    pub fn from_ws_cmd (cmd: &WsCmd)->std::result::Result<Self,String> {
+        match cmd {
+            WsCmd::Ping{..} => Err( "ping is not a device command".to_string()),
+            WsCmd::TriggerAlert{..} => Ok( CmdAction::TriggerAlert),
+            WsCmd::SwitchLights{ light_type, state, .. } => Ok( CmdAction::SwitchLights{ light_type: light_type.clone(), state: state.clone() }),
+            WsCmd::SwitchValve{ state, .. } => Ok( CmdAction::SwitchValve{ state: state.clone() }),
+        }
+    }
+
+    pub fn to_ws_cmd (&self, device_ids: Vec<DeviceId>, message_id: String)->WsCmd {
+        match self {
+            CmdAction::TriggerAlert => WsCmd::TriggerAlert { device_ids, message_id },
+            CmdAction::SwitchLights{ light_type, state } => WsCmd::SwitchLights { device_ids, light_type: light_type.clone(), state: state.clone(), message_id },
+            CmdAction::SwitchValve{ state } => WsCmd::SwitchValve { device_ids, state: state.clone(), message_id },
+        }
+    }
+
+    pub fn description (&self)->String {
+        match self {
+            CmdAction::TriggerAlert => "trigger-alert".to_string(),
+            CmdAction::SwitchLights{ light_type, state } => format!("switch-lights {light_type} {state}"),
+            CmdAction::SwitchValve{ state } => format!("switch-valve {state}"),
+        }
+    }
+
+    fn expects_response (&self)->bool {
+        self.to_ws_cmd( Vec::new(), String::new()).expects_response()
+    }
+}
+
+/// the devices a scheduled command is sent to. Empty fields don't restrict the target set
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+#[serde(default)]
+pub struct CmdTarget {
+    pub devices: Vec<String>,  // device id prefixes
+    pub area: Vec<(f64,f64)>,  // (lat,lon) degree vertices of a polygon the device has to be in
+}
+
+impl CmdTarget {
+    pub fn matches (&self, device_id: &str, pos: Option<(f64,f64)>)->bool {
+        (self.devices.is_empty() || self.devices.iter().any( |p| p == "*" || device_id.starts_with( p.as_str())))
+        && (self.area.is_empty() || pos.map( |p| is_inside( p, &self.area)).unwrap_or(false))
+    }
+}
+
+/// ray casting point-in-polygon test for (lat,lon) positions. Polygons are assumed to be small enough to ignore curvature
+pub fn is_inside (p: (f64,f64), polygon: &[(f64,f64)])->bool {
+    let (y,x) = p;
+    let n = polygon.len();
+    let mut inside = false;
+    let mut j = n.wrapping_sub(1);
+    for i in 0..n {
+        let (yi,xi) = polygon[i];
+        let (yj,xj) = polygon[j];
+        if ((yi > y) != (yj > y)) && (x < (xj - xi) * (y - yi) / (yj - yi) + xi) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct ScheduledCmd {
+    pub name: String,
+    pub action: CmdAction,
+    #[serde(default)] pub targets: CmdTarget,
+    #[serde(default)] pub interval: Option<Duration>, // None means run once
+    #[serde(default)] pub hours: Option<TimeWindow>,  // only run within this time-of-day window
+    #[serde(default)] pub flag: Option<String>,       // only run while this condition flag is set
+}
+
+impl ScheduledCmd {
+    fn is_enabled (&self, now: &DateTime<Utc>, flags: &HashSet<String>)->bool {
+        self.hours.as_ref().map( |w| w.contains( now)).unwrap_or(true)
+        && self.flag.as_ref().map( |f| flags.contains( f)).unwrap_or(true)
+    }
+}
+
+/* #endregion config */
+
+/* #region command status *******************************************************************************************/
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub enum CmdState {
+    Queued,       // not sent yet (or waiting for re-send after a failed send)
+    Pending,      // sent, waiting for device responses
+    Sent,         // sent, no response expected
+    Acknowledged, // all devices responded with success
+    Failed,       // retries exhausted
+    Cancelled
+}
+
+impl CmdState {
+    pub fn is_finished (&self)->bool {
+        matches!( self, CmdState::Sent | CmdState::Acknowledged | CmdState::Failed | CmdState::Cancelled)
+    }
+}
+
+/// the tracked state of a single command message. This is what we show in the command queue
+#[derive(Serialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct SentinelCmdStatus {
+    pub id: String, // the message_id
+    pub cmd: String,
+    #[serde(skip)] pub action: CmdAction,
+    pub schedule: Option<String>,
+    pub device_ids: Vec<DeviceId>,
+    pub state: CmdState,
+    pub attempts: u32,
+    #[serde(serialize_with="ser_epoch_millis")] pub created: DateTime<Utc>,
+    #[serde(serialize_with="ser_epoch_millis_option")] pub last_sent: Option<DateTime<Utc>>,
+    pub acknowledged: Vec<DeviceId>,
+    pub errors: Vec<String>,
+}
+
+impl SentinelCmdStatus {
+    fn new (action: CmdAction, device_ids: Vec<DeviceId>, schedule: Option<String>, now: DateTime<Utc>)->Self {
+        SentinelCmdStatus {
+            id: format!("odin-cmd-{}", get_next_msg_id()),
+            cmd: action.description(),
+            action, schedule, device_ids,
+            state: CmdState::Queued,
+            attempts: 0,
+            created: now,
+            last_sent: None,
+            acknowledged: Vec::new(),
+            errors: Vec::new()
+        }
+    }
+
+    pub fn unacknowledged (&self)->Vec<DeviceId> {
+        self.device_ids.iter().filter( |id| !self.acknowledged.contains( id)).cloned().collect()
+    }
+
+    fn ws_cmd (&self)->WsCmd {
+        self.action.to_ws_cmd( self.unacknowledged(), self.id.clone())
+    }
+}
+
+/* #endregion command status */
+
+/* #region command manager ******************************************************************************************/
+
+struct ScheduleEntry {
+    cmd: ScheduledCmd,
+    next_due: Option<DateTime<Utc>> // None means due right away
+}
+
+pub struct SentinelCmdManager {
+    config: SentinelCmdConfig,
+    schedules: Vec<ScheduleEntry>,
+    flags: HashSet<String>,
+    queue: VecDeque<SentinelCmdStatus>, // oldest first
+    changed: bool, // since last take_changed()
+}
+
+impl SentinelCmdManager {
+    pub fn new (config: SentinelCmdConfig)->Self {
+        let schedules = config.schedule.iter().map( |cmd| ScheduleEntry { cmd: cmd.clone(), next_due: None }).collect();
+        SentinelCmdManager { config, schedules, flags: HashSet::new(), queue: VecDeque::new(), changed: false }
+    }
+
+    pub fn config (&self)->&SentinelCmdConfig { &self.config }
+
+    /// add a schedule (replacing one with the same name)
+    pub fn schedule (&mut self, cmd: ScheduledCmd) {
+        self.schedules.retain( |e| e.cmd.name != cmd.name);
+        self.schedules.push( ScheduleEntry { cmd, next_due: None });
+    }
+
+    /// remove schedule `name` and cancel all its commands that are not finished yet. Returns false if there was no such schedule
+    pub fn cancel (&mut self, name: &str)->bool {
+        let n = self.schedules.len();
+        self.schedules.retain( |e| e.cmd.name != name);
+
+        for s in self.queue.iter_mut() {
+            if s.schedule.as_deref() == Some(name) && !s.state.is_finished() {
+                s.state = CmdState::Cancelled;
+                self.changed = true;
+            }
+        }
+        self.schedules.len() < n
+    }
+
+    pub fn scheduled_cmds (&self)->impl Iterator<Item=&ScheduledCmd> {
+        self.schedules.iter().map( |e| &e.cmd)
+    }
+
+    pub fn set_flag (&mut self, name: &str, active: bool) {
+        if active { self.flags.insert( name.to_string()); } else { self.flags.remove( name); }
+    }
+
+    pub fn has_flag (&self, name: &str)->bool {
+        self.flags.contains( name)
+    }
+
+    /// queue commands for `device_ids` in batches. Returns the command ids
+    pub fn enqueue (&mut self, action: CmdAction, device_ids: Vec<DeviceId>, schedule: Option<String>, now: DateTime<Utc>)->Vec<String> {
+        let mut ids = Vec::new();
+        for batch in device_ids.chunks( self.config.batch_size.max(1)) {
+            let status = SentinelCmdStatus::new( action.clone(), batch.to_vec(), schedule.clone(), now);
+            ids.push( status.id.clone());
+            self.queue.push_back( status);
+            self.changed = true;
+        }
+        ids
+    }
+
+    /// queue an explicit (on-demand) command. Note that we use our own message ids
+    pub fn enqueue_ws_cmd (&mut self, cmd: &WsCmd, now: DateTime<Utc>)->std::result::Result<Vec<String>,String> {
+        let action = CmdAction::from_ws_cmd( cmd)?;
+        Ok( self.enqueue( action, cmd.device_ids().to_vec(), None, now) )
+    }
+
+    /// queue commands for all enabled schedules that are due. `devices` are the known devices with their last positions.
+    /// Returns the number of queued commands
+    pub fn run_schedules (&mut self, now: DateTime<Utc>, devices: &[(DeviceId,Option<(f64,f64)>)])->usize {
+        let mut due: Vec<(CmdAction,Vec<DeviceId>,String)> = Vec::new();
+        let flags = &self.flags;
+
+        self.schedules.retain_mut( |e| {
+            if e.next_due.map( |t| t <= now).unwrap_or(true) && e.cmd.is_enabled( &now, flags) {
+                let device_ids: Vec<DeviceId> = devices.iter()
+                    .filter( |(id,pos)| e.cmd.targets.matches( id, *pos))
+                    .map( |(id,_)| id.clone())
+                    .collect();
+                if !device_ids.is_empty() {
+                    due.push( (e.cmd.action.clone(), device_ids, e.cmd.name.clone()));
+                }
+
+                if let Some(interval) = e.cmd.interval {
+                    e.next_due = TimeDelta::from_std( interval).ok().and_then( |d| now.checked_add_signed( d)); // None if out of range
+                    e.next_due.is_some()
+                } else {
+                    false // run-once schedule is done
+                }
+            } else {
+                true
+            }
+        });
+
+        let mut n = 0;
+        for (action,device_ids,name) in due {
+            n += self.enqueue( action, device_ids, Some(name), now).len();
+        }
+        n
+    }
+
+    /// the websocket commands we have to send now. This includes new commands and re-sends of commands that were not
+    /// acknowledged within `ack_timeout`. Commands that exceeded `max_retries` are marked as failed
+    pub fn next_messages (&mut self, now: DateTime<Utc>)->Vec<WsCmd> {
+        let ack_timeout = self.config.ack_timeout;
+        let max_attempts = self.config.max_retries + 1;
+        let mut msgs = Vec::new();
+
+        for s in self.queue.iter_mut() {
+            let is_due = match s.state {
+                CmdState::Queued => true,
+                CmdState::Pending => s.last_sent.map( |t| duration_since( &now, &t) >= ack_timeout).unwrap_or(true),
+                _ => false
+            };
+
+            if is_due {
+                if s.attempts >= max_attempts {
+                    s.state = CmdState::Failed;
+                    s.errors.push( format!("no response from {:?}", s.unacknowledged()));
+                } else {
+                    s.attempts += 1;
+                    s.last_sent = Some(now);
+                    s.state = if s.action.expects_response() { CmdState::Pending } else { CmdState::Sent };
+                    msgs.push( s.ws_cmd());
+                }
+                self.changed = true;
+            }
+        }
+
+        self.purge();
+        msgs
+    }
+
+    /// the connector could not send command `id`
+    pub fn send_failed (&mut self, id: &str, error: String) {
+        let max_attempts = self.config.max_retries + 1;
+        if let Some(s) = self.queue.iter_mut().find( |s| s.id == id) {
+            s.state = if s.attempts >= max_attempts { CmdState::Failed } else { CmdState::Queued };
+            s.errors.push( error);
+            self.changed = true;
+        }
+    }
+
+    /// process a websocket message from the server. Returns true if this was a response to one of our commands.
+    /// Server errors fail the pending command they refer to, or the oldest pending command if they don't contain an id
+    pub fn handle_response (&mut self, msg: &WsMsg)->bool {
+        if let WsMsg::Error { message } = msg {
+            let refers_to = |s: &SentinelCmdStatus| message.split( |c: char| !(c.is_alphanumeric() || c == '-')).any( |t| t == s.id);
+            let idx = self.queue.iter().position( |s| s.state == CmdState::Pending && refers_to( s))
+                .or_else( || self.queue.iter().position( |s| s.state == CmdState::Pending));
+            if let Some(s) = idx.and_then( |i| self.queue.get_mut( i)) {
+                s.state = CmdState::Failed;
+                s.errors.push( format!("server error: {message}"));
+                self.changed = true;
+                return true
+            }
+            return false
+        }
+
+        if let WsMsg::TriggerAlert { device_id, message_id, result } = msg {
+            if let Some(s) = self.queue.iter_mut().find( |s| &s.id == message_id) {
+                if s.state == CmdState::Cancelled { return true }
+
+                if result == "success" {
+                    if !s.acknowledged.contains( device_id) { s.acknowledged.push( device_id.clone()) }
+                    if s.unacknowledged().is_empty() { s.state = CmdState::Acknowledged }
+                } else {
+                    s.errors.push( format!("{device_id}: {result}"));
+                }
+                self.changed = true;
+                return true
+            }
+        }
+        false
+    }
+
+    /// all tracked commands, most recent first
+    pub fn status (&self)->Vec<SentinelCmdStatus> {
+        self.queue.iter().rev().cloned().collect()
+    }
+
+    pub fn get (&self, id: &str)->Option<&SentinelCmdStatus> {
+        self.queue.iter().find( |s| s.id == id)
+    }
+
+    /// did the command queue change since the last call
+    pub fn take_changed (&mut self)->bool {
+        std::mem::take( &mut self.changed)
+    }
+
+    // drop the oldest finished commands that exceed max_history
+    fn purge (&mut self) {
+        let mut n_finished = self.queue.iter().filter( |s| s.state.is_finished()).count();
+        while n_finished > self.config.max_history {
+            if let Some(idx) = self.queue.iter().position( |s| s.state.is_finished()) {
+                self.queue.remove( idx);
+                n_finished -= 1;
+            } else {
+                break
+            }
+        }
+    }
+}
+
+/* #endregion command manager */
@@ -84,6 +84,12 @@ pub enum OdinSentinelError {
     #[error("slack error {0}")]
     SlackError(String),
 
+    #[error("webhook error {0}")]
+    WebhookError(String),
+
+    #[error("MQTT error {0}")]
+    MqttError(String),
+
     #[error("timeout error {0}")]
     TimeoutError(String),
 
@@ -116,6 +122,9 @@ map_to_opaque_error!{ lettre::transport::smtp::Error => OdinSentinelError::SmtpE
 #[cfg(feature="signal_rpc")]
 map_to_opaque_error!{ jsonrpsee::core::client::Error => OdinSentinelError::RpcError }
 
+#[cfg(feature="mqtt")]
+map_to_opaque_error!{ rumqttc::ClientError => OdinSentinelError::MqttError }
+
 
 pub fn no_data (msg: impl ToString)->OdinSentinelError {
     OdinSentinelError::NoDataError(msg.to_string())
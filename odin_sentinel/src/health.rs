@@ -0,0 +1,393 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! per-device health analysis based on the records we keep in the [`crate::SentinelStore`]. While the `SentinelActor`
+//! only reports devices that did not send any update within the configured `inactive_duration`, this module looks at
+//! trends that usually precede a device going dark:
+//!   - battery state of charge, its trend and whether we see solar charging (from `Power` records)
+//!   - reporting cadence - late reports and missed reporting intervals (from periodic housekeeping records)
+//!   - GPS position drift (from `Gps` records)
+//!   - image capture failures - detections without stored image evidence and image capture stalls
+//!
+//! Each finding is reported as a [`HealthWarning`] and contributes to a 0..100 health score. Thresholds are
+//! configured with the `health` field of the [`crate::SentinelConfig`], e.g.
+//! ```ron
+//! health: SentinelHealthConfig(
+//!     low_soc: 30.0,
+//!     battery_horizon: (secs: 86400, nanos: 0),
+//!     gps_drift: 25.0,
+//! )
+//! ```
+//! The analysis is a pure function of the stored records and the current time so that it can be tested in isolation.
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+use chrono::{DateTime, Utc};
+use serde::{Serialize,Deserialize};
+use uom::si::{electric_current::ampere, electric_potential::volt};
+
+use odin_common::datetime::{duration_since, ser_epoch_millis, ser_epoch_millis_option};
+use crate::{distance_meters, DeviceId, GpsData, ImageData, PowerData, RecordDataBounds, SensorRecord, Sentinel};
+
+/* #region config ***************************************************************************************************/
+
+/// thresholds for device health analysis
+#[derive(Serialize,Deserialize,Debug,Clone)]
+#[serde(default)]
+pub struct SentinelHealthConfig {
+    pub low_soc: f64,               // battery state of charge [%] below which we warn
+    pub critical_soc: f64,          // battery state of charge [%] below which the device is about to shut down
+    pub battery_horizon: Duration,  // warn if the battery is projected to be empty within this duration
+    pub min_solar_current: f64,     // [A] above which we consider the battery to be charging
+    pub solar_window: Duration,     // warn if power records cover at least this duration without any solar charge
+    pub cadence_factor: f64,        // warn if a reporting gap exceeds this multiple of the median reporting interval
+    pub gps_drift: f64,             // [m] distance from the median position above which we warn
+    pub max_image_failures: usize,  // number of detections without stored image evidence we tolerate
+    pub image_stall: Duration,      // warn if we did not get images for this long while other records still come in
+}
+
+impl Default for SentinelHealthConfig {
+    fn default()->Self {
+        SentinelHealthConfig {
+            low_soc: 25.0,
+            critical_soc: 10.0,
+            battery_horizon: Duration::from_secs( 60*60*12),
+            min_solar_current: 0.05,
+            solar_window: Duration::from_secs( 60*60*24),
+            cadence_factor: 3.0,
+            gps_drift: 50.0,
+            max_image_failures: 1,
+            image_stall: Duration::from_secs( 60*60*6),
+        }
+    }
+}
+
+/* #endregion config */
+
+/* #region health report ********************************************************************************************/
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub enum HealthSeverity { Info, Warning, Critical }
+
+impl HealthSeverity {
+    /// how many points this reduces the health score
+    pub fn penalty (&self)->u32 {
+        match self {
+            HealthSeverity::Info => 5,
+            HealthSeverity::Warning => 20,
+            HealthSeverity::Critical => 50,
+        }
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub enum HealthIssue { LowBattery, BatteryDepletion, NoSolarCharge, LateReport, MissedReports, GpsDrift, ImageFailures, ImageStall }
+
+#[derive(Serialize,Debug,Clone,PartialEq)]
+pub struct HealthWarning {
+    pub issue: HealthIssue,
+    pub severity: HealthSeverity,
+    pub message: String,
+}
+
+impl HealthWarning {
+    fn new (issue: HealthIssue, severity: HealthSeverity, message: String)->Self {
+        HealthWarning { issue, severity, message }
+    }
+}
+
+#[derive(Serialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct BatteryHealth {
+    pub soc: f64,                      // latest state of charge [%]
+    pub voltage: f64,                  // latest battery voltage [V]
+    pub soc_rate: Option<f64>,         // state of charge trend [%/h] (negative means discharging)
+    pub hours_to_empty: Option<f64>,   // projected from soc_rate if discharging
+    pub solar_power: f64,              // latest solar charge power [W]
+    pub is_charging: bool,
+    #[serde(serialize_with="ser_epoch_millis_option")]
+    pub last_charge: Option<DateTime<Utc>>, // latest record that showed solar charging
+}
+
+#[derive(Serialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct ReportCadence {
+    pub median_interval: Option<u64>, // [ms] typical time between periodic records
+    pub current_gap: Option<u64>,     // [ms] since the last record of any type
+    pub missed_reports: usize,        // number of intervals in our history that exceeded the cadence threshold
+}
+
+#[derive(Serialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct GpsHealth {
+    pub fixes: usize,
+    pub drift: f64,     // [m] distance of latest fix from median position
+    pub max_drift: f64, // [m] max distance of any stored fix from median position
+    pub hdop: Option<f32>,
+}
+
+#[derive(Serialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct ImageHealth {
+    pub images: usize,
+    pub failures: usize, // detections for which we don't have any image evidence
+    #[serde(serialize_with="ser_epoch_millis_option")]
+    pub last_image: Option<DateTime<Utc>>,
+}
+
+/// the health state of a single device at a given time
+#[derive(Serialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct SentinelHealth {
+    pub device_id: DeviceId,
+    #[serde(serialize_with="ser_epoch_millis")]
+    pub time_computed: DateTime<Utc>,
+    pub score: u32, // 0 (dark) .. 100 (no issues)
+    pub battery: Option<BatteryHealth>,
+    pub cadence: ReportCadence,
+    pub gps: Option<GpsHealth>,
+    pub images: ImageHealth,
+    pub warnings: Vec<HealthWarning>,
+}
+
+impl SentinelHealth {
+    pub fn is_healthy (&self)->bool {
+        self.warnings.iter().all( |w| w.severity == HealthSeverity::Info)
+    }
+
+    pub fn max_severity (&self)->Option<HealthSeverity> {
+        self.warnings.iter().map( |w| w.severity).max()
+    }
+
+    pub fn has_issue (&self, issue: HealthIssue)->bool {
+        self.warnings.iter().any( |w| w.issue == issue)
+    }
+}
+
+/* #endregion health report */
+
+/* #region analysis *************************************************************************************************/
+
+impl Sentinel {
+    /// analyze the stored records of this device. Records are stored newest first
+    pub fn health (&self, config: &SentinelHealthConfig, now: DateTime<Utc>)->SentinelHealth {
+        let mut warnings: Vec<HealthWarning> = Vec::new();
+
+        let battery = battery_health( &self.power, config, &mut warnings);
+        let cadence = report_cadence( self, config, now, &mut warnings);
+        let gps = gps_health( &self.gps, config, &mut warnings);
+        let images = image_health( self, config, &mut warnings);
+
+        let penalty: u32 = warnings.iter().map( |w| w.severity.penalty()).sum();
+        let score = 100u32.saturating_sub( penalty);
+
+        SentinelHealth { device_id: self.device_id.clone(), time_computed: now, score, battery, cadence, gps, images, warnings }
+    }
+
+    /// the time of the newest record we have for this device (of any capability)
+    pub fn latest_record_time (&self)->Option<DateTime<Utc>> {
+        [ newest( &self.accelerometer), newest( &self.anemometer), newest( &self.cloudcover), newest( &self.event),
+          newest( &self.fire), newest( &self.gas), newest( &self.gps), newest( &self.gyro), newest( &self.image),
+          newest( &self.mag), newest( &self.orientation), newest( &self.person), newest( &self.power),
+          newest( &self.smoke), newest( &self.thermometer), newest( &self.valve), newest( &self.voc)
+        ].into_iter().flatten().max()
+    }
+}
+
+fn newest<T> (recs: &VecDeque<Arc<SensorRecord<T>>>)->Option<DateTime<Utc>> where T: RecordDataBounds {
+    recs.iter().map( |r| r.time_recorded).max()
+}
+
+fn hours (dur: Duration)->f64 { dur.as_secs_f64() / 3600.0 }
+
+fn battery_health (recs: &VecDeque<Arc<SensorRecord<PowerData>>>, config: &SentinelHealthConfig, warnings: &mut Vec<HealthWarning>)->Option<BatteryHealth> {
+    use HealthIssue::*;
+    use HealthSeverity::*;
+
+    let latest = recs.front()?;
+    let soc = latest.data.soc;
+    let voltage = latest.data.battery_voltage.get::<volt>();
+    let solar_current = latest.data.solar_current.get::<ampere>();
+    let solar_power = latest.data.solar_voltage.get::<volt>() * solar_current;
+    let is_charging = solar_current >= config.min_solar_current;
+    let last_charge = recs.iter().find( |r| r.data.solar_current.get::<ampere>() >= config.min_solar_current).map( |r| r.time_recorded);
+
+    // least squares fit of soc over time
+    let soc_rate = if recs.len() >= 3 {
+        let t0 = latest.time_recorded;
+        let pts: Vec<(f64,f64)> = recs.iter().map( |r| ((r.time_recorded - t0).num_milliseconds() as f64 / 3_600_000.0, r.data.soc)).collect();
+        linear_slope( &pts)
+    } else {
+        None
+    };
+    let hours_to_empty = soc_rate.filter( |rate| *rate < 0.0).map( |rate| soc / -rate);
+
+    if soc < config.critical_soc {
+        warnings.push( HealthWarning::new( LowBattery, Critical, format!("battery charge critical: {soc:.0}%")));
+    } else if soc < config.low_soc {
+        warnings.push( HealthWarning::new( LowBattery, Warning, format!("battery charge low: {soc:.0}%")));
+    }
+
+    if let Some(h) = hours_to_empty {
+        if !is_charging && h < hours( config.battery_horizon) {
+            warnings.push( HealthWarning::new( BatteryDepletion, Warning, format!("battery projected to be empty in {h:.1}h")));
+        }
+    }
+
+    if last_charge.is_none() {
+        if let Some(oldest) = recs.back() {
+            let covered = duration_since( &latest.time_recorded, &oldest.time_recorded);
+            if covered >= config.solar_window {
+                warnings.push( HealthWarning::new( NoSolarCharge, Warning, format!("no solar charge for {:.0}h", hours(covered))));
+            }
+        }
+    }
+
+    Some( BatteryHealth { soc, voltage, soc_rate, hours_to_empty, solar_power, is_charging, last_charge } )
+}
+
+// the periodic housekeeping records we use to determine reporting cadence. Detection and image records are event driven
+fn periodic_intervals (sentinel: &Sentinel)->Vec<Duration> {
+    fn add_intervals<T> (recs: &VecDeque<Arc<SensorRecord<T>>>, intervals: &mut Vec<Duration>) where T: RecordDataBounds {
+        let mut sensors: Vec<u32> = recs.iter().map( |r| r.sensor_no).collect();
+        sensors.sort();
+        sensors.dedup();
+        for sensor_no in sensors {
+            let times: Vec<DateTime<Utc>> = recs.iter().filter( |r| r.sensor_no == sensor_no).map( |r| r.time_recorded).collect();
+            for w in times.windows(2) {
+                intervals.push( duration_since( &w[0], &w[1]));
+            }
+        }
+    }
+
+    let mut intervals = Vec::new();
+    add_intervals( &sentinel.power, &mut intervals);
+    add_intervals( &sentinel.gps, &mut intervals);
+    add_intervals( &sentinel.gas, &mut intervals);
+    add_intervals( &sentinel.thermometer, &mut intervals);
+    add_intervals( &sentinel.anemometer, &mut intervals);
+    add_intervals( &sentinel.voc, &mut intervals);
+    intervals
+}
+
+fn report_cadence (sentinel: &Sentinel, config: &SentinelHealthConfig, now: DateTime<Utc>, warnings: &mut Vec<HealthWarning>)->ReportCadence {
+    use HealthIssue::*;
+    use HealthSeverity::*;
+
+    let mut intervals = periodic_intervals( sentinel);
+    let current_gap = sentinel.latest_record_time().map( |t| duration_since( &now, &t));
+
+    let median_interval = if intervals.len() >= 2 {
+        intervals.sort();
+        Some( intervals[intervals.len()/2])
+    } else {
+        None
+    };
+
+    let mut missed_reports = 0;
+    if let Some(median) = median_interval.filter( |d| !d.is_zero()) {
+        let threshold = median.mul_f64( config.cadence_factor);
+        missed_reports = intervals.iter().filter( |d| **d > threshold).count();
+
+        if let Some(gap) = current_gap {
+            if gap > threshold {
+                warnings.push( HealthWarning::new( LateReport, Warning, 
+                    format!("no report for {} min (usually every {} min)", gap.as_secs()/60, median.as_secs()/60)));
+            }
+        }
+        if missed_reports > 0 {
+            warnings.push( HealthWarning::new( MissedReports, Info, format!("{missed_reports} irregular reporting intervals")));
+        }
+    }
+
+    ReportCadence { 
+        median_interval: median_interval.map( |d| d.as_millis() as u64), 
+        current_gap: current_gap.map( |d| d.as_millis() as u64), 
+        missed_reports 
+    }
+}
+
+fn gps_health (recs: &VecDeque<Arc<SensorRecord<GpsData>>>, config: &SentinelHealthConfig, warnings: &mut Vec<HealthWarning>)->Option<GpsHealth> {
+    let latest = recs.front()?;
+    let fixes = recs.len();
+    let hdop = latest.data.hdop;
+
+    let (drift, max_drift) = if fixes >= 2 {
+        let mut lats: Vec<f64> = recs.iter().map( |r| r.data.latitude.degrees()).collect();
+        let mut lons: Vec<f64> = recs.iter().map( |r| r.data.longitude.degrees()).collect();
+        let reference = (median( &mut lats), median( &mut lons));
+
+        let dist = |r: &Arc<SensorRecord<GpsData>>| distance_meters( reference, (r.data.latitude.degrees(), r.data.longitude.degrees()));
+        (dist( latest), recs.iter().map( |r| dist(r)).fold( 0.0, f64::max))
+    } else {
+        (0.0, 0.0)
+    };
+
+    if drift > config.gps_drift {
+        warnings.push( HealthWarning::new( HealthIssue::GpsDrift, HealthSeverity::Warning, format!("position drifted {drift:.0}m")));
+    }
+
+    Some( GpsHealth { fixes, drift, max_drift, hdop } )
+}
+
+fn image_health (sentinel: &Sentinel, config: &SentinelHealthConfig, warnings: &mut Vec<HealthWarning>)->ImageHealth {
+    use HealthIssue::*;
+    use HealthSeverity::*;
+
+    let images = sentinel.image.len();
+    let last_image = newest( &sentinel.image);
+    let oldest_image = sentinel.image.iter().map( |r| r.time_recorded).min();
+
+    // detections older than our oldest stored image might just have lost their evidence due to max_history_len
+    let failures = missing_evidence( &sentinel.fire, &sentinel.image, oldest_image) + missing_evidence( &sentinel.smoke, &sentinel.image, oldest_image);
+
+    if failures > config.max_image_failures {
+        warnings.push( HealthWarning::new( ImageFailures, Warning, format!("{failures} detections without image")));
+    }
+
+    if let (Some(last_image), Some(latest)) = (last_image, sentinel.latest_record_time()) {
+        let stall = duration_since( &latest, &last_image);
+        if stall > config.image_stall {
+            warnings.push( HealthWarning::new( ImageStall, Warning, format!("no images for {:.0}h", hours(stall))));
+        }
+    }
+
+    ImageHealth { images, failures, last_image }
+}
+
+fn missing_evidence<T> (recs: &VecDeque<Arc<SensorRecord<T>>>, images: &VecDeque<Arc<SensorRecord<ImageData>>>, oldest_image: Option<DateTime<Utc>>)->usize 
+    where T: RecordDataBounds
+{
+    recs.iter()
+        .filter( |r| oldest_image.map( |t| r.time_recorded >= t).unwrap_or(true))
+        .filter( |r| !r.evidences.iter().any( |e| images.iter().any( |img| img.id == e.id)))
+        .count()
+}
+
+fn median (values: &mut Vec<f64>)->f64 {
+    values.sort_by( |a,b| a.total_cmp(b));
+    values[values.len()/2]
+}
+
+/// slope of least squares line through points, or None if all x values are the same
+fn linear_slope (pts: &[(f64,f64)])->Option<f64> {
+    let n = pts.len() as f64;
+    let mx = pts.iter().map( |p| p.0).sum::<f64>() / n;
+    let my = pts.iter().map( |p| p.1).sum::<f64>() / n;
+    let sxx: f64 = pts.iter().map( |p| (p.0 - mx).powi(2)).sum();
+    let sxy: f64 = pts.iter().map( |p| (p.0 - mx) * (p.1 - my)).sum();
+    if sxx > 0.0 { Some( sxy / sxx) } else { None }
+}
+
+/* #endregion analysis */
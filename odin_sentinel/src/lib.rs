@@ -18,7 +18,7 @@ use std::u64;
 #[doc = include_str!("../doc/odin_sentinel.md")]
 
 use std::{
-    cmp::{min, Ordering}, collections::{HashMap, VecDeque}, fmt::{self,Debug}, 
+    cmp::{min, Ordering}, collections::{HashMap, HashSet, VecDeque}, fmt::{self,Debug}, 
     fs::File, future::Future, io::{Read, Write}, ops::RangeBounds, path::{Path,PathBuf}, 
     rc::Rc, sync::{atomic::{self,AtomicU64}, Arc}, time::Duration
 };
@@ -49,11 +49,44 @@ pub use actor::*;
 mod alarm;
 pub use alarm::*;
 
+mod alarm_rules;
+pub use alarm_rules::*;
+
+mod alarm_lifecycle;
+pub use alarm_lifecycle::*;
+
+mod alarm_audit;
+pub use alarm_audit::*;
+
+mod health;
+pub use health::*;
+
+mod commands;
+pub use commands::*;
+
+mod sensor_network;
+pub use sensor_network::*;
+
+mod network_connector;
+pub use network_connector::*;
+
 pub mod ws;
 
 mod live_connector;
 pub use live_connector::*;
 
+mod archive;
+pub use archive::*;
+
+mod timeseries;
+pub use timeseries::*;
+
+mod replay_connector;
+pub use replay_connector::*;
+
+mod mock_server;
+pub use mock_server::*;
+
 mod errors;
 pub use errors::*;
 
@@ -75,6 +108,15 @@ pub use signal_cmd::*;
 #[cfg(feature="slack")] mod slack_messenger;
 #[cfg(feature="slack")] pub use slack_messenger::*;
 
+#[cfg(feature="webhook")] mod webhook_messenger;
+#[cfg(feature="webhook")] pub use webhook_messenger::*;
+
+#[cfg(feature="mqtt")] mod mqtt_messenger;
+#[cfg(feature="mqtt")] pub use mqtt_messenger::*;
+
+#[cfg(feature="mqtt")] mod mqtt_source;
+#[cfg(feature="mqtt")] pub use mqtt_source::*;
+
 lazy_static! {
     static ref MSG_COUNTER: AtomicU64 = AtomicU64::new(42);
 }
@@ -99,6 +141,8 @@ pub trait RecordDataBounds = CapabilityProvider + Serialize + for<'de2> Deserial
 pub struct SensorRecord <T> where T: RecordDataBounds {   
     pub id: RecordId, 
 
+    // Delphire sends RFC3339 but our own serialization (e.g. in recorded archives) uses epoch millis
+    #[serde(deserialize_with="odin_common::datetime::deserialize_rfc3339_or_epoch_millis")]
     pub time_recorded: DateTime<Utc>,
     pub sensor_no: u32,
     pub device_id: DeviceId,
@@ -227,6 +271,7 @@ define_algebraic_type!{
     pub fn sensor_no (&self)->u32 { __.sensor_no }
     pub fn time_recorded (&self)->DateTime<Utc> { __.time_recorded }
     pub fn capability (&self)->SensorCapability { __.capability() }
+    pub fn evidences (&self)->&Vec<RecordRef> { &__.evidences }
     pub fn description (&self)->String { __.description() }
 
     pub fn to_json (&self)->Result<String> { Ok(serde_json::to_string(&__)?) }
@@ -377,9 +422,9 @@ pub enum SensorCapability {
 }
 
 impl SensorCapability {
-    fn property_name (&self)->&'static str { self.into() }
+    pub fn property_name (&self)->&'static str { self.into() }
 
-    fn capability_of (rec_type: &str)->Option<SensorCapability> {
+    pub fn capability_of (rec_type: &str)->Option<SensorCapability> {
         match rec_type {
             "accelerometer" => Some( Self::Accelerometer ),
             "anemometer"    => Some( Self::Anemometer ),
@@ -465,34 +510,12 @@ impl SentinelStore {
         SentinelStore { sentinels: HashMap::new(), updates: HashMap::new() }
     }
     
+    /// initialize from the Delphire server configured in `config` (see [`DelphireAdapter`])
     pub async fn fetch_from_config (&mut self, client: &Client, config: &SentinelConfig)->Result<()> {
-        let base_uri = config.base_uri.as_str();
-        let access_token = config.access_token.as_str();
+        let adapter = DelphireAdapter::from_config( client, config);
         let n_last = config.max_history_len;  // number of initial records to retrieve
         let max_len = config.max_history_len; // max number of records to keep
-        let device_filter = &config.device_filter;
-    
-        let device_list = get_device_list( client, base_uri, access_token).await?;
-
-        for device in &device_list.data {
-            if device_filter.is_empty() || device_filter.contains( &device.id) {
-                let device_name = if let Some(info) = &device.info { info.clone() } else { "?".to_string() };
-                let mut sentinel = Sentinel::new( device.id.clone(), device_name, max_len);
-        
-                let sensor_list = get_sensor_list( client, base_uri, access_token, device.id.as_str()).await?;
-                for sensor_data in &sensor_list.data {
-                    for capability in &sensor_data.capabilities {
-                        let updates = sentinel.init_records(client, base_uri, access_token, sensor_data.no, *capability, n_last, max_len).await?;
-                        for u in updates { self.updates.insert( u.record_id().clone(), u); }
-                    }
-                }
-        
-                sentinel.set_time_recorded(); // from latest sensor record
-                self.insert( sentinel.device_id.clone(), sentinel);
-            }
-        }
-    
-        Ok(())
+        self.fetch_from_adapter( &adapter, n_last, max_len).await
     }
 
     pub fn is_empty(&self)->bool {
@@ -531,6 +554,10 @@ impl SentinelStore {
         self.updates.get(k)
     }
 
+    pub fn updates_iter (&self)->impl Iterator<Item = &SentinelUpdate> {
+        self.updates.values()
+    }
+
     pub fn to_json (&self, pretty: bool)->Result<String> {
         let list = SentinelList { sentinels: self.values() };
         if pretty {
@@ -559,7 +586,9 @@ impl SentinelStore {
         let update = sentinel_update.clone(); // we have to do this prior to loosing ownership
 
         if let Some(ref mut sentinel) = self.sentinels.get_mut( sentinel_update.device_id()) {
+            let time_recorded = sentinel_update.time_recorded();
             let (added_rec_id, removed_rec_id) = sentinel.update_with( sentinel_update);
+            if sentinel.time_recorded.map( |t| time_recorded > t).unwrap_or(true) { sentinel.time_recorded = Some(time_recorded) }
 
             let added = if let Some(added_rec_id) = added_rec_id { 
                 self.updates.insert(added_rec_id.clone(), update.clone());
@@ -580,6 +609,7 @@ impl SentinelStore {
             let mut new_sentinel = Sentinel::new( sentinel_update.device_id().clone(), "?".to_string(), max_len);
             self.updates.insert( sentinel_update.record_id().clone(), sentinel_update.clone());
 
+            new_sentinel.time_recorded = Some( sentinel_update.time_recorded());
             new_sentinel.update_with( sentinel_update);
             self.sentinels.insert( new_sentinel.device_id.clone(), new_sentinel);
 
@@ -587,6 +617,34 @@ impl SentinelStore {
         }
     }
 
+    /// health analysis for a single device (see [`health`] module)
+    pub fn device_health (&self, device_id: &String, config: &SentinelHealthConfig, now: DateTime<Utc>)->Option<SentinelHealth> {
+        self.sentinels.get( device_id).map( |sentinel| sentinel.health( config, now))
+    }
+
+    /// health analysis for all devices, sorted by ascending health score (i.e. devices that need attention first)
+    pub fn health_report (&self, config: &SentinelHealthConfig, now: DateTime<Utc>)->Vec<SentinelHealth> {
+        let mut report: Vec<SentinelHealth> = self.sentinels.values().map( |sentinel| sentinel.health( config, now)).collect();
+        report.sort_by( |a,b| a.score.cmp( &b.score).then_with( || a.device_id.cmp( &b.device_id)));
+        report
+    }
+
+    /// replace the devices (and their records) of all networks that are in `other` with the ones from `other`. This is
+    /// how stores of several connectors are combined, i.e. devices of other networks are kept
+    pub fn merge (&mut self, other: SentinelStore) {
+        let networks: HashSet<Option<String>> = other.sentinels.values().map( |s| s.network.clone()).collect();
+        let replaced: HashSet<DeviceId> = self.sentinels.iter()
+            .filter( |(id,s)| networks.contains( &s.network) || other.sentinels.contains_key( *id))
+            .map( |(id,_)| id.clone())
+            .collect();
+
+        self.sentinels.retain( |id,_| !replaced.contains( id));
+        self.updates.retain( |_,u| !replaced.contains( u.device_id()));
+
+        self.sentinels.extend( other.sentinels);
+        self.updates.extend( other.updates);
+    }
+
     pub fn latest_records (&self)->HashMap<String,String> {
         let mut latest_recs: HashMap<String,String> = HashMap::new();
         for (_,sentinel) in &self.sentinels {
@@ -612,6 +670,9 @@ define_struct! {
         device_id: DeviceId,
         device_name: String,
 
+        #[serde(skip_serializing_if = "Option::is_none")]
+        network: Option<String> = None, // the sensor network this device belongs to (see [`SensorNetworkAdapter`])
+
         #[serde(skip_serializing_if = "Option::is_none", serialize_with = "odin_common::datetime::ser_epoch_millis_option")]
         time_recorded: Option<DateTime<Utc>> = None, // the latest record timestamp we have
 
@@ -908,7 +969,14 @@ pub struct SentinelConfig {
     pub device_filter: Vec<String>, // optional list of device_ids to filter for
 
     pub inactive_duration: Duration, // max duration since last update after which a device is considered to be inactive
-    pub inactive_interval: Duration  // how often we check for inactive devices
+    pub inactive_interval: Duration, // how often we check for inactive devices
+
+    pub record_dir: Option<PathBuf>, // if set the live connector records all retrieved devices, records and files into this archive dir
+    pub timeseries: bool, // if set the live connector appends all records to per capability/day CSV files
+    pub timeseries_dir: Option<PathBuf>, // where to store time-series files (default is sentinel_timeseries_dir())
+
+    pub health: SentinelHealthConfig, // thresholds for device health analysis
+    pub commands: SentinelCmdConfig, // command schedules, batching and retries
 }
 
 impl Default for SentinelConfig {
@@ -926,7 +994,12 @@ impl Default for SentinelConfig {
             reconnect_delay: None,
             device_filter: Vec::new(), // default is no filter
             inactive_duration: Duration::from_secs( 7200), // inactive if no update for 2h
-            inactive_interval: Duration::from_secs(300) // check every 5 min
+            inactive_interval: Duration::from_secs(300), // check every 5 min
+            record_dir: None, // default is no recording
+            timeseries: false,
+            timeseries_dir: None,
+            health: SentinelHealthConfig::default(),
+            commands: SentinelCmdConfig::default(),
         }
     }
 }
@@ -938,6 +1011,14 @@ pub fn sentinel_cache_dir()->PathBuf {
     path
 }
 
+/// for data that has to persist (unlike cached files)
+pub fn sentinel_data_dir()->PathBuf {
+    let path = odin_build::data_dir().join("sentinel");
+    // Ok to panic - this is called during sys init
+    ensure_writable_dir(&path).expect( &format!("invalid sentinel data dir: {path:?}"));
+    path
+}
+
 
 /// device information that is not obtained through Delphire server APIs 
 #[derive(Serialize,Deserialize,Debug)]
@@ -1041,8 +1122,8 @@ pub type ExternalFileQuery = Query<GetExternalFile,SentinelFileResult>;
 
 /* #region connectors  ********************************************************************************************************/
 
-/// this is the abstraction over the actual source of external information, which can be either a live connection to a Delphire server
-/// or a replayer for archived data
+/// this is the abstraction over the actual source of external information, which can be either a live connection to a Delphire server,
+/// a replayer for archived data or a generic [`SensorNetworkConnector`] for other sensor networks
 #[async_trait]
 pub trait SentinelConnector {
 
@@ -1063,6 +1144,12 @@ pub trait SentinelConnector {
 
     /// duration how often we check for inactive status
     fn inactive_interval(&self)->Duration;
+
+    /// thresholds for device health analysis (performed together with inactive checks)
+    fn health_config(&self)->&SentinelHealthConfig;
+
+    /// command schedules and acknowledgement/retry parameters
+    fn cmd_config(&self)->&SentinelCmdConfig;
  }
 
 /* #endregion connectors */
@@ -1113,6 +1200,7 @@ pub async fn get_time_sorted_records <T> (client: &Client, base_uri: &str, acces
 pub async fn get_records_since <T> (client: &Client, base_uri: &str, access_token: &str, uri_path: &str, last: &str) -> Result<Vec<SensorRecord<T>>> 
     where T: RecordDataBounds
 {
+    let uri_path = uri_path.trim_start_matches('/'); // rec_keys are absolute paths
     let uri = format!("{base_uri}/{uri_path}?sort=timeRecorded,DESC&last={last}");
     let response = client.get(uri).bearer_auth(access_token).send().await?;
     let record_list: RecordList<T> = from_json(response).await?; 
@@ -41,9 +41,13 @@ use crate::ws::{WsStream,WsCmd,WsMsg, init_websocket, send_ws_text_msg, read_nex
 /// 
 /// Note also that LiveSentinelConnector is a configured object. Since the [`SentinelConfig`] data is shared with
 /// a number of background tasks managed by the `LiveConnection` we keep it in an `Arc`
-/// 
+///
+/// The network independent part is a [`SensorNetworkConnector`] with a [`DelphireAdapter`], which provides the initial
+/// store and processes complete Delphire records that are pushed through `push_sender()` (e.g. by a relay). The
+/// websocket (record notifications and commands), recording and file retrieval are what this connector adds on top
 pub struct LiveSentinelConnector { 
     config: Arc<SentinelConfig>,
+    network: SensorNetworkConnector,
     connection: Option<LiveConnection>
 }
 
@@ -51,12 +55,18 @@ impl LiveSentinelConnector {
 
     /// called before actor instantiation
     pub fn new (config: SentinelConfig)->Self {
-        LiveSentinelConnector { config: Arc::new(config), connection: None }
+        let network = SensorNetworkConnector::new( network_config( &config), DelphireAdapter::from_config( &Client::new(), &config));
+        LiveSentinelConnector { config: Arc::new(config), network, connection: None }
+    }
+
+    /// the channel for complete Delphire records from other sources than our websocket
+    pub fn push_sender (&self)->MpscSender<SensorMessage> {
+        self.network.push_sender()
     }
 
     /// called from actor ctor (2nd half of our initialization)
     async fn initialize (&mut self, hself: ActorHandle<SentinelActorMsg>)->Result<()> {
-        self.connection = Some(LiveConnection::new(self.config.clone(), hself).await?);
+        self.connection = Some(LiveConnection::new(self.config.clone(), &mut self.network, hself).await?);
         Ok(())
     }
 
@@ -101,18 +111,39 @@ impl SentinelConnector for LiveSentinelConnector {
             conn.terminate();
             self.connection = None;
         }
+        self.network.terminate();
     }
 
     fn max_history(&self)->usize {
-        self.config.max_history_len
+        self.network.max_history()
     }
 
     fn inactive_duration(&self)->Duration {
-        self.config.inactive_duration
+        self.network.inactive_duration()
     }
 
     fn inactive_interval(&self)->Duration {
-        self.config.inactive_interval
+        self.network.inactive_interval()
+    }
+
+    fn health_config(&self)->&SentinelHealthConfig {
+        self.network.health_config()
+    }
+
+    fn cmd_config(&self)->&SentinelCmdConfig {
+        self.network.cmd_config()
+    }
+}
+
+/// the network independent part of a [`SentinelConfig`]
+fn network_config (config: &SentinelConfig)->SensorNetworkConfig {
+    SensorNetworkConfig {
+        max_history_len: config.max_history_len,
+        inactive_duration: config.inactive_duration,
+        inactive_interval: config.inactive_interval,
+        health: config.health.clone(),
+        commands: config.commands.clone(),
+        ..SensorNetworkConfig::default()
     }
 }
 
@@ -147,16 +178,25 @@ struct LiveConnection {
 }
 
 impl LiveConnection {
-    async fn new (config: Arc<SentinelConfig>, hself: ActorHandle<SentinelActorMsg>)->Result<Self> {
+    async fn new (config: Arc<SentinelConfig>, network: &mut SensorNetworkConnector, hself: ActorHandle<SentinelActorMsg>)->Result<Self> {
         let cache_dir = Arc::new(sentinel_cache_dir());
 
         //--- get current sentinel data according to config (there is no point spawning tasks if we don't have a list of devices to watch)
         let http_client = Client::new();
-        let mut sentinel_store = SentinelStore::new();
-        sentinel_store.fetch_from_config( &http_client, &config).await?; // retrieve all records we need - this can take some time
+        let sentinel_store = network.fetch_store().await?; // retrieve all records we need - this can take some time
 
         let mut latest_recs = sentinel_store.latest_records();
 
+        if let Some(record_dir) = &config.record_dir {
+            info!("recording Sentinel data to {:?}", record_dir);
+            SentinelRecorder::new( record_dir)?.record_store( &sentinel_store)?;
+        }
+
+        if let Some(writer) = SentinelTimeSeriesWriter::from_config( &config) {
+            info!("appending Sentinel records to time-series in {:?}", writer.dir());
+            SentinelTimeSeriesWriter::new( writer.dir())?.append_store( &sentinel_store)?;
+        }
+
         //--- now open a websocket and register for the devices we've got (note that config might have a device_filter set)
         let device_ids = sentinel_store.get_device_ids();
         debug!("monitored Sentinel devices: {:?}", device_ids);
@@ -192,6 +232,7 @@ impl LiveConnection {
             live_conn.request_all_files( &config, &sentinel_store).await?;
 
             hself.send_msg( InitializeStore(sentinel_store)).await?;
+            network.start_push( hself)?;
             Ok(live_conn)
 
         } else {
@@ -293,29 +334,31 @@ impl LiveConnection {
                                    cache_dir: &PathBuf, file_request_tx: &MpscSender<FileRequest>)->Result<()> {
         if_let! {
             Message::Text(json) = { msg } else { Err(ws_protocol_error("ignored binary message")) }, // ignore binary messages
-            Ok(msg) = { serde_json::from_str::<WsMsg>(&json) } else { warn!("malformed websocket message {json}"); Err(ws_protocol_error("malformed message")) },
-            WsMsg::Record { device_id, sensor_no, rec_type } = { msg } else { Err(ws_protocol_error("unknown record type")) } => { // ignore other WsMsg variants
-                use SensorCapability::*;
-                match rec_type {
-                    Accelerometer => Self::get_and_send_update::<AccelerometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Anemometer    => Self::get_and_send_update::<AnemometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Cloudcover    => Self::get_and_send_update::<CloudcoverData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Event         => Self::get_and_send_update::<EventData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Fire          => Self::get_and_send_update::<FireData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Gas           => Self::get_and_send_update::<GasData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Gps           => Self::get_and_send_update::<GpsData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Gyroscope     => Self::get_and_send_update::<GyroscopeData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Magnetometer  => Self::get_and_send_update::<MagnetometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Orientation   => Self::get_and_send_update::<OrientationData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Person        => Self::get_and_send_update::<PersonData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Power         => Self::get_and_send_update::<PowerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Smoke         => Self::get_and_send_update::<SmokeData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Thermometer   => Self::get_and_send_update::<ThermometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Valve         => Self::get_and_send_update::<ValveData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-                    Voc           => Self::get_and_send_update::<VocData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
-
-                    Image         => Self::get_and_send_image_update( hself, client, config, &device_id, sensor_no, latest_recs, cache_dir, file_request_tx).await,
+            Ok(msg) = { serde_json::from_str::<WsMsg>(&json) } else { warn!("malformed websocket message {json}"); Err(ws_protocol_error("malformed message")) } => match msg {
+                WsMsg::Record { device_id, sensor_no, rec_type } => {
+                    use SensorCapability::*;
+                    match rec_type {
+                        Accelerometer => Self::get_and_send_update::<AccelerometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Anemometer    => Self::get_and_send_update::<AnemometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Cloudcover    => Self::get_and_send_update::<CloudcoverData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Event         => Self::get_and_send_update::<EventData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Fire          => Self::get_and_send_update::<FireData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Gas           => Self::get_and_send_update::<GasData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Gps           => Self::get_and_send_update::<GpsData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Gyroscope     => Self::get_and_send_update::<GyroscopeData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Magnetometer  => Self::get_and_send_update::<MagnetometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Orientation   => Self::get_and_send_update::<OrientationData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Person        => Self::get_and_send_update::<PersonData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Power         => Self::get_and_send_update::<PowerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Smoke         => Self::get_and_send_update::<SmokeData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Thermometer   => Self::get_and_send_update::<ThermometerData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Valve         => Self::get_and_send_update::<ValveData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+                        Voc           => Self::get_and_send_update::<VocData>( hself, client, config, &device_id, sensor_no, latest_recs).await,
+
+                        Image         => Self::get_and_send_image_update( hself, client, config, &device_id, sensor_no, latest_recs, cache_dir, file_request_tx).await,
+                    }
                 }
+                other => Self::send_cmd_response( hself, other).await
             }
         }
     }
@@ -327,16 +370,38 @@ impl LiveConnection {
         let rec = get_latest_record::<T>(client, &config.base_uri, &config.access_token, device_id, sensor_no).await?;
         let update = SentinelUpdate::from(Arc::new(rec));
         Self::update_latest_recs( latest_recs, &update);
+        Self::record_update( config, &update);
         hself.send_msg( UpdateStore( update)).await?;
 
         Ok(())
     }
 
+    // everything that is not a record notification might be a response to one of our commands
+    async fn send_cmd_response (hself: &ActorHandle<SentinelActorMsg>, msg: WsMsg)->Result<()> {
+        match msg {
+            WsMsg::Pong{..} => Ok(()), // our own keep-alive pings
+            WsMsg::Error { message } => { // most likely a rejected command, which has to be failed by the actor
+                warn!("websocket error message: {message}");
+                Ok( hself.send_msg( CmdResponse( WsMsg::Error{ message })).await? )
+            }
+            msg => Ok( hself.send_msg( CmdResponse(msg)).await? )
+        }
+    }
+
     fn update_latest_recs (latest_recs: &mut HashMap<String,String>, update: &SentinelUpdate) {
         let rec_key = rec_key( update.device_id(), update.sensor_no(), update.capability());
         latest_recs.insert(rec_key, update.record_id().clone());
     }
 
+    fn record_update (config: &SentinelConfig, update: &SentinelUpdate) {
+        if let Some(recorder) = SentinelRecorder::from_config( config) {
+            if let Err(e) = recorder.record_update( update) { warn!("failed to record update {}: {}", update.record_id(), e) }
+        }
+        if let Some(writer) = SentinelTimeSeriesWriter::from_config( config) {
+            if let Err(e) = writer.append_update( update) { warn!("failed to append update {} to time-series: {}", update.record_id(), e) }
+        }
+    }
+
     async fn get_and_send_image_update (hself: &ActorHandle<SentinelActorMsg>, client: &Client, config: &SentinelConfig, 
                                         device_id: &str, sensor_no: u32, latest_recs: &mut HashMap<String,String>,
                                         cache_dir: &PathBuf, file_request_tx: &MpscSender<FileRequest> ) -> Result<()>  
@@ -347,6 +412,7 @@ impl LiveConnection {
         Self::request_image_file( config, cache_dir, file_request_tx, &rec).await?;
         let update = SentinelUpdate::from(Arc::new(rec));
         Self::update_latest_recs( latest_recs, &update);
+        Self::record_update( config, &update);
         hself.send_msg( UpdateStore( update)).await?;
 
         Ok(())
@@ -400,6 +466,7 @@ impl LiveConnection {
         for rec in recs.into_iter() {
             let update = SentinelUpdate::from(Arc::new(rec));
             Self::update_latest_recs( latest_recs, &update);
+            Self::record_update( config, &update);
             hself.send_msg( UpdateStore(update)).await?;
         }
 
@@ -418,6 +485,7 @@ impl LiveConnection {
             Self::request_image_file( config, cache_dir, file_request_tx, &rec).await?;
             let update = SentinelUpdate::from(Arc::new(rec));
             Self::update_latest_recs( latest_recs, &update);
+            Self::record_update( config, &update);
             hself.send_msg( UpdateStore(update)).await?;
         }
 
@@ -525,6 +593,10 @@ impl RequestProcessor<FileRequest,Result<SentinelFile>> for FileFetcher {
     }
 
     async fn process_response (&self, request: &FileRequest, response: SentinelFileResult)->odin_actor::Result<()> {
+        if let (Some(recorder),Ok(file)) = (SentinelRecorder::from_config( &self.config), &response) {
+            if let Err(e) = recorder.record_file( file) { warn!("failed to record file {:?}: {}", file.pathname, e) }
+        }
+
         if let Some(query) = &request.query {
             query.respond(response).await
         } else { 
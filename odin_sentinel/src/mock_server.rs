@@ -0,0 +1,417 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! a local mock of the Delphire REST + websocket server that can be used for end-to-end tests and demos of
+//! [`LiveSentinelConnector`] based applications without credentials.
+//!
+//! Data is served from a fixture directory that uses the same format as [`SentinelArchive`] (i.e. it can be recorded
+//! by a `LiveSentinelConnector` with a configured `record_dir`). Initial records are available right away, all other
+//! records are released according to a script, which is either the recorded arrival timing (if `autoplay` is set) or
+//! explicit [`MockDelphireServer::release`] calls. Each released record is announced through a websocket `record` notification.
+//!
+//! The server supports fault injection through [`MockFaults`] to exercise reconnection and error handling
+
+use std::{net::SocketAddr, path::PathBuf, collections::HashMap, fs, 
+    sync::{Arc, Mutex, atomic::{AtomicUsize,Ordering}}
+};
+use axum::{
+    Router, routing::get, body::Body,
+    extract::{State, Path as AxumPath, Query as AxumQuery, Request, ws::{Message, WebSocket, WebSocketUpgrade}},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use tokio::{net::TcpListener, sync::broadcast, time::sleep};
+use chrono::{DateTime,Utc,TimeDelta};
+use serde::{Serialize,Deserialize};
+
+use odin_actor::prelude::*;
+use odin_common::datetime::duration_since;
+
+use crate::*;
+use crate::errors::*;
+use crate::ws::{WsMsg,WsCmd};
+
+const NOTIFICATION_BOUNDS: usize = 256;
+
+/* #region config ****************************************************************************************************/
+
+#[derive(Deserialize,Serialize,Debug,Clone)]
+#[serde(default)]
+pub struct MockDelphireConfig {
+    pub addr: SocketAddr, // use port 0 to get an ephemeral port (the actual address is available from the MockDelphireServer)
+    pub fixture_dir: PathBuf, // SentinelArchive format
+    pub access_token: String, // bearer token clients have to provide
+    pub autoplay: bool, // release non-initial fixture records with their recorded inter-arrival timing
+    pub timescale: u32, // autoplay speedup factor
+    pub faults: MockFaults
+}
+
+impl Default for MockDelphireConfig {
+    fn default()->Self {
+        MockDelphireConfig {
+            addr: SocketAddr::from( ([127,0,0,1], 0)),
+            fixture_dir: PathBuf::from("?"),
+            access_token: "mock-token".to_string(),
+            autoplay: false,
+            timescale: 1,
+            faults: MockFaults::default()
+        }
+    }
+}
+
+/// fault injection settings, which can be changed while the server is running
+#[derive(Deserialize,Serialize,Debug,Clone,Default)]
+#[serde(default)]
+pub struct MockFaults {
+    pub response_delay: Option<Duration>, // delay for each http response
+    pub malformed_paths: Vec<String>, // http path prefixes for which we return malformed JSON
+    pub reject_ws: usize, // number of websocket connection attempts to reject before accepting
+    pub drop_socket_after: Option<usize>, // drop websocket connections (without close frame) after sending N record notifications
+}
+
+/* #endregion config */
+
+/* #region server ****************************************************************************************************/
+
+/// the shared state of all mock server tasks
+struct MockState {
+    access_token: String,
+    devices: DeviceList,
+    files_dir: PathBuf,
+    image_files: HashMap<RecordId,String>,
+
+    records: Mutex<Vec<SentinelUpdate>>, // the released records
+    faults: Mutex<MockFaults>,
+    ws_attempts: AtomicUsize,
+
+    notify_tx: broadcast::Sender<(DeviceId,String)>, // (device_id, serialized WsMsg::Record)
+    drop_tx: broadcast::Sender<()>,
+}
+
+impl MockState {
+    fn faults (&self)->MockFaults {
+        self.faults.lock().unwrap().clone()
+    }
+
+    fn release (&self, update: SentinelUpdate) {
+        let device_id = update.device_id().clone();
+        let msg = WsMsg::Record { device_id: device_id.clone(), sensor_no: update.sensor_no(), rec_type: update.capability() };
+        self.records.lock().unwrap().push( update);
+
+        if let Ok(json) = serde_json::to_string( &msg) {
+            self.notify_tx.send( (device_id, json)); // Err just means nobody is connected
+        }
+    }
+}
+
+/// handle for a running mock server. The server is terminated when this object is dropped
+pub struct MockDelphireServer {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    server_task: AbortHandle,
+    script_task: Option<AbortHandle>,
+}
+
+impl MockDelphireServer {
+
+    pub async fn start (config: MockDelphireConfig)->Result<Self> {
+        let archive = SentinelArchive::open( &config.fixture_dir)?;
+
+        let (notify_tx,_) = broadcast::channel( NOTIFICATION_BOUNDS);
+        let (drop_tx,_) = broadcast::channel( 4);
+
+        let mut records = Vec::new();
+        for archived in archive.updates.iter().filter( |a| a.initial) {
+            records.push( archived.to_update( TimeDelta::zero())?);
+        }
+
+        let state = Arc::new( MockState {
+            access_token: config.access_token.clone(),
+            devices: archive.devices.clone(),
+            files_dir: archive.dir.join(FILES_DIR),
+            image_files: archive.image_files(),
+            records: Mutex::new( records),
+            faults: Mutex::new( config.faults.clone()),
+            ws_attempts: AtomicUsize::new(0),
+            notify_tx, drop_tx
+        });
+
+        let listener = TcpListener::bind( config.addr).await?;
+        let addr = listener.local_addr()?;
+        let router = Self::router( state.clone());
+        let server_task = spawn( "mock-delphire", async move {
+            if let Err(e) = axum::serve( listener, router).await { error!("mock Delphire server terminated: {e}") }
+        })?.abort_handle();
+        info!("mock Delphire server listening on {addr}");
+
+        let script_task = if config.autoplay {
+            Some( spawn( "mock-delphire-script", Self::autoplay( state.clone(), archive, config.timescale.max(1)))?.abort_handle() )
+        } else { None };
+
+        Ok( MockDelphireServer { addr, state, server_task, script_task } )
+    }
+
+    pub fn addr (&self)->SocketAddr { self.addr }
+
+    pub fn base_uri (&self)->String { format!("http://{}", self.addr) }
+
+    pub fn ws_uri (&self)->String { format!("ws://{}/ws", self.addr) }
+
+    /// a [`SentinelConfig`] that connects to this server
+    pub fn sentinel_config (&self)->SentinelConfig {
+        SentinelConfig {
+            base_uri: self.base_uri(),
+            ws_uri: self.ws_uri(),
+            access_token: self.state.access_token.clone(),
+            ..SentinelConfig::default()
+        }
+    }
+
+    /// make a record available and notify connected websocket clients that joined the respective device
+    pub fn release (&self, update: SentinelUpdate) {
+        self.state.release( update)
+    }
+
+    pub fn set_faults (&self, faults: MockFaults) {
+        *self.state.faults.lock().unwrap() = faults;
+    }
+
+    /// drop all current websocket connections (without close frame)
+    pub fn drop_sockets (&self) {
+        self.state.drop_tx.send(());
+    }
+
+    pub fn terminate (&mut self) {
+        if let Some(task) = self.script_task.take() { task.abort() }
+        self.server_task.abort();
+    }
+
+    fn router (state: Arc<MockState>)->Router {
+        Router::new()
+            .route( "/devices", get( devices_handler))
+            .route( "/devices/:device_id/sensors", get( sensors_handler))
+            .route( "/devices/:device_id/sensors/:sensor_no/:capability", get( records_handler))
+            .route( "/images/:record_id", get( image_handler))
+            .route( "/ws", get( ws_handler))
+            .route_layer( middleware::from_fn_with_state( state.clone(), fault_layer))
+            .with_state( state)
+    }
+
+    async fn autoplay (state: Arc<MockState>, archive: SentinelArchive, timescale: u32) {
+        let mut last_received: Option<DateTime<Utc>> = None;
+
+        for archived in archive.replay_updates() {
+            if let Some(last) = &last_received {
+                let dt = duration_since( &archived.received, last) / timescale;
+                if !dt.is_zero() { sleep(dt).await }
+            }
+            last_received = Some(archived.received);
+
+            match archived.to_update( TimeDelta::zero()) {
+                Ok(update) => state.release( update),
+                Err(e) => warn!("ignoring fixture record: {e}")
+            }
+        }
+        info!("mock Delphire script done")
+    }
+}
+
+impl Drop for MockDelphireServer {
+    fn drop (&mut self) {
+        self.terminate()
+    }
+}
+
+/* #endregion server */
+
+/* #region http handlers *********************************************************************************************/
+
+/// checks authorization and applies configured response delays and malformed JSON faults
+async fn fault_layer (State(state): State<Arc<MockState>>, req: Request, next: Next)->Response {
+    let auth = format!("Bearer {}", state.access_token);
+    let is_authorized = req.headers().get( header::AUTHORIZATION).map( |v| v.as_bytes() == auth.as_bytes()).unwrap_or(false);
+    if !is_authorized {
+        return (StatusCode::UNAUTHORIZED, "invalid access token").into_response()
+    }
+
+    let faults = state.faults();
+    if let Some(delay) = faults.response_delay {
+        sleep(delay).await;
+    }
+
+    let path = req.uri().path();
+    if faults.malformed_paths.iter().any( |p| path.starts_with( p.as_str())) {
+        return json_response( r#"{"data":[{"id":"#.to_string())
+    }
+
+    next.run( req).await
+}
+
+fn json_response (json: String)->Response {
+    (StatusCode::OK, [(header::CONTENT_TYPE, "application/json")], json).into_response()
+}
+
+fn to_json_response<T: Serialize> (data: &T)->Response {
+    match serde_json::to_string( data) {
+        Ok(json) => json_response( json),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+    }
+}
+
+async fn devices_handler (State(state): State<Arc<MockState>>)->Response {
+    to_json_response( &state.devices)
+}
+
+async fn sensors_handler (State(state): State<Arc<MockState>>, AxumPath(device_id): AxumPath<String>)->Response {
+    let mut data: Vec<SensorData> = Vec::new();
+
+    for update in state.records.lock().unwrap().iter().filter( |u| *u.device_id() == device_id) {
+        let capability = update.capability();
+        match data.iter_mut().find( |s| s.no == update.sensor_no()) {
+            Some(sensor) => if !sensor.capabilities.contains( &capability) { sensor.capabilities.push( capability) }
+            None => data.push( SensorData { no: update.sensor_no(), device_id: device_id.clone(), part_no: None, capabilities: vec![capability] })
+        }
+    }
+    data.sort_by_key( |s| s.no);
+
+    to_json_response( &SensorList { data })
+}
+
+/// this supports the `limit=N` (N latest records) and `last=<record-id>` (records newer than record-id) queries.
+/// Records are always sorted by descending timeRecorded
+async fn records_handler (State(state): State<Arc<MockState>>, 
+                          AxumPath((device_id,sensor_no,capability)): AxumPath<(String,u32,String)>,
+                          AxumQuery(params): AxumQuery<HashMap<String,String>>)->Response {
+    let Some(capability) = SensorCapability::capability_of( &capability) else {
+        return (StatusCode::NOT_FOUND, format!("unknown capability {capability}")).into_response()
+    };
+
+    let records = state.records.lock().unwrap();
+    let mut selected: Vec<&SentinelUpdate> = records.iter()
+        .filter( |u| *u.device_id() == device_id && u.sensor_no() == sensor_no && u.capability() == capability)
+        .collect();
+    selected.sort_by_key( |u| std::cmp::Reverse( u.time_recorded()));
+
+    if let Some(last) = params.get("last") {
+        if let Some(last_time) = records.iter().find( |u| u.record_id() == last).map( |u| u.time_recorded()) {
+            selected.retain( |u| u.time_recorded() > last_time);
+        }
+    }
+    if let Some(limit) = params.get("limit").and_then( |s| s.parse::<usize>().ok()) {
+        selected.truncate( limit);
+    }
+
+    // SentinelUpdate serializes as the untagged SensorRecord
+    to_json_response( &serde_json::json!({ "data": selected }))
+}
+
+async fn image_handler (State(state): State<Arc<MockState>>, AxumPath(record_id): AxumPath<String>)->Response {
+    if let Some(filename) = state.image_files.get( &record_id) {
+        if let Ok(bytes) = fs::read( state.files_dir.join( filename)) {
+            return (StatusCode::OK, Body::from( bytes)).into_response()
+        }
+    }
+    (StatusCode::NOT_FOUND, format!("no image for record {record_id}")).into_response()
+}
+
+/* #endregion http handlers */
+
+/* #region websocket *************************************************************************************************/
+
+async fn ws_handler (State(state): State<Arc<MockState>>, ws: WebSocketUpgrade)->Response {
+    let attempt = state.ws_attempts.fetch_add( 1, Ordering::Relaxed);
+    if attempt < state.faults().reject_ws {
+        return (StatusCode::SERVICE_UNAVAILABLE, "websocket rejected by fault injection").into_response()
+    }
+    ws.on_upgrade( move |socket| handle_socket( socket, state)).into_response()
+}
+
+async fn handle_socket (mut ws: WebSocket, state: Arc<MockState>) {
+    let mut notify_rx = state.notify_tx.subscribe();
+    let mut drop_rx = state.drop_tx.subscribe();
+    let mut joined: Vec<DeviceId> = Vec::new();
+    let mut n_sent: usize = 0;
+
+    if !send_ws_msg( &mut ws, &WsMsg::Connected { message: "connected".to_string() }).await { return }
+
+    loop {
+        tokio::select! {
+            maybe_msg = ws.recv() => {
+                match maybe_msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if !process_client_msg( &mut ws, &state, &mut joined, &text).await { return }
+                    }
+                    Some(Ok(Message::Close(_))) | None => return,
+                    Some(Ok(_)) => {} // no binary messages in the Delphire protocol
+                    Some(Err(e)) => { warn!("mock Delphire websocket read failed: {e}"); return }
+                }
+            }
+            notification = notify_rx.recv() => {
+                match notification {
+                    Ok((device_id,json)) => {
+                        if joined.contains( &device_id) {
+                            if ws.send( Message::Text(json)).await.is_err() { return }
+                            n_sent += 1;
+                            if let Some(max) = state.faults().drop_socket_after {
+                                if n_sent >= max { return } // just drop it
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => warn!("mock Delphire websocket skipped {n} notifications"),
+                    Err(_) => return
+                }
+            }
+            _ = drop_rx.recv() => return
+        }
+    }
+}
+
+/// returns false if the socket should be closed
+async fn process_client_msg (ws: &mut WebSocket, state: &MockState, joined: &mut Vec<DeviceId>, text: &str)->bool {
+    if let Ok(WsMsg::Join { device_ids, message_id }) = serde_json::from_str::<WsMsg>( text) {
+        let known = state.devices.get_device_ids();
+        *joined = device_ids.into_iter().filter( |id| known.contains(id)).collect();
+        send_ws_msg( ws, &WsMsg::Join { device_ids: joined.clone(), message_id }).await
+
+    } else if let Ok(cmd) = serde_json::from_str::<WsCmd>( text) {
+        match cmd {
+            WsCmd::Ping { request_time, message_id } => {
+                let response_time = Utc::now().timestamp_millis() as u64;
+                send_ws_msg( ws, &WsMsg::Pong { request_time, response_time, message_id }).await
+            }
+            WsCmd::TriggerAlert { device_ids, message_id } => {
+                for device_id in device_ids {
+                    let result = if joined.contains( &device_id) { "success" } else { "unknown device" }.to_string();
+                    if !send_ws_msg( ws, &WsMsg::TriggerAlert { device_id, message_id: message_id.clone(), result }).await { return false }
+                }
+                true
+            }
+            _ => true // we just accept light and valve commands
+        }
+
+    } else {
+        send_ws_msg( ws, &WsMsg::Error { message: format!("unknown message: {text}") }).await
+    }
+}
+
+async fn send_ws_msg (ws: &mut WebSocket, msg: &WsMsg)->bool {
+    match serde_json::to_string( msg) {
+        Ok(json) => ws.send( Message::Text(json)).await.is_ok(),
+        Err(_) => false
+    }
+}
+
+/* #endregion websocket */
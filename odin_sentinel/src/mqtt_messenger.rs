@@ -0,0 +1,143 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::time::Duration;
+use rumqttc::{AsyncClient, EventLoop, MqttOptions, QoS};
+use serde::{Deserialize, Deserializer, Serialize, de};
+use serde_json::{Map, Value};
+use async_trait::async_trait;
+use tokio::{task::JoinHandle, time::timeout};
+
+use odin_actor::{warn, sleep};
+use crate::{expand_json_template, expand_text_template, Alarm, AlarmMessenger, OdinSentinelError};
+use crate::errors::Result;
+
+#[derive(Deserialize,Serialize,Debug)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// topic to publish to, can contain `${var}` placeholders, e.g. "odin/sentinel/${deviceId}/${alarmType}".
+    /// MQTT wildcard and level separator chars ('+', '#', '/') in substituted values are replaced by '_' 
+    pub topic: String,
+
+    /// optional JSON payload template (see `expand_json_template`). If not set we publish `Alarm::to_json()`
+    #[serde(default)]
+    pub template: Option<String>,
+
+    #[serde(default="default_qos", deserialize_with="deserialize_qos")]
+    pub qos: u8,
+
+    #[serde(default)]
+    pub retain: bool,
+
+    pub keep_alive: Duration,
+    pub reconnect_delay: Duration,
+    pub timeout: Duration,
+}
+
+fn default_qos()->u8 { 1 }
+
+// we don't want to silently downgrade/upgrade invalid QoS levels
+fn deserialize_qos<'de,D> (deserializer: D)->std::result::Result<u8,D::Error> where D: Deserializer<'de> {
+    let qos = u8::deserialize( deserializer)?;
+    if qos > 2 { Err( de::Error::custom("qos has to be 0, 1 or 2")) } else { Ok(qos) }
+}
+
+/// `AlarmMessenger` that publishes alarms as JSON messages to a MQTT broker.
+/// The broker connection is maintained by a background task that reconnects after `reconnect_delay` if it gets closed.
+/// Note that `send_alarm` returns once the message is queued for publishing - delivery depends on the configured `qos`.
+/// Has to be created from within a tokio runtime
+pub struct MqttAlarmMessenger {
+    config: MqttConfig,
+    template: Option<Value>,
+    qos: QoS,
+    client: AsyncClient,
+    event_task: JoinHandle<()>,
+}
+
+impl MqttAlarmMessenger {
+    pub fn new (config: MqttConfig)->Self {
+        let mut opts = MqttOptions::new( &config.client_id, &config.host, config.port);
+        opts.set_keep_alive( config.keep_alive);
+        if let (Some(user),Some(pw)) = (&config.username, &config.password) {
+            opts.set_credentials( user, pw);
+        }
+
+        let (client, event_loop) = AsyncClient::new( opts, 16);
+        let event_task = tokio::spawn( run_event_loop( event_loop, config.reconnect_delay));
+
+        let template = config.template.as_ref().map( |t| serde_json::from_str::<Value>(t).expect("invalid MQTT template")); // toplevel object, panic Ok
+        let qos = match config.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            _ => QoS::ExactlyOnce
+        };
+
+        MqttAlarmMessenger { config, template, qos, client, event_task }
+    }
+
+    pub fn topic (&self, alarm: &Alarm)->String {
+        let vars: Map<String,Value> = alarm.to_json().into_iter().map( |(k,v)| match v {
+            Value::String(v) => (k, Value::String( sanitize_topic_level( &v))),
+            other => (k, other)
+        }).collect();
+        expand_text_template( &self.config.topic, &vars)
+    }
+
+    pub fn payload (&self, alarm: &Alarm)->Value {
+        let vars = alarm.to_json();
+        match &self.template {
+            Some(template) => expand_json_template( template, &vars),
+            None => Value::Object( vars)
+        }
+    }
+}
+
+// values must not add topic levels or (invalid) wildcards when substituted into the topic
+fn sanitize_topic_level (s: &str)->String {
+    s.replace( ['+', '#', '/'], "_")
+}
+
+// we have to poll the event loop to make progress, which also (re-)connects
+async fn run_event_loop (mut event_loop: EventLoop, reconnect_delay: Duration) {
+    loop {
+        if let Err(e) = event_loop.poll().await {
+            warn!("MQTT connection error: {e}");
+            sleep( reconnect_delay).await;
+        }
+    }
+}
+
+impl Drop for MqttAlarmMessenger {
+    fn drop (&mut self) {
+        self.event_task.abort();
+    }
+}
+
+#[async_trait]
+impl AlarmMessenger for MqttAlarmMessenger {
+    async fn send_alarm (&self, alarm: &Alarm)->Result<()> {
+        let topic = self.topic( alarm);
+        let payload = serde_json::to_vec( &self.payload( alarm))?;
+
+        Ok( timeout( self.config.timeout, self.client.publish( topic, self.qos, self.config.retain, payload)).await?? )
+    }
+}
@@ -0,0 +1,105 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::time::Duration;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, QoS, SubscribeFilter};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use odin_actor::{warn, sleep, MpscSender};
+use crate::SensorMessage;
+
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub struct MqttSourceConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// topic filters to subscribe to, e.g. "weather/+/readings"
+    pub topics: Vec<String>,
+
+    #[serde(default="default_qos")]
+    pub qos: u8,
+
+    pub keep_alive: Duration,
+    pub reconnect_delay: Duration,
+}
+
+fn default_qos()->u8 { 1 }
+
+/// transport that subscribes to MQTT topics and forwards all received publish messages to a [`crate::SensorNetworkConnector`]
+/// (see `SensorNetworkConnector::push_sender()`). Subscriptions are renewed whenever the broker connection is (re-)established.
+/// Has to be created from within a tokio runtime
+pub struct MqttSensorSource {
+    client: AsyncClient,
+    event_task: JoinHandle<()>,
+}
+
+impl MqttSensorSource {
+    pub fn new (config: MqttSourceConfig, push_tx: MpscSender<SensorMessage>)->Self {
+        let mut opts = MqttOptions::new( &config.client_id, &config.host, config.port);
+        opts.set_keep_alive( config.keep_alive);
+        if let (Some(user),Some(pw)) = (&config.username, &config.password) {
+            opts.set_credentials( user, pw);
+        }
+
+        let (client, event_loop) = AsyncClient::new( opts, 16);
+        let event_task = tokio::spawn( run_event_loop( client.clone(), event_loop, config, push_tx));
+
+        MqttSensorSource { client, event_task }
+    }
+}
+
+async fn run_event_loop (client: AsyncClient, mut event_loop: EventLoop, config: MqttSourceConfig, push_tx: MpscSender<SensorMessage>) {
+    let qos = match config.qos {
+        0 => QoS::AtMostOnce,
+        2 => QoS::ExactlyOnce,
+        _ => QoS::AtLeastOnce
+    };
+
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                // this task is the one that drains the client request channel, i.e. we must not wait for it here.
+                // A single SUBSCRIBE for all topics also keeps us independent of the channel capacity
+                let filters = config.topics.iter().map( |topic| SubscribeFilter::new( topic.clone(), qos));
+                if let Err(e) = client.try_subscribe_many( filters) { warn!("failed to subscribe to MQTT topics {:?}: {e}", config.topics) }
+            }
+            Ok(Event::Incoming(Packet::Publish(p))) => {
+                let msg = SensorMessage::new( p.topic, p.payload.to_vec());
+                if push_tx.send( msg).await.is_err() {
+                    warn!("sensor push channel closed, terminating MQTT source");
+                    return
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("MQTT connection error: {e}");
+                sleep( config.reconnect_delay).await;
+            }
+        }
+    }
+}
+
+impl Drop for MqttSensorSource {
+    fn drop (&mut self) {
+        self.event_task.abort();
+    }
+}
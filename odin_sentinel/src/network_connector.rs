@@ -0,0 +1,190 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Serialize,Deserialize};
+
+use odin_actor::prelude::*;
+
+use crate::*;
+use crate::actor::*;
+use crate::errors::*;
+use crate::ws::WsCmd;
+
+/* #region SensorNetworkConfig ***************************************************************************************/
+
+/// configuration for a [`SensorNetworkConnector`]. Network specific settings are part of the respective adapter
+#[derive(Deserialize,Serialize,Debug)]
+#[serde(default)]
+pub struct SensorNetworkConfig {
+    pub max_history_len: usize, // maximum number of records to store per device/sensor capability
+    pub push_queue_len: usize,  // bound of the channel through which transports push messages
+
+    pub inactive_duration: Duration, // max duration since last update after which a device is considered to be inactive
+    pub inactive_interval: Duration, // how often we check for inactive devices
+
+    pub health: SentinelHealthConfig, // thresholds for device health analysis
+    pub commands: SentinelCmdConfig,  // device commands are not supported by generic networks but still tracked
+}
+
+impl Default for SensorNetworkConfig {
+    fn default()->Self {
+        SensorNetworkConfig {
+            max_history_len: 10,
+            push_queue_len: 64,
+            inactive_duration: Duration::from_secs( 7200),
+            inactive_interval: Duration::from_secs(300),
+            health: SentinelHealthConfig::default(),
+            commands: SentinelCmdConfig::default(),
+        }
+    }
+}
+
+/* #endregion SensorNetworkConfig */
+
+/* #region SensorNetworkConnector ************************************************************************************/
+
+/// a raw message received by a transport (MQTT subscription, HTTP push endpoint etc.), to be parsed by the
+/// [`SensorNetworkAdapter`] of the connector it is sent to
+#[derive(Debug,Clone)]
+pub struct SensorMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+impl SensorMessage {
+    pub fn new (topic: impl ToString, payload: impl Into<Vec<u8>>)->Self {
+        SensorMessage { topic: topic.to_string(), payload: payload.into() }
+    }
+}
+
+/// a [`SentinelConnector`] for arbitrary sensor networks, which makes their devices available to the [`SentinelActor`]
+/// and hence to alarm monitors and the [`sentinel_service::SentinelService`].
+///
+/// The initial store is built from the [`SensorNetworkAdapter`] when the connector is started. Subsequent messages are
+/// pushed by transports through the sender returned by `push_sender()`, parsed by the adapter and sent as `UpdateStore`
+/// messages to the actor. Transports are decoupled from adapters, i.e. the same adapter can receive messages from
+/// different sources (e.g. [`MqttSensorSource`] if the "mqtt" feature is enabled)
+pub struct SensorNetworkConnector {
+    config: SensorNetworkConfig,
+    adapter: Arc<dyn SensorNetworkAdapter>,
+    push_tx: MpscSender<SensorMessage>,
+    push_rx: Option<MpscReceiver<SensorMessage>>, // moved into the push task when we start
+    push_task: Option<AbortHandle>,
+}
+
+impl SensorNetworkConnector {
+
+    /// called before actor instantiation
+    pub fn new (config: SensorNetworkConfig, adapter: impl SensorNetworkAdapter)->Self {
+        let (push_tx, push_rx) = create_mpsc_sender_receiver::<SensorMessage>( config.push_queue_len);
+        SensorNetworkConnector { config, adapter: Arc::new(adapter), push_tx, push_rx: Some(push_rx), push_task: None }
+    }
+
+    /// the channel transports use to send messages to this connector. Messages are only processed after the
+    /// connector was started
+    pub fn push_sender (&self)->MpscSender<SensorMessage> {
+        self.push_tx.clone()
+    }
+
+    pub fn network (&self)->&str {
+        self.adapter.network()
+    }
+
+    async fn initialize (&mut self, hself: ActorHandle<SentinelActorMsg>)->Result<()> {
+        let store = self.fetch_store().await?;
+        hself.send_msg( InitializeStore(store)).await?;
+        self.start_push( hself)
+    }
+
+    /// the initial store content from our adapter. This is also used by connectors that add their own
+    /// transports on top of this one (e.g. the [`LiveSentinelConnector`] websocket)
+    pub(crate) async fn fetch_store (&self)->Result<SentinelStore> {
+        let max_len = self.config.max_history_len;
+        let mut store = SentinelStore::new();
+        store.fetch_from_adapter( self.adapter.as_ref(), max_len, max_len).await?;
+        if store.is_empty() { Err( OdinSentinelError::NoDevicesError) } else { Ok(store) }
+    }
+
+    /// start processing pushed messages, which should only happen after the actor got the initial store
+    pub(crate) fn start_push (&mut self, hself: ActorHandle<SentinelActorMsg>)->Result<()> {
+        let push_rx = self.push_rx.take().ok_or_else( || op_failed("connector already started"))?;
+        let push_task = spawn( "sensor-network-push", Self::push_loop( hself, self.adapter.clone(), push_rx))?.abort_handle();
+        self.push_task = Some(push_task);
+        Ok(())
+    }
+
+    async fn push_loop (hself: ActorHandle<SentinelActorMsg>, adapter: Arc<dyn SensorNetworkAdapter>, push_rx: MpscReceiver<SensorMessage>) {
+        while let Ok(msg) = recv( &push_rx).await {
+            match adapter.parse_message( &msg.topic, &msg.payload) {
+                Ok(updates) => {
+                    for update in updates {
+                        if hself.send_msg( UpdateStore(update)).await.is_err() {
+                            return // actor is gone, nothing left to do
+                        }
+                    }
+                }
+                Err(e) => warn!("ignoring {} message from {}: {}", adapter.network(), msg.topic, e)
+            }
+        }
+        info!("{} push channel closed", adapter.network())
+    }
+}
+
+/// this is the interface used by the [`SentinelActor`] 
+#[async_trait]
+impl SentinelConnector for SensorNetworkConnector {
+    async fn start (&mut self, hself: ActorHandle<SentinelActorMsg>)->Result<()> {
+        self.initialize(hself).await
+    }
+
+    async fn send_cmd (&mut self, cmd: WsCmd)->Result<()> {
+        Err( op_failed!("{} devices do not support commands", self.adapter.network()))
+    }
+
+    async fn handle_sentinel_file_query (&self, query: Query<GetSentinelFile,Result<SentinelFile>>)->Result<()> {
+        let response = self.adapter.get_file( &query.question).await;
+        query.respond( response).await.map_err(|e| e.into())
+    }
+
+    fn terminate (&mut self) {
+        if let Some(task) = self.push_task.take() {
+            task.abort();
+        }
+    }
+
+    fn max_history(&self)->usize {
+        self.config.max_history_len
+    }
+
+    fn inactive_duration(&self)->Duration {
+        self.config.inactive_duration
+    }
+
+    fn inactive_interval(&self)->Duration {
+        self.config.inactive_interval
+    }
+
+    fn health_config(&self)->&SentinelHealthConfig {
+        &self.config.health
+    }
+
+    fn cmd_config(&self)->&SentinelCmdConfig {
+        &self.config.commands
+    }
+}
+
+/* #endregion SensorNetworkConnector */
@@ -0,0 +1,206 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::{sync::Arc, collections::HashMap, path::PathBuf, fs};
+use tokio::time::sleep;
+use chrono::{DateTime,Utc,TimeDelta};
+use async_trait::async_trait;
+
+use odin_actor::prelude::*;
+use odin_common::{datetime::duration_since, sim_clock};
+
+use crate::*;
+use crate::actor::*;
+use crate::errors::*;
+use crate::ws::WsCmd;
+
+/* #region SentinelReplayConfig **************************************************************************************/
+
+/// configuration for a [`ReplaySentinelConnector`]
+#[derive(Deserialize,Serialize,Debug)]
+#[serde(default)]
+pub struct SentinelReplayConfig {
+    pub archive_dir: PathBuf, // the dir that was used as `record_dir` by the LiveSentinelConnector
+
+    pub max_history_len: usize, // maximum number of records to store per device/sensor capability
+    pub rebase_times: bool, // shift record times so that the replay starts at the current (sim) time
+    pub max_gap: Duration, // longest pause between replayed records, longer ones are recording downtime
+
+    pub inactive_duration: Duration, // max duration since last update after which a device is considered to be inactive
+    pub inactive_interval: Duration, // how often we check for inactive devices
+
+    pub health: SentinelHealthConfig, // thresholds for device health analysis
+    pub commands: SentinelCmdConfig, // scheduled commands are ignored by the replay connector but still tracked
+}
+
+impl Default for SentinelReplayConfig {
+    fn default()->Self {
+        SentinelReplayConfig {
+            archive_dir: PathBuf::from("?"),
+            max_history_len: 10,
+            rebase_times: true,
+            max_gap: DEFAULT_MAX_REPLAY_GAP,
+            inactive_duration: Duration::from_secs( 7200),
+            inactive_interval: Duration::from_secs(300),
+            health: SentinelHealthConfig::default(),
+            commands: SentinelCmdConfig::default(),
+        }
+    }
+}
+
+/* #endregion SentinelReplayConfig */
+
+/* #region ReplaySentinelConnector ***********************************************************************************/
+
+/// a [`SentinelConnector`] that replays an archive recorded by a [`LiveSentinelConnector`] (see [`SentinelRecorder`]).
+///
+/// The initial records of the archive are sent as the `InitializeStore` message, all subsequent records are sent as
+/// `UpdateStore` messages with their original inter-arrival timing. If there is a settable [`sim_clock`] we scale
+/// this timing by its timescale.
+///
+/// Files (images) are served from the archive, i.e. we do not need credentials or a network connection. This makes
+/// the `ReplaySentinelConnector` suitable for demos and regression tests of the alarm pipeline
+pub struct ReplaySentinelConnector {
+    config: SentinelReplayConfig,
+    image_files: HashMap<RecordId,String>, // record_id -> archived filename
+    replay_task: Option<AbortHandle>
+}
+
+impl ReplaySentinelConnector {
+
+    /// called before actor instantiation
+    pub fn new (config: SentinelReplayConfig)->Self {
+        ReplaySentinelConnector { config, image_files: HashMap::new(), replay_task: None }
+    }
+
+    async fn initialize (&mut self, hself: ActorHandle<SentinelActorMsg>)->Result<()> {
+        let archive = SentinelArchive::open( &self.config.archive_dir)?;
+        if archive.devices.is_empty() { return Err( OdinSentinelError::NoDevicesError) }
+
+        let timescale = if sim_clock::is_settable().unwrap_or(false) { sim_clock::timescale().unwrap_or(1).max(1) } else { 1 };
+        let time_offset = if self.config.rebase_times {
+            let now = sim_clock::now().unwrap_or_else( |_| Utc::now());
+            archive.replay_start().or( archive.replay_end()).map( |start| now - start).unwrap_or( TimeDelta::zero())
+        } else {
+            TimeDelta::zero()
+        };
+
+        self.image_files = archive.image_files();
+
+        let store = archive.initial_store( self.config.max_history_len, time_offset)?;
+        for filename in self.image_files.values() {
+            cache_archived_file( &archive, filename, filename);
+        }
+        hself.send_msg( InitializeStore(store)).await?;
+
+        let max_gap = self.config.max_gap;
+        let replay_task = spawn( "sentinel-replay", Self::replay( hself, archive, time_offset, timescale, max_gap))?.abort_handle();
+        self.replay_task = Some(replay_task);
+
+        Ok(())
+    }
+
+    async fn replay (hself: ActorHandle<SentinelActorMsg>, archive: SentinelArchive, time_offset: TimeDelta, timescale: u32, max_gap: Duration) {
+        for step in archive.replay_steps( max_gap) {
+            let dt = step.delay / timescale;
+            if !dt.is_zero() { sleep(dt).await }
+
+            match step.update.to_update( time_offset - step.skipped) {
+                Ok(update) => {
+                    if hself.send_msg( UpdateStore(update)).await.is_err() {
+                        return // actor is gone, nothing left to do
+                    }
+                }
+                Err(e) => warn!("ignoring archived record: {}", e)
+            }
+        }
+        info!("Sentinel replay of {:?} done", archive.dir)
+    }
+}
+
+/// this is the interface used by the [`SentinelActor`] 
+#[async_trait]
+impl SentinelConnector for ReplaySentinelConnector {
+    async fn start (&mut self, hself: ActorHandle<SentinelActorMsg>)->Result<()> {
+        self.initialize(hself).await
+    }
+
+    async fn send_cmd (&mut self, cmd: WsCmd)->Result<()> {
+        warn!("ignoring command for replayed Sentinel data: {:?}", cmd);
+        Ok(())
+    }
+
+    /// note that the requested filename might be derived from a re-based record time, which is why we look up the
+    /// archived file by record_id first
+    async fn handle_sentinel_file_query (&self, query: Query<GetSentinelFile,Result<SentinelFile>>)->Result<()> {
+        let record_id = query.question.record_id.clone();
+        let filename = &query.question.filename;
+        let archived = self.image_files.get( &record_id).unwrap_or( filename);
+
+        let archive = self.config.archive_dir.join(FILES_DIR);
+        let response = if archive.join( archived).is_file() {
+            let pathname = sentinel_cache_dir().join( filename);
+            if pathname.is_file() {
+                Ok( SentinelFile { record_id, pathname } )
+            } else {
+                fs::copy( archive.join( archived), &pathname)
+                    .map( |_| SentinelFile { record_id, pathname })
+                    .map_err( |e| OdinSentinelError::FileRequestError( e.to_string()))
+            }
+        } else {
+            Err( OdinSentinelError::FileRequestError( format!("no archived file for record {record_id}")))
+        };
+
+        query.respond( response).await.map_err(|e| e.into())
+    }
+
+    fn terminate (&mut self) {
+        if let Some(task) = self.replay_task.take() {
+            task.abort();
+        }
+    }
+
+    fn max_history(&self)->usize {
+        self.config.max_history_len
+    }
+
+    fn inactive_duration(&self)->Duration {
+        self.config.inactive_duration
+    }
+
+    fn inactive_interval(&self)->Duration {
+        self.config.inactive_interval
+    }
+
+    fn health_config(&self)->&SentinelHealthConfig {
+        &self.config.health
+    }
+
+    fn cmd_config(&self)->&SentinelCmdConfig {
+        &self.config.commands
+    }
+}
+
+/// copy an archived file into the sentinel cache so that it can be served like a downloaded file
+fn cache_archived_file (archive: &SentinelArchive, archived: &str, filename: &str) {
+    let pathname = sentinel_cache_dir().join( filename);
+    if !pathname.is_file() {
+        if let Err(e) = fs::copy( archive.file_path( archived), &pathname) {
+            warn!("failed to cache archived file {}: {}", archived, e)
+        }
+    }
+}
+
+/* #endregion ReplaySentinelConnector */
@@ -0,0 +1,329 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! vendor-neutral sensor network abstraction. The internal data model of this crate ([`SensorRecord`], [`SentinelUpdate`],
+//! [`Sentinel`] and [`SentinelStore`]) is shared by all networks, the vendor specific part is a [`SensorNetworkAdapter`] that
+//! translates device lists, initial records and pushed messages of the respective network into this model.
+//!
+//! This crate includes two adapters:
+//!   - [`DelphireAdapter`] - the original Delphire Sentinel API (used by the [`LiveSentinelConnector`])
+//!   - [`JsonReadingAdapter`] - networks that push [`SensorReading`] JSON messages (e.g. weather stations behind a local
+//!     MQTT broker or HTTP relay)
+//!
+//! Adapters are used by the generic [`SensorNetworkConnector`], which gets pushed messages through a channel that can be
+//! fed by any transport.
+
+use std::{sync::Arc, collections::HashSet};
+use chrono::{DateTime,Utc};
+use serde::{Serialize,Deserialize};
+use serde_json::{json, Value};
+use async_trait::async_trait;
+use reqwest::Client;
+
+use odin_actor::warn;
+use odin_common::datetime::{deserialize_rfc3339_or_epoch_millis, ser_epoch_millis};
+
+use crate::*;
+use crate::errors::*;
+
+/* #region device model *********************************************************************************************/
+
+/// network independent description of a sensor device
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+#[serde(rename_all="camelCase")]
+pub struct SensorDevice {
+    pub id: DeviceId,
+    #[serde(default)] pub name: Option<String>,
+    #[serde(default)] pub sensors: Vec<SensorDescriptor>,
+}
+
+impl SensorDevice {
+    pub fn new (id: impl ToString, name: Option<String>, sensors: Vec<SensorDescriptor>)->Self {
+        SensorDevice { id: id.to_string(), name, sensors }
+    }
+
+    pub fn display_name (&self)->String {
+        self.name.clone().unwrap_or_else( || "?".to_string())
+    }
+}
+
+/// a single sensor of a [`SensorDevice`], which can provide several capabilities (e.g. a weather station
+/// sensor package that reports thermometer and anemometer records)
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct SensorDescriptor {
+    pub no: u32,
+    pub capabilities: Vec<SensorCapability>,
+}
+
+/// the network neutral form of a single observation. `data` is the JSON representation of the respective
+/// `<Capability>Data` type (e.g. `{"temperature": 24.5}` for a [`ThermometerData`]). If no `id` is given we derive one
+/// from network, device, sensor, capability and time
+#[derive(Serialize,Deserialize,Debug,Clone)]
+#[serde(rename_all="camelCase")]
+pub struct SensorReading {
+    #[serde(default)] pub id: Option<RecordId>,
+    pub device_id: DeviceId,
+    #[serde(default)] pub sensor_no: u32,
+    #[serde(serialize_with="ser_epoch_millis", deserialize_with="deserialize_rfc3339_or_epoch_millis")]
+    pub time_recorded: DateTime<Utc>,
+    pub capability: SensorCapability,
+    pub data: Value,
+    #[serde(default)] pub evidences: Vec<RecordId>,
+}
+
+impl SensorReading {
+    pub fn record_id (&self, network: &str)->RecordId {
+        self.id.clone().unwrap_or_else( || {
+            format!("{}-{}-{}-{}-{}", network, self.device_id, self.sensor_no, self.capability.property_name(), self.time_recorded.timestamp_millis())
+        })
+    }
+
+    pub fn to_update (&self, network: &str)->Result<SentinelUpdate> {
+        let evidences: Vec<Value> = self.evidences.iter().map( |id| json!({"id": id})).collect();
+        let mut rec = json!({
+            "id": self.record_id( network),
+            "timeRecorded": self.time_recorded.timestamp_millis(),
+            "sensorNo": self.sensor_no,
+            "deviceId": self.device_id,
+            "evidences": evidences,
+            "claims": []
+        });
+        rec[self.capability.property_name()] = self.data.clone();
+        update_from_value( self.capability, rec)
+    }
+}
+
+/// turn a generic JSON record (in our own or in Delphire format) into a [`SentinelUpdate`] of the given capability
+pub fn update_from_value (capability: SensorCapability, v: Value)->Result<SentinelUpdate> {
+    use SensorCapability::*;
+    match capability {
+        Accelerometer => to_update::<AccelerometerData>( v),
+        Anemometer    => to_update::<AnemometerData>( v),
+        Cloudcover    => to_update::<CloudcoverData>( v),
+        Event         => to_update::<EventData>( v),
+        Fire          => to_update::<FireData>( v),
+        Gas           => to_update::<GasData>( v),
+        Gps           => to_update::<GpsData>( v),
+        Gyroscope     => to_update::<GyroscopeData>( v),
+        Magnetometer  => to_update::<MagnetometerData>( v),
+        Orientation   => to_update::<OrientationData>( v),
+        Person        => to_update::<PersonData>( v),
+        Power         => to_update::<PowerData>( v),
+        Smoke         => to_update::<SmokeData>( v),
+        Thermometer   => to_update::<ThermometerData>( v),
+        Valve         => to_update::<ValveData>( v),
+        Voc           => to_update::<VocData>( v),
+
+        Image => {
+            let mut rec: SensorRecord<ImageData> = serde_json::from_value( v)?;
+            rec.set_local_filename();
+            Ok( SentinelUpdate::from( Arc::new(rec)) )
+        }
+    }
+}
+
+fn to_update<T> (v: Value)->Result<SentinelUpdate> where T: RecordDataBounds, SentinelUpdate: From<Arc<SensorRecord<T>>> {
+    let rec: SensorRecord<T> = serde_json::from_value( v)?;
+    Ok( SentinelUpdate::from( Arc::new(rec)) )
+}
+
+/// get the capability of a record in Delphire format, which is given by the name of its data property
+pub fn record_capability (v: &Value)->Option<SensorCapability> {
+    v.as_object().and_then( |o| o.keys().find_map( |k| SensorCapability::capability_of( k.as_str())))
+}
+
+/* #endregion device model */
+
+/* #region adapter **************************************************************************************************/
+
+/// the vendor specific part of a sensor network import
+#[async_trait]
+pub trait SensorNetworkAdapter: Send + Sync + 'static {
+
+    /// short name of the network (e.g. "delphire"), used to tag devices and to derive record ids
+    fn network (&self)->&str;
+
+    /// the devices we import from this network
+    async fn get_devices (&self)->Result<Vec<SensorDevice>>;
+
+    /// the last `n_last` records of a device sensor capability (newest first). Push-only networks return an empty list
+    async fn get_records (&self, device: &SensorDevice, sensor_no: u32, capability: SensorCapability, n_last: usize)->Result<Vec<SentinelUpdate>>;
+
+    /// translate a pushed (vendor specific) message into updates. `topic` identifies the source of the message
+    /// (e.g. MQTT topic or HTTP path) and can be used by adapters that encode device or sensor in it
+    fn parse_message (&self, topic: &str, payload: &[u8])->Result<Vec<SentinelUpdate>>;
+
+    /// retrieve a file (e.g. image) referenced by a record of this network
+    async fn get_file (&self, request: &GetSentinelFile)->Result<SentinelFile> {
+        Err( OdinSentinelError::FileRequestError( format!("{} does not support file requests", self.network())))
+    }
+}
+
+impl SentinelStore {
+    /// initialize the store with the devices and records of a [`SensorNetworkAdapter`]. This can be called several times
+    /// to combine devices of different networks in the same store
+    pub async fn fetch_from_adapter (&mut self, adapter: &dyn SensorNetworkAdapter, n_last: usize, max_len: usize)->Result<()> {
+        let network = adapter.network().to_string();
+
+        for device in adapter.get_devices().await? {
+            let mut sentinel = Sentinel::new( device.id.clone(), device.display_name(), max_len);
+            sentinel.network = Some(network.clone());
+            self.insert( device.id.clone(), sentinel);
+
+            for sensor in &device.sensors {
+                for capability in &sensor.capabilities {
+                    for update in adapter.get_records( &device, sensor.no, *capability, n_last).await? {
+                        self.update_with( update, max_len);
+                    }
+                }
+            }
+
+            if let Some(sentinel) = self.get_mut( &device.id) { sentinel.set_time_recorded() } // from latest sensor record
+        }
+
+        Ok(())
+    }
+}
+
+/* #endregion adapter */
+
+/* #region Delphire adapter *****************************************************************************************/
+
+/// the [`SensorNetworkAdapter`] for the Delphire Sentinel API. Delphire pushes only record notifications over its
+/// websocket (which is handled by the [`LiveSentinelConnector`]), hence pushed messages for this adapter are expected
+/// to be complete records in Delphire format (single record, list of records or `RecordList`), e.g. from a relay
+pub struct DelphireAdapter {
+    client: Client,
+    base_uri: String,
+    access_token: String,
+    device_filter: Vec<String>,
+}
+
+impl DelphireAdapter {
+    pub fn new (client: Client, base_uri: impl ToString, access_token: impl ToString, device_filter: Vec<String>)->Self {
+        DelphireAdapter { client, base_uri: base_uri.to_string(), access_token: access_token.to_string(), device_filter }
+    }
+
+    pub fn from_config (client: &Client, config: &SentinelConfig)->Self {
+        Self::new( client.clone(), &config.base_uri, &config.access_token, config.device_filter.clone())
+    }
+}
+
+#[async_trait]
+impl SensorNetworkAdapter for DelphireAdapter {
+    fn network (&self)->&str { "delphire" }
+
+    async fn get_devices (&self)->Result<Vec<SensorDevice>> {
+        let device_list = get_device_list( &self.client, &self.base_uri, &self.access_token).await?;
+        let mut devices = Vec::with_capacity( device_list.data.len());
+
+        for device in device_list.data {
+            if self.device_filter.is_empty() || self.device_filter.contains( &device.id) {
+                let sensor_list = get_sensor_list( &self.client, &self.base_uri, &self.access_token, device.id.as_str()).await?;
+                let sensors = sensor_list.data.into_iter().map( |s| SensorDescriptor { no: s.no, capabilities: s.capabilities }).collect();
+                devices.push( SensorDevice::new( device.id, device.info, sensors));
+            }
+        }
+        Ok(devices)
+    }
+
+    async fn get_records (&self, device: &SensorDevice, sensor_no: u32, capability: SensorCapability, n_last: usize)->Result<Vec<SentinelUpdate>> {
+        // Sentinel::init_records already knows how to map capabilities to record types, we just need the updates
+        let mut sentinel = Sentinel::new( device.id.clone(), device.display_name(), n_last);
+        sentinel.init_records( &self.client, &self.base_uri, &self.access_token, sensor_no, capability, n_last, n_last).await
+    }
+
+    fn parse_message (&self, topic: &str, payload: &[u8])->Result<Vec<SentinelUpdate>> {
+        let recs = match serde_json::from_slice::<Value>( payload)? {
+            Value::Array(recs) => recs,
+            Value::Object(mut o) if o.get("data").map( |d| d.is_array()).unwrap_or(false) => {
+                if let Some(Value::Array(recs)) = o.remove("data") { recs } else { Vec::new() }
+            }
+            v => vec![v]
+        };
+
+        recs.into_iter().map( |v| {
+            let capability = record_capability( &v).ok_or_else( || no_data( format!("no record data in message from {topic}")))?;
+            update_from_value( capability, v)
+        }).collect()
+    }
+}
+
+/* #endregion Delphire adapter */
+
+/* #region JSON reading adapter *************************************************************************************/
+
+/// configuration for a [`JsonReadingAdapter`]
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct JsonReadingNetwork {
+    pub network: String,
+    pub devices: Vec<SensorDevice>,
+
+    /// if set we reject readings from devices that are not in `devices`
+    #[serde(default)] pub strict: bool,
+}
+
+/// a push-only [`SensorNetworkAdapter`] for networks that send [`SensorReading`] JSON messages (single readings or
+/// lists thereof). Devices are configured since such networks normally don't have a query API
+pub struct JsonReadingAdapter {
+    config: JsonReadingNetwork,
+    device_ids: HashSet<DeviceId>,
+}
+
+impl JsonReadingAdapter {
+    pub fn new (config: JsonReadingNetwork)->Self {
+        let device_ids = config.devices.iter().map( |d| d.id.clone()).collect();
+        JsonReadingAdapter { config, device_ids }
+    }
+
+    fn to_update (&self, reading: SensorReading)->Result<SentinelUpdate> {
+        if self.config.strict && !self.device_ids.contains( &reading.device_id) {
+            Err( OdinSentinelError::NoSuchDeviceError( reading.device_id))
+        } else {
+            reading.to_update( &self.config.network)
+        }
+    }
+}
+
+#[async_trait]
+impl SensorNetworkAdapter for JsonReadingAdapter {
+    fn network (&self)->&str { self.config.network.as_str() }
+
+    async fn get_devices (&self)->Result<Vec<SensorDevice>> {
+        Ok( self.config.devices.clone() )
+    }
+
+    async fn get_records (&self, device: &SensorDevice, sensor_no: u32, capability: SensorCapability, n_last: usize)->Result<Vec<SentinelUpdate>> {
+        Ok( Vec::new() ) // we only get pushed readings
+    }
+
+    /// invalid or (in strict mode) unknown readings within a list are skipped, i.e. they do not invalidate the whole message
+    fn parse_message (&self, topic: &str, payload: &[u8])->Result<Vec<SentinelUpdate>> {
+        match serde_json::from_slice::<Value>( payload)? {
+            Value::Array(readings) => {
+                let updates = readings.into_iter().filter_map( |v| {
+                    match serde_json::from_value::<SensorReading>( v).map_err( OdinSentinelError::from).and_then( |reading| self.to_update( reading)) {
+                        Ok(update) => Some(update),
+                        Err(e) => { warn!("skipping {} reading from {topic}: {e}", self.config.network); None }
+                    }
+                }).collect();
+                Ok(updates)
+            }
+            v => Ok( vec![ self.to_update( serde_json::from_value( v)?)? ] )
+        }
+    }
+}
+
+/* #endregion JSON reading adapter */
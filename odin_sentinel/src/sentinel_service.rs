@@ -13,13 +13,16 @@
  */
 #![allow(unused)]
 
-use std::{net::SocketAddr,any::type_name,fs, time::Duration};
+use std::{net::SocketAddr,any::type_name,fs,fmt::Display,future::Future,time::Duration};
+use serde::{Serialize,Deserialize};
+use chrono::Utc;
 use async_trait::async_trait;
 use axum::{
     http::{Uri,StatusCode},
     body::Body,
     routing::{Router,get},
-    extract::{Path as AxumPath},
+    extract::{Path as AxumPath, Query as AxumQuery},
+    Json,
     response::{Response,IntoResponse},
 };
 
@@ -29,21 +32,46 @@ use odin_server::prelude::*;
 use odin_cesium::ImgLayerService;
 
 use crate::{
-    load_config, load_asset, sentinel_cache_dir, ExecSnapshotAction, SentinelConfig, SentinelActorMsg, SentinelStore, SentinelDeviceInfo, SentinelDeviceInfos
+    load_config, load_asset, sentinel_cache_dir, ExecSnapshotAction, SentinelConfig, SentinelActorMsg, SentinelStore, SentinelDeviceInfo, SentinelDeviceInfos,
+    AcknowledgeAlarm, ResolveAlarm, AddAlarmStatusAction, GetAlarmStatus, AlarmNo, AlarmStatus, SentinelAlarmMonitorMsg,
+    AlarmAuditStore, AlarmAuditQuery, AddHealthAction, SentinelHealth, SentinelHealthConfig,
+    AddCmdQueueAction, GetSentinelCmdQueue, SentinelCmdStatus, CancelSentinelCmd, SetSentinelCmdFlag
 };
 
+/// payload of "ackAlarm" and "resolveAlarm" websocket messages from the browser
+#[derive(Deserialize,Debug)]
+struct AlarmRef { no: AlarmNo }
+
+/// payload of "cancelCmd" websocket messages from the browser (name of the command schedule)
+#[derive(Deserialize,Debug)]
+struct CmdScheduleRef { name: String }
+
+/// payload of "setCmdFlag" websocket messages from the browser
+#[derive(Deserialize,Debug)]
+struct CmdFlag { name: String, active: bool }
+
 /// SpaService to show sentinel infos on a cesium display
 pub struct SentinelService {
     config: SentinelConfig,
     device_infos: SentinelDeviceInfos,
     hsentinel: ActorHandle<SentinelActorMsg>, // our data source
+    halarm: Option<ActorHandle<SentinelAlarmMonitorMsg>>, // optional alarm lifecycle source
+    is_alarm_action_registered: bool,
+    is_health_action_registered: bool,
+    is_cmd_action_registered: bool,
 }
 
 impl SentinelService {
     pub fn new (hsentinel: ActorHandle<SentinelActorMsg>, )->Self { 
         let config = load_config("sentinel.ron").expect("failed to load sentinel.ron config"); // Ok to panic in ctor
         let device_infos = load_config("sentinel_info.ron").expect("failed to load sentinel_info.ron config"); 
-        SentinelService{config,device_infos,hsentinel}
+        SentinelService{config,device_infos,hsentinel, halarm: None, is_alarm_action_registered: false, is_health_action_registered: false, is_cmd_action_registered: false}
+    }
+
+    /// show alarm lifecycle states and allow users to acknowledge/resolve alarms
+    pub fn with_alarm_monitor (mut self, halarm: ActorHandle<SentinelAlarmMonitorMsg>)->Self {
+        self.halarm = Some(halarm);
+        self
     }
 
     async fn image_handler (path: AxumPath<String>) -> Response {
@@ -56,6 +84,27 @@ impl SentinelService {
     }
 
     pub fn mod_path()->&'static str { type_name::<Self>() }
+
+    /// query initial state in a background task so that a slow source does not block the SpaServer (and hence all
+    /// other connections). If the query fails the client gets an empty list - later changes are still broadcast
+    fn send_state_async<F,T,E> (hself: &ActorHandle<SpaServerMsg>, conn_id: ConnectionId, msg_type: &'static str, query: F)
+        where F: Future<Output=std::result::Result<Vec<T>,E>> + Send + 'static, T: Serialize + Send + 'static, E: Display
+    {
+        let hself = hself.clone();
+        let res = spawn( msg_type, async move {
+            let state = query.await.unwrap_or_else( |e| {
+                warn!("failed to get {msg_type} for {conn_id}: {e}");
+                Vec::new()
+            });
+            match WsMsg::json( SentinelService::mod_path(), msg_type, state) {
+                Ok(data) => if let Err(e) = hself.send_msg( SendWsMsg{conn_id,data}).await {
+                    warn!("failed to send {msg_type} to {conn_id}: {e}")
+                }
+                Err(e) => warn!("failed to serialize {msg_type}: {e}")
+            }
+        });
+        if let Err(e) = res { warn!("failed to spawn {msg_type} query: {e}") }
+    }
 }
 
 #[async_trait]
@@ -81,12 +130,20 @@ impl SpaService for SentinelService {
 
         if self.hsentinel.id() == sender_id && data_type == type_name::<SentinelStore>() { // is this for us?
             if has_connections {
-                let action = dyn_dataref_action!( let hself: ActorHandle<SpaServerMsg> = hself.clone() => |data: &SentinelStore| {
-                    let sentinels = data.values();
-                    //let data = ws_msg!( MOD_PATH, sentinels).to_json()?;
-                    let data = WsMsg::json( SentinelService::mod_path(), "sentinels", sentinels)?;
-                    Ok( hself.try_send_msg( BroadcastWsMsg{data})? )
-                });
+                let action = dyn_dataref_action!{
+                    let hself: ActorHandle<SpaServerMsg> = hself.clone(),
+                    let health_config: SentinelHealthConfig = self.config.health.clone() =>
+                    |data: &SentinelStore| {
+                        let health = data.health_report( &health_config, Utc::now());
+                        let sentinels = data.values();
+                        //let data = ws_msg!( MOD_PATH, sentinels).to_json()?;
+                        let data = WsMsg::json( SentinelService::mod_path(), "sentinels", sentinels)?;
+                        hself.try_send_msg( BroadcastWsMsg{data})?;
+
+                        let data = WsMsg::json( SentinelService::mod_path(), "health", health)?;
+                        Ok( hself.try_send_msg( BroadcastWsMsg{data})? )
+                    }
+                };
                 self.hsentinel.send_msg( ExecSnapshotAction(action)).await?;
             }
             is_our_data = true;
@@ -96,34 +153,157 @@ impl SpaService for SentinelService {
 
     // send an ExecSnapshotAction to the SentinelActor to send a JSON websocket message to the new connection
     async fn init_connection (&mut self, hself: &ActorHandle<SpaServerMsg>, is_data_available: bool, conn: &mut SpaConnection) -> OdinServerResult<()> {
-        let remote_addr = conn.remote_addr;
+        let conn_id = conn.id;
 
         //--- send device_infos message to browser
         let device_infos = &self.device_infos;
         //let data = ws_msg!( MOD_PATH, device_infos).to_json()?;
         let data = WsMsg::json( SentinelService::mod_path(), "device_infos", device_infos)?;
-        hself.try_send_msg( SendWsMsg{remote_addr,data})?;
+        hself.try_send_msg( SendWsMsg{conn_id,data})?;
 
         //--- send inactive_duration to browser
         let inactive_duration = self.config.inactive_duration.as_millis() as u64;
         //let data = ws_msg!( MOD_PATH, inactive_duration).to_json()?;
         let data = WsMsg::json( SentinelService::mod_path(), "inactive_duration", inactive_duration)?;
-        hself.try_send_msg( SendWsMsg{remote_addr,data})?;
+        hself.try_send_msg( SendWsMsg{conn_id,data})?;
 
         if is_data_available {
             let action = dyn_dataref_action!{
                 let hself: ActorHandle<SpaServerMsg> = hself.clone(), 
-                let remote_addr: SocketAddr = remote_addr => 
+                let conn_id: ConnectionId = conn_id,
+                let health_config: SentinelHealthConfig = self.config.health.clone() => 
                 |data: &SentinelStore| {
+                    let health = data.health_report( &health_config, Utc::now());
                     let sentinels = data.values();
                     //let data = ws_msg!( MOD_PATH, sentinels).to_json()?;
                     let data = WsMsg::json( SentinelService::mod_path(), "sentinels", sentinels)?;
-                    let remote_addr = remote_addr.clone();
-                    Ok( hself.try_send_msg( SendWsMsg{remote_addr,data})? )
+                    let conn_id = conn_id.clone();
+                    hself.try_send_msg( SendWsMsg{conn_id,data})?;
+
+                    let data = WsMsg::json( SentinelService::mod_path(), "health", health)?;
+                    Ok( hself.try_send_msg( SendWsMsg{conn_id,data})? )
                 }
             };
             self.hsentinel.send_msg( ExecSnapshotAction(action)).await?;
         }
+
+        if !self.is_health_action_registered { // the SentinelActor periodically re-computes health, we just need to broadcast it
+            let action = dyn_data_action!( let hself: ActorHandle<SpaServerMsg> = hself.clone() => |health: Vec<SentinelHealth>| {
+                let data = WsMsg::json( SentinelService::mod_path(), "health", health)?;
+                Ok( hself.try_send_msg( BroadcastWsMsg{data})? )
+            });
+            self.hsentinel.send_msg( AddHealthAction(action)).await?;
+            self.is_health_action_registered = true;
+        }
+
+        if !self.is_cmd_action_registered { // the SentinelActor publishes command queue changes
+            let action = dyn_data_action!( let hself: ActorHandle<SpaServerMsg> = hself.clone() => |cmds: Vec<SentinelCmdStatus>| {
+                let data = WsMsg::json( SentinelService::mod_path(), "cmdQueue", cmds)?;
+                Ok( hself.try_send_msg( BroadcastWsMsg{data})? )
+            });
+            self.hsentinel.send_msg( AddCmdQueueAction(action)).await?;
+            self.is_cmd_action_registered = true;
+        }
+
+        let hsentinel = self.hsentinel.clone();
+        Self::send_state_async( hself, conn_id, "cmdQueue", async move {
+            timeout_query_ref::<_,Vec<SentinelCmdStatus>,_>( &hsentinel, GetSentinelCmdQueue, secs(2)).await
+        });
+
+        if let Some(halarm) = &self.halarm {
+            if !self.is_alarm_action_registered { // we only need one action to broadcast lifecycle changes
+                let action = dyn_data_action!( let hself: ActorHandle<SpaServerMsg> = hself.clone() => |status: AlarmStatus| {
+                    let data = WsMsg::json( SentinelService::mod_path(), "alarmStatus", status)?;
+                    Ok( hself.try_send_msg( BroadcastWsMsg{data})? )
+                });
+                halarm.send_msg( AddAlarmStatusAction(action)).await?;
+                self.is_alarm_action_registered = true;
+            }
+
+            let halarm = halarm.clone();
+            Self::send_state_async( hself, conn_id, "alarms", async move {
+                timeout_query_ref::<_,Vec<AlarmStatus>,_>( &halarm, GetAlarmStatus, secs(2)).await
+            });
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn handle_ws_msg (&mut self, 
+        hself: &ActorHandle<SpaServerMsg>, conn_id: &ConnectionId, user: Option<&str>, ws_msg_parts: &WsMsgParts
+    ) -> OdinServerResult<WsMsgReaction> {
+        if ws_msg_parts.mod_path == SentinelService::mod_path() {
+            // command queue changes are published through the command queue action
+            match ws_msg_parts.msg_type {
+                "cancelCmd" => match serde_json::from_str::<CmdScheduleRef>( ws_msg_parts.payload) {
+                    Ok(CmdScheduleRef{name}) => self.hsentinel.send_msg( CancelSentinelCmd{name}).await?,
+                    Err(e) => warn!("ignoring malformed cancelCmd message from {conn_id}: {e}")
+                }
+                "setCmdFlag" => match serde_json::from_str::<CmdFlag>( ws_msg_parts.payload) {
+                    Ok(CmdFlag{name,active}) => self.hsentinel.send_msg( SetSentinelCmdFlag{name,active}).await?,
+                    Err(e) => warn!("ignoring malformed setCmdFlag message from {conn_id}: {e}")
+                }
+                _ => {}
+            }
+
+            if let Some(halarm) = &self.halarm {
+                let by = user.map( |u| u.to_string()).unwrap_or( conn_id.to_string());
+                let via = "web".to_string();
+
+                // we don't respond directly - all clients get the new state from the alarm status action
+                match ws_msg_parts.msg_type {
+                    "ackAlarm" => match serde_json::from_str::<AlarmRef>( ws_msg_parts.payload) {
+                        Ok(AlarmRef{no}) => halarm.send_msg( AcknowledgeAlarm{ no: Some(no), by, via }).await?,
+                        Err(e) => warn!("ignoring malformed ackAlarm message from {conn_id}: {e}")
+                    }
+                    "resolveAlarm" => match serde_json::from_str::<AlarmRef>( ws_msg_parts.payload) {
+                        Ok(AlarmRef{no}) => halarm.send_msg( ResolveAlarm{ no: Some(no), by, via }).await?,
+                        Err(e) => warn!("ignoring malformed resolveAlarm message from {conn_id}: {e}")
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok( WsMsgReaction::None )
+    }
+}
+/// SpaService to show and filter the alarm audit trail (see [`crate::AlarmAuditStore`]). This only reads the audit files,
+/// i.e. it does not require the `SentinelAlarmMonitor` to run in the same process
+pub struct SentinelAlarmAuditService {
+    store: AlarmAuditStore,
+}
+
+impl SentinelAlarmAuditService {
+    pub fn new (store: AlarmAuditStore)->Self {
+        SentinelAlarmAuditService { store }
+    }
+
+    /// http query API, e.g. `GET <app>/sentinel-alarm-audit?device=roo7&alarm_type=smoke&since=2024-06-01T00:00:00Z&limit=100`
+    async fn query_handler (store: AlarmAuditStore, query: AlarmAuditQuery) -> Response {
+        // the store reads (possibly large) audit files, which we don't want to do on a runtime worker thread
+        let res = match spawn_blocking( "sentinel-audit-query", move || store.query( &query)) {
+            Ok(task) => task.await.map_err( |e| e.to_string()).and_then( |r| r.map_err( |e| e.to_string())),
+            Err(e) => Err( e.to_string())
+        };
+
+        match res {
+            Ok(entries) => Json(entries).into_response(),
+            Err(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+        }
+    }
+}
+
+#[async_trait]
+impl SpaService for SentinelAlarmAuditService {
+    fn add_components (&self, spa: &mut SpaComponents) -> OdinServerResult<()>  {
+        spa.add_assets( self_crate!(), load_asset);
+        spa.add_module( asset_uri!("odin_sentinel_audit.js"));
+
+        let store = self.store.clone();
+        spa.add_route( move |router, spa_server_state| {
+            router.route( &format!("/{}/sentinel-alarm-audit", spa_server_state.name.as_str()), 
+                get( move |AxumQuery(query): AxumQuery<AlarmAuditQuery>| Self::query_handler( store, query)))
+        });
+
+        Ok(())
+    }
+}
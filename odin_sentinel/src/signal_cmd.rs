@@ -14,12 +14,13 @@
 
 use std::{path::PathBuf,time::Duration};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::{process::{Command,Child},time::{timeout,error::Elapsed}};
 //use std::process::Command;
 use which::which;
 use async_trait::async_trait;
 use odin_common::if_let;
-use crate::{Alarm,AlarmMessenger,EvidenceInfo, OdinSentinelError};
+use crate::{Alarm,AlarmMessenger,AlarmReply,EvidenceInfo, OdinSentinelError};
 use crate::errors::{op_failed,Result};
 
 #[derive(Deserialize,Serialize)]
@@ -28,6 +29,10 @@ pub struct SignalCmdConfig {
     pub recipients: Vec<String>,
     pub group_ids: Vec<String>,
     pub timeout: Duration,
+
+    /// poll signal-cli for replies to alarms (acknowledgements). Don't set if signal-cli messages are also used elsewhere
+    #[serde(default)]
+    pub receive_replies: bool,
 }
 
 /// `AlarmMessenger` implementation that send alarms as text messages to Signal accounts
@@ -67,9 +72,13 @@ impl AlarmMessenger for SignalCmdAlarmMessenger {
             acc
         });
 
+        // if the alarm has on-call contacts we only notify those
+        let alarm_recipients: Vec<&String> = if alarm.contacts.is_empty() { config.recipients.iter().collect() } else { alarm.contact_addresses( |c| c.signal.as_ref()) };
+        if alarm_recipients.is_empty() { return Err( op_failed( format!("no signal recipients for alarm {}", alarm.id))) }
+
         let mut usernames: Vec<&String> = Vec::new();
         let mut recipients: Vec<&String> = Vec::new();
-        for r in &config.recipients {
+        for r in alarm_recipients {
             if r.starts_with("+") { recipients.push(r) } else { usernames.push(r) }
         }
 
@@ -105,4 +114,32 @@ impl AlarmMessenger for SignalCmdAlarmMessenger {
             Err(e) => Err( OdinSentinelError::CommandError(e.to_string()) )
         }
     }
+    async fn receive_replies (&self)->Result<Vec<AlarmReply>> {
+        if !self.config.receive_replies { return Ok(Vec::new()) }
+
+        let mut cmd = Command::new( self.config.cmd.as_str());
+        cmd
+            .arg("-o").arg("json")
+            .arg("receive")
+            .arg("--timeout").arg("1");
+
+        let output = timeout( self.config.timeout, cmd.output()).await??;
+        if output.status.success() {
+            let stdout = String::from_utf8_lossy( &output.stdout);
+            Ok( stdout.lines().filter_map( |line| serde_json::from_str::<Value>(line).ok()).filter_map( |v| parse_signal_envelope( &v)).collect() )
+        } else {
+            Err( OdinSentinelError::CommandError( String::from_utf8_lossy( &output.stderr).to_string()) )
+        }
+    }
+}
+
+/// extract sender and text from a signal-cli JSON message envelope (either `{"envelope":{..}}` or the envelope itself).
+/// Returns `None` for receipts, typing indicators and other non-text messages
+pub(crate) fn parse_signal_envelope (v: &Value)->Option<AlarmReply> {
+    let envelope = v.get("envelope").unwrap_or(v);
+    let from = envelope.get("sourceNumber").and_then( |s| s.as_str())
+        .or_else( || envelope.get("source").and_then( |s| s.as_str()))?;
+    let text = envelope.get("dataMessage")?.get("message")?.as_str()?;
+
+    Some( AlarmReply { from: from.to_string(), via: "signal".to_string(), text: text.to_string() })
 }
\ No newline at end of file
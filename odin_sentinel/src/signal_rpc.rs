@@ -24,7 +24,8 @@ use jsonrpsee::async_client::Client;
 use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
 
 use odin_common::if_let;
-use crate::{Alarm,AlarmMessenger,EvidenceInfo};
+use crate::{Alarm,AlarmMessenger,AlarmReply,EvidenceInfo};
+use crate::signal_cmd::parse_signal_envelope;
 use crate::errors::{op_failed,OdinSentinelError,Result as SentinelResult};
 
 #[derive(Deserialize,Serialize)]
@@ -34,6 +35,10 @@ pub struct SignalRpcConfig {
     pub recipients: Vec<String>,
     pub group_ids: Vec<String>,
     pub timeout: Duration,
+
+    /// poll the signal-cli server for replies to alarms (requires the server to run with `--receive-mode=manual`)
+    #[serde(default)]
+    pub receive_replies: bool,
 }
 
 /// send-message RPC definition.
@@ -53,6 +58,13 @@ pub trait Rpc {
         attachments:Vec<String>,
         notify_self: bool,  // TODO - as of signal-cli 0.13.4-SNAPSHOT this is only working when invoking the "send" command interactively
     ) -> Result<Value, ErrorObjectOwned>;
+
+    #[method(name = "receive", param_kind = map)]
+    fn receive(
+        &self,
+        account: Option<&String>,
+        timeout: f64, // [sec]
+    ) -> Result<Value, ErrorObjectOwned>;
 }
 
 fn create_client (uri: &str)->HttpClient {
@@ -98,10 +110,18 @@ impl AlarmMessenger for SignalRpcAlarmMessenger {
             }
         }
 
+        // if the alarm has on-call contacts we only notify those (not the groups)
+        let (recipients, group_ids) = if alarm.contacts.is_empty() {
+            (config.recipients.clone(), config.group_ids.clone())
+        } else {
+            (alarm.contact_addresses( |c| c.signal.as_ref()).into_iter().cloned().collect(), Vec::new())
+        };
+        if recipients.is_empty() && group_ids.is_empty() { return Err( op_failed( format!("no signal recipients for alarm {}", alarm.id))) }
+
         let rpc_fut = self.client.send(
             Some(&config.signal_account),
-            &config.recipients,
-            &config.group_ids,
+            &recipients,
+            &group_ids,
             message,
             attachments,
             true, // always notify self - it's an alarm
@@ -124,4 +144,13 @@ impl AlarmMessenger for SignalRpcAlarmMessenger {
             other => Err( OdinSentinelError::RpcError( format!("invalid RPC response: {other:?}")))
         }
     }
+    async fn receive_replies (&self)->SentinelResult<Vec<AlarmReply>> {
+        if !self.config.receive_replies { return Ok(Vec::new()) }
+
+        let rpc_fut = self.client.receive( Some(&self.config.signal_account), 1.0);
+        match timeout( self.config.timeout, rpc_fut).await?? {
+            Value::Array(envelopes) => Ok( envelopes.iter().filter_map( parse_signal_envelope).collect() ),
+            other => Err( OdinSentinelError::RpcError( format!("invalid RPC response: {other:?}")))
+        }
+    }
 }
\ No newline at end of file
@@ -71,17 +71,23 @@ impl AlarmMessenger for SlackAlarmMessenger {
     async fn send_alarm (&self, alarm: &Alarm)->Result<()> {
         let config = &self.config;
         let files = get_file_attachments(alarm);
-        if files.is_empty() {
-            for alarm_channel in &self.config.alarm_channels {
-                if alarm_channel.matches(alarm) {
-                    slack::send_msg( &config.token, &alarm_channel.id, &alarm.description, None).await?;
-                }
-            }
+
+        // if the alarm has on-call contacts we only notify those
+        let channel_ids: Vec<&String> = if alarm.contacts.is_empty() {
+            config.alarm_channels.iter().filter( |c| c.matches(alarm)).map( |c| &c.id).collect()
         } else {
-            for alarm_channel in &self.config.alarm_channels {
-                if alarm_channel.matches(alarm) {
-                    slack::send_msg_with_files( &config.token, &alarm_channel.id, &alarm.description, &files).await?;
-                }
+            alarm.contact_addresses( |c| c.slack.as_ref())
+        };
+        // channels that don't match the alarm type are a routing decision, on-call contacts without slack ids are not
+        if channel_ids.is_empty() && !alarm.contacts.is_empty() {
+            return Err( op_failed!("no slack address for on-call contacts of alarm {}", alarm.id))
+        }
+
+        for channel_id in channel_ids {
+            if files.is_empty() {
+                slack::send_msg( &config.token, channel_id, &alarm.description, None).await?;
+            } else {
+                slack::send_msg_with_files( &config.token, channel_id, &alarm.description, &files).await?;
             }
         }
 
@@ -15,14 +15,14 @@
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{Message, SmtpTransport, AsyncSmtpTransport, Transport, AsyncTransport, Tokio1Executor};
 use lettre::message::{Mailbox, MultiPart, SinglePart, Attachment, Body, header::ContentType};
-use std::{fs, path::{Path,PathBuf}, time::Duration};
+use std::{fs, hash::{BuildHasher,RandomState}, path::{Path,PathBuf}, time::Duration};
 use serde::{Deserialize, Serialize};
 use async_trait::async_trait;
 use tokio::time::timeout;
 
 use odin_actor::{warn,error};
 use odin_common::if_let;
-use crate::{op_failed, Alarm, AlarmMessenger, EvidenceInfo, OdinSentinelError};
+use crate::{op_failed, parse_alarm_command, parse_alarm_reply, Alarm, AlarmMessenger, AlarmNo, AlarmReply, EvidenceInfo, OdinSentinelError};
 use crate::errors::Result;
 
 #[derive(Deserialize,Serialize,Debug)]
@@ -33,6 +33,12 @@ pub struct SmtpConfig {
     pub sender: String,
     pub recipients: Vec<String>,
     pub timeout: Duration,
+
+    /// Maildir that receives replies to alarm emails (e.g. populated by fetchmail/procmail), which are
+    /// checked for acknowledgement commands. Replies have to include the reply token of the alarm (see
+    /// [`SmtpAlarmMessenger::reply_token`]) since the sender address of emails is not authenticated
+    #[serde(default)]
+    pub reply_maildir: Option<PathBuf>,
 }
 
  /// SMTP based AlarmMessenger
@@ -46,7 +52,8 @@ pub struct SmtpAlarmMessenger {
     config: SmtpConfig,
 
     from_addr: Mailbox,
-    bcc_addrs: Vec<Mailbox>
+    bcc_addrs: Vec<Mailbox>,
+    reply_key: RandomState, // random key for reply tokens
 }
 
 impl SmtpAlarmMessenger {
@@ -56,7 +63,13 @@ impl SmtpAlarmMessenger {
         let bcc_addrs: Vec<Mailbox> = config.recipients.iter().map(|r| r.parse::<Mailbox>().unwrap()).collect();
         if bcc_addrs.is_empty() { warn!("no alarm receiver configured" )}
 
-        SmtpAlarmMessenger { config, from_addr, bcc_addrs }
+        SmtpAlarmMessenger { config, from_addr, bcc_addrs, reply_key: RandomState::new() }
+    }
+
+    /// the token we add to the subject of alarm emails and expect in replies, so that a forged From header is not
+    /// enough to acknowledge an alarm. Tokens are keyed with a random per-messenger key, i.e. they do not survive restarts
+    pub fn reply_token (&self, no: AlarmNo)->String {
+        format!("{:016x}", self.reply_key.hash_one( no))
     }
 }
 
@@ -73,12 +86,95 @@ impl AlarmMessenger for SmtpAlarmMessenger {
             .credentials(creds)
             .build();
 
+        // if the alarm has on-call contacts we only notify those
+        let contact_addrs: Vec<Mailbox>;
+        let recipients = if alarm.contacts.is_empty() {
+            &self.bcc_addrs
+        } else {
+            contact_addrs = alarm.contact_addresses( |c| c.email.as_ref()).iter().filter_map( |a| a.parse::<Mailbox>().ok()).collect();
+            &contact_addrs
+        };
+        if recipients.is_empty() { return Err( op_failed!("no email recipients for alarm {}", alarm.id)) }
+
+        let subject = match (alarm.no, &config.reply_maildir) { // so that replies refer to it
+            (0, _) => "alarm".to_string(),
+            (no, Some(_)) => format!("alarm #{no} [{}]", self.reply_token( no)),
+            (no, None) => format!("alarm #{no}")
+        };
         let evidences = get_attachments(&alarm);
-        let message = create_message(&self.from_addr, &self.bcc_addrs, "alarm", &alarm.description, &evidences)?;
+        let message = create_message(&self.from_addr, recipients, &subject, &alarm.description, &evidences)?;
 
         let response = timeout( self.config.timeout, mailer.send(message)).await??;
         if response.is_positive() { Ok(()) } else { Err( OdinSentinelError::SmtpError( format!("{response:?}"))) }
     }
+    async fn receive_replies (&self)->Result<Vec<AlarmReply>> {
+        match &self.config.reply_maildir {
+            Some(dir) => read_maildir_replies( dir, |no| self.reply_token( no)),
+            None => Ok(Vec::new())
+        }
+    }
+}
+
+/// read all new messages from a Maildir and move them to its 'cur' subdir. Replies are recognized by a command line
+/// in the body ("ack 42") or - if the body has none - by a command line in the subject ("Re: alarm #42" with body "ack").
+/// Replies are only returned if they contain the `reply_token` of the alarm they refer to. Unreadable messages are
+/// skipped (and moved to 'cur' so that they don't block subsequent polls)
+pub fn read_maildir_replies (dir: &Path, reply_token: impl Fn(AlarmNo)->String)->Result<Vec<AlarmReply>> {
+    let mut replies = Vec::new();
+    let cur_dir = dir.join("cur");
+    fs::create_dir_all( &cur_dir)?;
+
+    for entry in fs::read_dir( dir.join("new"))? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => { warn!("failed to read Maildir entry: {e}"); continue }
+        };
+        if !path.is_file() { continue }
+
+        match fs::read( &path) {
+            Ok(bytes) => match parse_mail_reply( &String::from_utf8_lossy( &bytes), &reply_token) {
+                Some(reply) => replies.push( reply),
+                None => warn!("ignoring mail reply without alarm command or reply token: {:?}", path)
+            }
+            Err(e) => warn!("failed to read mail reply {:?}: {e}", path)
+        }
+
+        if let Some(fname) = path.file_name() { // mark as seen
+            if let Err(e) = fs::rename( &path, cur_dir.join( format!("{}:2,S", fname.to_string_lossy()))) {
+                warn!("failed to move mail reply {:?} to cur: {e}", path)
+            }
+        }
+    }
+
+    Ok(replies)
+}
+
+fn parse_mail_reply (mail: &str, reply_token: &impl Fn(AlarmNo)->String)->Option<AlarmReply> {
+    let (header, body) = mail.split_once("\r\n\r\n").or_else( || mail.split_once("\n\n"))?;
+
+    let header_value = |name: &str| header.lines().find_map( |l| {
+        l.split_once(':').filter( |(k,_)| k.trim().eq_ignore_ascii_case(name)).map( |(_,v)| v.trim().to_string())
+    });
+    let from = header_value("From")?;
+    let from = from.rsplit_once('<').and_then( |(_,a)| a.split_once('>')).map( |(a,_)| a.to_string()).unwrap_or(from);
+    let subject_no = header_value("Subject").and_then( |s| s.rsplit_once('#').and_then( |(_,n)| {
+        let digits: String = n.chars().take_while( |c| c.is_ascii_digit()).collect();
+        digits.parse::<AlarmNo>().ok()
+    }));
+
+    // the first body line that is a command (we skip quoted text and MIME part headers this way)
+    let is_cmd = |l: &str| l.split_whitespace().next().and_then( parse_alarm_command).is_some();
+    let text = body.lines().map( |l| l.trim()).find( |l| !l.starts_with('>') && is_cmd(l))?;
+    let text = match (parse_alarm_reply(text), subject_no) {
+        (None, Some(no)) => format!("{} {no}", text.split_whitespace().next()?), // the subject refers to the alarm
+        _ => text.to_string()
+    };
+
+    // the token is in the subject of the alarm email, i.e. it should be in the reply subject or quoted body
+    let (_,no) = parse_alarm_reply( &text)?;
+    if !mail.contains( &reply_token( no)) { return None }
+
+    Some( AlarmReply { from, via: "email".to_string(), text })
 }
 
  pub fn get_attachments (alarm: &Alarm)->Vec<(String,PathBuf)> {
@@ -0,0 +1,338 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! long term time-series archive of sensor records. The [`SentinelStore`] only keeps the last `max_history_len` records
+//! per device and capability - if the [`SentinelConfig`] has `timeseries` set the [`LiveSentinelConnector`] also appends
+//! each received record to a CSV file per capability and (UTC) day:
+//! ```text
+//!   <timeseries_dir>/thermometer/2024-06-01.csv
+//!   <timeseries_dir>/anemometer/2024-06-01.csv
+//!   ...
+//! ```
+//! The `timeseries_dir` defaults to `<data_dir>/sentinel/timeseries` (see [`sentinel_timeseries_dir`]).
+//! Each file has a header line. The first columns are the same for all capabilities
+//! (`timeRecorded,epochMillis,deviceId,sensorNo,recordId,evidences`), followed by the (scalar) fields of the
+//! capability specific record data in their JSON serialization format (e.g. `temperature` in Kelvin). Nested values
+//! are stored as JSON strings.
+//!
+//! Since we only append, records can be duplicated (e.g. initial records after a restart). [`SentinelTimeSeries::export`]
+//! removes such duplicates.
+
+use std::{fs, path::{Path,PathBuf}, io::{BufRead,BufReader,Write}, collections::HashSet};
+use chrono::{DateTime,Utc,NaiveDate,SecondsFormat,Days};
+use serde::{Serialize,Deserialize};
+use serde_json::Value;
+
+use odin_actor::warn;
+use odin_common::fs::{ensure_writable_dir,append_line_to_file};
+
+use crate::*;
+use crate::errors::*;
+
+pub const TIMESERIES_DIR: &str = "timeseries";
+
+const COMMON_COLUMNS: [&str;6] = ["timeRecorded","epochMillis","deviceId","sensorNo","recordId","evidences"];
+const EPOCH_COL: usize = 1;
+const DEVICE_COL: usize = 2;
+const RECORD_COL: usize = 4;
+
+/// the default location for time-series archives (under `odin_build::data_dir()`)
+pub fn sentinel_timeseries_dir()->PathBuf {
+    sentinel_data_dir().join(TIMESERIES_DIR)
+}
+
+/* #region SentinelTimeSeriesWriter **********************************************************************************/
+
+/// the writer side of the time-series archive
+#[derive(Debug,Clone)]
+pub struct SentinelTimeSeriesWriter {
+    dir: PathBuf
+}
+
+impl SentinelTimeSeriesWriter {
+    pub fn new (dir: impl AsRef<Path>)->Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        ensure_writable_dir( &dir)?;
+        Ok( SentinelTimeSeriesWriter { dir } )
+    }
+
+    /// get the writer for a configured time-series archive without checking the dir. This is used for individual updates once
+    /// the writer was created with [`SentinelTimeSeriesWriter::new`]
+    pub(crate) fn from_config (config: &SentinelConfig)->Option<Self> {
+        if config.timeseries {
+            let dir = config.timeseries_dir.clone().unwrap_or_else( sentinel_timeseries_dir);
+            Some( SentinelTimeSeriesWriter { dir } )
+        } else {
+            None
+        }
+    }
+
+    pub fn dir (&self)->&Path { &self.dir }
+
+    /// append all records of the store (in time order). This is called once we have the initial store contents
+    pub fn append_store (&self, store: &SentinelStore)->Result<()> {
+        let mut updates: Vec<&SentinelUpdate> = store.updates_iter().collect();
+        updates.sort_by_key( |u| u.time_recorded());
+        for update in updates {
+            self.append_update( update)?;
+        }
+        Ok(())
+    }
+
+    pub fn append_update (&self, update: &SentinelUpdate)->Result<()> {
+        let capability = update.capability();
+        let cap_dir = self.dir.join( capability.property_name());
+        ensure_writable_dir( &cap_dir)?;
+
+        let path = cap_dir.join( day_filename( update.time_recorded().date_naive()));
+        let (header, row) = csv_row( update)?;
+        if !path.is_file() {
+            append_line_to_file( &path, &header)?;
+        }
+        Ok( append_line_to_file( &path, &row)? )
+    }
+}
+
+fn day_filename (date: NaiveDate)->String {
+    format!("{}.csv", date.format("%Y-%m-%d"))
+}
+
+// turn update into (header,row) CSV lines
+fn csv_row (update: &SentinelUpdate)->Result<(String,String)> {
+    let time_recorded = update.time_recorded();
+    let evidences: Vec<&str> = update.evidences().iter().map( |r| r.id.as_str()).collect();
+
+    let mut header: Vec<String> = COMMON_COLUMNS.iter().map( |c| c.to_string()).collect();
+    let mut row: Vec<String> = vec![
+        time_recorded.to_rfc3339_opts( SecondsFormat::Millis, true),
+        time_recorded.timestamp_millis().to_string(),
+        csv_field( update.device_id()),
+        update.sensor_no().to_string(),
+        csv_field( update.record_id()),
+        csv_field( &evidences.join(";")),
+    ];
+
+    let rec = serde_json::to_value( update)?;
+    match rec.get( update.capability().property_name()) {
+        Some(Value::Object(data)) => {
+            for (k,v) in data {
+                header.push( k.clone());
+                row.push( csv_value( v));
+            }
+        }
+        Some(v) => { // not a struct, store as single 'value' column
+            header.push( "value".to_string());
+            row.push( csv_value( v));
+        }
+        None => {}
+    }
+
+    Ok( (header.join(","), row.join(",")) )
+}
+
+fn csv_value (v: &Value)->String {
+    match v {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => csv_field( s),
+        other => csv_field( &other.to_string()) // nested values are stored as JSON
+    }
+}
+
+fn csv_field (s: &str)->String {
+    if s.contains( |c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// split a CSV line into its (unquoted) fields
+pub fn split_csv_line (line: &str)->Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') { field.push('"'); chars.next(); } else { in_quotes = false }
+            } else {
+                field.push(c)
+            }
+        } else {
+            match c {
+                '"' => in_quotes = true,
+                ',' => fields.push( std::mem::take( &mut field)),
+                _ => field.push(c)
+            }
+        }
+    }
+    fields.push( field);
+    fields
+}
+
+/* #endregion SentinelTimeSeriesWriter */
+
+/* #region SentinelTimeSeries ****************************************************************************************/
+
+/// what to export from a time-series archive. Empty `devices` or `capabilities` means all
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct TimeSeriesQuery {
+    pub devices: Vec<DeviceId>,
+    pub capabilities: Vec<SensorCapability>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TimeSeriesQuery {
+    pub fn new (start: DateTime<Utc>, end: DateTime<Utc>)->Self {
+        TimeSeriesQuery { devices: Vec::new(), capabilities: Vec::new(), start, end }
+    }
+
+    pub fn with_devices (mut self, devices: Vec<DeviceId>)->Self {
+        self.devices = devices;
+        self
+    }
+
+    pub fn with_capabilities (mut self, capabilities: Vec<SensorCapability>)->Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    fn matches_device (&self, device_id: &str)->bool {
+        self.devices.is_empty() || self.devices.iter().any( |d| d == device_id)
+    }
+
+    fn matches_capability (&self, capability: SensorCapability)->bool {
+        self.capabilities.is_empty() || self.capabilities.contains( &capability)
+    }
+}
+
+/// the reader side of the time-series archive
+#[derive(Debug,Clone)]
+pub struct SentinelTimeSeries {
+    dir: PathBuf
+}
+
+impl SentinelTimeSeries {
+    pub fn open (dir: impl AsRef<Path>)->Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        if !dir.is_dir() { return Err( OdinSentinelError::ConfigError( format!("no time-series dir {dir:?}"))) }
+        Ok( SentinelTimeSeries { dir } )
+    }
+
+    /// the capabilities for which we have archived records
+    pub fn capabilities (&self)->Result<Vec<SensorCapability>> {
+        let mut capabilities = Vec::new();
+        for entry in fs::read_dir( &self.dir)? {
+            let entry = entry?;
+            if entry.path().is_dir() {
+                if let Some(capability) = entry.file_name().to_str().and_then( SensorCapability::capability_of) {
+                    capabilities.push( capability);
+                }
+            }
+        }
+        Ok(capabilities)
+    }
+
+    /// the archived day files of a capability that overlap with the query time range, in time order
+    fn day_files (&self, capability: SensorCapability, query: &TimeSeriesQuery)->Vec<PathBuf> {
+        let cap_dir = self.dir.join( capability.property_name());
+        let mut files = Vec::new();
+        let mut date = query.start.date_naive();
+        let end = query.end.date_naive();
+
+        while date <= end {
+            let path = cap_dir.join( day_filename( date));
+            if path.is_file() { files.push( path) }
+            if let Some(next) = date.checked_add_days( Days::new(1)) { date = next } else { break }
+        }
+        files
+    }
+
+    /// write the records of `capability` that match the query as CSV (with header) to `out`. Returns the number of records
+    /// written, which is 0 (without header) if there are no matching records
+    pub fn export<W: Write> (&self, capability: SensorCapability, query: &TimeSeriesQuery, out: &mut W)->Result<usize> {
+        let mut n = 0;
+        if !query.matches_capability( capability) { return Ok(n) }
+
+        let start = query.start.timestamp_millis();
+        let end = query.end.timestamp_millis();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut header: Option<String> = None;
+
+        for path in self.day_files( capability, query) {
+            let reader = BufReader::new( fs::File::open( &path)?);
+            let mut lines = reader.lines();
+
+            if let Some(file_header) = lines.next() {
+                let file_header = file_header?;
+                match &header {
+                    None => header = Some(file_header),
+                    Some(h) => if *h != file_header { warn!("column mismatch in {path:?}") }
+                }
+            }
+
+            for line in lines {
+                let line = line?;
+                if line.trim().is_empty() { continue }
+
+                let fields = split_csv_line( &line);
+                if fields.len() <= RECORD_COL { 
+                    warn!("ignoring malformed time-series record in {path:?}");
+                    continue
+                }
+
+                if let Ok(t) = fields[EPOCH_COL].parse::<i64>() {
+                    if t >= start && t <= end && query.matches_device( &fields[DEVICE_COL]) && seen.insert( fields[RECORD_COL].clone()) {
+                        if n == 0 {
+                            if let Some(h) = &header { writeln!( out, "{h}")?; }
+                        }
+                        writeln!( out, "{line}")?;
+                        n += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(n)
+    }
+
+    /// export all capabilities of the query into `<dir>/<capability>.csv` files. Returns the list of
+    /// written files with their number of records
+    pub fn export_to_dir (&self, query: &TimeSeriesQuery, dir: impl AsRef<Path>)->Result<Vec<(PathBuf,usize)>> {
+        let dir = dir.as_ref();
+        ensure_writable_dir( dir)?;
+
+        let mut exported = Vec::new();
+        for capability in self.capabilities()? {
+            if query.matches_capability( capability) {
+                let mut buf: Vec<u8> = Vec::new();
+                let n = self.export( capability, query, &mut buf)?;
+                if n > 0 {
+                    let path = dir.join( format!("{}.csv", capability.property_name()));
+                    fs::write( &path, buf)?;
+                    exported.push( (path,n));
+                }
+            }
+        }
+        Ok(exported)
+    }
+}
+
+/* #endregion SentinelTimeSeries */
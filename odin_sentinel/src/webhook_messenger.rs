@@ -0,0 +1,173 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::{collections::HashMap, time::Duration};
+use reqwest::{Client, Method, StatusCode, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use odin_actor::{warn, sleep};
+use crate::{op_failed, expand_json_template, Alarm, AlarmMessenger, OdinSentinelError};
+use crate::errors::Result;
+
+#[derive(Deserialize,Serialize,Debug)]
+pub struct WebhookConfig {
+    pub uri: String,
+
+    #[serde(default="default_method")]
+    pub method: String,
+
+    /// additional request headers (e.g. for authorization)
+    #[serde(default)]
+    pub headers: HashMap<String,String>,
+
+    /// optional JSON payload template with `${var}` placeholders (see `expand_json_template`). If not set we send `Alarm::to_json()`
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// if set requests are signed with HMAC-SHA256 over "<timestamp>.<body>"
+    #[serde(default)]
+    pub secret: Option<String>,
+
+    #[serde(default="default_signature_header")]
+    pub signature_header: String,
+
+    #[serde(default="default_timestamp_header")]
+    pub timestamp_header: String,
+
+    pub timeout: Duration, // for each request
+
+    #[serde(default="default_max_retries")]
+    pub max_retries: u32,
+
+    #[serde(default="default_retry_delay")]
+    pub retry_delay: Duration, // doubled for each retry
+
+    #[serde(default="default_max_delivery_time")]
+    pub max_delivery_time: Duration, // upper bound for all attempts, including retry delays
+}
+
+fn default_method()->String { "POST".into() }
+fn default_signature_header()->String { "X-Odin-Signature".into() }
+fn default_timestamp_header()->String { "X-Odin-Timestamp".into() }
+fn default_max_retries()->u32 { 3 }
+fn default_retry_delay()->Duration { Duration::from_secs(2) }
+fn default_max_delivery_time()->Duration { Duration::from_secs(30) }
+
+/// compute the value of the signature header for a given secret, timestamp (epoch seconds) and body.
+/// Receivers can use this to verify requests
+pub fn webhook_signature (secret: &str, timestamp: i64, body: &str)->Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice( secret.as_bytes()).map_err( |e| op_failed!("invalid webhook secret: {e}"))?;
+    mac.update( timestamp.to_string().as_bytes());
+    mac.update( b".");
+    mac.update( body.as_bytes());
+    Ok( format!("sha256={}", hex::encode( mac.finalize().into_bytes())) )
+}
+
+/// generic HTTP webhook `AlarmMessenger` that sends alarms as JSON (e.g. to CAD dispatch systems). Requests that fail with
+/// network errors, 5xx or 429 responses are retried up to `max_retries` times. `send_alarm` returns the delivery result
+/// after at most `max_delivery_time`, i.e. a slow endpoint can only hold up the caller for a bounded time
+pub struct WebhookAlarmMessenger {
+    config: WebhookConfig,
+    method: Method,
+    template: Option<Value>,
+    client: Client,
+}
+
+impl WebhookAlarmMessenger {
+    pub fn new (config: WebhookConfig)->Self {
+        // this is a toplevel object, panic is Ok
+        let method = Method::from_bytes( config.method.to_uppercase().as_bytes()).expect("invalid webhook method");
+        let template = config.template.as_ref().map( |t| serde_json::from_str::<Value>(t).expect("invalid webhook template"));
+        let client = Client::new();
+
+        WebhookAlarmMessenger { config, method, template, client }
+    }
+
+    pub fn payload (&self, alarm: &Alarm)->Value {
+        let vars = alarm.to_json();
+        match &self.template {
+            Some(template) => expand_json_template( template, &vars),
+            None => Value::Object( vars)
+        }
+    }
+
+    async fn deliver (&self, body: &str)->Result<()> {
+        let mut delay = self.config.retry_delay;
+        let mut n_retries = 0;
+
+        loop {
+            match self.send_request( body).await {
+                Ok(()) => return Ok(()),
+                Err((e,true)) if n_retries < self.config.max_retries => {
+                    warn!("webhook request failed: {e}, retrying in {delay:?}");
+                    sleep( delay).await;
+                    delay *= 2;
+                    n_retries += 1;
+                }
+                Err((e,_)) => return Err(e)
+            }
+        }
+    }
+
+    // the bool in the error tuple indicates if this is worth retrying
+    async fn send_request (&self, body: &str)->std::result::Result<(),(OdinSentinelError,bool)> {
+        let config = &self.config;
+        let mut req = self.client.request( self.method.clone(), &config.uri)
+            .timeout( config.timeout)
+            .header( CONTENT_TYPE, "application/json");
+
+        for (k,v) in &config.headers {
+            req = req.header( k, v);
+        }
+
+        if let Some(secret) = &config.secret {
+            let timestamp = Utc::now().timestamp();
+            let signature = webhook_signature( secret, timestamp, body).map_err( |e| (e,false))?;
+            req = req
+                .header( &config.timestamp_header, timestamp.to_string())
+                .header( &config.signature_header, signature);
+        }
+
+        match req.body( body.to_string()).send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status.is_success() {
+                    Ok(())
+                } else {
+                    let retry = status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS;
+                    Err( (OdinSentinelError::WebhookError( format!("{} returned {status}", config.uri)), retry) )
+                }
+            }
+            Err(e) => Err( (e.into(), true) )
+        }
+    }
+}
+
+#[async_trait]
+impl AlarmMessenger for WebhookAlarmMessenger {
+    async fn send_alarm (&self, alarm: &Alarm)->Result<()> {
+        let body = serde_json::to_string( &self.payload( alarm))?;
+        let max_time = self.config.max_delivery_time;
+
+        match tokio::time::timeout( max_time, self.deliver( &body)).await {
+            Ok(res) => res,
+            Err(_) => Err( OdinSentinelError::WebhookError( format!("{} not delivered within {max_time:?}", self.config.uri)))
+        }
+    }
+}
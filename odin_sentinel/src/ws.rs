@@ -157,6 +157,29 @@ impl WsCmd {
     pub fn new_ping (msg_id: impl ToString)-> WsCmd {
         WsCmd::Ping { request_time: Utc::now().timestamp_millis() as u64, message_id: msg_id.to_string() }
     }
+
+    pub fn message_id (&self)->&str {
+        match self {
+            WsCmd::Ping { message_id, .. } => message_id,
+            WsCmd::TriggerAlert { message_id, .. } => message_id,
+            WsCmd::SwitchLights { message_id, .. } => message_id,
+            WsCmd::SwitchValve { message_id, .. } => message_id,
+        }
+    }
+
+    pub fn device_ids (&self)->&[String] {
+        match self {
+            WsCmd::Ping { .. } => &[],
+            WsCmd::TriggerAlert { device_ids, .. } => device_ids,
+            WsCmd::SwitchLights { device_ids, .. } => device_ids,
+            WsCmd::SwitchValve { device_ids, .. } => device_ids,
+        }
+    }
+
+    /// does the server respond to this command with a message that has the same message_id
+    pub fn expects_response (&self)->bool {
+        matches!( self, WsCmd::Ping{..} | WsCmd::TriggerAlert{..})
+    }
 }
 
 /* #endregion websocket messages */
\ No newline at end of file
@@ -0,0 +1,63 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! record helpers shared between odin_sentinel integration tests (these use the Delphire JSON format)
+
+use std::sync::Arc;
+use odin_sentinel::{Result, SensorRecord, SentinelUpdate, RecordDataBounds, SmokeData, ThermometerData, GpsData};
+
+pub fn update<T> (json: String)->SentinelUpdate where T: RecordDataBounds, SentinelUpdate: From<Arc<SensorRecord<T>>> {
+    let rec: SensorRecord<T> = serde_json::from_str( &json).unwrap();
+    SentinelUpdate::from( Arc::new(rec))
+}
+
+/// expand a "hh:mm:ss" time of day into a full timestamp on the (fixed) test day. Full RFC3339 dates are passed through
+pub fn timestamp (time: &str)->String {
+    if time.contains('T') { time.to_string() } else { format!("2024-06-01T{time}.000Z") }
+}
+
+pub fn rec_json (id: &str, device_id: &str, time: &str, sensor_no: u32, evidences: &[&str], property: &str, data: &str)->String {
+    let time = timestamp( time);
+    let evidences: Vec<String> = evidences.iter().map( |e| format!(r#"{{"id":"{e}"}}"#)).collect();
+    format!(r#"{{"id":"{id}","timeRecorded":"{time}","sensorNo":{sensor_no},"deviceId":"{device_id}","evidences":[{}],"claims":[],"{property}":{data}}}"#,
+            evidences.join(","))
+}
+
+pub fn smoke (id: &str, device_id: &str, time: &str, prob: f64)->SentinelUpdate {
+    smoke_with_evidences( id, device_id, time, prob, &[])
+}
+
+pub fn smoke_with_evidences (id: &str, device_id: &str, time: &str, prob: f64, evidences: &[&str])->SentinelUpdate {
+    update::<SmokeData>( rec_json( id, device_id, time, 7, evidences, "smoke", &format!(r#"{{"smokeProb":{prob}}}"#)))
+}
+
+pub fn temp (id: &str, device_id: &str, time: &str, kelvin: f64)->SentinelUpdate {
+    update::<ThermometerData>( rec_json( id, device_id, time, 4, &[], "thermometer", &format!(r#"{{"temperature":{kelvin}}}"#)))
+}
+
+pub fn gps (id: &str, device_id: &str, time: &str, lat: f64, lon: f64)->SentinelUpdate {
+    update::<GpsData>( rec_json( id, device_id, time, 9, &[], "gps",
+        &format!(r#"{{"latitude":{lat},"longitude":{lon},"altitude":null,"quality":null,"numberOfSatellites":null,"HDOP":1.2}}"#)))
+}
+
+//--- recorded Delphire GPS records
+
+pub const GPS_1: &str = r#"{"id":"rUEGekTnRjD7opkqxJAw","type":"gps","timeRecorded":"2023-01-29T19:31:03.000Z","sensorNo":9,"deviceId":"roo7gd1dldn3","gps":{"latitude":34.16381325,"longitude":-118.10208675,"altitude":null,"quality":null,"numberOfSatellites":null,"HDOP":null},"evidences":[],"claims":[]}"#;
+pub const GPS_2: &str = r#"{"id":"Za1Y9LIYQ7KXSNbeDNBb","type":"gps","timeRecorded":"2023-01-29T19:31:34.000Z","sensorNo":9,"deviceId":"roo7gd1dldn3","gps":{"latitude":34.163813383333334,"longitude":-118.10208601666666,"altitude":null,"quality":null,"numberOfSatellites":null,"HDOP":null},"evidences":[],"claims":[]}"#;
+
+pub fn gps_update (json: &str)->Result<SentinelUpdate> {
+    let rec: SensorRecord<GpsData> = serde_json::from_str(json)?;
+    Ok( SentinelUpdate::from( Arc::new(rec)) )
+}
@@ -0,0 +1,122 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::fs;
+use chrono::{DateTime, TimeDelta, Utc};
+use odin_sentinel::{Result, AlarmAuditEntry, AlarmAuditQuery, AlarmAuditStore, AuditAction};
+
+fn date (s: &str)->DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+fn create_store (name: &str)->Result<AlarmAuditStore> {
+    let dir = std::env::temp_dir().join( format!("odin_sentinel_audit_{}_{}", name, std::process::id()));
+    if dir.is_dir() { fs::remove_dir_all(&dir)?; }
+    AlarmAuditStore::open( &dir)
+}
+
+fn populate (store: &AlarmAuditStore)->Result<()> {
+    let t0 = date("2024-06-05T03:00:00Z");
+
+    store.append( &AlarmAuditEntry::new( AuditAction::BelowThreshold, "roo7gd1dldn3", "smoke").with_time( t0)
+        .with_record( "s1").with_confidence( 0.4, Some(0.7)))?;
+    store.append( &AlarmAuditEntry::new( AuditAction::Sent, "roo7gd1dldn3", "smoke").with_time( t0 + TimeDelta::minutes(1))
+        .with_record( "s2").with_alarm( "smoke(roo7gd1dldn3,...)", 1).with_confidence( 0.8, None)
+        .with_evidences( vec!["20240605-030100_roo7gd1dldn3_1.webp".to_string()]))?;
+    store.append( &AlarmAuditEntry::new( AuditAction::MessengerFailure, "roo7gd1dldn3", "smoke").with_time( t0 + TimeDelta::minutes(1))
+        .with_alarm( "smoke(roo7gd1dldn3,...)", 1).with_messenger( "SmtpAlarmMessenger").with_detail( "timeout error"))?;
+    store.append( &AlarmAuditEntry::new( AuditAction::SuppressedDuplicate, "roo7gd1dldn3", "smoke").with_time( t0 + TimeDelta::minutes(2))
+        .with_record( "s3"))?;
+    store.append( &AlarmAuditEntry::new( AuditAction::Sent, "xyz1", "fire").with_time( t0 + TimeDelta::minutes(3))
+        .with_record( "f1").with_alarm( "fire(xyz1,...)", 2))?;
+    store.append( &AlarmAuditEntry::new( AuditAction::Acknowledged, "roo7gd1dldn3", "smoke").with_time( t0 + TimeDelta::minutes(4))
+        .with_alarm( "smoke(roo7gd1dldn3,...)", 1).with_detail( "by alice via signal"))?;
+    Ok(())
+}
+
+#[test]
+fn test_query()->Result<()> {
+    let store = create_store( "query")?;
+    populate( &store)?;
+
+    let all = store.query( &AlarmAuditQuery::default())?;
+    assert_eq!( all.len(), 6);
+    assert_eq!( all[0].action, AuditAction::Acknowledged); // most recent first
+    assert_eq!( all[4].evidences, vec!["20240605-030100_roo7gd1dldn3_1.webp".to_string()]);
+    assert_eq!( all[5].threshold, Some(0.7));
+
+    let q = AlarmAuditQuery { device: Some("roo7".into()), action: Some(AuditAction::Sent), ..AlarmAuditQuery::default() };
+    let sent = store.query( &q)?;
+    assert_eq!( sent.len(), 1);
+    assert_eq!( sent[0].record_id.as_deref(), Some("s2"));
+
+    let q = AlarmAuditQuery { alarm_type: Some("fire".into()), ..AlarmAuditQuery::default() };
+    assert_eq!( store.query( &q)?.len(), 1);
+
+    let q = AlarmAuditQuery { alarm_type: Some("fire, smoke".into()), ..AlarmAuditQuery::default() };
+    assert_eq!( store.query( &q)?.len(), 6);
+
+    let q = AlarmAuditQuery { since: Some(date("2024-06-05T03:01:00Z")), until: Some(date("2024-06-05T03:03:00Z")), ..AlarmAuditQuery::default() };
+    let window = store.query( &q)?;
+    assert_eq!( window.iter().map( |e| e.action).collect::<Vec<_>>(), 
+                vec![AuditAction::SuppressedDuplicate, AuditAction::MessengerFailure, AuditAction::Sent]);
+
+    let q = AlarmAuditQuery { limit: Some(2), ..AlarmAuditQuery::default() };
+    assert_eq!( store.query( &q)?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_daily_files()->Result<()> {
+    let store = create_store( "daily")?;
+    let t0 = date("2024-06-05T23:59:00Z");
+
+    store.append( &AlarmAuditEntry::new( AuditAction::Sent, "roo7gd1dldn3", "smoke").with_time( t0).with_record( "s1"))?;
+    store.append( &AlarmAuditEntry::new( AuditAction::Sent, "roo7gd1dldn3", "smoke").with_time( t0 + TimeDelta::minutes(2)).with_record( "s2"))?;
+    store.append( &AlarmAuditEntry::new( AuditAction::Sent, "roo7gd1dldn3", "smoke").with_time( t0 + TimeDelta::days(2)).with_record( "s3"))?;
+    assert!( store.path( t0.date_naive()).is_file());
+    assert!( store.path( (t0 + TimeDelta::days(1)).date_naive()).is_file());
+
+    // a bad line (invalid UTF-8) only skips that line
+    let mut bytes = fs::read( store.path( t0.date_naive()))?;
+    bytes.extend_from_slice( b"\xff\xfe\n");
+    fs::write( store.path( t0.date_naive()), bytes)?;
+
+    let all = store.query( &AlarmAuditQuery::default())?;
+    assert_eq!( all.iter().map( |e| e.record_id.as_deref().unwrap()).collect::<Vec<_>>(), vec!["s3", "s2", "s1"]);
+
+    let q = AlarmAuditQuery { limit: Some(2), ..AlarmAuditQuery::default() };
+    assert_eq!( store.query( &q)?.iter().map( |e| e.record_id.as_deref().unwrap()).collect::<Vec<_>>(), vec!["s3", "s2"]);
+
+    let q = AlarmAuditQuery { until: Some(date("2024-06-06T00:00:00Z")), ..AlarmAuditQuery::default() };
+    assert_eq!( store.query( &q)?.len(), 1);
+
+    // retention is checked against the current time
+    let store = store.with_retention( std::time::Duration::from_secs( 24*3600));
+    store.purge( t0 + TimeDelta::days(2));
+    assert!( !store.path( t0.date_naive()).is_file());
+    assert_eq!( store.query( &AlarmAuditQuery::default())?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_http_query_params() {
+    // this is how the SentinelAlarmAuditService gets queries
+    let q: AlarmAuditQuery = serde_json::from_value( serde_json::json!({
+        "device": "roo7", "action": "Sent", "since": "2024-06-05T03:00:00Z", "limit": 10
+    })).unwrap();
+    assert_eq!( q.action, Some(AuditAction::Sent));
+    assert_eq!( q.since, Some(date("2024-06-05T03:00:00Z")));
+}
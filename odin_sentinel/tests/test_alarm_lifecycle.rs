@@ -0,0 +1,173 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::time::Duration;
+use chrono::{DateTime, TimeDelta, Utc};
+use odin_sentinel::{
+    parse_alarm_reply, AlarmCommand, AlarmReply, AlarmState, AlarmTracker, OnCallSchedule, SentinelAlarmMonitorConfig
+};
+
+const SCHEDULE: &str = r#"
+OnCallSchedule(
+    contacts: [
+        AlarmContact( name: "alice", signal: Some("+15551234567"), email: Some("alice@example.com")),
+        AlarmContact( name: "bob", signal: Some("+15557654321")),
+        AlarmContact( name: "dispatch", email: Some("dispatch@example.com")),
+    ],
+    regions: [
+        OnCallRegion(
+            name: "north",
+            devices: ["dev-n"],
+            shifts: [
+                OnCallShift( days: ["Sat","Sun"], hours: TimeWindow( start: "00:00:00", end: "23:59:59", utc: true), tiers: [["dispatch"]]),
+                OnCallShift( hours: TimeWindow( start: "22:00:00", end: "07:00:00", utc: true), tiers: [["bob"], ["dispatch"]]),
+            ],
+            tiers: [["alice"], ["bob","dispatch"]],
+        ),
+        OnCallRegion( name: "rest", devices: ["*"], tiers: [["dispatch"]]),
+    ],
+)
+"#;
+
+fn date (s: &str)->DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+fn names (schedule: &OnCallSchedule, device_id: &str, d: &str, tier: usize)->Vec<String> {
+    schedule.recipients( device_id, &date(d), tier).into_iter().map( |c| c.name).collect()
+}
+
+#[test]
+fn test_on_call_schedule() {
+    let schedule: OnCallSchedule = ron::from_str( SCHEDULE).unwrap();
+    schedule.check().unwrap();
+
+    // 2024-06-05 is a Wednesday
+    assert_eq!( names( &schedule, "dev-n1", "2024-06-05T12:00:00Z", 0), vec!["alice"]);
+    assert_eq!( names( &schedule, "dev-n1", "2024-06-05T12:00:00Z", 1), vec!["bob", "dispatch"]);
+    assert!( names( &schedule, "dev-n1", "2024-06-05T12:00:00Z", 2).is_empty());
+
+    // the 3am shift (wraps around midnight)
+    assert_eq!( names( &schedule, "dev-n1", "2024-06-05T03:00:00Z", 0), vec!["bob"]);
+    assert_eq!( schedule.num_tiers( "dev-n1", &date("2024-06-05T03:00:00Z")), 2);
+
+    // weekend shift takes precedence since it comes first
+    assert_eq!( names( &schedule, "dev-n1", "2024-06-08T03:00:00Z", 0), vec!["dispatch"]);
+
+    // other region
+    assert_eq!( names( &schedule, "dev-s1", "2024-06-05T12:00:00Z", 0), vec!["dispatch"]);
+
+    assert_eq!( schedule.contact_for_address( "+15557654321").map( |c| c.name.as_str()), Some("bob"));
+    assert_eq!( schedule.contact_for_address( "Alice@Example.com").map( |c| c.name.as_str()), Some("alice"));
+}
+
+#[test]
+fn test_invalid_schedule() {
+    let src = SCHEDULE.replace( r#"[["dispatch"]]),"#, r#"[["carol"]]),"#);
+    let schedule: OnCallSchedule = ron::from_str( &src).unwrap();
+    assert!( schedule.check().is_err());
+}
+
+#[test]
+fn test_escalation() {
+    let mut tracker = AlarmTracker::new();
+    let t0 = date("2024-06-05T03:00:00Z");
+    let ack_timeout = Duration::from_secs(600);
+
+    let no = tracker.add( "smoke(dev-n1,...)".into(), "dev-n1".into(), "smoke".into(), t0, true, t0).no;
+    let status_no = tracker.add( "inactive dev-n2".into(), "dev-n2".into(), "status".into(), t0, false, t0).no;
+    assert_eq!( tracker.get(status_no).unwrap().state, AlarmState::Resolved); // nothing to acknowledge
+
+    assert!( tracker.due_escalations( t0 + TimeDelta::minutes(5), ack_timeout).is_empty());
+
+    let t1 = t0 + TimeDelta::minutes(10);
+    assert_eq!( tracker.due_escalations( t1, ack_timeout), vec![no]);
+    let status = tracker.escalate( no, 1, t1).unwrap();
+    assert_eq!( status.state, AlarmState::Escalated);
+    assert_eq!( status.tier, 1);
+    assert!( tracker.due_escalations( t1 + TimeDelta::minutes(5), ack_timeout).is_empty()); // notification time was reset
+
+    let t2 = t1 + TimeDelta::minutes(10);
+    assert_eq!( tracker.escalate( no, 1, t2).unwrap().tier, 1); // we stay on the last tier
+
+    let status = tracker.acknowledge( None, "bob", "signal", t2).unwrap();
+    assert_eq!( status.no, no);
+    assert_eq!( status.state, AlarmState::Acknowledged);
+    assert_eq!( status.changed_by.as_deref(), Some("bob"));
+    assert!( tracker.due_escalations( t2 + TimeDelta::hours(1), ack_timeout).is_empty());
+    assert!( tracker.acknowledge( Some(no), "alice", "web", t2).is_err()); // already acknowledged
+
+    assert_eq!( tracker.resolve( Some(no), "alice", "web", t2).unwrap().state, AlarmState::Resolved);
+
+    tracker.purge( t0 + TimeDelta::hours(2), Duration::from_secs(3600));
+    assert_eq!( tracker.len(), 0);
+}
+
+#[test]
+fn test_no_escalation_without_timeout() {
+    let mut tracker = AlarmTracker::new();
+    let t0 = date("2024-06-05T03:00:00Z");
+    tracker.add( "fire(dev-n1,...)".into(), "dev-n1".into(), "fire".into(), t0, true, t0);
+    assert!( tracker.due_escalations( t0 + TimeDelta::hours(1), Duration::ZERO).is_empty());
+}
+
+#[test]
+fn test_no_escalation_without_on_call() {
+    let config = SentinelAlarmMonitorConfig::default(); // no on_call schedule
+    assert!( !config.ack_timeout.is_zero());
+    assert!( !config.accepts_replies());
+
+    let mut tracker = AlarmTracker::new();
+    let t0 = date("2024-06-05T03:00:00Z");
+    tracker.add( "smoke(dev-n1,...)".into(), "dev-n1".into(), "smoke".into(), t0, true, t0);
+    assert!( tracker.due_escalations( t0 + TimeDelta::hours(24), config.escalation_timeout()).is_empty());
+
+    let config = SentinelAlarmMonitorConfig { on_call: Some( ron::from_str( SCHEDULE).unwrap()), ..SentinelAlarmMonitorConfig::default() };
+    assert!( config.accepts_replies());
+    assert_eq!( tracker.due_escalations( t0 + TimeDelta::hours(24), config.escalation_timeout()).len(), 1);
+}
+
+#[test]
+fn test_parse_reply() {
+    assert_eq!( parse_alarm_reply( "ack 42"), Some((AlarmCommand::Acknowledge, 42)));
+    assert_eq!( parse_alarm_reply( "ACK #42"), Some((AlarmCommand::Acknowledge, 42)));
+    assert_eq!( parse_alarm_reply( "resolved 7 - false alarm"), Some((AlarmCommand::Resolve, 7)));
+    assert_eq!( parse_alarm_reply( "ack"), None); // alarm number is required
+    assert_eq!( parse_alarm_reply( "Ok 42"), None);
+    assert_eq!( parse_alarm_reply( "a 42"), None);
+    assert_eq!( parse_alarm_reply( "done"), None);
+    assert_eq!( parse_alarm_reply( "ack forty-two"), None);
+    assert_eq!( parse_alarm_reply( "on my way"), None);
+    assert_eq!( parse_alarm_reply( ""), None);
+}
+
+#[test]
+fn test_accept_reply() {
+    let schedule: OnCallSchedule = ron::from_str( SCHEDULE).unwrap();
+    let reply = |from: &str, text: &str| AlarmReply { from: from.to_string(), via: "signal".to_string(), text: text.to_string() };
+
+    let (cmd,no,contact) = schedule.accept_reply( &reply( "+15557654321", "ack 42")).unwrap();
+    assert_eq!( (cmd, no, contact.name.as_str()), (AlarmCommand::Acknowledge, 42, "bob"));
+
+    // unknown sender
+    let res = schedule.accept_reply( &reply( "+15550000000", "ack 42"));
+    println!("{res:?}");
+    assert!( res.is_err());
+
+    // no alarm number
+    let res = schedule.accept_reply( &reply( "+15557654321", "ack"));
+    println!("{res:?}");
+    assert!( res.is_err());
+}
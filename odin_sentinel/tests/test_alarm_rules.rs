@@ -0,0 +1,179 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+mod common;
+use common::*;
+
+use odin_sentinel::{AlarmRuleSet, AlarmRuleEngine, AlarmRuleSkipReason};
+
+const RULES: &str = r#"
+AlarmRuleSet(
+    params: { "smoke_prob": 0.7 },
+    rules: [
+        AlarmRule(
+            name: "smoke_and_rising_temp",
+            alarm_type: "smoke",
+            trigger: [smoke],
+            condition: All([
+                Threshold( capability: smoke, field: "smokeProb", op: Ge, value: 0.5, param: Some("smoke_prob"), within: "1m"),
+                Rising( capability: thermometer, field: "temperature", min_delta: 2.0, within: "5m"),
+            ]),
+            confidence: Some( FieldRef( capability: smoke, field: "smokeProb")),
+        ),
+    ],
+    overrides: { "dev-strict": DeviceOverride( params: { "smoke_prob": 0.95 }) },
+)
+"#;
+
+fn engine (ron_src: &str)->AlarmRuleEngine {
+    let rules: AlarmRuleSet = ron::from_str( ron_src).unwrap();
+    rules.check().unwrap();
+    AlarmRuleEngine::new( rules)
+}
+
+#[test]
+fn test_smoke_and_rising_temp() {
+    let mut engine = engine( RULES);
+
+    assert!( engine.process( &temp( "t1", "dev-1", "12:00:00", 300.0)).is_empty());
+    assert!( engine.process( &temp( "t2", "dev-1", "12:02:00", 303.0)).is_empty()); // not a trigger capability
+
+    let matches = engine.process( &smoke( "s1", "dev-1", "12:03:00", 0.8));
+    println!("matches: {matches:#?}");
+    assert_eq!( matches.len(), 1);
+    assert_eq!( matches[0].rule, "smoke_and_rising_temp");
+    assert_eq!( matches[0].confidence, 0.8);
+
+    // dedup (default 10min)
+    assert!( engine.process( &smoke( "s2", "dev-1", "12:05:00", 0.9)).is_empty());
+
+    // outside of dedup window
+    assert!( engine.process( &temp( "t3", "dev-1", "12:16:00", 300.0)).is_empty());
+    assert!( engine.process( &temp( "t4", "dev-1", "12:18:00", 303.0)).is_empty());
+    assert_eq!( engine.process( &smoke( "s3", "dev-1", "12:20:00", 0.9)).len(), 1);
+
+    // late records older than the last fired alarm do not re-fire it
+    assert!( engine.process( &smoke( "s4", "dev-1", "12:04:00", 0.9)).is_empty());
+}
+
+#[test]
+fn test_no_rising_temp() {
+    let mut engine = engine( RULES);
+
+    engine.process( &temp( "t1", "dev-1", "12:00:00", 300.0));
+    engine.process( &temp( "t2", "dev-1", "12:02:00", 300.5));
+    assert!( engine.process( &smoke( "s1", "dev-1", "12:03:00", 0.8)).is_empty());
+
+    // temperature rise outside of the 5min window does not count
+    engine.process( &temp( "t3", "dev-1", "12:20:00", 303.0));
+    assert!( engine.process( &smoke( "s2", "dev-1", "12:21:00", 0.8)).is_empty());
+}
+
+#[test]
+fn test_device_override() {
+    let mut engine = engine( RULES);
+
+    engine.process( &temp( "t1", "dev-strict", "12:00:00", 300.0));
+    engine.process( &temp( "t2", "dev-strict", "12:02:00", 305.0));
+    assert!( engine.process( &smoke( "s1", "dev-strict", "12:03:00", 0.8)).is_empty()); // device threshold is 0.95
+    assert_eq!( engine.process( &smoke( "s2", "dev-strict", "12:03:30", 0.97)).len(), 1);
+}
+
+#[test]
+fn test_suppression_window() {
+    let mut engine = engine( r#"
+    AlarmRuleSet( rules: [
+        AlarmRule(
+            name: "smoke",
+            alarm_type: "smoke",
+            condition: Threshold( capability: smoke, field: "smokeProb", op: Ge, value: 0.7, within: "1m"),
+            suppress: [ TimeWindow( start: "22:00:00", end: "06:00:00", utc: true) ],
+            dedup: "0s",
+        ),
+    ])"#);
+
+    assert!( engine.process( &smoke( "s1", "dev-1", "23:00:00", 0.9)).is_empty());
+    assert!( engine.process( &smoke( "s2", "dev-1", "05:59:00", 0.9)).is_empty());
+    assert_eq!( engine.process( &smoke( "s3", "dev-1", "06:00:00", 0.9)).len(), 1);
+}
+
+#[test]
+fn test_skips() {
+    let mut engine = engine( r#"
+    AlarmRuleSet( rules: [
+        AlarmRule(
+            name: "smoke",
+            alarm_type: "smoke",
+            condition: Threshold( capability: smoke, field: "smokeProb", op: Ge, value: 0.7, within: "1m"),
+            suppress: [ TimeWindow( start: "22:00:00", end: "06:00:00", utc: true) ],
+        ),
+    ])"#);
+
+    let (matches, skips) = engine.process_with_skips( &smoke( "s1", "dev-1", "23:00:00", 0.9));
+    assert!( matches.is_empty());
+    assert_eq!( skips.len(), 1);
+    assert_eq!( skips[0].reason, AlarmRuleSkipReason::Suppressed);
+    assert_eq!( skips[0].record_id, "s1");
+
+    let (matches, skips) = engine.process_with_skips( &smoke( "s2", "dev-1", "12:00:00", 0.9));
+    assert_eq!( matches.len(), 1);
+    assert!( skips.is_empty());
+
+    let (matches, skips) = engine.process_with_skips( &smoke( "s3", "dev-1", "12:01:00", 0.9));
+    assert!( matches.is_empty());
+    assert_eq!( skips.len(), 1);
+    assert_eq!( skips[0].reason, AlarmRuleSkipReason::Duplicate);
+
+    // below threshold is not a skip
+    let (matches, skips) = engine.process_with_skips( &smoke( "s4", "dev-1", "12:30:00", 0.2));
+    assert!( matches.is_empty() && skips.is_empty());
+}
+
+#[test]
+fn test_corroboration() {
+    let mut engine = engine( r#"
+    AlarmRuleSet( rules: [
+        AlarmRule(
+            name: "corroborated_smoke",
+            alarm_type: "smoke",
+            condition: Threshold( capability: smoke, field: "smokeProb", op: Ge, value: 0.7, within: "1m"),
+            corroboration: Some( Corroboration( min_devices: 2, radius: 5000.0, within: "10m")),
+        ),
+    ])"#);
+
+    engine.process( &gps( "g1", "dev-1", "12:00:00", 34.16, -118.10));
+    engine.process( &gps( "g2", "dev-2", "12:00:00", 34.17, -118.11)); // ~1.4km
+    engine.process( &gps( "g3", "dev-far", "12:00:00", 35.0, -118.10)); // ~93km
+
+    assert!( engine.process( &smoke( "s1", "dev-far", "12:01:00", 0.9)).is_empty());
+    let (matches, skips) = engine.process_with_skips( &smoke( "s2", "dev-1", "12:02:00", 0.9)); // dev-far is too far away
+    assert!( matches.is_empty());
+    assert_eq!( skips.len(), 1);
+    assert_eq!( skips[0].reason, AlarmRuleSkipReason::Uncorroborated{ devices: 1, min_devices: 2 });
+
+    let matches = engine.process( &smoke( "s3", "dev-2", "12:05:00", 0.8));
+    assert_eq!( matches.len(), 1);
+    assert_eq!( matches[0].corroborating_devices, vec!["dev-1".to_string()]);
+}
+
+#[test]
+fn test_invalid_rules() {
+    let rules: AlarmRuleSet = ron::from_str( r#"
+    AlarmRuleSet( rules: [
+        AlarmRule( name: "a", alarm_type: "fire", condition: Threshold( capability: fire, field: "fireProb", op: Gt, value: 0.5, within: "1m")),
+        AlarmRule( name: "a", alarm_type: "fire", condition: Threshold( capability: fire, field: "fireProb", op: Gt, value: 0.8, within: "1m")),
+    ])"#).unwrap();
+    assert!( rules.check().is_err());
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+mod common;
+use common::{GPS_1, GPS_2, gps_update};
+
+use std::fs;
+use std::time::Duration;
+use chrono::{DateTime,Utc,TimeDelta};
+use odin_sentinel::{Result, GpsData, SensorRecord, SentinelUpdate, SentinelStore, SentinelRecorder, SentinelArchive, ArchivedUpdate, DeviceList};
+
+#[test]
+fn test_epoch_millis_roundtrip()->Result<()> {
+    // our own serialization uses epoch millis for timeRecorded, which has to be readable for replay
+    let rec: SensorRecord<GpsData> = serde_json::from_str(GPS_1)?;
+    let json = serde_json::to_string(&rec)?;
+    println!("serialized: {json}");
+
+    let rec2: SensorRecord<GpsData> = serde_json::from_str(&json)?;
+    assert_eq!( rec.time_recorded, rec2.time_recorded);
+    assert_eq!( rec.data, rec2.data);
+    Ok(())
+}
+
+#[test]
+fn test_record_and_read_archive()->Result<()> {
+    let dir = std::env::temp_dir().join( format!("odin_sentinel_archive_{}", std::process::id()));
+    if dir.is_dir() { fs::remove_dir_all(&dir)?; }
+
+    //--- record
+    let recorder = SentinelRecorder::new( &dir)?;
+
+    let mut store = SentinelStore::new();
+    store.update_with( gps_update(GPS_1)?, 10);
+    recorder.record_store( &store)?;
+
+    let update = gps_update(GPS_2)?;
+    recorder.record_update( &update)?;
+
+    //--- read back
+    let archive = SentinelArchive::open( &dir)?;
+    println!("archive: {archive:#?}");
+    assert_eq!( archive.devices.data.len(), 1);
+    assert_eq!( archive.updates.len(), 2);
+    assert_eq!( archive.replay_updates().count(), 1);
+    assert!( archive.replay_start().is_some());
+
+    let initial = archive.initial_store( 10, TimeDelta::zero())?;
+    assert!( initial.get_update( &"rUEGekTnRjD7opkqxJAw".to_string()).is_some());
+    assert!( initial.get_update( &"Za1Y9LIYQ7KXSNbeDNBb".to_string()).is_none());
+
+    //--- replayed records can be shifted in time
+    let offset = TimeDelta::hours(1);
+    let replayed = archive.replay_updates().next().unwrap().to_update( offset)?;
+    assert_eq!( replayed.record_id(), update.record_id());
+    assert_eq!( replayed.time_recorded(), update.time_recorded() + offset);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_continued_recording()->Result<()> {
+    let dir = std::env::temp_dir().join( format!("odin_sentinel_archive_cont_{}", std::process::id()));
+    if dir.is_dir() { fs::remove_dir_all(&dir)?; }
+
+    let mut store = SentinelStore::new();
+    store.update_with( gps_update(GPS_1)?, 10);
+
+    let recorder = SentinelRecorder::new( &dir)?;
+    assert!( recorder.is_new());
+    recorder.record_store( &store)?;
+    recorder.record_update( &gps_update(GPS_2)?)?;
+
+    // a restarted connection continues the recording without another initial snapshot
+    let recorder = SentinelRecorder::new( &dir)?;
+    assert!( !recorder.is_new());
+    recorder.record_store( &store)?;
+
+    let archive = SentinelArchive::open( &dir)?;
+    assert_eq!( archive.devices.data.len(), 1);
+    assert_eq!( archive.updates.len(), 2);
+    assert_eq!( archive.updates.iter().filter( |a| a.initial).count(), 1);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_replay_steps()->Result<()> {
+    let t0: DateTime<Utc> = DateTime::parse_from_rfc3339("2023-01-29T19:30:00Z").unwrap().with_timezone(&Utc);
+    let gps_1 = gps_update(GPS_1)?;
+    let gps_2 = gps_update(GPS_2)?;
+
+    // two sessions (each with its own initial snapshot) and a long pause within the second one
+    let updates = vec![
+        ArchivedUpdate::new( t0, true, &gps_1)?,
+        ArchivedUpdate::new( t0 + TimeDelta::seconds(10), false, &gps_2)?,
+        ArchivedUpdate::new( t0 + TimeDelta::hours(2), true, &gps_2)?,
+        ArchivedUpdate::new( t0 + TimeDelta::hours(2) + TimeDelta::seconds(5), false, &gps_2)?,
+        ArchivedUpdate::new( t0 + TimeDelta::hours(3), false, &gps_2)?,
+    ];
+    let archive = SentinelArchive { dir: std::env::temp_dir(), devices: DeviceList { data: vec![] }, updates };
+
+    let initial = archive.initial_store( 10, TimeDelta::zero())?;
+    assert_eq!( initial.updates_iter().count(), 1); // only from the first session
+
+    let steps = archive.replay_steps( Duration::from_secs(60));
+    let delays: Vec<u64> = steps.iter().map( |s| s.delay.as_secs()).collect();
+    println!("replay delays: {delays:?}");
+    assert_eq!( delays, vec![0, 0, 60]); // session gap skipped, pause clamped
+
+    let skipped: Vec<i64> = steps.iter().map( |s| s.skipped.num_seconds()).collect();
+    assert_eq!( skipped, vec![0, 7195, 7195 + 3595 - 60]);
+    Ok(())
+}
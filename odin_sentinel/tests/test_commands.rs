@@ -0,0 +1,223 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::time::Duration;
+use chrono::{DateTime,NaiveTime,Utc};
+use odin_sentinel::{
+    SentinelCmdConfig, SentinelCmdManager, ScheduledCmd, CmdAction, CmdTarget, CmdState, TimeWindow, is_inside,
+    ws::{WsCmd,WsMsg}
+};
+
+fn time (t: &str)->DateTime<Utc> {
+    DateTime::parse_from_rfc3339( &format!("2024-06-01T{t}.000Z")).unwrap().to_utc()
+}
+
+fn config (batch_size: usize)->SentinelCmdConfig {
+    SentinelCmdConfig { batch_size, ack_timeout: Duration::from_secs(30), max_retries: 2, ..SentinelCmdConfig::default() }
+}
+
+fn ack (device_id: &str, message_id: &str)->WsMsg {
+    WsMsg::TriggerAlert { device_id: device_id.to_string(), message_id: message_id.to_string(), result: "success".to_string() }
+}
+
+fn ids (n: usize)->Vec<String> {
+    (0..n).map( |i| format!("dev{i}")).collect()
+}
+
+const AREA: [(f64,f64);4] = [(37.0,-122.0), (37.0,-121.0), (38.0,-121.0), (38.0,-122.0)];
+
+#[test]
+fn test_polygon () {
+    assert!( is_inside( (37.5,-121.5), &AREA));
+    assert!( !is_inside( (38.5,-121.5), &AREA));
+    assert!( !is_inside( (37.5,-120.5), &AREA));
+}
+
+#[test]
+fn test_batching () {
+    let mut mgr = SentinelCmdManager::new( config(4));
+    let cmd_ids = mgr.enqueue( CmdAction::SwitchValve{ state: "on".to_string() }, ids(10), None, time("12:00:00"));
+    assert_eq!( cmd_ids.len(), 3);
+
+    let msgs = mgr.next_messages( time("12:00:00"));
+    let sizes: Vec<usize> = msgs.iter().map( |m| m.device_ids().len()).collect();
+    println!("batch sizes: {sizes:?}");
+    assert_eq!( sizes, vec![4,4,2]);
+
+    // switch-valve has no response, i.e. it is done once it is sent
+    assert!( mgr.status().iter().all( |s| s.state == CmdState::Sent));
+    assert!( mgr.next_messages( time("12:10:00")).is_empty());
+}
+
+#[test]
+fn test_area_schedule () {
+    let mut mgr = SentinelCmdManager::new( config(10));
+    mgr.schedule( ScheduledCmd {
+        name: "lights".to_string(),
+        action: CmdAction::SwitchLights{ light_type: "infrared".to_string(), state: "on".to_string() },
+        targets: CmdTarget { devices: Vec::new(), area: AREA.to_vec() },
+        interval: Some( Duration::from_secs(300)),
+        hours: None,
+        flag: Some("red_flag".to_string())
+    });
+
+    let devices = vec![
+        ("roo7".to_string(), Some((37.5,-121.5))),
+        ("roo8".to_string(), Some((39.0,-121.5))),
+        ("roo9".to_string(), None)
+    ];
+
+    assert_eq!( mgr.run_schedules( time("12:00:00"), &devices), 0); // flag not set
+    mgr.set_flag( "red_flag", true);
+    assert_eq!( mgr.run_schedules( time("12:00:00"), &devices), 1);
+    assert_eq!( mgr.status()[0].device_ids, vec!["roo7".to_string()]);
+
+    assert_eq!( mgr.run_schedules( time("12:04:00"), &devices), 0); // not due yet
+    assert_eq!( mgr.run_schedules( time("12:05:00"), &devices), 1);
+
+    mgr.set_flag( "red_flag", false);
+    assert_eq!( mgr.run_schedules( time("12:10:00"), &devices), 0);
+}
+
+#[test]
+fn test_hours () {
+    let mut mgr = SentinelCmdManager::new( config(10));
+    mgr.schedule( ScheduledCmd {
+        name: "night-alert".to_string(),
+        action: CmdAction::TriggerAlert,
+        targets: CmdTarget { devices: vec!["roo".to_string()], area: Vec::new() },
+        interval: None,
+        hours: Some( TimeWindow { start: NaiveTime::from_hms_opt(20,0,0).unwrap(), end: NaiveTime::from_hms_opt(6,0,0).unwrap(), utc: true }),
+        flag: None
+    });
+    let devices = vec![ ("roo7".to_string(), None), ("xyz1".to_string(), None) ];
+
+    assert_eq!( mgr.run_schedules( time("12:00:00"), &devices), 0);
+    assert_eq!( mgr.run_schedules( time("22:00:00"), &devices), 1);
+    assert_eq!( mgr.status()[0].device_ids, vec!["roo7".to_string()]);
+    assert_eq!( mgr.run_schedules( time("23:00:00"), &devices), 0); // run-once schedule is removed
+    assert_eq!( mgr.scheduled_cmds().count(), 0);
+}
+
+#[test]
+fn test_ack_and_retry () {
+    let mut mgr = SentinelCmdManager::new( config(10));
+    let cmd = WsCmd::TriggerAlert { device_ids: vec!["roo7".to_string(), "roo8".to_string()], message_id: "ui-42".to_string() };
+    let id = mgr.enqueue_ws_cmd( &cmd, time("12:00:00")).unwrap().remove(0);
+
+    let msgs = mgr.next_messages( time("12:00:00"));
+    assert_eq!( msgs.len(), 1);
+    assert_eq!( msgs[0].message_id(), id.as_str()); // we use our own message ids
+    assert_eq!( mgr.get(&id).unwrap().state, CmdState::Pending);
+
+    assert!( mgr.handle_response( &ack( "roo7", &id)));
+    assert!( !mgr.handle_response( &ack( "roo7", "unknown")));
+    assert!( mgr.next_messages( time("12:00:10")).is_empty()); // still within ack_timeout
+
+    // re-send goes only to the device that did not acknowledge
+    let msgs = mgr.next_messages( time("12:00:30"));
+    assert_eq!( msgs.len(), 1);
+    assert_eq!( msgs[0].device_ids(), &["roo8".to_string()]);
+
+    assert!( mgr.handle_response( &ack( "roo8", &id)));
+    let status = mgr.get(&id).unwrap();
+    println!("{status:?}");
+    assert_eq!( status.state, CmdState::Acknowledged);
+    assert_eq!( status.attempts, 2);
+}
+
+#[test]
+fn test_failure () {
+    let mut mgr = SentinelCmdManager::new( config(10));
+    let id = mgr.enqueue( CmdAction::TriggerAlert, vec!["roo7".to_string()], None, time("12:00:00")).remove(0);
+
+    assert_eq!( mgr.next_messages( time("12:00:00")).len(), 1);
+    mgr.send_failed( &id, "connection closed".to_string());
+    assert_eq!( mgr.get(&id).unwrap().state, CmdState::Queued);
+
+    assert_eq!( mgr.next_messages( time("12:00:05")).len(), 1);
+    assert_eq!( mgr.next_messages( time("12:00:35")).len(), 1); // max_retries = 2
+    assert!( mgr.next_messages( time("12:01:05")).is_empty());
+
+    let status = mgr.get(&id).unwrap();
+    println!("{status:?}");
+    assert_eq!( status.state, CmdState::Failed);
+    assert_eq!( status.errors.len(), 2);
+}
+
+#[test]
+fn test_server_error () {
+    let mut mgr = SentinelCmdManager::new( config(10));
+    let id1 = mgr.enqueue( CmdAction::TriggerAlert, vec!["roo7".to_string()], None, time("12:00:00")).remove(0);
+    let id2 = mgr.enqueue( CmdAction::TriggerAlert, vec!["roo8".to_string()], None, time("12:00:00")).remove(0);
+    assert!( !mgr.handle_response( &WsMsg::Error{ message: "invalid command".to_string() })); // nothing pending yet
+    assert_eq!( mgr.next_messages( time("12:00:00")).len(), 2);
+
+    // errors that refer to a command id fail that command..
+    assert!( mgr.handle_response( &WsMsg::Error{ message: format!("unknown device in {id2}") }));
+    assert_eq!( mgr.get(&id2).unwrap().state, CmdState::Failed);
+    assert_eq!( mgr.get(&id1).unwrap().state, CmdState::Pending);
+
+    // ..otherwise they fail the oldest pending one, which is not re-sent
+    assert!( mgr.handle_response( &WsMsg::Error{ message: "invalid command".to_string() }));
+    let status = mgr.get(&id1).unwrap();
+    assert_eq!( status.state, CmdState::Failed);
+    assert_eq!( status.errors, vec!["server error: invalid command".to_string()]);
+    assert!( mgr.next_messages( time("12:00:30")).is_empty());
+}
+
+#[test]
+fn test_cancel () {
+    let mut mgr = SentinelCmdManager::new( config(10));
+    mgr.schedule( ScheduledCmd {
+        name: "ping-alert".to_string(),
+        action: CmdAction::TriggerAlert,
+        targets: CmdTarget::default(),
+        interval: Some( Duration::from_secs(60)),
+        hours: None,
+        flag: None
+    });
+
+    let devices = vec![ ("roo7".to_string(), None) ];
+    assert_eq!( mgr.run_schedules( time("12:00:00"), &devices), 1);
+    mgr.next_messages( time("12:00:00"));
+    assert!( mgr.take_changed());
+
+    assert!( mgr.cancel( "ping-alert"));
+    assert!( !mgr.cancel( "ping-alert"));
+    assert!( mgr.take_changed());
+    assert_eq!( mgr.status()[0].state, CmdState::Cancelled);
+    assert_eq!( mgr.run_schedules( time("12:01:00"), &devices), 0);
+    assert!( mgr.next_messages( time("12:01:00")).is_empty());
+}
+
+#[test]
+fn test_ping_is_not_queued () {
+    let mut mgr = SentinelCmdManager::new( config(10));
+    let res = mgr.enqueue_ws_cmd( &WsCmd::new_ping( "ui-1"), time("12:00:00"));
+    println!("{res:?}");
+    assert!( res.is_err());
+    assert!( mgr.status().is_empty());
+}
+
+#[test]
+fn test_check_interval () {
+    let config: SentinelCmdConfig = ron::from_str( "SentinelCmdConfig( check_interval: (secs: 10, nanos: 0))").unwrap();
+    assert_eq!( config.check_interval, Duration::from_secs(10));
+
+    let res = ron::from_str::<SentinelCmdConfig>( "SentinelCmdConfig( check_interval: (secs: 0, nanos: 0))");
+    println!("{res:?}");
+    assert!( res.is_err());
+}
@@ -0,0 +1,155 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+mod common;
+use common::*;
+
+use chrono::{DateTime,Utc};
+use odin_sentinel::{
+    SentinelUpdate, SentinelStore, PowerData, ImageData, SentinelHealthConfig, HealthIssue, HealthSeverity
+};
+
+const MAX_LEN: usize = 10;
+
+fn time (t: &str)->DateTime<Utc> {
+    DateTime::parse_from_rfc3339( &timestamp(t)).unwrap().to_utc()
+}
+
+fn power (id: &str, device_id: &str, time: &str, soc: f64, solar_current: f64)->SentinelUpdate {
+    update::<PowerData>( rec_json( id, device_id, time, 1, &[], "power", &format!(
+        r#"{{"batteryVoltage":12.1,"batteryCurrent":0.2,"solarVoltage":18.0,"solarCurrent":{solar_current},"loadVoltage":12.0,"loadCurrent":0.3,"soc":{soc},"batteryTemp":300.0,"controllerTemp":305.0}}"#)))
+}
+
+fn image (id: &str, device_id: &str, time: &str)->SentinelUpdate {
+    update::<ImageData>( rec_json( id, device_id, time, 2, &[], "image", r#"{"filename":"x.webp","isInfrared":false,"orientationRecord":null}"#))
+}
+
+fn store_with (updates: Vec<SentinelUpdate>)->SentinelStore {
+    let mut store = SentinelStore::new();
+    for u in updates { store.update_with( u, MAX_LEN); }
+    store
+}
+
+//--- the tests
+
+#[test]
+fn test_healthy_device () {
+    let store = store_with( vec![
+        power( "p1", "dev", "10:00:00", 80.0, 0.8), power( "p2", "dev", "10:10:00", 81.0, 0.8), power( "p3", "dev", "10:20:00", 82.0, 0.7),
+        gps( "g1", "dev", "10:00:00", 37.0, -122.0), gps( "g2", "dev", "10:10:00", 37.0, -122.0), gps( "g3", "dev", "10:20:00", 37.0, -122.0),
+        image( "i1", "dev", "10:05:00"), smoke_with_evidences( "s1", "dev", "10:05:00", 0.8, &["i1"]),
+    ]);
+
+    let health = store.device_health( &"dev".to_string(), &SentinelHealthConfig::default(), time("10:25:00")).unwrap();
+    println!("{health:#?}");
+
+    assert!( health.warnings.is_empty());
+    assert_eq!( health.score, 100);
+    assert!( health.battery.as_ref().unwrap().is_charging);
+    assert!( health.battery.as_ref().unwrap().soc_rate.unwrap() > 0.0);
+    assert_eq!( health.cadence.median_interval, Some(600_000));
+    assert_eq!( health.images.failures, 0);
+}
+
+#[test]
+fn test_battery_depletion () {
+    let store = store_with( vec![
+        power( "p1", "dev", "01:00:00", 20.0, 0.0), power( "p2", "dev", "02:00:00", 18.0, 0.0),
+        power( "p3", "dev", "03:00:00", 16.0, 0.0), power( "p4", "dev", "04:00:00", 14.0, 0.0),
+    ]);
+
+    let health = store.device_health( &"dev".to_string(), &SentinelHealthConfig::default(), time("04:10:00")).unwrap();
+    println!("{health:#?}");
+
+    let battery = health.battery.as_ref().unwrap();
+    assert!( (battery.soc_rate.unwrap() + 2.0).abs() < 1e-6);
+    assert!( (battery.hours_to_empty.unwrap() - 7.0).abs() < 1e-6);
+    assert!( health.has_issue( HealthIssue::LowBattery));
+    assert!( health.has_issue( HealthIssue::BatteryDepletion));
+    assert!( !health.has_issue( HealthIssue::NoSolarCharge)); // 3h of records are not enough to tell
+    assert_eq!( health.max_severity(), Some(HealthSeverity::Warning));
+    assert!( health.score < 100);
+}
+
+#[test]
+fn test_late_report () {
+    let store = store_with( vec![
+        power( "p1", "dev", "10:00:00", 80.0, 0.5), power( "p2", "dev", "10:10:00", 80.0, 0.5), 
+        power( "p3", "dev", "10:20:00", 80.0, 0.5), power( "p4", "dev", "11:00:00", 80.0, 0.5), // one missed interval
+    ]);
+    let config = SentinelHealthConfig::default();
+
+    let health = store.device_health( &"dev".to_string(), &config, time("11:05:00")).unwrap();
+    assert_eq!( health.cadence.missed_reports, 1);
+    assert!( health.has_issue( HealthIssue::MissedReports));
+    assert!( !health.has_issue( HealthIssue::LateReport));
+    assert!( health.is_healthy()); // irregular intervals alone are only informational
+
+    let health = store.device_health( &"dev".to_string(), &config, time("11:45:00")).unwrap();
+    println!("{health:#?}");
+    assert!( health.has_issue( HealthIssue::LateReport));
+    assert!( !health.is_healthy());
+}
+
+#[test]
+fn test_gps_drift () {
+    let store = store_with( vec![
+        gps( "g1", "dev", "10:00:00", 37.0, -122.0), gps( "g2", "dev", "10:10:00", 37.0, -122.0),
+        gps( "g3", "dev", "10:20:00", 37.0, -122.0), gps( "g4", "dev", "10:30:00", 37.001, -122.0), // ~111m north
+    ]);
+
+    let health = store.device_health( &"dev".to_string(), &SentinelHealthConfig::default(), time("10:31:00")).unwrap();
+    println!("{health:#?}");
+
+    let gps = health.gps.as_ref().unwrap();
+    assert_eq!( gps.fixes, 4);
+    assert!( gps.drift > 100.0 && gps.drift < 120.0);
+    assert!( health.has_issue( HealthIssue::GpsDrift));
+}
+
+#[test]
+fn test_image_failures () {
+    let store = store_with( vec![
+        image( "i1", "dev", "10:00:00"),
+        smoke_with_evidences( "s1", "dev", "10:01:00", 0.8, &["i1"]), // Ok
+        smoke_with_evidences( "s2", "dev", "10:02:00", 0.8, &["i2"]), // image never arrived
+        smoke_with_evidences( "s3", "dev", "10:03:00", 0.8, &[]),     // no image taken
+    ]);
+
+    let health = store.device_health( &"dev".to_string(), &SentinelHealthConfig::default(), time("10:04:00")).unwrap();
+    println!("{health:#?}");
+
+    assert_eq!( health.images.images, 1);
+    assert_eq!( health.images.failures, 2);
+    assert!( health.has_issue( HealthIssue::ImageFailures));
+}
+
+#[test]
+fn test_health_report_order () {
+    let store = store_with( vec![
+        power( "a1", "good", "10:00:00", 90.0, 0.5),
+        power( "b1", "bad", "10:00:00", 5.0, 0.0),
+    ]);
+
+    let report = store.health_report( &SentinelHealthConfig::default(), time("10:01:00"));
+    assert_eq!( report.len(), 2);
+    assert_eq!( report[0].device_id, "bad");
+    assert_eq!( report[0].max_severity(), Some(HealthSeverity::Critical));
+    assert_eq!( report[1].score, 100);
+
+    let json = serde_json::to_string( &report).unwrap();
+    println!("{json}");
+    assert!( json.contains(r#""issue":"LowBattery""#));
+}
@@ -0,0 +1,119 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+mod common;
+use common::{GPS_1, GPS_2, gps_update};
+
+use std::{fs, path::PathBuf};
+use reqwest::Client;
+use odin_sentinel::{
+    Result, GpsData, SensorRecord, SentinelUpdate, SentinelStore, SentinelRecorder, SensorCapability,
+    MockDelphireServer, MockDelphireConfig, MockFaults, get_device_list, get_records_since, rec_key,
+    ws::{WsMsg, init_websocket, read_next_ws_msg}
+};
+
+const DEVICE_ID: &str = "roo7gd1dldn3";
+
+/// create a fixture with a single initial GPS record
+fn create_fixture (name: &str)->Result<PathBuf> {
+    let dir = std::env::temp_dir().join( format!("odin_sentinel_mock_{}_{}", name, std::process::id()));
+    if dir.is_dir() { fs::remove_dir_all(&dir)?; }
+
+    let mut store = SentinelStore::new();
+    store.update_with( gps_update(GPS_1)?, 10);
+    SentinelRecorder::new( &dir)?.record_store( &store)?;
+    Ok(dir)
+}
+
+async fn start_server (name: &str, faults: MockFaults)->Result<(MockDelphireServer,PathBuf)> {
+    let fixture_dir = create_fixture( name)?;
+    let config = MockDelphireConfig { fixture_dir: fixture_dir.clone(), faults, ..MockDelphireConfig::default() };
+    Ok( (MockDelphireServer::start( config).await?, fixture_dir) )
+}
+
+#[tokio::test]
+async fn test_initial_store()->Result<()> {
+    let (server, dir) = start_server( "store", MockFaults::default()).await?;
+
+    let mut store = SentinelStore::new();
+    store.fetch_from_config( &Client::new(), &server.sentinel_config()).await?;
+    println!("store: {}", store.to_json_pretty()?);
+
+    assert_eq!( store.get_device_ids(), vec![DEVICE_ID.to_string()]);
+    assert!( store.get_update( &"rUEGekTnRjD7opkqxJAw".to_string()).is_some());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_record_notification()->Result<()> {
+    let (server, dir) = start_server( "notify", MockFaults::default()).await?;
+    let config = server.sentinel_config();
+
+    let mut ws = init_websocket( &config, &vec![DEVICE_ID.to_string()]).await?;
+    server.release( gps_update(GPS_2)?);
+
+    let msg = read_next_ws_msg( &mut ws).await?;
+    println!("got notification: {msg:?}");
+    assert_eq!( msg, WsMsg::Record { device_id: DEVICE_ID.to_string(), sensor_no: 9, rec_type: SensorCapability::Gps });
+
+    // this is what the LiveConnection uses to catch up after a reconnect
+    let uri_path = rec_key( DEVICE_ID, 9, SensorCapability::Gps);
+    let recs = get_records_since::<GpsData>( &Client::new(), &server.base_uri(), "mock-token", &uri_path, "rUEGekTnRjD7opkqxJAw").await?;
+    assert_eq!( recs.len(), 1);
+    assert_eq!( recs[0].id, "Za1Y9LIYQ7KXSNbeDNBb");
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_faults()->Result<()> {
+    let faults = MockFaults { malformed_paths: vec!["/devices".to_string()], ..MockFaults::default() };
+    let (server, dir) = start_server( "http_faults", faults).await?;
+    let client = Client::new();
+
+    assert!( get_device_list( &client, &server.base_uri(), "mock-token").await.is_err()); // malformed JSON
+
+    server.set_faults( MockFaults::default());
+    assert!( get_device_list( &client, &server.base_uri(), "wrong-token").await.is_err()); // not authorized
+    assert!( get_device_list( &client, &server.base_uri(), "mock-token").await.is_ok());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ws_faults()->Result<()> {
+    let faults = MockFaults { reject_ws: 1, drop_socket_after: Some(1), ..MockFaults::default() };
+    let (server, dir) = start_server( "ws_faults", faults).await?;
+    let config = server.sentinel_config();
+    let device_ids = vec![DEVICE_ID.to_string()];
+
+    assert!( init_websocket( &config, &device_ids).await.is_err()); // first attempt is rejected
+    let mut ws = init_websocket( &config, &device_ids).await?;
+
+    server.release( gps_update(GPS_2)?);
+    assert!( read_next_ws_msg( &mut ws).await.is_ok());
+    assert!( read_next_ws_msg( &mut ws).await.is_err()); // dropped after first notification
+
+    let mut ws = init_websocket( &config, &device_ids).await?; // reconnect works
+    server.drop_sockets();
+    assert!( read_next_ws_msg( &mut ws).await.is_err());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,134 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![cfg(feature="mqtt")]
+#![allow(unused)]
+
+use std::time::Duration;
+use chrono::Utc;
+use serde_json::{json, Value};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}, sync::mpsc, time::timeout};
+use odin_sentinel::{Result, Alarm, AlarmMessenger, MqttConfig, MqttAlarmMessenger};
+
+/* #region minimal MQTT 3.1.1 stand-in broker *********************************************************************/
+
+// this only supports what a publishing client needs: CONNECT, PUBLISH (QoS 0/1), PINGREQ and DISCONNECT
+
+async fn read_packet (stream: &mut TcpStream)->Option<(u8,Vec<u8>)> {
+    let header = stream.read_u8().await.ok()?;
+
+    let mut len: usize = 0;
+    let mut shift = 0;
+    loop { // variable length encoding
+        let b = stream.read_u8().await.ok()?;
+        len += ((b & 0x7f) as usize) << shift;
+        if b & 0x80 == 0 { break }
+        shift += 7;
+    }
+
+    let mut body = vec![0u8; len];
+    stream.read_exact( &mut body).await.ok()?;
+    Some( (header, body) )
+}
+
+async fn serve_client (mut stream: TcpStream, tx: mpsc::Sender<(String,Vec<u8>)>) {
+    while let Some((header,body)) = read_packet( &mut stream).await {
+        match header >> 4 {
+            1 => { stream.write_all( &[0x20, 0x02, 0x00, 0x00]).await; } // CONNECT -> CONNACK (accepted)
+            3 => { // PUBLISH
+                let qos = (header >> 1) & 0x03;
+                let topic_len = u16::from_be_bytes( [body[0], body[1]]) as usize;
+                let topic = String::from_utf8_lossy( &body[2..2+topic_len]).to_string();
+                let mut payload_start = 2 + topic_len;
+                if qos > 0 {
+                    stream.write_all( &[0x40, 0x02, body[payload_start], body[payload_start+1]]).await; // PUBACK
+                    payload_start += 2;
+                }
+                tx.send( (topic, body[payload_start..].to_vec())).await;
+            }
+            12 => { stream.write_all( &[0xd0, 0x00]).await; } // PINGREQ -> PINGRESP
+            14 => break, // DISCONNECT
+            _ => {}
+        }
+    }
+}
+
+async fn start_broker ()->Result<(u16, mpsc::Receiver<(String,Vec<u8>)>)> {
+    let listener = TcpListener::bind( "127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let (tx, rx) = mpsc::channel(16);
+
+    tokio::spawn( async move {
+        while let Ok((stream,_)) = listener.accept().await {
+            tokio::spawn( serve_client( stream, tx.clone()));
+        }
+    });
+
+    Ok( (port, rx) )
+}
+
+/* #endregion minimal MQTT broker */
+
+fn alarm ()->Alarm {
+    Alarm {
+        id: "fire(roo7gd1dldn3,2024-06-05T03:00:00UTC)".to_string(), record_id: "rec-1".to_string(), no: 7, tier: 1, contacts: Vec::new(),
+        device_id: "roo7gd1dldn3".to_string(), description: "🔥 fire".to_string(), time_recorded: Utc::now(), pos: None,
+        alarm_type: "fire".to_string(), confidence: 0.93, evidence_info: Vec::new()
+    }
+}
+
+#[tokio::test]
+async fn test_publish()->Result<()> {
+    let (port, mut rx) = start_broker().await?;
+
+    let config: MqttConfig = ron::from_str( &format!(r#"MqttConfig(
+        host: "127.0.0.1", port: {port}, client_id: "odin-test",
+        topic: "odin/sentinel/${{deviceId}}/${{alarmType}}",
+        template: Some("{{\"device\":\"${{deviceId}}\",\"confidence\":\"${{confidence}}\",\"tier\":\"${{tier}}\"}}"),
+        keep_alive: (secs: 10, nanos: 0), reconnect_delay: (secs: 1, nanos: 0), timeout: (secs: 2, nanos: 0)
+    )"#)).unwrap();
+    let messenger = MqttAlarmMessenger::new( config);
+
+    messenger.send_alarm( &alarm()).await?;
+
+    let (topic, payload) = timeout( Duration::from_secs(5), rx.recv()).await.expect("no message published").unwrap();
+    let payload: Value = serde_json::from_slice( &payload)?;
+    println!("{topic}: {payload}");
+
+    assert_eq!( topic, "odin/sentinel/roo7gd1dldn3/fire");
+    assert_eq!( payload, json!({"device":"roo7gd1dldn3","confidence":0.93,"tier":1}));
+    Ok(())
+}
+
+fn config_src (qos: u8)->String {
+    format!(r#"MqttConfig(
+        host: "127.0.0.1", port: 1883, client_id: "odin-test", topic: "odin/sentinel/${{deviceId}}/${{alarmType}}", qos: {qos},
+        keep_alive: (secs: 10, nanos: 0), reconnect_delay: (secs: 1, nanos: 0), timeout: (secs: 2, nanos: 0)
+    )"#)
+}
+
+#[test]
+fn test_reject_invalid_qos() {
+    assert!( ron::from_str::<MqttConfig>( &config_src(2)).is_ok());
+    assert!( ron::from_str::<MqttConfig>( &config_src(3)).is_err());
+}
+
+#[tokio::test]
+async fn test_sanitized_topic()->Result<()> {
+    let messenger = MqttAlarmMessenger::new( ron::from_str( &config_src(1)).unwrap());
+    let mut alarm = alarm();
+    alarm.device_id = "a/b+#c".to_string();
+
+    assert_eq!( messenger.topic( &alarm), "odin/sentinel/a_b__c/fire");
+    Ok(())
+}
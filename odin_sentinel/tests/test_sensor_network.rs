@@ -0,0 +1,152 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use reqwest::Client;
+use odin_sentinel::{
+    SensorNetworkAdapter, JsonReadingAdapter, JsonReadingNetwork, DelphireAdapter, SensorDevice, SensorDescriptor, SensorReading,
+    SensorCapability, SentinelStore, SentinelUpdate, Result
+};
+
+const READING: &str = r#"{"deviceId":"ws-1","sensorNo":2,"timeRecorded":"2024-06-01T12:00:00Z","capability":"thermometer","data":{"temperature":300.15}}"#;
+
+fn weather_stations (strict: bool)->JsonReadingNetwork {
+    JsonReadingNetwork {
+        network: "wx".to_string(),
+        devices: vec![
+            SensorDevice::new( "ws-1", Some("Skyline".to_string()), vec![ SensorDescriptor { no: 2, capabilities: vec![SensorCapability::Thermometer] } ])
+        ],
+        strict
+    }
+}
+
+#[test]
+fn test_reading_to_update ()->Result<()> {
+    let reading: SensorReading = serde_json::from_str( READING)?;
+    let update = reading.to_update( "wx")?;
+    println!("update: {}", update.to_json()?);
+
+    assert_eq!( update.device_id(), "ws-1");
+    assert_eq!( update.sensor_no(), 2);
+    assert_eq!( update.capability(), SensorCapability::Thermometer);
+    assert_eq!( update.record_id(), &format!("wx-ws-1-2-thermometer-{}", update.time_recorded().timestamp_millis()));
+    Ok(())
+}
+
+#[test]
+fn test_json_adapter ()->Result<()> {
+    let adapter = JsonReadingAdapter::new( weather_stations( false));
+    assert_eq!( adapter.parse_message( "wx/ws-1", READING.as_bytes())?.len(), 1);
+
+    let readings = format!("[{READING},{}]", READING.replace("ws-1", "ws-2").replace("thermometer", "smoke").replace(r#"{"temperature":300.15}"#, r#"{"smokeProb":0.2}"#));
+    let updates = adapter.parse_message( "wx", readings.as_bytes())?;
+    assert_eq!( updates.len(), 2);
+    assert_eq!( updates[1].capability(), SensorCapability::Smoke);
+
+    // unknown devices are rejected in strict mode, which only skips the respective readings
+    let adapter = JsonReadingAdapter::new( weather_stations( true));
+    let updates = adapter.parse_message( "wx", readings.as_bytes())?;
+    assert_eq!( updates.len(), 1);
+    assert_eq!( updates[0].device_id(), "ws-1");
+    assert!( adapter.parse_message( "wx", READING.replace("ws-1", "ws-2").as_bytes()).is_err());
+
+    // malformed readings in a list are skipped
+    let readings = format!(r#"[{{"deviceId":"ws-1"}},{READING},{}]"#, READING.replace("thermometer", "bogus"));
+    assert_eq!( adapter.parse_message( "wx", readings.as_bytes())?.len(), 1);
+    assert!( adapter.parse_message( "wx", b"{\"deviceId\":\"ws-1\"}").is_err()); // malformed
+    Ok(())
+}
+
+#[test]
+fn test_delphire_adapter_parse ()->Result<()> {
+    let adapter = DelphireAdapter::new( Client::new(), "http://localhost", "", Vec::new());
+    let rec = r#"{"id":"rec-1","timeRecorded":"2024-06-01T12:00:00.000Z","sensorNo":7,"deviceId":"roo7","evidences":[],"claims":[],"smoke":{"smokeProb":0.8}}"#;
+
+    let updates = adapter.parse_message( "relay", rec.as_bytes())?;
+    assert_eq!( updates.len(), 1);
+    assert_eq!( updates[0].capability(), SensorCapability::Smoke);
+    assert_eq!( updates[0].record_id(), "rec-1");
+
+    let rec_list = format!(r#"{{"data":[{rec}]}}"#);
+    assert_eq!( adapter.parse_message( "relay", rec_list.as_bytes())?.len(), 1);
+    assert!( adapter.parse_message( "relay", br#"{"id":"rec-2"}"#).is_err());
+    Ok(())
+}
+
+/// a network adapter that has records to retrieve (like a camera network with a query API)
+struct StaticAdapter;
+
+#[async_trait]
+impl SensorNetworkAdapter for StaticAdapter {
+    fn network (&self)->&str { "static" }
+
+    async fn get_devices (&self)->Result<Vec<SensorDevice>> {
+        Ok( vec![ SensorDevice::new( "ws-1", None, vec![ SensorDescriptor { no: 2, capabilities: vec![SensorCapability::Thermometer] } ]) ] )
+    }
+
+    async fn get_records (&self, device: &SensorDevice, sensor_no: u32, capability: SensorCapability, n_last: usize)->Result<Vec<SentinelUpdate>> {
+        let mut updates = Vec::new();
+        for min in (0..5).rev().take( n_last) { // newest first
+            let json = READING.replace("12:00:00", &format!("12:0{min}:00"));
+            updates.push( serde_json::from_str::<SensorReading>( &json)?.to_update( self.network())?);
+        }
+        Ok(updates)
+    }
+
+    fn parse_message (&self, topic: &str, payload: &[u8])->Result<Vec<SentinelUpdate>> {
+        Ok( Vec::new() )
+    }
+}
+
+#[tokio::test]
+async fn test_fetch_from_adapters ()->Result<()> {
+    let mut store = SentinelStore::new();
+    store.fetch_from_adapter( &StaticAdapter, 3, 3).await?;
+
+    let sentinel = serde_json::to_value( store.get( &"ws-1".to_string()).unwrap())?;
+    println!("sentinel: {}", serde_json::to_string_pretty( &sentinel)?);
+    assert_eq!( sentinel["network"], "static");
+    assert_eq!( sentinel["thermometer"].as_array().unwrap().len(), 3);
+    assert_eq!( store.updates_iter().count(), 3);
+
+    // devices of several networks can share the same store
+    let adapter = JsonReadingAdapter::new( JsonReadingNetwork { network: "wx".to_string(), devices: vec![ SensorDevice::new( "ws-2", None, Vec::new()) ], strict: false });
+    store.fetch_from_adapter( &adapter, 3, 3).await?;
+    assert_eq!( store.values().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_merge_network_stores ()->Result<()> {
+    let mut store = SentinelStore::new();
+    store.fetch_from_adapter( &StaticAdapter, 3, 3).await?;
+
+    let mut wx_store = SentinelStore::new();
+    let adapter = JsonReadingAdapter::new( JsonReadingNetwork { network: "wx".to_string(), devices: vec![ SensorDevice::new( "ws-2", None, Vec::new()) ], strict: false });
+    wx_store.fetch_from_adapter( &adapter, 3, 3).await?;
+    store.merge( wx_store);
+    assert_eq!( store.values().len(), 2);
+    assert_eq!( store.updates_iter().count(), 3);
+
+    // re-initializing one network keeps the devices of the other
+    let mut static_store = SentinelStore::new();
+    static_store.fetch_from_adapter( &StaticAdapter, 1, 1).await?;
+    store.merge( static_store);
+    assert_eq!( store.values().len(), 2);
+    assert_eq!( store.updates_iter().count(), 1);
+    assert!( store.get( &"ws-2".to_string()).is_some());
+    Ok(())
+}
@@ -0,0 +1,62 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![cfg(feature="smtp")]
+#![allow(unused)]
+
+//! tests for reading alarm replies from a local Maildir
+
+use std::{fs, path::PathBuf};
+use odin_sentinel::{Result, AlarmNo, read_maildir_replies};
+
+fn token (no: AlarmNo)->String { format!("tok{no}") }
+
+fn create_maildir (name: &str)->Result<PathBuf> {
+    let dir = std::env::temp_dir().join( format!("odin_sentinel_maildir_{}_{}", name, std::process::id()));
+    if dir.is_dir() { fs::remove_dir_all(&dir)?; }
+    fs::create_dir_all( dir.join("new"))?;
+    Ok(dir)
+}
+
+fn mail (from: &str, subject: &str, body: &str)->String {
+    format!("From: {from}\nSubject: {subject}\n\n{body}\n")
+}
+
+#[test]
+fn test_maildir_replies()->Result<()> {
+    let dir = create_maildir( "replies")?;
+    let new_dir = dir.join("new");
+
+    fs::write( new_dir.join("1"), mail( "Alice <alice@example.com>", "Re: alarm #42 [tok42]", "ack"))?;
+    fs::write( new_dir.join("2"), vec![0xff, 0xfe, 0x00, 0x41])?; // not UTF-8
+    fs::write( new_dir.join("3"), mail( "bob@example.com", "Re: alarm #7 [tok7]", "resolve 7\n> original alarm"))?;
+    fs::write( new_dir.join("4"), mail( "mallory@example.com", "Re: alarm #42", "ack 42"))?; // no token
+
+    let mut replies = read_maildir_replies( &dir, token)?;
+    replies.sort_by( |a,b| a.from.cmp( &b.from));
+    println!("{replies:#?}");
+
+    assert_eq!( replies.len(), 2);
+    assert_eq!( replies[0].from, "alice@example.com");
+    assert_eq!( replies[0].text, "ack 42");
+    assert_eq!( replies[1].from, "bob@example.com");
+    assert_eq!( replies[1].text, "resolve 7");
+
+    // all messages (including the unreadable one) are moved out of new/ so that they don't block the next poll
+    assert_eq!( fs::read_dir( &new_dir)?.count(), 0);
+    assert_eq!( fs::read_dir( dir.join("cur"))?.count(), 4);
+    assert!( read_maildir_replies( &dir, token)?.is_empty());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+mod common;
+use common::*;
+
+use std::fs;
+use chrono::{DateTime,Utc};
+use odin_sentinel::{
+    Result, SentinelStore, SensorCapability, SentinelTimeSeriesWriter, SentinelTimeSeries, TimeSeriesQuery, split_csv_line
+};
+
+fn date (s: &str)->DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().to_utc()
+}
+
+#[test]
+fn test_split_csv_line() {
+    let fields = split_csv_line( r#"a,"b,c",,"say ""hi""""#);
+    assert_eq!( fields, vec!["a", "b,c", "", r#"say "hi""#]);
+}
+
+#[test]
+fn test_append_and_export()->Result<()> {
+    let dir = std::env::temp_dir().join( format!("odin_sentinel_timeseries_{}", std::process::id()));
+    if dir.is_dir() { fs::remove_dir_all(&dir)?; }
+
+    //--- append
+    let writer = SentinelTimeSeriesWriter::new( &dir)?;
+
+    let mut store = SentinelStore::new();
+    store.update_with( temp( "t1", "dev_a", "2024-06-01T23:50:00.000Z", 290.0), 10);
+    store.update_with( gps( "g1", "dev_a", "2024-06-01T23:50:00.000Z", 37.0, -122.0), 10);
+    writer.append_store( &store)?;
+
+    writer.append_update( &temp( "t2", "dev_a", "2024-06-02T00:10:00.000Z", 291.0))?;
+    writer.append_update( &temp( "t3", "dev_b", "2024-06-02T00:20:00.000Z", 288.0))?;
+    writer.append_update( &temp( "t2", "dev_a", "2024-06-02T00:10:00.000Z", 291.0))?; // duplicate
+
+    assert!( dir.join("thermometer/2024-06-01.csv").is_file());
+    assert!( dir.join("thermometer/2024-06-02.csv").is_file());
+    assert!( dir.join("gps/2024-06-01.csv").is_file());
+
+    let content = fs::read_to_string( dir.join("thermometer/2024-06-02.csv"))?;
+    println!("{content}");
+    assert!( content.starts_with("timeRecorded,epochMillis,deviceId,sensorNo,recordId,evidences,temperature\n"));
+
+    //--- export
+    let timeseries = SentinelTimeSeries::open( &dir)?;
+    let mut capabilities = timeseries.capabilities()?;
+    assert_eq!( capabilities.len(), 2);
+
+    let query = TimeSeriesQuery::new( date("2024-06-01T00:00:00Z"), date("2024-06-03T00:00:00Z"));
+    let mut buf: Vec<u8> = Vec::new();
+    let n = timeseries.export( SensorCapability::Thermometer, &query, &mut buf)?;
+    let csv = String::from_utf8(buf).unwrap();
+    println!("{csv}");
+    assert_eq!( n, 3); // duplicate removed
+    assert_eq!( csv.lines().count(), 4); // single header
+
+    let query = TimeSeriesQuery::new( date("2024-06-02T00:00:00Z"), date("2024-06-02T00:15:00Z"));
+    let n = timeseries.export( SensorCapability::Thermometer, &query, &mut Vec::new())?;
+    assert_eq!( n, 1);
+
+    let query = TimeSeriesQuery::new( date("2024-06-01T00:00:00Z"), date("2024-06-03T00:00:00Z")).with_devices( vec!["dev_b".to_string()]);
+    let n = timeseries.export( SensorCapability::Thermometer, &query, &mut Vec::new())?;
+    assert_eq!( n, 1);
+
+    let query = TimeSeriesQuery::new( date("2024-06-01T00:00:00Z"), date("2024-06-03T00:00:00Z"))
+        .with_devices( vec!["dev_a".to_string()])
+        .with_capabilities( vec![SensorCapability::Gps, SensorCapability::Thermometer]);
+    let export_dir = dir.join("export");
+    let mut exported = timeseries.export_to_dir( &query, &export_dir)?;
+    exported.sort();
+    println!("{exported:?}");
+    assert_eq!( exported, vec![ (export_dir.join("gps.csv"), 1), (export_dir.join("thermometer.csv"), 2) ]);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![cfg(feature="webhook")]
+#![allow(unused)]
+
+use std::{sync::{Arc,Mutex}, time::Duration};
+use axum::{Router, routing::post, extract::State, http::{HeaderMap, StatusCode}};
+use chrono::Utc;
+use serde_json::{json, Value};
+use odin_sentinel::{
+    Result, Alarm, AlarmMessenger, WebhookConfig, WebhookAlarmMessenger, webhook_signature, expand_json_template
+};
+
+const SECRET: &str = "top-secret";
+
+/// what our stand-in receiver got
+#[derive(Default)]
+struct Received {
+    n_requests: usize,
+    bodies: Vec<Value>,
+}
+
+/// local webhook receiver that verifies signatures and fails the first `n_failures` requests with a 503
+async fn start_receiver (n_failures: usize)->Result<(String,Arc<Mutex<Received>>)> {
+    let received = Arc::new( Mutex::new( Received::default()));
+
+    let app = Router::new().route( "/alarm", post( move |State(received): State<Arc<Mutex<Received>>>, headers: HeaderMap, body: String| async move {
+        let mut received = received.lock().unwrap();
+        received.n_requests += 1;
+        if received.n_requests <= n_failures { return StatusCode::SERVICE_UNAVAILABLE }
+
+        let timestamp: i64 = headers.get("X-Odin-Timestamp").and_then( |v| v.to_str().ok()).and_then( |v| v.parse().ok()).unwrap_or(0);
+        let signature = headers.get("X-Odin-Signature").and_then( |v| v.to_str().ok()).unwrap_or("");
+        if webhook_signature( SECRET, timestamp, &body).unwrap() != signature { return StatusCode::UNAUTHORIZED }
+
+        received.bodies.push( serde_json::from_str( &body).unwrap());
+        StatusCode::OK
+    })).with_state( received.clone());
+
+    let listener = tokio::net::TcpListener::bind( "127.0.0.1:0").await?;
+    let uri = format!("http://{}/alarm", listener.local_addr()?);
+    tokio::spawn( async move { axum::serve( listener, app).await });
+
+    Ok( (uri, received) )
+}
+
+fn config (uri: String, template: Option<&str>)->WebhookConfig {
+    let src = format!(r#"WebhookConfig( uri: "{uri}", secret: Some("{SECRET}"), timeout: (secs: 2, nanos: 0), retry_delay: (secs: 0, nanos: 10000000))"#);
+    let mut config: WebhookConfig = ron::from_str( &src).unwrap();
+    config.template = template.map( |t| t.to_string());
+    config
+}
+
+fn alarm ()->Alarm {
+    Alarm {
+        id: "smoke(roo7gd1dldn3,2024-06-05T03:00:00UTC)".to_string(), record_id: "rec-1".to_string(), no: 42, tier: 0, contacts: Vec::new(),
+        device_id: "roo7gd1dldn3".to_string(), description: "🔥 smoke".to_string(), time_recorded: Utc::now(), pos: None,
+        alarm_type: "smoke".to_string(), confidence: 0.87, evidence_info: Vec::new()
+    }
+}
+
+#[test]
+fn test_template() {
+    let template = json!({ "event": "wildfire-${alarmType}", "unit": "${deviceId}", "confidence": "${confidence}", "loc": ["${lon}","${lat}"], "ref": "#${no}" });
+    let payload = expand_json_template( &template, &alarm().to_json());
+    println!("payload: {payload}");
+
+    assert_eq!( payload["event"], json!("wildfire-smoke"));
+    assert_eq!( payload["unit"], json!("roo7gd1dldn3"));
+    assert_eq!( payload["confidence"], json!(0.87)); // typed value
+    assert_eq!( payload["loc"], json!([null,null]));
+    assert_eq!( payload["ref"], json!("#42"));
+}
+
+#[tokio::test]
+async fn test_signed_post_with_retries()->Result<()> {
+    let (uri, received) = start_receiver( 2).await?;
+    let messenger = WebhookAlarmMessenger::new( config( uri, Some(r#"{"type":"${alarmType}","device":"${deviceId}","no":"${no}"}"#)));
+
+    messenger.send_alarm( &alarm()).await?;
+
+    let received = received.lock().unwrap();
+    assert_eq!( received.n_requests, 3);
+    assert_eq!( received.bodies, vec![ json!({"type":"smoke","device":"roo7gd1dldn3","no":42}) ]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retries_exhausted()->Result<()> {
+    let (uri, received) = start_receiver( 10).await?;
+    let messenger = WebhookAlarmMessenger::new( config( uri, None));
+
+    assert!( messenger.send_alarm( &alarm()).await.is_err());
+    assert_eq!( received.lock().unwrap().n_requests, 4); // first attempt plus default max_retries
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_delivery_time()->Result<()> {
+    let (uri, received) = start_receiver( 1000).await?;
+    let mut config = config( uri, None);
+    config.max_retries = 1000;
+    config.retry_delay = Duration::from_millis(50);
+    config.max_delivery_time = Duration::from_millis(300);
+    let messenger = WebhookAlarmMessenger::new( config);
+
+    let t0 = std::time::Instant::now();
+    assert!( messenger.send_alarm( &alarm()).await.is_err());
+    assert!( t0.elapsed() < Duration::from_secs(2));
+    assert!( received.lock().unwrap().n_requests < 1000);
+    Ok(())
+}
@@ -2,7 +2,7 @@
 // note this needs to be in a script element of type text/javascript to make sure document.currentScript is defined
 
 const searchParams = new URLSearchParams(window.location.search);
-let theme = searchParams.get('theme');
+let theme = searchParams.get('theme') || document.documentElement.dataset.theme; // query overrides app default
 let lnk = document.createElement('link');
 lnk.id = "theme";
 lnk.type ='text/css';
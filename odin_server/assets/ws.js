@@ -19,6 +19,10 @@ var ws = undefined;
 var wsUrl = "./ws";
 var isShutdown = false;
 
+// the mod_path of messages that are processed by this module (see odin_server::ws_service::WsService::mod_path())
+const MOD_PATH = "odin_server::ws_service::WsService";
+const RELOAD_DELAY = 1000; // [ms] give the server a chance to restart if this was caused by a config change
+
 // wsHandlers is a map object from module-names to handler functions.
 // each handler function takes the msg name and the payload object as arguments:
 //      `function (msgName, msgObject) {...}`
@@ -36,7 +40,9 @@ export function addWsHandler(modName,newHandler) {
 function handleServerMessage(msg) {
     //console.log(JSON.stringify(msg));
     let modName = msg.mod;
-    if (modName) {
+    if (modName == MOD_PATH) {
+        handleWsServiceMessage( Object.keys(msg)[1], Object.values(msg)[1]);
+    } else if (modName) {
         let handlerFunc = wsHandlers.get(modName);
         if (handlerFunc) {
             let msgName = Object.keys(msg)[1]; // 2nd property is the payload message
@@ -50,6 +56,17 @@ function handleServerMessage(msg) {
     }
 }
 
+function handleWsServiceMessage (msgType, msg) {
+    switch (msgType) {
+        case "reload": 
+            console.log("server requested reload: ", msg);
+            setTimeout( () => window.location.reload(), RELOAD_DELAY);
+            break;
+        default:
+            console.log("unknown ws service message: ", msgType);
+    }
+}
+
 export function sendWsMessage (modPath, msgType, msgData) {
     let msg = {};
     msg.mod = modPath;
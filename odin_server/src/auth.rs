@@ -0,0 +1,112 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! access policies for the routes of a `SpaApp`. Policies are checked by a route layer that is applied to all routes
+//! of an app (document, assets, proxies, websocket and service specific routes). Successfully authenticated requests
+//! get a `SpaUser` request extension that can be used by route handlers.
+//!
+//! Note that credentials are transmitted in clear text unless the server is configured to use TLS
+
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use axum::{
+    extract::{connect_info::ConnectInfo, Request},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use headers::{authorization::{Basic, Bearer}, Authorization, HeaderMapExt};
+use serde::{Deserialize,Serialize};
+
+/// the access policy of a SpaApp
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+pub enum SpaAuth {
+    /// no restrictions
+    #[default]
+    Public,
+
+    /// only clients on the same machine (loopback addresses)
+    LocalOnly,
+
+    /// HTTP Basic authentication with a map of user -> password
+    Basic { realm: String, users: HashMap<String,String> },
+
+    /// HTTP Bearer tokens, mapped to the name of the user (or client tool) they were issued to
+    Bearer { tokens: HashMap<String,String> },
+}
+
+/// request extension that identifies the authenticated user (if any)
+#[derive(Debug,Clone,PartialEq)]
+pub struct SpaUser (pub Option<String>);
+
+impl SpaAuth {
+    pub fn is_public (&self)->bool {
+        matches!( self, SpaAuth::Public)
+    }
+
+    /// check if the request is permitted and return the authenticated user name for it
+    /// note that `LocalOnly` requires the router to be served with `into_make_service_with_connect_info`
+    fn check (&self, req: &Request)->Result<Option<String>,Response> {
+        match self {
+            SpaAuth::Public => Ok(None),
+
+            SpaAuth::LocalOnly => {
+                match req.extensions().get::<ConnectInfo<SocketAddr>>() {
+                    Some(ConnectInfo(addr)) if addr.ip().is_loopback() => Ok(None),
+                    _ => Err( StatusCode::FORBIDDEN.into_response())
+                }
+            }
+
+            SpaAuth::Basic { realm, users } => {
+                if let Some(Authorization(basic)) = req.headers().typed_get::<Authorization<Basic>>() {
+                    if users.get( basic.username()).map( |pw| constant_time_eq( pw.as_bytes(), basic.password().as_bytes())).unwrap_or(false) {
+                        return Ok( Some( basic.username().to_string()))
+                    }
+                }
+                let challenge = HeaderValue::from_str( &format!("Basic realm=\"{realm}\""))
+                    .unwrap_or( HeaderValue::from_static("Basic"));
+                Err( (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, challenge)]).into_response())
+            }
+
+            SpaAuth::Bearer { tokens } => {
+                if let Some(Authorization(bearer)) = req.headers().typed_get::<Authorization<Bearer>>() {
+                    // check all tokens so that response times don't depend on how many tokens share a prefix
+                    let token = bearer.token().as_bytes();
+                    let user = tokens.iter().fold( None, |found, (t,user)| if constant_time_eq( t.as_bytes(), token) { Some(user) } else { found });
+                    if let Some(user) = user {
+                        return Ok( Some( user.clone()))
+                    }
+                }
+                Err( (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))]).into_response())
+            }
+        }
+    }
+}
+
+/// compare secrets in time that only depends on their length (not on the position of the first difference)
+fn constant_time_eq (a: &[u8], b: &[u8])->bool {
+    if a.len() != b.len() { return false }
+    a.iter().zip( b.iter()).fold( 0u8, |acc, (x,y)| acc | (x ^ y)) == 0
+}
+
+/// the middleware function that is used in the route layer of restricted apps
+pub async fn check_auth (auth: Arc<SpaAuth>, mut req: Request, next: Next)->Response {
+    match auth.check( &req) {
+        Ok(user) => {
+            req.extensions_mut().insert( SpaUser(user));
+            next.run( req).await
+        }
+        Err(response) => response
+    }
+}
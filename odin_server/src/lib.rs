@@ -14,7 +14,7 @@
 #![allow(unused)]
 //#![feature(diagnostic_namespace)]
 
-use std::{net::SocketAddr, path::{Path,PathBuf}};
+use std::{net::SocketAddr, path::{Path,PathBuf}, time::Duration, io::ErrorKind};
 
 use axum::{body::Body, response::{Response,IntoResponse}, Router, http::{header,StatusCode as AxStatusCode, HeaderMap, HeaderName}};
 use axum_server::{service::MakeService, tls_rustls::RustlsConfig};
@@ -25,9 +25,10 @@ use reqwest::StatusCode;
 use bytes::Bytes;
 
 use serde::{Deserialize,Serialize};
-use tokio::task::JoinHandle;
+use tokio::{net::TcpListener, task::JoinHandle};
 
 use odin_build::prelude::*;
+use odin_actor::{warn,error};
 use odin_common::{strings, fs, net, if_let};
 
 pub mod prelude;
@@ -37,6 +38,11 @@ pub mod ui_service;
 pub mod ws_service;
 pub use ws_service::{WsMsg,WsMsgParts};
 
+pub mod proxy_cache;
+use proxy_cache::ProxyCacheConfig;
+pub mod tile_seeder;
+pub mod auth;
+
 pub mod errors;
 use errors::{OdinServerResult,op_failed};
 
@@ -49,6 +55,9 @@ type Result<T> = OdinServerResult<T>;
 pub struct ServerConfig {
     pub sock_addr: SocketAddr,
     pub tls: Option<TlsConfig>, // if set use TLS (https)
+
+    #[serde(default)]
+    pub proxy_cache: ProxyCacheConfig, // on-disk cache for proxied requests
 }
 
 impl ServerConfig {
@@ -83,22 +92,52 @@ fn build_ok_response (content_type: &str, encoding: Option<&str>, bytes: Bytes)-
     builder.body( Body::from(bytes)).unwrap()
 }
 
+/// max number of attempts to bind the server socket if it is still in use (e.g. by a previous server instance)
+const BIND_ATTEMPTS: u32 = 7; // with exponential backoff from 100ms this waits up to 6.3 sec
+
 pub fn spawn_server_task (config: &ServerConfig, router: Router) -> JoinHandle<()> {
     let sock_addr = config.sock_addr.clone();
     let router_svc = router.into_make_service_with_connect_info::<SocketAddr>();
+    let tls_paths = config.tls.as_ref().map( |tls| (strings::env_expand( &tls.cert_path), strings::env_expand( &tls.key_path)));
+
+    tokio::spawn( async move {
+        let listener = match bind_listener( sock_addr).await {
+            Ok(listener) => listener,
+            Err(e) => { error!("failed to bind server socket {sock_addr}: {e}"); return }
+        };
+
+        if let Some((cert_path,key_path)) = tls_paths {
+            let tls_config = match RustlsConfig::from_pem_file(PathBuf::from(cert_path), PathBuf::from(key_path)).await {
+                Ok(tls_config) => tls_config,
+                Err(e) => { error!("failed to load TLS config: {e}"); return }
+            };
+            let result = match listener.into_std() {
+                Ok(listener) => axum_server::from_tcp_rustls( listener, tls_config).serve( router_svc).await,
+                Err(e) => Err(e)
+            };
+            if let Err(e) = result { error!("server terminated: {e}") }
+        } else {
+            if let Err(e) = axum::serve( listener, router_svc).await { error!("server terminated: {e}") }
+        }
+    })
+}
 
-    if let Some(tls) = &config.tls {
-        let cert_path = strings::env_expand( &tls.cert_path);
-        let key_path = strings::env_expand( &tls.key_path);
-        tokio::spawn( async move {
-            let tls_config = RustlsConfig::from_pem_file(PathBuf::from(cert_path), PathBuf::from(key_path)).await.unwrap();
-            axum_server::bind_rustls( sock_addr, tls_config).serve( router_svc).await.unwrap();
-        })
-    } else {
-        tokio::spawn( async move {
-            let listener = tokio::net::TcpListener::bind(sock_addr).await.unwrap();
-            axum::serve( listener, router_svc).await.unwrap();    
-        })
+/// bind a listener to the given socket address, retrying with exponential backoff if the address is still in use
+pub async fn bind_listener (sock_addr: SocketAddr)->std::io::Result<TcpListener> {
+    let mut delay = Duration::from_millis(100);
+    let mut attempt = 1;
+
+    loop {
+        match TcpListener::bind( sock_addr).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) if e.kind() == ErrorKind::AddrInUse && attempt < BIND_ATTEMPTS => {
+                warn!("server socket {sock_addr} still in use, retrying in {delay:?}");
+                tokio::time::sleep( delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e)
+        }
     }
 }
 
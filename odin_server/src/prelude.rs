@@ -12,9 +12,10 @@
  * and limitations under the License.
  */
 pub use crate::{
-    self_crate, asset_uri, proxy_uri, build_service,
-    spa::{SpaServer, SpaServerMsg, SpaServerState, SpaComponents, SpaService, SpaConnection, SpaServiceList, DataAvailable, SendWsMsg, BroadcastWsMsg, WsMsgReaction}, 
+    self_crate, asset_uri, proxy_uri, build_service, ServerConfig,
+    spa::{SpaServer, SpaApp, SpaServerMsg, SpaServerState, SpaComponents, SpaService, SpaConnection, ConnectionId, ConnectionSender, SpaServiceList, DataAvailable, SendWsMsg, BroadcastWsMsg, BroadcastUserWsMsg, UserFilter, WsMsgReaction}, 
     ui_service::UiService,
+    auth::{SpaAuth, SpaUser},
     errors::{OdinServerError,OdinServerResult},
     ws_service::{WsService, WsMsg, WsMsgParts, ws_msg_from_json}, define_ws_payload, ws_msg,
 };
@@ -0,0 +1,567 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the proxy_cache module implements the on-disk response cache used by the `SpaServer` proxy route.
+//! Cache entries are stored as pairs of `<hash>.meta` (JSON) and `<hash>.body` files under
+//! `odin_build::cache_dir()/proxies/<proxy-name>/`. Freshness is determined by (in order of precedence)
+//! configured per-proxy policies, upstream `Cache-Control`/`Expires` headers, proxy specific TTLs set by
+//! the SpaService that added the proxy, and the configured default TTL. Stale entries are revalidated
+//! with conditional requests (`If-None-Match`, `If-Modified-Since`) and served as-is if the upstream
+//! server is unreachable or does not return the resource anymore, which is essential for field deployments
+//! with intermittent uplinks. Response bodies are streamed to disk (or directly to the client if they are
+//! not cached), i.e. we never keep whole bodies in memory.
+//! The `<hash>` is the (truncated) SHA-256 of the upstream uri, which is stable across toolchain versions
+
+use std::{
+    path::{Path,PathBuf}, time::Duration,
+    sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}}
+};
+use axum::{body::Body, http::{header, HeaderMap, HeaderValue, StatusCode}, response::{IntoResponse, Response}};
+use bytes::Bytes;
+use chrono::DateTime;
+use futures::stream::StreamExt;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{fs::File, io::AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+use odin_actor::{warn,error};
+use odin_common::{
+    datetime::{epoch_millis, deserialize_duration, serialize_duration, deserialize_optional_duration, serialize_optional_duration},
+    fs::{dir_size, lru_dir_bound, set_accessed}
+};
+
+use crate::errors::{op_failed, OdinServerResult};
+
+/// configuration of the proxy cache. This is part of the `ServerConfig` and all fields have defaults, i.e. a
+/// config file only needs to specify what differs
+#[derive(Deserialize,Serialize,Debug,Clone)]
+#[serde(default)]
+pub struct ProxyCacheConfig {
+    pub enabled: bool,
+
+    /// upper bound (in bytes) for the cumulated size of cache files. Least recently accessed files are removed
+    /// once this is exceeded
+    pub max_size: u64,
+
+    /// freshness lifetime to use if neither a policy, the upstream response nor the proxy spec define one
+    #[serde(deserialize_with="deserialize_duration", serialize_with="serialize_duration")]
+    pub default_ttl: Duration,
+
+    /// how long past their expiration we serve stale entries if upstream is unreachable (None means unbounded)
+    #[serde(deserialize_with="deserialize_optional_duration", serialize_with="serialize_optional_duration")]
+    pub max_stale: Option<Duration>,
+
+    /// per-proxy overrides
+    pub policies: Vec<ProxyCachePolicy>,
+
+    /// enable the `proxy-seed` server routes that let clients start pre-fetching tiles into the cache
+    pub seed_api: bool,
+
+    /// max zoom level of tile seed requests
+    pub seed_max_zoom: u32,
+
+    /// max number of tiles per seed request
+    pub seed_max_tiles: u64,
+}
+
+impl Default for ProxyCacheConfig {
+    fn default()->Self {
+        ProxyCacheConfig {
+            enabled: true,
+            max_size: 2 * 1024 * 1024 * 1024, // 2GB
+            default_ttl: Duration::from_secs( 60*60*24), // one day
+            max_stale: None,
+            policies: Vec::new(),
+            seed_api: false,
+            seed_max_zoom: 18,
+            seed_max_tiles: 500_000,
+        }
+    }
+}
+
+/// per-proxy cache policy, referring to the symbolic proxy name used in `SpaComponents::add_proxy(..)`
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub struct ProxyCachePolicy {
+    pub proxy: String,
+
+    #[serde(deserialize_with="deserialize_duration", serialize_with="serialize_duration")]
+    pub ttl: Duration,
+
+    /// if set we use `ttl` regardless of upstream `Cache-Control`/`Expires` headers (e.g. for tile servers that
+    /// send `no-cache` although their tiles hardly ever change)
+    #[serde(default)]
+    pub ignore_cache_control: bool,
+}
+
+/// the meta information we store for each cached response
+#[derive(Deserialize,Serialize,Debug,Clone)]
+pub struct CacheEntryMeta {
+    pub uri: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub must_revalidate: bool,
+    pub stored: i64,  // epoch millis
+    pub expires: i64, // epoch millis
+}
+
+impl CacheEntryMeta {
+    fn from_headers (uri: &str, status: StatusCode, hdrs: &HeaderMap, now: i64, ttl: Duration, must_revalidate: bool)->Self {
+        CacheEntryMeta {
+            uri: uri.to_string(),
+            status: status.as_u16(),
+            content_type: header_string( hdrs, header::CONTENT_TYPE),
+            content_encoding: header_string( hdrs, header::CONTENT_ENCODING),
+            etag: header_string( hdrs, header::ETAG),
+            last_modified: header_string( hdrs, header::LAST_MODIFIED),
+            must_revalidate,
+            stored: now,
+            expires: now + ttl.as_millis() as i64,
+        }
+    }
+
+    pub fn is_fresh (&self, now: i64)->bool {
+        now < self.expires
+    }
+}
+
+fn header_string (hdrs: &HeaderMap, key: header::HeaderName)->Option<String> {
+    hdrs.get(key).and_then( |v| v.to_str().ok()).map( |s| s.to_string())
+}
+
+/// the parts of a `Cache-Control` header value we care about
+#[derive(Debug,Default,PartialEq)]
+pub struct CacheControl {
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub private: bool,
+    pub must_revalidate: bool,
+    pub max_age: Option<u64>,
+    pub s_maxage: Option<u64>,
+}
+
+impl CacheControl {
+    pub fn parse (s: &str)->Self {
+        let mut cc = CacheControl::default();
+
+        for directive in s.split(',') {
+            let directive = directive.trim();
+            let (name, value) = match directive.split_once('=') {
+                Some((n,v)) => (n.trim(), Some(v.trim().trim_matches('"'))),
+                None => (directive, None)
+            };
+
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" => cc.no_store = true,
+                "no-cache" => cc.no_cache = true,
+                "private" => cc.private = true,
+                "must-revalidate" | "proxy-revalidate" => cc.must_revalidate = true,
+                "max-age" => cc.max_age = value.and_then( |v| v.parse().ok()),
+                "s-maxage" => cc.s_maxage = value.and_then( |v| v.parse().ok()),
+                _ => {} // ignore the rest
+            }
+        }
+        cc
+    }
+
+    pub fn from_headers (hdrs: &HeaderMap)->Self {
+        hdrs.get( header::CACHE_CONTROL)
+            .and_then( |v| v.to_str().ok())
+            .map( |s| Self::parse(s))
+            .unwrap_or_default()
+    }
+}
+
+/// the body of a [`ProxyResponse`], which is either an open cache file or a (not yet read) upstream response
+pub enum ProxyBody {
+    File(File),
+    Upstream(reqwest::Response),
+}
+
+impl ProxyBody {
+    fn into_body (self)->Body {
+        match self {
+            ProxyBody::File(file) => Body::from_stream( ReaderStream::new( file)),
+            ProxyBody::Upstream(response) => Body::from_stream( response.bytes_stream()),
+        }
+    }
+}
+
+/// what we return from a proxy cache lookup, which is either a (fresh or stale) cached entry or an uncached upstream response
+pub struct ProxyResponse {
+    pub meta: CacheEntryMeta,
+    pub body: ProxyBody,
+    pub is_stale: bool,
+}
+
+impl ProxyResponse {
+    /// turn this into an axum response for a client with the given request headers. This checks for client side
+    /// conditional requests and responds with `304 Not Modified` if the client already has the current version
+    pub fn into_client_response (self, client_hdrs: &HeaderMap)->Response {
+        if let Some(etag) = &self.meta.etag {
+            if let Some(inm) = client_hdrs.get( header::IF_NONE_MATCH).and_then( |v| v.to_str().ok()) {
+                if inm.split(',').any( |t| t.trim() == etag || t.trim() == "*") {
+                    return (StatusCode::NOT_MODIFIED, Body::empty()).into_response()
+                }
+            }
+        } else if let Some(lm) = &self.meta.last_modified {
+            if let Some(ims) = client_hdrs.get( header::IF_MODIFIED_SINCE).and_then( |v| v.to_str().ok()) {
+                if ims == lm {
+                    return (StatusCode::NOT_MODIFIED, Body::empty()).into_response()
+                }
+            }
+        }
+
+        let mut builder = Response::builder().status( self.meta.status);
+        if let Some(v) = &self.meta.content_type { builder = builder.header( header::CONTENT_TYPE, v) }
+        if let Some(v) = &self.meta.content_encoding { builder = builder.header( header::CONTENT_ENCODING, v) }
+        if let Some(v) = &self.meta.etag { builder = builder.header( header::ETAG, v) }
+        if let Some(v) = &self.meta.last_modified { builder = builder.header( header::LAST_MODIFIED, v) }
+        if self.is_stale { builder = builder.header( header::WARNING, "110 - \"Response is Stale\"") }
+
+        builder.body( self.body.into_body()).unwrap()
+    }
+}
+
+/// the on-disk cache for proxied requests. This is shared between proxy route handler invocations
+pub struct ProxyCache {
+    config: ProxyCacheConfig,
+    dir: PathBuf,
+    http_client: Client,
+
+    size: Arc<AtomicU64>,     // approximate cumulated size of cache files
+    pruning: Arc<AtomicBool>, // set while we are removing LRU files
+}
+
+impl ProxyCache {
+    pub fn new (config: ProxyCacheConfig)->Self {
+        Self::with_dir( config, odin_build::cache_dir().join("proxies"))
+    }
+
+    pub fn with_dir (config: ProxyCacheConfig, dir: PathBuf)->Self {
+        let size = if dir.is_dir() { dir_size( &dir, true).unwrap_or(0) } else { 0 };
+
+        ProxyCache {
+            config,
+            dir,
+            http_client: Client::new(),
+            size: Arc::new( AtomicU64::new(size)),
+            pruning: Arc::new( AtomicBool::new(false)),
+        }
+    }
+
+    pub fn http_client (&self)->&Client { &self.http_client }
+
+    pub fn config (&self)->&ProxyCacheConfig { &self.config }
+
+    pub fn dir (&self)->&Path { &self.dir }
+
+    /// the file path (without extension) for a given proxy name and (full) upstream uri. This has to be stable
+    /// across restarts and toolchain updates, hence we don't use `std::hash` here
+    pub fn entry_path (&self, proxy: &str, uri: &str)->PathBuf {
+        let hash = Sha256::digest( uri.as_bytes());
+        let name: String = hash[..16].iter().map( |b| format!("{b:02x}")).collect();
+        self.dir.join(proxy).join( name)
+    }
+
+    /// check if we have a fresh entry for the given uri without making any upstream request
+    pub async fn has_fresh_entry (&self, proxy: &str, uri: &str)->bool {
+        let path = self.entry_path( proxy, uri);
+        match self.read_meta( &path, uri).await {
+            Some(meta) => meta.is_fresh( epoch_millis()),
+            None => false
+        }
+    }
+
+    /// the main function of the cache, which returns either a fresh cached entry, a revalidated or newly retrieved
+    /// response, or a stale entry if the upstream server is not reachable.
+    /// `spec_ttl` is the (optional) TTL set by the SpaService that added the proxy
+    pub async fn get (&self, proxy: &str, spec_ttl: Option<Duration>, uri: &str, req_builder: RequestBuilder)->OdinServerResult<ProxyResponse> {
+        if !self.config.enabled {
+            return self.fetch_uncached( uri, req_builder).await
+        }
+
+        let path = self.entry_path( proxy, uri);
+        let now = epoch_millis();
+        let cached = self.read_entry( &path, uri).await;
+
+        let cached = match cached {
+            Some(entry) if entry.meta.is_fresh(now) => {
+                self.touch( &path);
+                return Ok( ProxyResponse{ meta: entry.meta, body: ProxyBody::File(entry.body), is_stale: false } )
+            }
+            other => other
+        };
+
+        let mut req_builder = req_builder;
+        if let Some(entry) = &cached { // turn this into a conditional request
+            if let Some(etag) = &entry.meta.etag { req_builder = req_builder.header( header::IF_NONE_MATCH, etag) }
+            if let Some(lm) = &entry.meta.last_modified { req_builder = req_builder.header( header::IF_MODIFIED_SINCE, lm) }
+        }
+
+        let response = match req_builder.send().await {
+            Ok(response) => response,
+            Err(e) => return self.stale_or_err( cached, now, uri, e.to_string())
+        };
+
+        let status = response.status();
+        let hdrs = response.headers().clone();
+
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(entry) = cached {
+                let mut meta = entry.meta;
+                if let Some(ttl) = self.ttl( proxy, spec_ttl, &hdrs, now) {
+                    meta.expires = now + ttl.as_millis() as i64;
+                    meta.stored = now;
+                    if let Err(e) = self.write_meta( &path, &meta).await {
+                        warn!("failed to update proxy cache entry for {uri}: {e}");
+                    }
+                }
+                self.touch( &path);
+                return Ok( ProxyResponse{ meta, body: ProxyBody::File(entry.body), is_stale: false } )
+            }
+        }
+
+        if status.is_server_error() {
+            return self.stale_or_err( cached, now, uri, format!("upstream server error {status}"))
+        }
+
+        let cc = CacheControl::from_headers( &hdrs);
+        let ttl = if status.is_success() { self.ttl( proxy, spec_ttl, &hdrs, now) } else { None };
+        let meta = CacheEntryMeta::from_headers( uri, status, &hdrs, now, ttl.unwrap_or(Duration::ZERO), cc.must_revalidate);
+
+        if !status.is_success() {
+            // a 404 or similar from an upstream server does not invalidate what we already have (e.g. tile servers
+            // with changed layer configuration). We keep the entry and serve it as stale if the policy allows
+            if let Some(entry) = cached {
+                if self.is_usable_stale( &entry.meta, now) {
+                    warn!("upstream request for {uri} returned {status}, serving stale cache entry");
+                    return Ok( ProxyResponse{ meta: entry.meta, body: ProxyBody::File(entry.body), is_stale: true } )
+                }
+            }
+            return Ok( ProxyResponse{ meta, body: ProxyBody::Upstream(response), is_stale: false } )
+        }
+
+        if ttl.is_some() {
+            match self.store( &path, &meta, response).await {
+                Ok(file) => Ok( ProxyResponse{ meta, body: ProxyBody::File(file), is_stale: false } ),
+                Err(e) => self.stale_or_err( cached, now, uri, format!("failed to store response: {e}"))
+            }
+        } else {
+            if cached.is_some() {
+                self.remove( &path).await; // upstream says we are not supposed to keep it anymore
+            }
+            Ok( ProxyResponse{ meta, body: ProxyBody::Upstream(response), is_stale: false } )
+        }
+    }
+
+    async fn fetch_uncached (&self, uri: &str, req_builder: RequestBuilder)->OdinServerResult<ProxyResponse> {
+        let response = req_builder.send().await.map_err( |e| op_failed(e))?;
+        let meta = CacheEntryMeta::from_headers( uri, response.status(), response.headers(), epoch_millis(), Duration::ZERO, false);
+
+        Ok( ProxyResponse{ meta, body: ProxyBody::Upstream(response), is_stale: false } )
+    }
+
+    fn stale_or_err (&self, cached: Option<CachedEntry>, now: i64, uri: &str, msg: String)->OdinServerResult<ProxyResponse> {
+        if let Some(entry) = cached {
+            if self.is_usable_stale( &entry.meta, now) {
+                warn!("upstream request for {uri} failed ({msg}), serving stale cache entry");
+                return Ok( ProxyResponse{ meta: entry.meta, body: ProxyBody::File(entry.body), is_stale: true } )
+            }
+        }
+        Err( op_failed( format!("proxy request for {uri} failed: {msg}")) )
+    }
+
+    fn is_usable_stale (&self, meta: &CacheEntryMeta, now: i64)->bool {
+        if meta.must_revalidate {
+            false
+        } else if let Some(max_stale) = &self.config.max_stale {
+            now - meta.expires <= max_stale.as_millis() as i64
+        } else {
+            true
+        }
+    }
+
+    /// compute the freshness lifetime of a response. Returns None if the response is not supposed to be stored
+    fn ttl (&self, proxy: &str, spec_ttl: Option<Duration>, hdrs: &HeaderMap, now: i64)->Option<Duration> {
+        let policy = self.config.policies.iter().find( |p| p.proxy == proxy);
+        let fallback_ttl = policy.map( |p| p.ttl).or( spec_ttl).unwrap_or( self.config.default_ttl);
+
+        if let Some(policy) = policy {
+            if policy.ignore_cache_control { return Some(fallback_ttl) }
+        }
+
+        let cc = CacheControl::from_headers( hdrs);
+        if cc.no_store || cc.private {
+            None
+        } else if cc.no_cache {
+            Some(Duration::ZERO) // store but always revalidate
+        } else if let Some(secs) = cc.s_maxage.or( cc.max_age) {
+            Some( Duration::from_secs(secs))
+        } else if let Some(expires) = hdrs.get( header::EXPIRES).and_then( |v| v.to_str().ok()) {
+            match DateTime::parse_from_rfc2822( expires) {
+                Ok(dt) => Some( Duration::from_millis( (dt.timestamp_millis() - now).max(0) as u64)),
+                Err(_) => Some(Duration::ZERO) // invalid Expires values mean "already expired"
+            }
+        } else {
+            Some(fallback_ttl)
+        }
+    }
+
+    //--- file operations
+
+    async fn read_meta (&self, path: &Path, uri: &str)->Option<CacheEntryMeta> {
+        let data = tokio::fs::read( path.with_extension("meta")).await.ok()?;
+        let meta: CacheEntryMeta = serde_json::from_slice( &data).ok()?;
+        if meta.uri == uri { Some(meta) } else { None } // hash collision
+    }
+
+    /// note this only opens the body file, i.e. the entry stays valid even if the file gets replaced or removed
+    async fn read_entry (&self, path: &Path, uri: &str)->Option<CachedEntry> {
+        let meta = self.read_meta( path, uri).await?;
+        let body = File::open( path.with_extension("body")).await.ok()?;
+        Some( CachedEntry{ meta, body } )
+    }
+
+    async fn write_meta (&self, path: &Path, meta: &CacheEntryMeta)->OdinServerResult<()> {
+        let data = serde_json::to_vec( meta)?;
+        let meta_path = path.with_extension("meta");
+        let old_len = file_len( &meta_path).await;
+
+        write_atomic( &meta_path, &data).await?;
+        self.update_size( old_len, data.len() as u64);
+        Ok(())
+    }
+
+    /// stream the response body into the cache and return the opened body file
+    async fn store (&self, path: &Path, meta: &CacheEntryMeta, response: reqwest::Response)->OdinServerResult<File> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all( parent).await?;
+        }
+
+        // body first so that we never have a meta file referring to a missing or incomplete body
+        let body_path = path.with_extension("body");
+        let old_len = file_len( &body_path).await;
+        let tmp_path = tmp_path( &body_path);
+
+        match write_stream( &tmp_path, response).await {
+            Ok(len) => {
+                tokio::fs::rename( &tmp_path, &body_path).await?;
+                self.update_size( old_len, len);
+            }
+            Err(e) => {
+                tokio::fs::remove_file( &tmp_path).await;
+                return Err(e)
+            }
+        }
+        let file = File::open( &body_path).await?; // open before the meta update so that concurrent stores can't swap the body
+        self.write_meta( path, meta).await?;
+
+        if self.size.load( Ordering::Relaxed) > self.config.max_size {
+            self.prune();
+        }
+        Ok(file)
+    }
+
+    async fn remove (&self, path: &Path) {
+        for path in [path.with_extension("meta"), path.with_extension("body")] {
+            let len = file_len( &path).await;
+            if tokio::fs::remove_file( &path).await.is_ok() {
+                self.update_size( len, 0);
+            }
+        }
+    }
+
+    /// account for a file that changed size from `old_len` to `new_len` (0 for new or removed files)
+    fn update_size (&self, old_len: u64, new_len: u64) {
+        if new_len >= old_len {
+            self.size.fetch_add( new_len - old_len, Ordering::Relaxed);
+        } else {
+            let delta = old_len - new_len;
+            self.size.fetch_update( Ordering::Relaxed, Ordering::Relaxed, |size| Some( size.saturating_sub(delta)));
+        }
+    }
+
+    /// the approximate cumulated size of cache files
+    pub fn size (&self)->u64 {
+        self.size.load( Ordering::Relaxed)
+    }
+
+    fn touch (&self, path: &Path) {
+        // filesystems might be mounted with noatime so we explicitly set the access time used for LRU pruning
+        set_accessed( &path.with_extension("meta"));
+        set_accessed( &path.with_extension("body"));
+    }
+
+    /// remove least recently used files until we are below our size limit. This runs in a blocking task
+    fn prune (&self) {
+        if self.pruning.swap( true, Ordering::AcqRel) { return } // already pruning
+
+        let dir = self.dir.clone();
+        let max_size = self.config.max_size;
+        let size = self.size.clone();
+        let pruning = self.pruning.clone();
+
+        tokio::task::spawn_blocking( move || {
+            if let Err(e) = lru_dir_bound( &dir, true, max_size) {
+                error!("failed to prune proxy cache {dir:?}: {e}");
+            }
+            size.store( dir_size( &dir, true).unwrap_or(0), Ordering::Relaxed);
+            pruning.store( false, Ordering::Release);
+        });
+    }
+}
+
+struct CachedEntry {
+    meta: CacheEntryMeta,
+    body: File,
+}
+
+/// a unique temp file path so that concurrent requests for the same uri don't write into the same file
+fn tmp_path (path: &Path)->PathBuf {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push( format!(".{:016x}.tmp", rand::random::<u64>()));
+    PathBuf::from( tmp_path)
+}
+
+async fn file_len (path: &Path)->u64 {
+    tokio::fs::metadata( path).await.map( |m| m.len()).unwrap_or(0)
+}
+
+async fn write_atomic (path: &Path, data: &[u8])->OdinServerResult<()> {
+    let tmp_path = tmp_path( path);
+    tokio::fs::write( &tmp_path, data).await?;
+    tokio::fs::rename( &tmp_path, path).await?;
+    Ok(())
+}
+
+/// write a response body to the given file, returning the number of bytes written
+async fn write_stream (path: &Path, response: reqwest::Response)->OdinServerResult<u64> {
+    let mut file = File::create( path).await?;
+    let mut stream = response.bytes_stream();
+    let mut len: u64 = 0;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err( |e| op_failed(e))?;
+        file.write_all( &chunk).await?;
+        len += chunk.len() as u64;
+    }
+    file.flush().await?;
+    Ok(len)
+}
@@ -13,9 +13,9 @@
  */
 #![allow(unused)]
 
-use std::{boxed, collections::HashMap, sync::Arc, ops::{Deref,DerefMut}, 
-    net::SocketAddr, future::{Future,ready}, time::SystemTime,
-    path::{PathBuf}, any::type_name, fmt::Write,
+use std::{boxed, collections::HashMap, sync::{Arc, atomic::{AtomicU64,Ordering}}, ops::{Deref,DerefMut}, 
+    net::SocketAddr, future::{Future,ready}, time::{SystemTime,Duration},
+    path::{PathBuf}, any::type_name, fmt::{self,Write},
     result::Result, error::Error
 };
 use axum::{
@@ -26,9 +26,9 @@ use axum::{
         FromRef, Path as AxumPath, Query, RawQuery, Request, State
     },
     http::{HeaderMap, StatusCode, Uri},
-    middleware::map_request, response::{Html, IntoResponse, Response},
+    middleware::{self, map_request}, response::{Html, IntoResponse, Response},
     routing::get,
-    Router,ServiceExt
+    Json, Router, ServiceExt
 };
 use axum_server::{service::MakeService, tls_rustls::RustlsConfig};
 use bytes::Bytes;
@@ -39,17 +39,17 @@ use odin_build::OdinBuildError;
 use tower_http::{services::ServeDir,trace::TraceLayer};
 use tracing_subscriber::EnvFilter;
 use reqwest::{header::{self, SET_COOKIE}, Client, RequestBuilder};
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, RequestBuilder as MwRequestBuilder};
-use http_cache_reqwest::{Cache, CacheMode, CACacheManager, HttpCache, HttpCacheOptions};
 use serde::{Deserialize,Serialize};
 use async_trait::async_trait;
+use tokio::sync::mpsc::{self, error::TrySendError};
 
 use odin_build::LoadAssetFp;
 use odin_common::{fs::get_file_basename,strings::{self, mk_query_string}};
 use odin_macro::define_struct;
 use odin_actor::prelude::*;
 
-use crate::{get_asset_response, spawn_server_task, ServerConfig, WsMsg, WsMsgParts, ws_service};
+use crate::{get_asset_response, spawn_server_task, ServerConfig, WsMsg, WsMsgParts, ws_service::{self, WsService}, proxy_cache::ProxyCache,
+    tile_seeder::{TileSeeder, SeedRequest, SeedManifest}, auth::{SpaAuth, check_auth}};
 use crate::errors::{connect_error, init_error, op_failed, OdinServerError, OdinServerResult};
 
 /// the trait that abstracts a single page application service, which normally represents a visualization
@@ -90,8 +90,9 @@ pub trait SpaService: Send + Sync + 'static {
     /// called from within the server task. Override if service processes incomingg websocket message.
     /// Although we pass in hself and hence services could send SendWsMsg/BroadcastWsMsg messages to respond we also
     /// use a result type that can bypass additional messages since this is already executing in the SpaServer actor task
+    /// `user` is the authenticated user of the connection (if any), which can be used for access control
     async fn handle_ws_msg (&mut self, 
-        hself: &ActorHandle<SpaServerMsg>, remote_addr: &SocketAddr, ws_msg_parts: &WsMsgParts
+        hself: &ActorHandle<SpaServerMsg>, conn_id: &ConnectionId, user: Option<&str>, ws_msg_parts: &WsMsgParts
     ) -> OdinServerResult<WsMsgReaction> {
         Ok( WsMsgReaction::None )
     }
@@ -108,13 +109,15 @@ pub enum WsMsgReaction {
 /// SpaServer internal structure to keep track of SpaService objects and their server-specific state
 struct SpaSvc {
     service: Box<dyn SpaService>, // we can keep this in a Box since this is not shared and only used from within the actor task
+    type_name: &'static str, // by convention this is also the mod_path of websocket messages for this service
     is_data_available: bool, // this is where we store the data_available() response of the service
 }
 
 impl SpaSvc {
-    pub fn new (service: impl SpaService)->Self {
+    pub fn new<T> (service: T)->Self where T: SpaService {
         SpaSvc {
             service: Box::new(service),
+            type_name: type_name::<T>(),
             is_data_available: false,
         }
     }
@@ -156,17 +159,63 @@ impl SpaServiceList {
     }
 }
 
+/// the transport of a SpaConnection. Normally this is a websocket but clients that cannot use websockets
+/// (e.g. behind proxies that strip them) can get the same messages through channels that feed
+/// Server-Sent Event streams or REST snapshot responses
+pub enum ConnectionSender {
+    Ws(SplitSink<WebSocket,Message>),
+    Channel(mpsc::Sender<String>),
+}
+
+/// unique id of a client connection. We can't use the remote socket address for this since several connections
+/// (e.g. multiplexed HTTP/2 streams or browser tabs behind the same NAT or proxy) can share it
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct ConnectionId(u64);
+
+impl ConnectionId {
+    pub fn new ()->Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        ConnectionId( NEXT_ID.fetch_add( 1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for ConnectionId {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>)->fmt::Result {
+        write!( f, "conn-{}", self.0)
+    }
+}
+
 /// struct to keep track of active SinglePageApp connections
 pub struct SpaConnection {
-    pub remote_addr: SocketAddr,
-    pub ws_sender: SplitSink<WebSocket,Message>, // used to send through the websocket
-    pub ws_receiver_task: JoinHandle<()> // the task that (async) reads from the websocket
+    pub id: ConnectionId, // the key to address this connection (e.g. in `SendWsMsg`)
+    pub remote_addr: SocketAddr, // only informational - this is not unique
+    pub app: Arc<String>, // the name of the SpaApp this connection was made for
+    pub user: Option<String>, // the authenticated user of the connection request (see `SpaUser`)
+    pub sender: ConnectionSender, // used to send through the websocket (or channel)
+    pub ws_receiver_task: Option<JoinHandle<()>> // the task that (async) reads from the websocket
 }
 
 impl SpaConnection {
     // note this should not be used if we send multiple messages to the same connection (use feed() or send_all() in this case)
     pub async fn send (&mut self, msg: String)->OdinServerResult<()> {
-        Ok( self.ws_sender.send( Message::Text(msg)).await? )
+        match &mut self.sender {
+            ConnectionSender::Ws(ws_sender) => Ok( ws_sender.send( Message::Text(msg)).await? ),
+            ConnectionSender::Channel(tx) => {
+                // we don't want to block the server on slow http clients
+                tx.try_send( msg).map_err( |e| match e {
+                    TrySendError::Full(_) => op_failed("channel full, message dropped"),
+                    TrySendError::Closed(_) => connect_error("channel closed")
+                })
+            }
+        }
+    }
+
+    /// this only detects closed channel connections - closed websockets are reported by their receiver task
+    pub fn is_closed (&self)->bool {
+        match &self.sender {
+            ConnectionSender::Ws(_) => false,
+            ConnectionSender::Channel(tx) => tx.is_closed()
+        }
     }
 }
 
@@ -178,36 +227,87 @@ pub struct SpaServerState {
     pub hself: ActorHandle<SpaServerMsg>
 }
 
+/// a single page application document with its own services, which is served under a `/<name>` URL prefix.
+/// A SpaServer can host several apps (e.g. a public and an operator view) that share the same socket address,
+/// proxy cache and actor
+pub struct SpaApp {
+    name: Arc<String>, // this is not from the config so that we can have the same for different servers
+    services: Vec<SpaSvc>,
+    auth: Arc<SpaAuth>,
+    theme: Option<String>, // default theme of the document (can be overridden by a "?theme=.." query)
+}
+
+impl SpaApp {
+    pub fn new (name: impl ToString, service_list: SpaServiceList)->Self {
+        SpaApp {
+            name: Arc::new( name.to_string()),
+            services: service_list.services,
+            auth: Arc::new( SpaAuth::Public),
+            theme: None
+        }
+    }
+
+    pub fn with_auth (mut self, auth: SpaAuth)->Self {
+        self.auth = Arc::new(auth);
+        self
+    }
+
+    pub fn with_theme (mut self, theme: impl ToString)->Self {
+        self.theme = Some(theme.to_string());
+        self
+    }
+
+    pub fn name (&self)->&str {
+        self.name.as_str()
+    }
+
+    fn has_service (&self, type_name: &str)->bool {
+        self.services.iter().any( |svc| svc.type_name == type_name)
+    }
+}
+
 /// the actor state for a single page application server actor
 pub struct SpaServer {
     config: ServerConfig,
-    name: String, // this is not from the config so that we can have the same for different apps
-    services: Vec<SpaSvc>,
+    apps: Vec<SpaApp>,
 
-    connections: HashMap<SocketAddr,SpaConnection>, // updated when receiving an AddConnection actor message
+    connections: HashMap<ConnectionId,SpaConnection>, // updated when receiving an AddConnection actor message
     server_task: Option<JoinHandle<()>>, // for the server task itself, initialized upon _Start_
 }
 
 impl SpaServer {
 
+    /// create a server for a single app
     pub fn new (config: ServerConfig, name: impl ToString, service_list: SpaServiceList)->Self {
+        Self::with_apps( config, vec![ SpaApp::new( name, service_list) ])
+    }
+
+    /// create a server that mounts several apps under their respective name prefixes
+    pub fn with_apps (config: ServerConfig, apps: Vec<SpaApp>)->Self {
         SpaServer {
             config,
-            name: name.to_string(),
-            services: service_list.services,
+            apps,
             connections: HashMap::new(),
             server_task: None,
         }
     }
 
     fn requires_websocket (&self)->bool {
-        self.services.iter().find( |s| s.is_websocket()).is_some()
+        self.apps.iter().any( |app| app.services.iter().any( |s| s.is_websocket()))
     }
 
     fn has_connections (&self)->bool {
         !self.connections.is_empty()
     }
 
+    fn has_app_connections (&self, app_name: &str)->bool {
+        self.connections.values().any( |conn| conn.app.as_str() == app_name)
+    }
+
+    fn app_index (&self, app_name: &str)->Option<usize> {
+        self.apps.iter().position( |app| app.name.as_str() == app_name)
+    }
+
     /// called when receiving _Start_ message
     fn start_server (&mut self, hself: ActorHandle<SpaServerMsg>)->OdinServerResult<()> {
         if self.server_task.is_none() {
@@ -219,7 +319,9 @@ impl SpaServer {
                     .try_init();
             }
 
-            println!("serving SPA on {}/{}", self.config.url(), self.name);
+            for app in &self.apps {
+                println!("serving SPA on {}/{}", self.config.url(), app.name);
+            }
             let router = self.build_router( &hself)?;
             self.server_task = Some(spawn_server_task( &self.config, router));
             Ok(())
@@ -230,14 +332,51 @@ impl SpaServer {
     }
 
     fn build_router (&self, hself: &ActorHandle<SpaServerMsg>)->OdinServerResult<Router> {
-        let comps = SpaComponents::from_svcs( &self.services)?;
-        let doc = Arc::new(comps.to_html( &self.name));
-        let proxies = comps.proxies;
-        let assets = comps.assets;
+        // components are collected per app but assets and proxies are shared between all apps of this server
+        let mut app_comps: Vec<SpaComponents> = Vec::with_capacity( self.apps.len());
+        let mut proxies: HashMap<String,ProxySpec> = HashMap::new();
+        let mut assets: HashMap<&'static str,LoadAssetFp> = HashMap::new();
+        for app in &self.apps {
+            let mut comps = SpaComponents::from_svcs( &app.services)?;
+            proxies.extend( comps.proxies.drain());
+            assets.extend( comps.assets.drain());
+            app_comps.push( comps);
+        }
+
+        let proxies = Arc::new(proxies);
+        let proxy_cache = Arc::new( ProxyCache::new( self.config.proxy_cache.clone()));
+
+        // optional API to pre-fetch proxied map tiles into the proxy cache
+        let seeder = if self.config.proxy_cache.seed_api && !proxies.is_empty() {
+            Some( TileSeeder::new( proxy_cache.clone(), proxies.clone()))
+        } else {
+            None
+        };
+
+        let mut router = Router::new();
+        for (app,comps) in self.apps.iter().zip( app_comps.into_iter()) {
+            let app_router = Self::build_app_router( hself, app, comps, &proxy_cache, &proxies, &assets, &seeder);
+            router = router.merge( app_router);
+        }
+
+        // note this won't do anything unless there also is a tracing subscriber set somewhere
+        if cfg!(feature="trace_server") {
+            router = router.layer(TraceLayer::new_for_http());
+        }
+
+        Ok(router)
+    }
+
+    /// the routes of a single app, which all start with the app name
+    fn build_app_router (hself: &ActorHandle<SpaServerMsg>, app: &SpaApp, comps: SpaComponents,
+                         proxy_cache: &Arc<ProxyCache>, proxies: &Arc<HashMap<String,ProxySpec>>,
+                         assets: &HashMap<&'static str,LoadAssetFp>, seeder: &Option<TileSeeder>) -> Router {
+        let name = app.name.as_str();
+        let doc = Arc::new(comps.to_app_html( name, app.theme.as_deref()));
 
         let mut router = Router::new()
             //--- the document route
-            .route( &format!("/{}", self.name), get({
+            .route( &format!("/{name}"), get({
                 let doc = doc.clone();
                 move |req: Request| { Self::doc_handler( req, doc) }
             }));
@@ -245,7 +384,7 @@ impl SpaServer {
         // add service specific routes
         if !comps.routes.is_empty() {
             let spa_server_state = SpaServerState { // note this is immutable state
-                name: Arc::new( self.name.clone()),
+                name: app.name.clone(),
                 hself: hself.clone(),
             };
             for rf in comps.routes {
@@ -255,28 +394,42 @@ impl SpaServer {
 
         // now add the generic routes for proxies and assets
         router = router
-            .route( &format!("/{}/proxy/*unmatched", self.name), get({
-                let mode = CacheMode::Default;
-                let manager = CACacheManager { path: odin_build::cache_dir().join("proxies") };
-                let options = HttpCacheOptions::default();
-                let http_client = ClientBuilder::new(Client::new())
-                    .with( Cache( HttpCache {mode, manager, options}))
-                    .build();
-                //move |uri_elems: Path<(String,String)>, req: Request| { Self::proxy_handler(uri_elems, req, http_client, proxies) }
-                move |path: AxumPath<String>, query: RawQuery, req: Request| { Self::proxy_handler(path, query, req, http_client, proxies) }
+            .route( &format!("/{name}/proxy/*unmatched"), get({
+                let proxy_cache = proxy_cache.clone();
+                let proxies = proxies.clone();
+                move |path: AxumPath<String>, query: RawQuery, req: Request| { Self::proxy_handler(path, query, req, proxy_cache, proxies) }
             }))
 
             // 'key' is the owning crate
-            .route( &format!("/{}/asset/:key/*unmatched", self.name), get({
+            .route( &format!("/{name}/asset/:key/*unmatched"), get({
+                let assets = assets.clone();
                 move |uri_elems: AxumPath<(String,String)>, req: Request| { Self::asset_handler(uri_elems, req, assets)}
             }));
 
-        // note this won't do anything unless there also is a tracing subscriber set somewhere
-        if cfg!(feature="trace_server") {
-            router = router.layer(TraceLayer::new_for_http());
+        if let Some(seeder) = seeder {
+            router = router
+                .route( &format!("/{name}/proxy-seed"), get({
+                    let seeder = seeder.clone();
+                    move || { Self::seed_list_handler( seeder) }
+                }).post({
+                    let seeder = seeder.clone();
+                    move |Json(request): Json<SeedRequest>| { Self::seed_start_handler( request, seeder) }
+                }))
+                .route( &format!("/{name}/proxy-seed/:id"), get({
+                    let seeder = seeder.clone();
+                    move |AxumPath(id): AxumPath<String>| { Self::seed_status_handler( id, seeder) }
+                }));
         }
 
-        Ok(router)
+        // restricted apps check each request (including websocket upgrades and assets)
+        if !app.auth.is_public() {
+            let auth = app.auth.clone();
+            router = router.route_layer( middleware::from_fn( move |req: Request, next: middleware::Next| {
+                check_auth( auth.clone(), req, next)
+            }));
+        }
+
+        router
     }
 
     async fn doc_handler (req: Request, doc: Arc<String>) -> Response {
@@ -284,25 +437,22 @@ impl SpaServer {
     }
 
     async fn proxy_handler (path: AxumPath<String>, query: RawQuery, req: Request,
-                            http_client: ClientWithMiddleware, proxies: HashMap<String,ProxySpec>) -> Response {
+                            proxy_cache: Arc<ProxyCache>, proxies: Arc<HashMap<String,ProxySpec>>) -> Response {
         if let Some(idx) = path.find('/') {
             let key = &path[0..idx];
 
             if let Some(proxy_spec) = proxies.get(key) {
                 let rel_path = &path[idx+1..];
-                let proxy_req = proxy_spec.create_request( &http_client, rel_path, &query, req.headers());
-
-                let reqwest_response = match proxy_req.send().await {
-                    Ok(res) => res,
-                    Err(err) => {
-                        return (StatusCode::BAD_REQUEST, Body::empty()).into_response();
+                let uri = proxy_spec.get_uri( rel_path, query.0.as_deref());
+                let proxy_req = proxy_spec.create_request( proxy_cache.http_client(), &uri, req.headers());
+
+                match proxy_cache.get( key, proxy_spec.ttl, &uri, proxy_req).await {
+                    Ok(proxy_response) => proxy_response.into_client_response( req.headers()),
+                    Err(e) => {
+                        warn!("{e}");
+                        (StatusCode::BAD_GATEWAY, Body::empty()).into_response()
                     }
-                };
-
-                Response::builder()
-                    .status(reqwest_response.status().as_u16())
-                    .body(Body::from_stream(reqwest_response.bytes_stream()))
-                    .unwrap()
+                }
 
             } else {
                 (StatusCode::BAD_REQUEST, "not proxied").into_response()
@@ -312,6 +462,38 @@ impl SpaServer {
         }
     }
 
+    async fn seed_list_handler (seeder: TileSeeder) -> Response {
+        Json( seeder.list_manifests()).into_response()
+    }
+
+    async fn seed_status_handler (id: String, seeder: TileSeeder) -> Response {
+        match seeder.load_manifest( &id) {
+            Some(manifest) => Json( manifest).into_response(),
+            None => (StatusCode::NOT_FOUND, "unknown seed job").into_response()
+        }
+    }
+
+    /// start or resume a seed job in the background and respond with its current manifest
+    async fn seed_start_handler (request: SeedRequest, seeder: TileSeeder) -> Response {
+        if let Err(e) = seeder.validate( &request) {
+            return (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+
+        let id = request.id();
+        if seeder.is_running( &id) {
+            return (StatusCode::CONFLICT, "seed job already running").into_response()
+        }
+
+        let manifest = seeder.load_manifest( &id).unwrap_or_else( || SeedManifest::new( request.clone()));
+        tokio::spawn( async move {
+            match seeder.run( request, |_| {}).await {
+                Ok(manifest) => info!("seed job {} completed: {} fetched, {} not cacheable, {} failed", manifest.id, manifest.fetched, manifest.uncacheable, manifest.pending.len()),
+                Err(e) => error!("seed job {id} failed: {e}")
+            }
+        });
+
+        (StatusCode::ACCEPTED, Json(manifest)).into_response()
+    }
 
     async fn asset_handler (uri_elems: AxumPath<(String,String)>, req: Request,
                             assets: HashMap<&'static str,LoadAssetFp>) -> Response {
@@ -335,14 +517,13 @@ impl SpaServer {
 
     /// called when receiving AddConnection message
     /// note that we shouldn't block in an await for sending to ourselves
-    async fn add_connection(&mut self, hself: ActorHandle<SpaServerMsg>, remote_addr: SocketAddr, ws: WebSocket)->OdinServerResult<()> {
-        let raddr = remote_addr.clone();
-        let name = raddr.to_string();
+    async fn add_connection(&mut self, hself: ActorHandle<SpaServerMsg>, remote_addr: SocketAddr, app: Arc<String>, user: Option<String>, ws: WebSocket)->OdinServerResult<()> {
+        let conn_id = ConnectionId::new();
+        let name = format!("{conn_id}-{remote_addr}");
         let (mut ws_sender, mut ws_receiver) = ws.split();
 
         let ws_receiver_task = {
             let hself = hself.clone();
-            let remote_addr = remote_addr.clone();
 
             spawn( &name, async move {
                 while let Some(Ok(msg)) = ws_receiver.next().await {
@@ -350,68 +531,108 @@ impl SpaServer {
                         Ok(msg) => {
                             if !msg.is_empty() {
                                 //println!("@@ received ws: {}", msg);
-                                hself.send_msg( DispatchIncomingWsMsg{remote_addr,ws_msg: msg}).await;
+                                hself.send_msg( DispatchIncomingWsMsg{conn_id,ws_msg: msg}).await;
                             }
                         }
                         Err(e) => println!("ignoring binary message")
                     }
 
                 }
-                hself.send_msg( RemoveConnection{remote_addr}).await;
+                hself.send_msg( RemoveConnection{conn_id}).await;
             })?
         };
 
-        let conn = SpaConnection { remote_addr, ws_sender, ws_receiver_task };
-        self.connections.insert( raddr, conn);
-        let conn_ref = self.connections.get_mut( &raddr).unwrap();
+        let conn = SpaConnection { id: conn_id, remote_addr, app, user, sender: ConnectionSender::Ws(ws_sender), ws_receiver_task: Some(ws_receiver_task) };
+        self.init_connection( hself, conn).await
+    }
+
+    /// called when receiving an AddChannelConnection message (for SSE and REST clients)
+    async fn add_channel_connection (&mut self, hself: ActorHandle<SpaServerMsg>, conn_id: ConnectionId, remote_addr: SocketAddr, 
+                                     app: Arc<String>, user: Option<String>, sender: mpsc::Sender<String>)->OdinServerResult<()> {
+        if self.connections.contains_key( &conn_id) {
+            // this drops the sender, which ends the respective stream
+            return Err( connect_error( format!("connection {conn_id} already exists")))
+        }
 
-        for svc in self.services.iter_mut() { // tell services to send their initial data
-            svc.service.init_connection( &hself, svc.is_data_available, conn_ref).await.map_err(|e| connect_error(e))?;
+        let conn = SpaConnection { id: conn_id, remote_addr, app, user, sender: ConnectionSender::Channel(sender), ws_receiver_task: None };
+        self.init_connection( hself, conn).await
+    }
+
+    /// if any of the services fails to initialize the connection we drop it, which also closes channel connections
+    async fn init_connection (&mut self, hself: ActorHandle<SpaServerMsg>, conn: SpaConnection)->OdinServerResult<()> {
+        let conn_id = conn.id;
+        let app_idx = self.app_index( &conn.app).ok_or_else( || connect_error( format!("unknown app {}", conn.app)))?;
+        self.connections.insert( conn_id, conn);
+        let conn_ref = self.connections.get_mut( &conn_id).unwrap();
+
+        for svc in self.apps[app_idx].services.iter_mut() { // tell services of this app to send their initial data
+            if let Err(e) = svc.service.init_connection( &hself, svc.is_data_available, conn_ref).await {
+                self.connections.remove( &conn_id);
+                return Err( connect_error(e))
+            }
         }
 
         Ok(())
     }
 
-    fn remove_connection (&mut self, remote_addr: SocketAddr)->OdinServerResult<()> {
-        self.connections.remove(&remote_addr);
+    fn remove_connection (&mut self, conn_id: ConnectionId)->OdinServerResult<()> {
+        self.connections.remove(&conn_id);
         Ok(())
     }
 
     // TODO - these should use timeouts (we can't have a connection block the server)
 
+    /// note that services broadcast to the connections of all apps that include the same service type. If the same type
+    /// is mounted in several apps we therefore only tell the first instance there are connections, so that clients
+    /// don't get the same message once per app
     async fn data_available (&mut self, hself: ActorHandle<SpaServerMsg>, sender_id: &'static str, data_type: &'static str)->OdinServerResult<()> {
-        let has_connections = self.has_connections();
-
-        for svc in self.services.iter_mut() {
-            match svc.data_available( &hself, has_connections, sender_id, data_type).await {
-                Ok(true) => svc.is_data_available = true,
-                Ok(false) => {}
-                Err(e) => error!("data available check failed: {e}")
+        let mut notified: Vec<&'static str> = Vec::new(); // the service types that can already broadcast
+
+        for i in 0..self.apps.len() {
+            for j in 0..self.apps[i].services.len() {
+                let type_name = self.apps[i].services[j].type_name;
+                let has_connections = !notified.contains( &type_name) && self.has_service_connections( type_name);
+                if has_connections { notified.push( type_name) }
+
+                let svc = &mut self.apps[i].services[j];
+                match svc.data_available( &hself, has_connections, sender_id, data_type).await {
+                    Ok(true) => svc.is_data_available = true,
+                    Ok(false) => {}
+                    Err(e) => error!("data available check failed: {e}")
+                }
             }
         }
         Ok(())
     }
 
     /// called when receiving a DispatchIncomingWsMsg actor message
-    async fn dispatch_incoming_ws_msg (&mut self, hself: ActorHandle<SpaServerMsg>, remote_addr: SocketAddr, msg: String)->OdinServerResult<()> {
+    async fn dispatch_incoming_ws_msg (&mut self, hself: ActorHandle<SpaServerMsg>, conn_id: ConnectionId, msg: String)->OdinServerResult<()> {
+        let (app_idx, user) = match self.connections.get( &conn_id).and_then( |conn| self.app_index( &conn.app).map( |idx| (idx, conn.user.clone()))) {
+            Some(e) => e,
+            None => return Err( connect_error( format!("no app connection for {conn_id}")))
+        };
+
         if let Some( ws_msg_parts ) = ws_service::extract_ws_msg_parts(&msg) {
             // this is ugly - we have to sequentialize the service loop and the response processing so that we don't keep the mutable self borrow open, 
             // which would prohibit to call broadcast_/send_ws_msg(&mut self,...). The nested loops are just a way to avoid heap allocating the results
             let mut i = 0;
-            let n = self.services.len();
+            let n = self.apps[app_idx].services.len();
 
             while i < n {
                 let mut response: WsMsgReaction = WsMsgReaction::None;
 
-                for svc in &mut self.services[i..] {
-                    response = svc.handle_ws_msg( &hself, &remote_addr, &ws_msg_parts).await?;
+                for svc in &mut self.apps[app_idx].services[i..] {
+                    response = svc.handle_ws_msg( &hself, &conn_id, user.as_deref(), &ws_msg_parts).await?;
                     i += 1;
                     if response != WsMsgReaction::None { break }
                 }
 
                 match response {
-                    WsMsgReaction::Broadcast(m) => self.broadcast_ws_msg(m).await?,
-                    WsMsgReaction::Send(m) => self.send_ws_msg( remote_addr, m).await?,
+                    WsMsgReaction::Broadcast(m) => {
+                        let app = self.apps[app_idx].name.clone();
+                        self.broadcast_app_ws_msg( &app, m).await?
+                    }
+                    WsMsgReaction::Send(m) => self.send_ws_msg( conn_id, m).await?,
                     WsMsgReaction::None => {}
                 }
             }
@@ -419,29 +640,97 @@ impl SpaServer {
         Ok(())
     }
 
-    /// send a ws message to all connections.
-    /// this does not bail on message delivery failure
+    /// do we have connections for any app that includes the given service type
+    fn has_service_connections (&self, type_name: &str)->bool {
+        self.apps.iter().any( |app| app.has_service( type_name) && self.has_app_connections( &app.name))
+    }
+
+    /// the names of the apps that should receive a broadcast message, which are the apps that include the service the
+    /// message is for (by convention the mod_path of a message is the type name of its service). None means all apps
+    fn recipient_apps (&self, m: &str)->Option<Vec<Arc<String>>> {
+        ws_service::extract_ws_msg_parts( m).and_then( |parts| {
+            let apps: Vec<Arc<String>> = self.apps.iter()
+                .filter( |app| app.has_service( parts.mod_path))
+                .map( |app| app.name.clone())
+                .collect();
+            if apps.is_empty() { None } else { Some(apps) }
+        })
+    }
+
+    /// send a ws message to all connections of apps that include the service the message is for. If no app has
+    /// such a service the message is sent to all connections. This makes sure we don't send data to apps that are
+    /// not supposed to see it. Each connection gets the message at most once.
+    /// This does not bail on message delivery failure
     async fn broadcast_ws_msg (&mut self, m: String)->OdinServerResult<()> {
+        let recipients = self.recipient_apps( &m);
+        self.send_to_connections( m, |conn| recipients.as_ref().map( |apps| apps.contains( &conn.app)).unwrap_or(true)).await
+    }
+
+    /// like `broadcast_ws_msg` but only send to connections whose user passes the provided filter
+    async fn broadcast_user_ws_msg (&mut self, m: String, user_filter: UserFilter)->OdinServerResult<()> {
+        let recipients = self.recipient_apps( &m);
+        self.send_to_connections( m, |conn| {
+            recipients.as_ref().map( |apps| apps.contains( &conn.app)).unwrap_or(true) && user_filter( conn.user.as_deref())
+        }).await
+    }
+
+    /// send a ws message to all connections of the given app
+    async fn broadcast_app_ws_msg (&mut self, app: &str, m: String)->OdinServerResult<()> {
+        self.send_to_connections( m, |conn| conn.app.as_str() == app).await
+    }
+
+    async fn send_to_connections (&mut self, m: String, filter: impl Fn(&SpaConnection)->bool)->OdinServerResult<()> {
         // TODO - use feed() or send_all() for batches
-        let ws_msg = Message::Text(m);
-        for conn in self.connections.values_mut() {
-            if let Err(e) = conn.ws_sender.send(ws_msg.clone()).await {
-                error!("failed to broadcast ws message to {:?}: {}", conn.remote_addr, e);
+        for conn in self.connections.values_mut().filter( |conn| filter(conn)) {
+            if let Err(e) = conn.send( m.clone()).await {
+                if !conn.is_closed() {
+                    error!("failed to broadcast ws message to {} ({}): {}", conn.id, conn.remote_addr, e);
+                }
             }
         }
+
+        self.connections.retain( |_,conn| !conn.is_closed()); // clean up SSE/REST clients that went away
         Ok(())
     }
 
-    /// send a ws message to the connection of the provided client address
-    async fn send_ws_msg (&mut self, remote_addr: SocketAddr, m: String)->OdinServerResult<()> {
-        if let Some(conn) = self.connections.get_mut( &remote_addr) {
+    /// send a ws message to the connection with the provided id
+    async fn send_ws_msg (&mut self, conn_id: ConnectionId, m: String)->OdinServerResult<()> {
+        if let Some(conn) = self.connections.get_mut( &conn_id) {
             if let Err(e) = conn.send( m).await {
-                error!("failed to send ws message to {:?}: {}", conn.remote_addr, e);
+                if conn.is_closed() {
+                    self.connections.remove( &conn_id);
+                } else {
+                    error!("failed to send ws message to {} ({}): {}", conn.id, conn.remote_addr, e);
+                }
             }
         }
         Ok(())
     }
 
+    /// called when receiving a Reconfigure<ServerConfig> message (e.g. from a ResourceWatcher). We have to restart the
+    /// server task since the config can change the socket address, TLS and the proxy cache. Clients are told to reload.
+    /// We wait for the old server task to finish so that its listener is dropped before the new task binds the socket
+    /// (which is also retried with backoff in case the OS has not released the address yet)
+    async fn reconfigure (&mut self, hself: ActorHandle<SpaServerMsg>, config: ServerConfig)->OdinServerResult<()> {
+        self.config = config;
+        if let Some(server_task) = self.server_task.take() {
+            self.request_client_reload( "server config").await?;
+            server_task.abort();
+            server_task.await; // returns a (cancelled) JoinError once the task is gone
+            self.start_server( hself)?;
+        }
+        Ok(())
+    }
+
+    /// tell all connected clients to reload the document, e.g. after assets have changed
+    async fn request_client_reload (&mut self, reason: impl ToString)->OdinServerResult<()> {
+        if self.has_connections() {
+            let msg = WsMsg::json( WsService::mod_path(), "reload", reason.to_string())?;
+            self.send_to_connections( msg, |_| true).await?;
+        }
+        Ok(())
+    }
+
     /// called when receiving _Terminate_ message
     fn stop_server (&mut self)->OdinServerResult<()> {
         if let Some(jh) = &self.server_task {
@@ -460,12 +749,30 @@ impl SpaServer {
 #[derive(Debug)]
 pub struct AddConnection {
     pub remote_addr: SocketAddr,
+    pub app: Arc<String>,
+    pub user: Option<String>,
     pub ws: WebSocket
 }
 
 #[derive(Debug)]
 pub struct RemoveConnection {
+    pub conn_id: ConnectionId,
+}
+
+/// message to add a non-websocket connection that receives the same messages through a channel. The id is
+/// created by the sender so that it can remove the connection once its client goes away
+#[derive(Debug)]
+pub struct AddChannelConnection {
+    pub conn_id: ConnectionId,
     pub remote_addr: SocketAddr,
+    pub app: Arc<String>,
+    pub user: Option<String>,
+    pub sender: mpsc::Sender<String>
+}
+
+#[derive(Debug)]
+pub struct RemoveChannelConnection {
+    pub conn_id: ConnectionId,
 }
 
 #[derive(Debug)]
@@ -476,7 +783,7 @@ pub struct DataAvailable {
 
 #[derive(Debug)]
 pub struct DispatchIncomingWsMsg {
-    pub remote_addr: SocketAddr,
+    pub conn_id: ConnectionId,
     pub ws_msg: String
 }
 
@@ -485,13 +792,28 @@ pub struct BroadcastWsMsg {
     pub data: String
 }
 
+/// predicate for the (optional) user of connections
+pub type UserFilter = Arc<dyn Fn(Option<&str>)->bool + Send + Sync>;
+
+/// broadcast message that is only sent to connections whose user passes the filter (e.g. to enforce access control)
+pub struct BroadcastUserWsMsg {
+    pub data: String,
+    pub user_filter: UserFilter
+}
+
+impl std::fmt::Debug for BroadcastUserWsMsg {
+    fn fmt (&self, f: &mut std::fmt::Formatter<'_>)->std::fmt::Result {
+        f.debug_struct("BroadcastUserWsMsg").field("data", &self.data).finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug)]
 pub struct SendWsMsg {
-    pub remote_addr: SocketAddr,
+    pub conn_id: ConnectionId,
     pub data: String
 }
 
-define_actor_msg_set! { pub SpaServerMsg = AddConnection | DataAvailable | DispatchIncomingWsMsg | BroadcastWsMsg | SendWsMsg | RemoveConnection }
+define_actor_msg_set! { pub SpaServerMsg = AddConnection | AddChannelConnection | DataAvailable | DispatchIncomingWsMsg | BroadcastWsMsg | BroadcastUserWsMsg | SendWsMsg | RemoveConnection | RemoveChannelConnection | Reconfigure<ServerConfig> | AssetChanged }
 
 impl_actor! { match actor_msg for Actor<SpaServer,SpaServerMsg> as
     _Start_ => cont! {
@@ -502,10 +824,16 @@ impl_actor! { match actor_msg for Actor<SpaServer,SpaServerMsg> as
     }
     AddConnection => cont! {
         let hself = self.hself.clone();
-        if let Err(e) = self.add_connection( hself, actor_msg.remote_addr, actor_msg.ws).await {
+        if let Err(e) = self.add_connection( hself, actor_msg.remote_addr, actor_msg.app, actor_msg.user, actor_msg.ws).await {
             error!("failed to add connection to {:?}: {:?}", actor_msg.remote_addr, e);
         }
     }
+    AddChannelConnection => cont! {
+        let hself = self.hself.clone();
+        if let Err(e) = self.add_channel_connection( hself, actor_msg.conn_id, actor_msg.remote_addr, actor_msg.app, actor_msg.user, actor_msg.sender).await {
+            error!("failed to add channel connection to {:?}: {:?}", actor_msg.remote_addr, e);
+        }
+    }
     DataAvailable => cont! {
         let hself = self.hself.clone();
         if let Err(e) = self.data_available( hself, actor_msg.sender_id, actor_msg.data_type).await {
@@ -514,7 +842,7 @@ impl_actor! { match actor_msg for Actor<SpaServer,SpaServerMsg> as
     }
     DispatchIncomingWsMsg => cont! {
         let hself = self.hself.clone();
-        if let Err(e) = self.dispatch_incoming_ws_msg( hself, actor_msg.remote_addr, actor_msg.ws_msg).await {
+        if let Err(e) = self.dispatch_incoming_ws_msg( hself, actor_msg.conn_id, actor_msg.ws_msg).await {
             error!("failed to dispatch incoming ws message: {e:?}");
         }
     }
@@ -523,14 +851,35 @@ impl_actor! { match actor_msg for Actor<SpaServer,SpaServerMsg> as
             error!("failed to broadcast ws message: {e:?}");
         }
     }
+    BroadcastUserWsMsg => cont! {
+        if let Err(e) = self.broadcast_user_ws_msg( actor_msg.data, actor_msg.user_filter).await {
+            error!("failed to broadcast ws message: {e:?}");
+        }
+    }
     SendWsMsg => cont! {
-        if let Err(e) = self.send_ws_msg( actor_msg.remote_addr, actor_msg.data).await {
+        if let Err(e) = self.send_ws_msg( actor_msg.conn_id, actor_msg.data).await {
             error!("failed to send ws message: {e:?}");
         }
     }
     RemoveConnection => cont! {
-        if let Err(e) = self.remove_connection( actor_msg.remote_addr) {
-            error!("failed to remove connection to {:?}: {:?}", actor_msg.remote_addr, e);
+        if let Err(e) = self.remove_connection( actor_msg.conn_id) {
+            error!("failed to remove connection {}: {:?}", actor_msg.conn_id, e);
+        }
+    }
+    RemoveChannelConnection => cont! {
+        if let Err(e) = self.remove_connection( actor_msg.conn_id) {
+            error!("failed to remove channel connection {}: {:?}", actor_msg.conn_id, e);
+        }
+    }
+    Reconfigure<ServerConfig> => cont! {
+        let hself = self.hself.clone();
+        if let Err(e) = self.reconfigure( hself, actor_msg.config).await {
+            error!("failed to reconfigure server: {e:?}");
+        }
+    }
+    AssetChanged => cont! {
+        if let Err(e) = self.request_client_reload( actor_msg.filename).await {
+            error!("failed to notify clients of changed asset: {e:?}");
         }
     }
     _Terminate_ => stop! {
@@ -586,25 +935,26 @@ define_struct! { pub SpaComponents =
 
 /// struct to define how we create requests for proxied URIs
 #[derive(Debug,Clone)]
-struct ProxySpec {
+pub struct ProxySpec {
     uri: String,                     // the target URI to get the data from
     copy_hdrs: Vec<String>,          // header keys to copy from the incoming request
     add_hdrs: Vec<(String,String)>,  // header key/value strings to add
     copy_query: bool,                // shall we copy the query string from the incoming request
-    add_query: Option<String>        // query string to add
+    add_query: Option<String>,       // query string to add
+    ttl: Option<Duration>,           // service provided cache TTL (can be overridden by ProxyCacheConfig policies)
 }
 
 impl ProxySpec {
 
-    fn create_request (&self, http_client: &ClientWithMiddleware, rel_path: &str, query: &RawQuery, hdr_map: &HeaderMap) -> MwRequestBuilder {
-        let uri = self.get_uri( rel_path, query);
+    pub fn create_request (&self, http_client: &Client, uri: &str, hdr_map: &HeaderMap) -> RequestBuilder {
         let request_builder = http_client.get(uri);
 
         self.add_headers( request_builder, hdr_map)
     }
 
-    fn get_uri (&self, rel_path: &str, query: &RawQuery) -> String {
-        let qs = if let Some(qs) = &query.0 { qs.as_str() } else { "" };
+    /// the upstream uri for a given path relative to the proxy, and an optional query string
+    pub fn get_uri (&self, rel_path: &str, query: Option<&str>) -> String {
+        let qs = query.unwrap_or("");
         let add_qs = if let Some(add_qs) = &self.add_query { add_qs.as_str() } else { "" };
 
         let mut len = self.uri.len() + rel_path.len() + 1 + qs.len() + 1 + add_qs.len() + 1; // just the upper bound
@@ -633,7 +983,11 @@ impl ProxySpec {
         uri
     }
 
-    fn add_headers (&self, req_builder: MwRequestBuilder, hdr_map: &HeaderMap) -> MwRequestBuilder {
+    pub fn ttl (&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    fn add_headers (&self, req_builder: RequestBuilder, hdr_map: &HeaderMap) -> RequestBuilder {
         let mut req_builder = req_builder;
 
         if !self.copy_hdrs.is_empty() {
@@ -693,6 +1047,11 @@ impl SpaComponents {
         self.routes.push( Box::new(rf));
     }
 
+    /// the proxies added so far. This is mostly used by tools that operate on the proxy cache (e.g. tile seeding)
+    pub fn proxies (&self) -> &HashMap<String,ProxySpec> {
+        &self.proxies
+    }
+
     pub fn add_assets (&mut self, key: &'static str, load_asset_fn: LoadAssetFp) {
         self.assets.insert( key, load_asset_fn);
     }
@@ -718,18 +1077,35 @@ impl SpaComponents {
             Some( mk_query_string(add_query.iter()) )
         };
 
-        let proxy_spec = ProxySpec{uri,copy_hdrs,add_hdrs,copy_query,add_query};
+        let proxy_spec = ProxySpec{uri,copy_hdrs,add_hdrs,copy_query,add_query,ttl: None};
         self.proxies.insert( key.to_string(), proxy_spec);
     }
 
+    /// set the cache TTL for responses of a previously added proxy. This is used if the upstream server does not
+    /// provide explicit `Cache-Control` or `Expires` headers, and can be overridden by `ProxyCacheConfig` policies
+    pub fn set_proxy_ttl (&mut self, key: &str, ttl: Duration) {
+        if let Some(proxy_spec) = self.proxies.get_mut(key) {
+            proxy_spec.ttl = Some(ttl);
+        }
+    }
+
     /// render HTML document. We could use a lib such as build_html but our documents are rather simple so there is no
     /// need for another intermediate doc model
     /// TODO - remove newlines in production
     pub fn to_html(&self, name: &str)->String {
+        self.to_app_html( name, None)
+    }
+
+    /// render HTML document with an optional default theme that is used by ui_load_theme.js
+    pub fn to_app_html(&self, name: &str, theme: Option<&str>)->String {
         let mut buf = String::with_capacity(4096);
 
         write!( buf, "<!DOCTYPE html>\n");
-        write!( buf, "<html>\n");
+        if let Some(theme) = theme {
+            write!( buf, "<html data-theme=\"{theme}\">\n");
+        } else {
+            write!( buf, "<html>\n");
+        }
         write!( buf, "<head>\n");
 
         write!( buf, "<title>{name}</title>\n");
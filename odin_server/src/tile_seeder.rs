@@ -0,0 +1,364 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the tile_seeder module supports pre-fetching map tiles of proxied tile servers into the `ProxyCache`, which is
+//! required to deploy servers to sites without internet access. Seeding jobs are described by a `SeedRequest`
+//! (proxy name, relative tile uri template, bounding box and zoom range) and keep their progress in a
+//! `SeedManifest` JSON file that is stored under `odin_build::cache_dir()/proxy_seeds/`. Re-running the same
+//! request resumes from the last saved position, and retries tiles that could not be retrieved in previous runs.
+//! Requests are checked against the configured `seed_max_zoom` and `seed_max_tiles` limits of the `ProxyCacheConfig`
+//!
+//! NOTE - some tile servers (e.g. OpenStreetMap) do not permit bulk downloads. Check the respective usage policies
+
+use std::{
+    collections::{HashMap,HashSet}, f64::consts::PI,
+    path::{Path,PathBuf}, sync::{Arc,Mutex}
+};
+use axum::http::{header, HeaderMap, HeaderValue};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize,Serialize};
+use sha2::{Digest, Sha256};
+
+use odin_common::{datetime::epoch_millis, geo::GeoBoundingBox};
+
+use crate::{
+    errors::{op_failed, OdinServerResult},
+    proxy_cache::{ProxyBody, ProxyCache},
+    spa::ProxySpec
+};
+
+/// how many tiles we process between manifest updates
+const SAVE_INTERVAL: u64 = 100;
+
+/// max web mercator latitude
+const MAX_LAT: f64 = 85.05112878;
+
+/// the largest zoom level we can compute tile indices for (without overflowing u32 tile coordinates)
+pub const MAX_TILE_ZOOM: u32 = 30;
+
+/// the (z,x,y) coordinates of a tile
+pub type TileCoord = (u32,u32,u32);
+
+/// specification of a tile seeding job
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct SeedRequest {
+    pub proxy: String,          // symbolic proxy name as used in `SpaComponents::add_proxy(..)`
+    pub tile_uri: String,       // tile uri template relative to proxy, with {z},{x},{y} placeholders (e.g. "{z}/{x}/{y}.png")
+    pub bbox: GeoBoundingBox,
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+
+    #[serde(default="default_concurrency")]
+    pub concurrency: usize,     // max number of simultaneous upstream requests
+}
+
+fn default_concurrency()->usize { 4 }
+
+impl SeedRequest {
+    /// the id is derived from the request content so that re-submitting the same request resumes the job. Since
+    /// manifests are persisted this has to be stable across toolchain versions, hence we don't use `std::hash`
+    pub fn id (&self)->String {
+        let b = &self.bbox;
+        let spec = format!("{}|{}|{:.7},{:.7},{:.7},{:.7}|{}|{}", self.proxy, self.tile_uri,
+            b.west.degrees(), b.south.degrees(), b.east.degrees(), b.north.degrees(), self.min_zoom, self.max_zoom);
+        let hash = Sha256::digest( spec.as_bytes());
+        let hex: String = hash[..8].iter().map( |b| format!("{b:02x}")).collect();
+        format!("{}-{}", self.proxy, hex)
+    }
+
+    /// check if this is a well formed request within the given limits, returning the number of tiles if it is
+    pub fn validate (&self, max_zoom: u32, max_tiles: u64)->OdinServerResult<u64> {
+        let b = &self.bbox;
+        if ![b.west.degrees(), b.south.degrees(), b.east.degrees(), b.north.degrees()].iter().all( |d| d.is_finite()) {
+            return Err( op_failed( "invalid bounding box"))
+        }
+        if b.south.degrees() > b.north.degrees() {
+            return Err( op_failed( "bounding box south > north"))
+        }
+        if self.min_zoom > self.max_zoom {
+            return Err( op_failed( format!("min_zoom {} > max_zoom {}", self.min_zoom, self.max_zoom)))
+        }
+        if self.max_zoom > max_zoom.min( MAX_TILE_ZOOM) {
+            return Err( op_failed( format!("max_zoom {} exceeds limit {}", self.max_zoom, max_zoom.min( MAX_TILE_ZOOM))))
+        }
+
+        let total = self.total_tiles();
+        if total > max_tiles {
+            return Err( op_failed( format!("request covers {total} tiles, limit is {max_tiles}")))
+        }
+        Ok(total)
+    }
+
+    /// note this does not allocate tile index ranges, i.e. it can be used to check requests before running them
+    pub fn total_tiles (&self)->u64 {
+        (self.min_zoom..=self.max_zoom).map( |z| tile_count( &self.bbox, z)).sum()
+    }
+
+    /// iterate over all (z,x,y) tile coordinates of this request in a deterministic order
+    pub fn tiles (&self)->impl Iterator<Item=TileCoord> + '_ {
+        (self.min_zoom..=self.max_zoom).flat_map( move |z| {
+            let (xs,ys) = tile_ranges( &self.bbox, z);
+            xs.into_iter().flat_map( move |x| ys.clone().into_iter().map( move |y| (z,x,y)))
+        })
+    }
+
+    pub fn tile_uri (&self, z: u32, x: u32, y: u32)->String {
+        self.tile_uri
+            .replace("{z}", &z.to_string())
+            .replace("{x}", &x.to_string())
+            .replace("{y}", &y.to_string())
+    }
+}
+
+/// what happened to a single tile of a seeding job
+enum SeedOutcome { Fetched, Skipped, Uncacheable }
+
+/// the persistent state of a seeding job
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct SeedManifest {
+    pub id: String,
+    pub request: SeedRequest,
+    pub total: u64,
+    pub next_index: u64,        // all tiles before this index have been processed
+    pub fetched: u64,           // number of tiles retrieved from upstream and stored in the cache
+    pub skipped: u64,           // number of tiles that were already in the cache
+    #[serde(default)]
+    pub uncacheable: u64,       // number of tiles retrieved from upstream that must not be stored (e.g. `no-store`)
+    #[serde(default)]
+    pub pending: Vec<TileCoord>, // tiles we could not retrieve yet - these are retried when the job is resumed
+    pub started: i64,           // epoch millis
+    pub updated: i64,           // epoch millis
+    pub completed: bool,
+}
+
+impl SeedManifest {
+    pub fn new (request: SeedRequest)->Self {
+        let now = epoch_millis();
+        SeedManifest {
+            id: request.id(),
+            total: request.total_tiles(),
+            request,
+            next_index: 0,
+            fetched: 0,
+            skipped: 0,
+            uncacheable: 0,
+            pending: Vec::new(),
+            started: now,
+            updated: now,
+            completed: false,
+        }
+    }
+
+    pub fn percent_done (&self)->f64 {
+        if self.total > 0 { (self.next_index as f64 * 100.0) / self.total as f64 } else { 100.0 }
+    }
+}
+
+/// get the web mercator x and y tile index ranges for a given bounding box and zoom level. Note the x range
+/// wraps around if the bounding box crosses the antimeridian. Zoom levels above [`MAX_TILE_ZOOM`] yield empty ranges
+pub fn tile_ranges (bbox: &GeoBoundingBox, zoom: u32)->(Vec<u32>,Vec<u32>) {
+    if zoom > MAX_TILE_ZOOM { return (Vec::new(), Vec::new()) }
+    let n = 1u32 << zoom;
+
+    let x_west = lon_to_tile_x( bbox.west.degrees(), n);
+    let x_east = lon_to_tile_x( bbox.east.degrees(), n);
+    let xs: Vec<u32> = if x_west <= x_east {
+        (x_west..=x_east).collect()
+    } else {
+        (x_west..n).chain( 0..=x_east).collect()
+    };
+
+    let y_north = lat_to_tile_y( bbox.north.degrees(), n);
+    let y_south = lat_to_tile_y( bbox.south.degrees(), n);
+    let ys: Vec<u32> = (y_north.min(y_south)..=y_north.max(y_south)).collect();
+
+    (xs,ys)
+}
+
+/// the number of tiles [`tile_ranges`] would return for a given bounding box and zoom level
+pub fn tile_count (bbox: &GeoBoundingBox, zoom: u32)->u64 {
+    if zoom > MAX_TILE_ZOOM { return 0 }
+    let n = 1u32 << zoom;
+
+    let x_west = lon_to_tile_x( bbox.west.degrees(), n) as u64;
+    let x_east = lon_to_tile_x( bbox.east.degrees(), n) as u64;
+    let nx = if x_west <= x_east { x_east - x_west + 1 } else { n as u64 - x_west + x_east + 1 };
+
+    let y_north = lat_to_tile_y( bbox.north.degrees(), n) as u64;
+    let y_south = lat_to_tile_y( bbox.south.degrees(), n) as u64;
+    let ny = y_north.abs_diff( y_south) + 1;
+
+    nx * ny
+}
+
+fn lon_to_tile_x (lon: f64, n: u32)->u32 {
+    let x = ((lon + 180.0) / 360.0 * n as f64).floor() as i64;
+    x.clamp( 0, n as i64 - 1) as u32
+}
+
+fn lat_to_tile_y (lat: f64, n: u32)->u32 {
+    let lat_rad = lat.clamp( -MAX_LAT, MAX_LAT).to_radians();
+    let y = ((1.0 - (lat_rad.tan() + 1.0 / lat_rad.cos()).ln() / PI) / 2.0 * n as f64).floor() as i64;
+    y.clamp( 0, n as i64 - 1) as u32
+}
+
+/// the object that runs seeding jobs. This is used both from the server API and from stand-alone tools
+#[derive(Clone)]
+pub struct TileSeeder {
+    cache: Arc<ProxyCache>,
+    proxies: Arc<HashMap<String,ProxySpec>>,
+    manifest_dir: PathBuf,
+    running: Arc<Mutex<HashSet<String>>>, // ids of currently running jobs
+}
+
+impl TileSeeder {
+    pub fn new (cache: Arc<ProxyCache>, proxies: Arc<HashMap<String,ProxySpec>>)->Self {
+        let manifest_dir = odin_build::cache_dir().join("proxy_seeds");
+        let running = Arc::new( Mutex::new( HashSet::new()));
+        TileSeeder { cache, proxies, manifest_dir, running }
+    }
+
+    /// check the request against the configured limits of our cache, returning the number of tiles if it is acceptable
+    pub fn validate (&self, request: &SeedRequest)->OdinServerResult<u64> {
+        if !self.proxies.contains_key( &request.proxy) {
+            return Err( op_failed( format!("unknown proxy {}", request.proxy)))
+        }
+        let config = self.cache.config();
+        request.validate( config.seed_max_zoom, config.seed_max_tiles)
+    }
+
+    pub fn is_running (&self, id: &str)->bool {
+        self.running.lock().unwrap().contains(id)
+    }
+
+    pub fn manifest_path (&self, id: &str)->PathBuf {
+        self.manifest_dir.join( format!("{id}.json"))
+    }
+
+    pub fn load_manifest (&self, id: &str)->Option<SeedManifest> {
+        let data = std::fs::read( self.manifest_path(id)).ok()?;
+        serde_json::from_slice( &data).ok()
+    }
+
+    pub fn list_manifests (&self)->Vec<SeedManifest> {
+        let mut list = Vec::new();
+        if let Ok(entries) = std::fs::read_dir( &self.manifest_dir) {
+            for e in entries.flatten() {
+                if let Ok(data) = std::fs::read( e.path()) {
+                    if let Ok(manifest) = serde_json::from_slice::<SeedManifest>( &data) {
+                        list.push( manifest);
+                    }
+                }
+            }
+        }
+        list
+    }
+
+    fn save_manifest (&self, manifest: &SeedManifest)->OdinServerResult<()> {
+        std::fs::create_dir_all( &self.manifest_dir)?;
+        let data = serde_json::to_vec_pretty( manifest)?;
+        Ok( std::fs::write( self.manifest_path( &manifest.id), data)? )
+    }
+
+    /// run (or resume) a seeding job. The `progress` callback is invoked each time the manifest is saved
+    pub async fn run (&self, request: SeedRequest, progress: impl Fn(&SeedManifest))->OdinServerResult<SeedManifest> {
+        self.validate( &request)?;
+        let proxy_spec = self.proxies.get( &request.proxy)
+            .ok_or_else( || op_failed( format!("unknown proxy {}", request.proxy)))?
+            .clone();
+
+        let id = request.id();
+        if !self.running.lock().unwrap().insert( id.clone()) {
+            return Err( op_failed( format!("seed job {id} already running")))
+        }
+
+        let result = self.run_job( request, &proxy_spec, progress).await;
+        self.running.lock().unwrap().remove( &id);
+        result
+    }
+
+    async fn run_job (&self, request: SeedRequest, proxy_spec: &ProxySpec, progress: impl Fn(&SeedManifest))->OdinServerResult<SeedManifest> {
+        let mut manifest = match self.load_manifest( &request.id()) {
+            Some(mut manifest) if !manifest.completed || !manifest.pending.is_empty() => {
+                manifest.completed = false;
+                manifest
+            }
+            _ => SeedManifest::new( request.clone())
+        };
+
+        let mut hdrs = HeaderMap::new(); // some tile servers reject requests without user agent
+        hdrs.insert( header::USER_AGENT, HeaderValue::from_static( concat!("odin_server/", env!("CARGO_PKG_VERSION"))));
+
+        let proxy = request.proxy.as_str();
+        let concurrency = request.concurrency.max(1);
+
+        // pending tiles of previous runs stay in the manifest until they are retrieved, so that we don't lose them if
+        // this run gets interrupted
+        let retries: Vec<(TileCoord,bool)> = manifest.pending.iter().map( |tile| (*tile, true)).collect();
+        let tiles = retries.into_iter().chain( request.tiles().skip( manifest.next_index as usize).map( |tile| (tile, false)));
+        let mut n_processed: u64 = 0;
+
+        let mut results = stream::iter( tiles)
+            .map( |(tile,is_retry)| {
+                let (z,x,y) = tile;
+                let rel_uri = request.tile_uri( z, x, y);
+                let uri = proxy_spec.get_uri( &rel_uri, None);
+                let hdrs = &hdrs;
+                async move {
+                    if self.cache.has_fresh_entry( proxy, &uri).await {
+                        (tile, is_retry, Ok(SeedOutcome::Skipped))
+                    } else {
+                        let req_builder = proxy_spec.create_request( self.cache.http_client(), &uri, hdrs);
+                        let res = self.cache.get( proxy, proxy_spec.ttl(), &uri, req_builder).await
+                            .and_then( |r| if (200..300).contains( &r.meta.status) { Ok(r) } else { Err( op_failed( format!("status {}", r.meta.status))) })
+                            .map( |r| match r.body { // successful upstream bodies are only returned directly if they were not stored
+                                ProxyBody::File(_) => SeedOutcome::Fetched,
+                                ProxyBody::Upstream(_) => SeedOutcome::Uncacheable
+                            });
+                        (tile, is_retry, res)
+                    }
+                }
+            })
+            .buffered( concurrency);
+
+        while let Some((tile, is_retry, res)) = results.next().await {
+            match res {
+                Ok(SeedOutcome::Fetched) => manifest.fetched += 1,
+                Ok(SeedOutcome::Skipped) => manifest.skipped += 1,
+                Ok(SeedOutcome::Uncacheable) => manifest.uncacheable += 1,
+                Err(_) => if !is_retry { manifest.pending.push( tile) }
+            }
+            if is_retry {
+                if res.is_ok() { manifest.pending.retain( |t| *t != tile) }
+            } else {
+                manifest.next_index += 1;
+            }
+
+            n_processed += 1;
+            if n_processed % SAVE_INTERVAL == 0 {
+                manifest.updated = epoch_millis();
+                self.save_manifest( &manifest)?;
+                progress( &manifest);
+            }
+        }
+
+        manifest.completed = true;
+        manifest.updated = epoch_millis();
+        self.save_manifest( &manifest)?;
+        progress( &manifest);
+
+        Ok(manifest)
+    }
+}
@@ -13,28 +13,48 @@
  */
 #![allow(unused)]
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, convert::Infallible, time::{Duration,Instant}};
 use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade, CloseFrame},
-    response::{Response,IntoResponse},
+    extract::Path as AxumPath,
+    http::{header, StatusCode},
+    response::{Response,IntoResponse, sse::{Event, KeepAlive, Sse}},
     routing::{Router,get},
-    extract::connect_info::ConnectInfo
+    extract::connect_info::ConnectInfo,
+    Extension
 };
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::{sink::SinkExt, stream::{self, StreamExt}};
 use regex::Match;
+use tokio::sync::mpsc;
+use odin_actor::{ActorHandle, spawn, warn, errors::OdinActorError};
 
 use crate::{
-    asset_uri, load_asset, self_crate, spa::{AddConnection, SpaComponents, SpaServerState, SpaService}, OdinServerResult
+    asset_uri, load_asset, self_crate, spa::{AddConnection, AddChannelConnection, RemoveChannelConnection, ConnectionId, SpaComponents, SpaServerMsg, SpaServerState, SpaService}, auth::SpaUser, OdinServerResult
 };
 
+/// max number of pending messages for SSE and REST connections
+const CHANNEL_BOUNDS: usize = 256;
+
+/// snapshot responses are complete once we haven't received any message for this duration..
+const SNAPSHOT_IDLE: Duration = Duration::from_millis(300);
+/// ..or after this total duration
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// a SpaService that adds a shared websocket for all services that register for it
-/// this mostly adds a route for the websocket and adds a respective JS module
+/// this mostly adds a route for the websocket and adds a respective JS module.
+/// For clients that cannot use websockets we also add generic HTTP routes that provide the same `WsMsg` messages:
+///   - `<app>/sse[/<mod>]` for a Server-Sent Event stream of all messages (or the messages of a given service module)
+///   - `<app>/snapshot[/<mod>]` for a JSON array of the initial messages services send to new connections
+/// `<mod>` is matched as a prefix of the `mod` field of messages, i.e. it can be a service mod_path or just a crate name
 pub struct WsService {
     // tbd
 }
 
 impl WsService {
     pub fn new()->Self { WsService{} }
+
+    /// the mod_path of messages that are processed by ws.js itself (e.g. client reload requests)
+    pub fn mod_path()->&'static str { type_name::<Self>() }
 }
 
 impl SpaService for WsService {
@@ -43,22 +63,117 @@ impl SpaService for WsService {
         spa.add_module( asset_uri!("ws.js"));
 
         spa.add_route( |router, spa_server_state| {
-            router.route( &format!("/{}/ws", spa_server_state.name.as_str()), get( {
-                let state = spa_server_state.clone();
-                move |ws: WebSocketUpgrade, ci: ConnectInfo<SocketAddr>| { ws_handler(ws, ci, state) }
-            }))
+            let name = spa_server_state.name.as_str();
+            router
+                .route( &format!("/{name}/ws"), get( {
+                    let state = spa_server_state.clone();
+                    move |ws: WebSocketUpgrade, ci: ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>| { ws_handler(ws, ci, user, state) }
+                }))
+                .route( &format!("/{name}/sse"), get( {
+                    let state = spa_server_state.clone();
+                    move |ci: ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>| { sse_handler(ci, user, None, state) }
+                }))
+                .route( &format!("/{name}/sse/:mod"), get( {
+                    let state = spa_server_state.clone();
+                    move |ci: ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>, AxumPath(m): AxumPath<String>| { sse_handler(ci, user, Some(m), state) }
+                }))
+                .route( &format!("/{name}/snapshot"), get( {
+                    let state = spa_server_state.clone();
+                    move |ci: ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>| { snapshot_handler(ci, user, None, state) }
+                }))
+                .route( &format!("/{name}/snapshot/:mod"), get( {
+                    let state = spa_server_state.clone();
+                    move |ci: ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>, AxumPath(m): AxumPath<String>| { snapshot_handler(ci, user, Some(m), state) }
+                }))
         });
 
         Ok(())
     }
 }
 
-async fn ws_handler (ws: WebSocketUpgrade, ConnectInfo(addr): ConnectInfo<SocketAddr>, sss: SpaServerState)->Response {
-    ws.on_upgrade( move |socket| handle_socket(socket, addr, sss)).into_response()
+/// the (authenticated) user of a request. There is no `SpaUser` extension for public apps
+fn user_name (user: Option<Extension<SpaUser>>)->Option<String> {
+    user.and_then( |Extension(SpaUser(name))| name)
+}
+
+async fn ws_handler (ws: WebSocketUpgrade, ConnectInfo(addr): ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>, sss: SpaServerState)->Response {
+    let user = user_name( user);
+    ws.on_upgrade( move |socket| handle_socket(socket, addr, user, sss)).into_response()
+}
+
+async fn handle_socket(mut ws: WebSocket, remote_addr: SocketAddr, user: Option<String>, sss: SpaServerState) {
+    sss.hself.send_msg( AddConnection{ remote_addr, app: sss.name.clone(), user, ws }).await;
+}
+
+/// check if a serialized WsMsg is for the given module prefix
+fn is_mod_msg (msg: &str, mod_prefix: &Option<String>)->bool {
+    match mod_prefix {
+        Some(prefix) => extract_ws_msg_parts( msg).map( |parts| parts.mod_path.starts_with( prefix.as_str())).unwrap_or(false),
+        None => true
+    }
+}
+
+/// removes a channel connection from the server once the stream that owns it is dropped (e.g. because the client went away)
+struct ChannelConnectionGuard {
+    conn_id: ConnectionId,
+    hserver: ActorHandle<SpaServerMsg>
 }
 
-async fn handle_socket(mut ws: WebSocket, remote_addr: SocketAddr, sss: SpaServerState) {
-    sss.hself.send_msg( AddConnection{remote_addr,ws}).await;
+impl Drop for ChannelConnectionGuard {
+    fn drop (&mut self) {
+        let msg = RemoveChannelConnection{ conn_id: self.conn_id };
+        if let Err(OdinActorError::ReceiverFull) = self.hserver.try_send_msg( msg) { // we can't await in drop
+            let hserver = self.hserver.clone();
+            let conn_id = self.conn_id;
+            if let Err(e) = spawn( "remove-sse-connection", async move { hserver.send_msg( RemoveChannelConnection{ conn_id }).await }) {
+                warn!("failed to remove SSE connection {conn_id}: {e}");
+            }
+        }
+    }
+}
+
+async fn sse_handler (ConnectInfo(remote_addr): ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>, mod_prefix: Option<String>, sss: SpaServerState)->Response {
+    let (sender, receiver) = mpsc::channel::<String>( CHANNEL_BOUNDS);
+    let conn_id = ConnectionId::new();
+    if sss.hself.send_msg( AddChannelConnection{ conn_id, remote_addr, app: sss.name.clone(), user: user_name( user), sender }).await.is_err() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "server not running").into_response()
+    }
+
+    // axum drops the stream once the client goes away, which drops the guard and removes the connection
+    let guard = ChannelConnectionGuard{ conn_id, hserver: sss.hself.clone() };
+    let stream = stream::unfold( (receiver, guard), |(mut receiver, guard)| async move {
+            receiver.recv().await.map( |msg| (msg, (receiver, guard)))
+        })
+        .filter( move |msg| std::future::ready( is_mod_msg( msg, &mod_prefix)))
+        .map( |msg| Ok::<Event,Infallible>( Event::default().data(msg)));
+
+    Sse::new( stream).keep_alive( KeepAlive::default()).into_response()
+}
+
+async fn snapshot_handler (ConnectInfo(remote_addr): ConnectInfo<SocketAddr>, user: Option<Extension<SpaUser>>, mod_prefix: Option<String>, sss: SpaServerState)->Response {
+    let (sender, mut receiver) = mpsc::channel::<String>( CHANNEL_BOUNDS);
+    let conn_id = ConnectionId::new();
+    if sss.hself.send_msg( AddChannelConnection{ conn_id, remote_addr, app: sss.name.clone(), user: user_name( user), sender }).await.is_err() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "server not running").into_response()
+    }
+
+    // init messages can come from data actors, i.e. we don't know when we are done and have to collect until we go idle.
+    // Since the server holds the only sender a closed channel means it could not initialize the connection
+    let deadline = Instant::now() + SNAPSHOT_TIMEOUT;
+    let mut msgs: Vec<String> = Vec::new();
+    loop {
+        match tokio::time::timeout( SNAPSHOT_IDLE, receiver.recv()).await {
+            Ok(Some(msg)) => if is_mod_msg( &msg, &mod_prefix) { msgs.push( msg) }
+            Ok(None) => return (StatusCode::INTERNAL_SERVER_ERROR, "failed to initialize snapshot").into_response(),
+            Err(_) => break // idle
+        }
+        if Instant::now() > deadline { break }
+    }
+
+    sss.hself.send_msg( RemoveChannelConnection{ conn_id }).await;
+
+    let json = format!("[{}]", msgs.join(","));
+    (StatusCode::OK, [(header::CONTENT_TYPE, "application/json")], json).into_response()
 }
 
 /* #region WsMsg serialization  *******************************************************************************/
@@ -86,7 +201,7 @@ pub struct WsMsg<T>  {
 }
 
 fn match_str<'a> (s: &'a str, capture: &Match<'a>)-> &'a str {
-    &s[capture.start()..capture.len()]
+    &s[capture.start()..capture.end()]
 }
 
 /// extrace substrings for module_path, msg_type and payload from incoming JSON string
@@ -0,0 +1,177 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::{path::PathBuf, time::Duration, sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}}};
+use axum::{Router, routing::get, extract::{Path, State}, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}};
+use odin_server::proxy_cache::{CacheControl, ProxyCache, ProxyCacheConfig, ProxyResponse};
+
+#[test]
+fn test_cache_control_parse() {
+    let cc = CacheControl::parse("public, max-age=3600, must-revalidate");
+    assert_eq!( cc.max_age, Some(3600));
+    assert!( cc.must_revalidate);
+    assert!( !cc.no_store);
+
+    let cc = CacheControl::parse("no-store, private");
+    assert!( cc.no_store && cc.private);
+    assert_eq!( cc.max_age, None);
+
+    let cc = CacheControl::parse(r#"s-maxage="600", No-Cache"#);
+    assert_eq!( cc.s_maxage, Some(600));
+    assert!( cc.no_cache);
+}
+
+#[test]
+fn test_proxy_cache_config() {
+    let config: ProxyCacheConfig = ron::from_str(r#"(
+        default_ttl: "2 days",
+        policies: [ (proxy: "globe-osm", ttl: "30 days", ignore_cache_control: true) ]
+    )"#).unwrap();
+
+    assert!( config.enabled);
+    assert_eq!( config.default_ttl, Duration::from_secs( 2*24*60*60));
+    assert_eq!( config.max_stale, None);
+    assert_eq!( config.policies[0].ttl, Duration::from_secs( 30*24*60*60));
+}
+
+#[test]
+fn test_entry_path_stable() {
+    // cache file names have to survive restarts and toolchain updates
+    let cache = ProxyCache::with_dir( ProxyCacheConfig::default(), PathBuf::from("/tmp/odin-proxy-cache-test"));
+    let path = cache.entry_path( "globe-osm", "https://tile.openstreetmap.org/3/4/2.png");
+    assert_eq!( path, PathBuf::from("/tmp/odin-proxy-cache-test/globe-osm/7e345b2abe75eced32dc28203e84657e"));
+}
+
+/* #region cache behavior against a local upstream server ***********************************************************/
+
+/// what our stand-in upstream server got
+#[derive(Default)]
+struct Upstream {
+    n_requests: AtomicUsize,
+    n_not_modified: AtomicUsize, // conditional requests we answered with 304
+    fail: AtomicBool,            // respond with 500
+}
+
+/// local upstream server. `/max-age/<name>` responses are fresh for an hour, `/no-cache/<name>` responses have to be
+/// revalidated on each request. Both have an ETag and answer matching conditional requests with `304 Not Modified`
+async fn start_upstream ()->(String,Arc<Upstream>) {
+    let upstream = Arc::new( Upstream::default());
+
+    let app = Router::new().route( "/:policy/:name", get( |State(upstream): State<Arc<Upstream>>, Path((policy,name)): Path<(String,String)>, hdrs: HeaderMap| async move {
+        upstream.n_requests.fetch_add( 1, Ordering::Relaxed);
+        if upstream.fail.load( Ordering::Relaxed) { return StatusCode::INTERNAL_SERVER_ERROR.into_response() }
+
+        let etag = format!("\"{name}-v1\"");
+        let cache_control = if policy == "max-age" { "max-age=3600" } else { "no-cache" };
+        if hdrs.get( header::IF_NONE_MATCH).and_then( |v| v.to_str().ok()) == Some( etag.as_str()) {
+            upstream.n_not_modified.fetch_add( 1, Ordering::Relaxed);
+            return (StatusCode::NOT_MODIFIED, [(header::CACHE_CONTROL, cache_control.to_string()), (header::ETAG, etag)]).into_response()
+        }
+        ([(header::CACHE_CONTROL, cache_control.to_string()), (header::ETAG, etag)], format!("content of {name}")).into_response()
+    })).with_state( upstream.clone());
+
+    let listener = tokio::net::TcpListener::bind( "127.0.0.1:0").await.unwrap();
+    let base_uri = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn( async move { axum::serve( listener, app).await });
+
+    (base_uri, upstream)
+}
+
+fn create_cache (name: &str, config: ProxyCacheConfig)->ProxyCache {
+    let dir = std::env::temp_dir().join( format!("odin_proxy_cache_{}_{}", name, std::process::id()));
+    if dir.is_dir() { std::fs::remove_dir_all( &dir).unwrap(); }
+    ProxyCache::with_dir( config, dir)
+}
+
+async fn get (cache: &ProxyCache, uri: &str)->ProxyResponse {
+    cache.get( "test", None, uri, cache.http_client().get( uri)).await.unwrap()
+}
+
+async fn body_text (response: ProxyResponse)->String {
+    let response = response.into_client_response( &HeaderMap::new());
+    let bytes = axum::body::to_bytes( response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8( bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_fresh_hit() {
+    let (base_uri, upstream) = start_upstream().await;
+    let cache = create_cache( "fresh", ProxyCacheConfig::default());
+    let uri = format!("{base_uri}/max-age/tile");
+
+    let response = get( &cache, &uri).await;
+    assert!( !response.is_stale);
+    assert_eq!( body_text( response).await, "content of tile");
+    assert!( cache.has_fresh_entry( "test", &uri).await);
+
+    let response = get( &cache, &uri).await; // served from cache without contacting upstream
+    assert!( !response.is_stale);
+    assert_eq!( body_text( response).await, "content of tile");
+    assert_eq!( upstream.n_requests.load( Ordering::Relaxed), 1);
+
+    // clients that already have the current version get a 304
+    let mut client_hdrs = HeaderMap::new();
+    client_hdrs.insert( header::IF_NONE_MATCH, "\"tile-v1\"".parse().unwrap());
+    let response: Response = get( &cache, &uri).await.into_client_response( &client_hdrs);
+    assert_eq!( response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn test_revalidate() {
+    let (base_uri, upstream) = start_upstream().await;
+    let cache = create_cache( "revalidate", ProxyCacheConfig::default());
+    let uri = format!("{base_uri}/no-cache/tile");
+
+    assert_eq!( body_text( get( &cache, &uri).await).await, "content of tile");
+    assert!( !cache.has_fresh_entry( "test", &uri).await); // stored but has to be revalidated
+
+    let response = get( &cache, &uri).await;
+    assert!( !response.is_stale);
+    assert_eq!( body_text( response).await, "content of tile");
+    assert_eq!( upstream.n_requests.load( Ordering::Relaxed), 2);
+    assert_eq!( upstream.n_not_modified.load( Ordering::Relaxed), 1); // sent as conditional request
+}
+
+#[tokio::test]
+async fn test_stale_on_upstream_failure() {
+    let (base_uri, upstream) = start_upstream().await;
+    let cache = create_cache( "stale", ProxyCacheConfig::default());
+    let uri = format!("{base_uri}/no-cache/tile");
+
+    assert_eq!( body_text( get( &cache, &uri).await).await, "content of tile");
+
+    // upstream server error
+    upstream.fail.store( true, Ordering::Relaxed);
+    let response = get( &cache, &uri).await;
+    assert!( response.is_stale);
+    let response = response.into_client_response( &HeaderMap::new());
+    assert!( response.headers().contains_key( header::WARNING));
+
+    // upstream not reachable
+    let response = cache.get( "test", None, &uri, cache.http_client().get( "http://127.0.0.1:1/no-cache/tile")).await.unwrap();
+    assert!( response.is_stale);
+    assert_eq!( body_text( response).await, "content of tile");
+
+    // nothing to fall back to
+    let other = format!("{base_uri}/no-cache/other");
+    assert!( cache.get( "test", None, &other, cache.http_client().get( &other)).await.is_err());
+
+    // max_stale bounds how long past their expiration entries are served
+    let config = ProxyCacheConfig { max_stale: Some( Duration::ZERO), ..ProxyCacheConfig::default() };
+    let cache = ProxyCache::with_dir( config, cache.dir().to_path_buf());
+    tokio::time::sleep( Duration::from_millis(10)).await;
+    assert!( cache.get( "test", None, &uri, cache.http_client().get( &uri)).await.is_err());
+}
+
+/* #endregion cache behavior */
@@ -0,0 +1,74 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use odin_common::geo::GeoBoundingBox;
+use odin_server::tile_seeder::{tile_count, tile_ranges, SeedRequest};
+
+#[test]
+fn test_tile_ranges() {
+    let world = GeoBoundingBox::from_wsen_degrees( &[-180.0, -85.0, 179.999, 85.0]);
+    let (xs,ys) = tile_ranges( &world, 2);
+    assert_eq!( xs, vec![0,1,2,3]);
+    assert_eq!( ys, vec![0,1,2,3]);
+
+    // CZU fire area (Santa Cruz mountains) at zoom 10
+    let czu = GeoBoundingBox::from_wsen_degrees( &[-122.40, 37.00, -122.00, 37.30]);
+    let (xs,ys) = tile_ranges( &czu, 10);
+    assert_eq!( xs, vec![163,164]);
+    assert_eq!( ys, vec![397,398]);
+}
+
+#[test]
+fn test_seed_request() {
+    let request = SeedRequest {
+        proxy: "globe-osm".into(),
+        tile_uri: "{z}/{x}/{y}.png".into(),
+        bbox: GeoBoundingBox::from_wsen_degrees( &[-122.40, 37.00, -122.00, 37.30]),
+        min_zoom: 9,
+        max_zoom: 10,
+        concurrency: 2,
+    };
+
+    let tiles: Vec<(u32,u32,u32)> = request.tiles().collect();
+    assert_eq!( tiles.len() as u64, request.total_tiles());
+    assert_eq!( tiles[0], (9,81,198));
+    assert_eq!( request.tile_uri( 10, 163, 397), "10/163/397.png");
+    assert_eq!( request.id(), request.clone().id());
+    assert_eq!( request.id(), "globe-osm-1d2fc1e5e08a59f9"); // ids have to be stable across toolchain versions
+}
+
+#[test]
+fn test_seed_request_limits() {
+    let mut request = SeedRequest {
+        proxy: "globe-osm".into(),
+        tile_uri: "{z}/{x}/{y}.png".into(),
+        bbox: GeoBoundingBox::from_wsen_degrees( &[-180.0, -85.0, 179.999, 85.0]),
+        min_zoom: 0,
+        max_zoom: 8,
+        concurrency: 2,
+    };
+    assert_eq!( request.validate( 18, 100_000).unwrap(), (0..=8).map( |z| 1u64 << (2*z)).sum::<u64>());
+
+    request.max_zoom = 12; // 22M tiles
+    assert!( request.validate( 18, 100_000).is_err());
+
+    request.max_zoom = 40; // beyond what we can represent
+    assert!( request.validate( 50, u64::MAX).is_err());
+    assert_eq!( tile_count( &request.bbox, 40), 0);
+    assert!( tile_ranges( &request.bbox, 40).0.is_empty());
+
+    request.min_zoom = 10;
+    request.max_zoom = 9;
+    assert!( request.validate( 18, u64::MAX).is_err());
+}
@@ -13,6 +13,7 @@
  */
 
 use odin_server::prelude::*;
+use odin_server::ws_service::extract_ws_msg_parts;
 
 define_ws_payload!{ pub Sentinel = 
     pub device_id: String
@@ -31,4 +32,21 @@ fn test_ws_msg()->OdinServerResult<()> {
     println!("{json}");
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_extract_ws_msg_parts() {
+    // parts have to be sliced by their capture ranges, not their lengths (which only works for the first part)
+    let json = r#"{"mod":"odin_sentinel/sentinel_service","sentinels":[{"deviceId":"one"}]}"#;
+    let parts = extract_ws_msg_parts( json).unwrap();
+    assert_eq!( parts.mod_path, "odin_sentinel/sentinel_service");
+    assert_eq!( parts.msg_type, "sentinels");
+    assert_eq!( parts.payload, r#"[{"deviceId":"one"}]"#);
+
+    let json = WsMsg::json( "odin_share/share_service", "removeSharedItem", Sentinel{device_id: "two".into()}).unwrap();
+    let parts = extract_ws_msg_parts( &json).unwrap();
+    assert_eq!( parts.mod_path, "odin_share/share_service");
+    assert_eq!( parts.msg_type, "removeSharedItem");
+
+    assert!( extract_ws_msg_parts( r#"{"type":"ping"}"#).is_none());
+}
@@ -23,7 +23,8 @@ import { ExpandableTreeNode } from "../odin_server/ui_data.js";
 const MOD_PATH = "odin_share::share_service::ShareService";
 
 var sharedCategories = new Map();
-var sharedItems = new Map();
+var sharedItems = new Map(); // key -> SharedItem ({type,comment,owner,data}), only updated from server messages
+var sharedRevs = new Map(); // key -> last known server revision number of item
 
 var shareHandlers = []; // the list of share message handlers set by other modules
 
@@ -31,7 +32,9 @@ ws.addWsHandler( MOD_PATH, handleWsMessages); // we process websocket messages f
 
 var keyEntry = undefined;
 var commentEntry = undefined;
+var ttlEntry = undefined;
 var dataEntry = undefined;
+var historyView = undefined;
 
 compileConfigGlobs();
 createIcon();
@@ -39,6 +42,7 @@ createWindow();
 
 var dirView = initDirView();
 var suffixList = initSuffixList();
+initHistoryView();
 
 //--- end init
 
@@ -72,13 +76,28 @@ function createWindow() {
         ui.Panel("edit item", true)(
             (keyEntry = ui.TextInput( "key","share.obj.key", "30rem", {isFixed: true, placeHolder: "enter item key", changeAction: keyChanged})),
             (commentEntry = ui.TextInput( "comment", "share.obj.cmt", "30rem", {isFixed: true, placeHolder: "enter (optional) item comment"})),
+            (ttlEntry = ui.TextInput( "ttl", "share.obj.ttl", "8rem", {isFixed: true, placeHolder: "e.g. 2h"})),
             (dataEntry = ui.TextArea("share.obj.text", "30rem", "8lh", {isFixed: true})),
             ui.RowContainer()(
                 ui.CheckBox("global", null, "share.obj.cb"),
                 ui.HorizontalSpacer(4),
                 ui.Button("delete", removeItem),
+                ui.Button("rename", renameItem),
                 ui.Button("save", saveItem)
             )
+        ),
+        ui.Panel("item history", false)(
+            (historyView = ui.List("share.history.list", 6, selectHistoryEntry)),
+            ui.RowContainer()(
+                ui.Button("history", requestHistory),
+                ui.Button("revert", revertItem)
+            )
+        ),
+        ui.Panel("GeoJSON", false)(
+            ui.RowContainer()(
+                ui.Button("export", exportGeoJson),
+                ui.Button("import", importGeoJson)
+            )
         )
     );
 }
@@ -105,6 +124,17 @@ function initSuffixList() {
     return view;
 }
 
+function initHistoryView() {
+    let view = ui.getList("share.history.list");
+    if (view) {
+        ui.setListItemDisplayColumns(view, ["fit", "header"], [
+            { name: "rev", tip: "item revision", width: "3rem", attrs: ["alignRight", "small"], map: e => e.rev },
+            { name: "modified", tip: "modification date", width: "10rem", attrs: ["small"], map: e => e.modified ? util.toLocalDateTimeString(new Date(e.modified)) : "initial" },
+            { name: "value", tip: "item value", width: "16rem", attrs: ["small"], map: e => e.value ? JSON.stringify(e.value.data) : "<removed>" }
+        ]);
+    }
+}
+
 // answer if this item is a sealed prefix (for global values), an extensible dir or a value
 function itemCategory (e) {
     if (!e.value) { 
@@ -145,20 +175,24 @@ function selectShareEntry(event) {
         key = e.key;
         if (e.value) {
             ui.setField( commentEntry, e.value.comment);
+            ui.setField( ttlEntry, e.value.ttl);
             ui.setTextAreaContent( dataEntry, JSON.stringify(e.value.data, 0, 2));
         } else { // dir entry
             ui.setField( commentEntry, null);
+            ui.setField( ttlEntry, null);
             ui.setTextAreaContent( dataEntry, null);   
         }
     } else { // no item selected but check for branch nodes
         let node = ui.getSelectedTreeNode(dirView);
         key =  node ? node.collectNamesUp('/') : null;
         ui.setField( commentEntry, null);
+        ui.setField( ttlEntry, null);
         ui.setTextAreaContent( dataEntry, null);    
     }
 
     ui.setField( keyEntry, key);
     setSuffixList(key);
+    ui.setListItems( historyView, null);
 }
 
 function setSuffixList (key) {
@@ -187,19 +221,150 @@ function selectSuffix(event) {
     }
 }
 
+// note that we don't update our view here - all changes (including our own) come back as broadcasts from the server
+
 function removeItem(event) {
-    // TBD
+    let key = ui.getFieldValue(keyEntry);
+    if (key && sharedItems.has(key)) {
+        ws.sendWsMessage( MOD_PATH, "removeSharedItem", { key: key });
+    } else {
+        alert("no shared item to remove: " + key);
+    }
+}
+
+// rename the selected item to the key entered in the key field
+function renameItem(event) {
+    let e = ui.getSelectedListItem(dirView);
+    let newKey = ui.getFieldValue(keyEntry);
+
+    if (e && e.value && newKey && newKey != e.key) {
+        if (!sharedItems.has(newKey) || confirm("replace existing item " + newKey + " ?")) {
+            ws.sendWsMessage( MOD_PATH, "renameSharedItem", { oldKey: e.key, newKey: newKey });
+        }
+    } else {
+        alert("select item and enter new key to rename");
+    }
 }
 
 function saveItem(event) {
-    // TBD
+    let key = ui.getFieldValue(keyEntry);
+    if (!key || key.trim() != key) {
+        alert("invalid item key: '" + key + "'");
+        return;
+    }
+
+    let text = ui.getTextAreaContent(dataEntry);
+    let data = undefined;
+    try {
+        data = JSON.parse(text);
+    } catch (err) {
+        data = text; // not JSON - store as string
+    }
+
+    let type = itemType(key, data);
+    let comment = ui.getFieldValue(commentEntry);
+    let item = {
+        type: type,
+        comment: comment ? comment : null,
+        owner: null,
+        data: (type == "Json") ? JSON.stringify(data) : data
+    };
+
+    // validFrom/validUntil are not editable here but we don't want to lose them
+    let ttl = ui.getFieldValue(ttlEntry);
+    if (ttl) item.ttl = ttl;
+    let oldItem = sharedItems.get(key);
+    if (oldItem) {
+        if (oldItem.validFrom) item.validFrom = oldItem.validFrom;
+        if (oldItem.validUntil) item.validUntil = oldItem.validUntil;
+    }
+
+    // we only overwrite the revision we know about (0 if we don't know the item yet)
+    let expectedRev = sharedRevs.has(key) ? sharedRevs.get(key) : 0;
+    ws.sendWsMessage( MOD_PATH, "setSharedItem", { key: key, item: item, expectedRev: expectedRev });
+}
+
+function requestHistory(event) {
+    let key = ui.getFieldValue(keyEntry);
+    if (key) {
+        ws.sendWsMessage( MOD_PATH, "getSharedItemHistory", { key: key });
+    }
+}
+
+function selectHistoryEntry(event) {
+    let e = ui.getSelectedListItem(historyView);
+    if (e && e.value) {
+        ui.setField( commentEntry, e.value.comment);
+        ui.setTextAreaContent( dataEntry, JSON.stringify(e.value.data, 0, 2));
+    }
+}
+
+function revertItem(event) {
+    let key = ui.getFieldValue(keyEntry);
+    let e = ui.getSelectedListItem(historyView);
+    if (key && e) {
+        ws.sendWsMessage( MOD_PATH, "revertSharedItem", { key: key, rev: e.rev });
+    } else {
+        alert("select item revision to revert to");
+    }
+}
+
+// get the SharedItem variant for a key/data pair - existing items keep their type, otherwise we check the
+// configured typeInfos and finally infer the type from the data
+function itemType(key, data) {
+    let item = sharedItems.get(key);
+    if (item) return item.type;
+
+    for (var e of config.typeInfos) {
+        if (e.type && e.glob && key.match(e.glob)) return e.type;
+    }
+
+    if (typeof data === "number") {
+        return (Number.isInteger(data) && data >= 0) ? "U64" : "F64";
+    } else if (typeof data === "string") {
+        return "String";
+    } else if (Array.isArray(data) && data.every( p=> p && p.lat_deg !== undefined && p.lon_deg !== undefined)) {
+        return "Polyline";
+    } else if (Array.isArray(data) && data.length > 0 && data.every( p=> p && p.pos !== undefined && p.date !== undefined)) {
+        return "Track";
+    } else if (data && data.exterior !== undefined) {
+        return "Polygon";
+    } else if (data && data.polygons !== undefined) {
+        return "MultiPolygon";
+    } else if (data && data.center !== undefined && data.radius !== undefined) {
+        return "Circle";
+    } else if (data && data.west !== undefined && data.south !== undefined && data.east !== undefined && data.north !== undefined) {
+        return "BoundingBox";
+    } else if (data && data.start !== undefined && data.end !== undefined) {
+        return "TimeRange";
+    } else if (data && data.type === "FeatureCollection") {
+        return "GeoJson";
+    } else if (data && data.lat !== undefined && data.lon !== undefined && data.alt !== undefined) {
+        return "Point3D";
+    } else if (data && data.lat_deg !== undefined && data.lon_deg !== undefined) {
+        return "Point2D";
+    } else {
+        return "Json";
+    }
 }
 
 // this is how we get data and/or sync operations from the server
 function handleWsMessages(msgType, msg) {
     switch (msgType) {
         case "initSharedItems": initSharedItems(msg); break;
-        default: console.log("ignoring unknown share message of type: ", msgType);
+        case "setSharedItem": setSharedItem(msg); break;
+        case "removeSharedItem": removeSharedItem(msg); break;
+        case "renameSharedItem": renameSharedItem(msg); break;
+        case "sharedItemHistory": setSharedItemHistory(msg); return;
+        case "sharedItemConflict": reportConflict(msg); return;
+        case "sharedItemRejected": reportRejection(msg); return;
+        case "geoJson": saveGeoJson(msg); return;
+        case "sharedItemsAt": break; // only of interest for share handlers (see requestSharedItemsAt)
+        default: console.log("ignoring unknown share message of type: ", msgType); return;
+    }
+
+    for (var h of shareHandlers) {
+        h(msgType, msg);
     }
 }
 
@@ -207,15 +372,111 @@ export function addShareHandler(newHandler) {
     shareHandlers.push( newHandler);
 }
 
+// ask the server for items that were (or will be) valid at the given time. The response is a "sharedItemsAt" message
+// that is passed to the share handlers
+export function requestSharedItemsAt(pattern, date) {
+    ws.sendWsMessage( MOD_PATH, "getSharedItemsAt", { pattern: pattern, time: date.toISOString() });
+}
+
+// the initSharedItems payload maps keys to {item,rev} objects
 function initSharedItems(o) {
+    sharedItems.clear();
+    sharedRevs.clear();
+    for (var [key,e] of Object.entries(o)) {
+        sharedItems.set( key, e.item);
+        if (e.rev) sharedRevs.set( key, e.rev.rev);
+    }
+    updateDirView();
+}
+
+function setSharedItem(msg) {
+    sharedItems.set( msg.key, msg.item);
+    if (msg.rev) sharedRevs.set( msg.key, msg.rev.rev);
+    updateDirView();
+}
+
+function removeSharedItem(msg) {
+    sharedRevs.delete( msg.key);
+    if (sharedItems.delete( msg.key)) {
+        updateDirView();
+    }
+}
+
+function renameSharedItem(msg) {
+    sharedItems.delete( msg.oldKey);
+    sharedRevs.delete( msg.oldKey);
+    if (msg.item) {
+        sharedItems.set( msg.newKey, msg.item);
+        if (msg.rev) sharedRevs.set( msg.newKey, msg.rev.rev);
+    }
+    updateDirView();
+}
+
+function setSharedItemHistory(msg) {
+    if (msg.key == ui.getFieldValue(keyEntry)) {
+        ui.setListItems( historyView, msg.revisions.slice().reverse()); // show newest first
+    }
+}
+
+function reportConflict(msg) {
+    alert(`item ${msg.key} was changed by another user (revision ${msg.current}, expected ${msg.expected}).\n` +
+          "Check the current value and save again to overwrite.");
+}
+
+function reportRejection(msg) {
+    alert(`item ${msg.key} was rejected: ${msg.reason}`);
+}
+
+// export geospatial items under the current key prefix (or all of them if there is none)
+function exportGeoJson(event) {
+    let prefix = ui.getFieldValue(keyEntry);
+    ws.sendWsMessage( MOD_PATH, "exportGeoJson", { prefix: prefix ? prefix : null });
+}
+
+function saveGeoJson(featureCollection) {
+    let blob = new Blob( [JSON.stringify(featureCollection, null, 2)], {type: "application/geo+json"});
+    const link = document.createElement('a');
+    link.href = URL.createObjectURL(blob);
+    link.download = "shared_items.geojson";
+    document.body.appendChild(link);
+    link.click();
+    document.body.removeChild(link);
+    URL.revokeObjectURL(link.href);
+}
+
+// import features from a local GeoJSON file. Features without "key" property are stored under the current key prefix
+function importGeoJson(event) {
+    let prefix = ui.getFieldValue(keyEntry) || "import/";
+    if (!prefix.endsWith("/")) prefix += "/";
+
+    const input = document.createElement('input');
+    input.type = "file";
+    input.accept = ".geojson,.json";
+    input.onchange = () => {
+        let file = input.files[0];
+        if (file) {
+            file.text().then( text => {
+                try {
+                    let collection = JSON.parse(text);
+                    if (collection.type === "Feature") collection = { type: "FeatureCollection", features: [collection] };
+                    ws.sendWsMessage( MOD_PATH, "importGeoJson", { prefix: prefix, collection: collection });
+                } catch (e) {
+                    alert(`not a valid GeoJSON file: ${e}`);
+                }
+            });
+        }
+    };
+    input.click();
+}
+
+function updateDirView() {
     let items = config.categories.slice();
 
-    for (var e of Object.entries(o)) {
-        let item = { key: e[0], global: true, value: e[1] };
+    for (var [key,value] of sharedItems) {
+        let item = { key: key, global: true, value: value };
         items.push(item);
     }
 
     let tree = ExpandableTreeNode.from( items, e=>e.key );
     ui.setTree( dirView, tree);
 }
-
@@ -13,31 +13,54 @@ export const config = {
 
     completions: [
         { pattern: "incident",
-            completion: ["/●/view", "/●/origin", "/●/bbox"]
+            completion: ["/●/view", "/●/origin", "/●/bbox", "/●/perimeter"]
         },
         { pattern: "incident/*",
-            completion: ["/view", "/origin", "/bbox"]
+            completion: ["/view", "/origin", "/bbox", "/perimeter"]
         },
         { pattern: "{bbox,point,view}",
             completion: ["/●"]
         }
     ],
 
-    // associates key glob patterns with (server) types tags and Javascript template objects
+    // associates key glob patterns with (server) types tags, SharedItem variant types and Javascript template objects
     // type tags can be empty (or omitted) in which case the server side just stores the data as JSON strings
     // template objects are used to generate JSON templates and check user input 
     typeInfos: [
         { pattern: "{view/**,**/view/**,**/view}",    
             tag: "odin_common::geo::GeoPos", 
+            type: "Point3D",
             template: {lat: 0.0, lon: 0.0, alt: 0.0} 
         },
-        { pattern: "{point/**,**/point/**,**/point}", 
+        { pattern: "{point/**,**/point/**,**/point,**/origin}", 
             tag: "odin_common::geo::LatLon", 
-            template: {lat: 0.0, lon: 0.0} 
+            type: "Point2D",
+            template: {lat_deg: 0.0, lon_deg: 0.0} 
         },
         { pattern: "{bbox/**,**/bbox/**,**/bbox}",    
             tag: "odin_common::geo::GeoBoundingBox", 
+            type: "BoundingBox",
             template: {west: 0.0, south: 0.0, east: 0.0, north: 0.0} 
+        },
+        { pattern: "{**/perimeter,**/perimeter/**,**/area}",
+            tag: "odin_share::geo_items::Polygon",
+            type: "Polygon",
+            template: {exterior: [{lat_deg: 0.0, lon_deg: 0.0}], holes: []}
+        },
+        { pattern: "{**/circle,**/circle/**}",
+            tag: "odin_share::geo_items::Circle",
+            type: "Circle",
+            template: {center: {lat_deg: 0.0, lon_deg: 0.0}, radius: 1000.0}
+        },
+        { pattern: "{**/track,**/track/**}",
+            tag: "odin_common::geo::DatedGeoPos",
+            type: "Track",
+            template: [{pos: {lat: 0.0, lon: 0.0, alt: 0.0}, date: "2024-01-01T00:00:00Z"}]
+        },
+        { pattern: "{**/period,**/time_range}",
+            tag: "odin_share::geo_items::TimeRange",
+            type: "TimeRange",
+            template: {start: "2024-01-01T00:00:00Z", end: "2024-01-01T00:00:00Z"}
         }
     ]
 }
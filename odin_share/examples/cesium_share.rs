@@ -42,12 +42,8 @@ run_actor_system!( actor_system => {
                 Ok( hserver.try_send_msg( DataAvailable{sender_id:"store",data_type: type_name::<SharedItem>()} )? )
             }
         ),
-        data_action!( let hserver: ActorHandle<SpaServerMsg> = hserver.clone() => 
-            |update: SharedStoreChange<SharedItem>| {
-                Ok(())
-            }
-        )
-    ))?;
+        share_change_action( hserver.clone())
+    ).with_validator( SharedItem::validate).with_validity( SharedItem::validity, SharedItem::set_validity))?;
 
     Ok(())
 });
@@ -58,6 +54,7 @@ fn create_store()->HashMap<String,SharedItem> {
             SharedItemValue {
                 comment: None,
                 owner: None,
+                validity: Validity::default(),
                 data: Arc::new(GeoPos::new( LatAngle::from_degrees(38.15910), LonAngle::from_degrees(-122.67800), 800000.0))
             }
         )),
@@ -65,6 +62,7 @@ fn create_store()->HashMap<String,SharedItem> {
             SharedItemValue {
                 comment: Some("origin of fire at blabla".to_string()),
                 owner: None,
+                validity: Validity::default(),
                 data: Arc::new(LatLon::from_degrees( 37.137, -122.2854))
             }
         )),
@@ -72,8 +70,17 @@ fn create_store()->HashMap<String,SharedItem> {
             SharedItemValue {
                 comment: Some("preliminary".to_string()),
                 owner: None,
+                validity: Validity::default(),
                 data: Arc::new("dry lightning".to_string())
             }
         )),
+        ("incident/czu/staging".to_string(), SharedItem::Circle(
+            SharedItemValue {
+                comment: Some("temporary staging area".to_string()),
+                owner: None,
+                validity: Validity { ttl: Some( std::time::Duration::from_secs(3600)), ..Validity::default() },
+                data: Arc::new( Circle{ center: LatLon::from_degrees( 37.17, -122.22), radius: 300.0 })
+            }
+        )),
     ])
 }
\ No newline at end of file
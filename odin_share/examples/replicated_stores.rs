@@ -0,0 +1,76 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use odin_actor::prelude::*;
+use odin_share::prelude::*;
+
+/// example of two replicated SharedStoreActors. In real applications these would run in different ODIN servers,
+/// here we connect them through the loopback interface
+
+type Item = String;
+
+struct Tester {
+    hstore_a: ActorHandle<SharedStoreActorMsg<Item>>,
+    hstore_b: ActorHandle<SharedStoreActorMsg<Item>>,
+}
+
+define_actor_msg_set! { TesterMsg }
+
+impl_actor! { match msg for Actor<Tester,TesterMsg> as
+    _Start_ => cont! {
+        println!("setting value in store A");
+        let set = SetSharedStoreValue { key: "incident/czu/origin".into(), value: "37.137,-122.2854".into(), expected_rev: None, requester: Requester::System };
+        self.hstore_a.send_msg( set).await;
+        self.start_oneshot_timer( 1, secs(2));
+    }
+    _Timer_ => term! {
+        match timeout_query_ref( &self.hstore_b, "incident/czu/origin".to_string(), secs(1)).await {
+            Ok(Some(v)) => println!("store B got replicated value: {v}"),
+            Ok(None) => println!("value was not replicated to store B yet"),
+            Err(e) => println!("query failed: {e}")
+        }
+    }
+}
+
+fn config (node_id: &str, listen: Option<SocketAddr>, peers: Vec<String>)->ReplicationConfig {
+    ReplicationConfig { node_id: node_id.into(), listen, peers, token: Some("secret".into()), reconnect_interval: secs(5) }
+}
+
+run_actor_system!( asys => {
+    let addr: SocketAddr = "127.0.0.1:9100".parse()?;
+
+    //--- node A (accepts peer connections)
+    let pre_repl_a = PreActorHandle::<ShareReplicatorMsg<Item>>::new( &asys, "replicator_a", 64);
+    let hstore_a = spawn_actor!( asys, "store_a", SharedStoreActor::new(
+        HashMap::new(),
+        no_shared_store_action(),
+        replicate_changes( pre_repl_a.to_actor_handle(), no_data_action())
+    ))?;
+    spawn_pre_actor!( asys, pre_repl_a, ShareReplicator::new( config( "a", Some(addr), vec![]), hstore_a.clone()))?;
+
+    //--- node B (connects to node A)
+    let pre_repl_b = PreActorHandle::<ShareReplicatorMsg<Item>>::new( &asys, "replicator_b", 64);
+    let hstore_b = spawn_actor!( asys, "store_b", SharedStoreActor::new(
+        HashMap::new(),
+        no_shared_store_action(),
+        replicate_changes( pre_repl_b.to_actor_handle(), no_data_action())
+    ))?;
+    spawn_pre_actor!( asys, pre_repl_b, ShareReplicator::new( config( "b", None, vec![format!("ws://{addr}")]), hstore_b.clone()))?;
+
+    spawn_actor!( asys, "tester", Tester{ hstore_a, hstore_b })?;
+
+    Ok(())
+});
@@ -0,0 +1,93 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use odin_actor::prelude::*;
+use odin_action::no_data_action;
+use odin_common::geo::LatLon;
+use std::{sync::Arc, collections::HashMap};
+use odin_share::prelude::*;
+
+//--- an analysis actor that subscribes to ignition points (e.g. to run a wind simulation for them)
+
+struct IgnitionAnalyzer {
+    hstore: ActorHandle<SharedStoreActorMsg<SharedItem>>,
+    n_updates: usize
+}
+
+define_actor_msg_set! { IgnitionAnalyzerMsg = SharedStoreUpdate<SharedItem> }
+
+impl_actor! { match msg for Actor<IgnitionAnalyzer,IgnitionAnalyzerMsg> as 
+    _Start_ => cont! {
+        let subscribe = SubscribeSharedStoreChanges { 
+            glob_pattern: "incidents/*/origin".into(), 
+            with_value: true, 
+            subscriber: self.hself.clone().into() 
+        };
+        self.hstore.send_msg( subscribe).await;
+    }
+    SharedStoreUpdate<SharedItem> => {
+        match msg {
+            SharedStoreUpdate{ change: SharedStoreChange::Set{key,..}, value: Some(SharedItem::Point2D(p)), .. } => {
+                println!("analyzer: new ignition point for {key}: {:?}, starting simulation..", p.data)
+            }
+            SharedStoreUpdate{ change, .. } => println!("analyzer: ignoring {change:?}")
+        }
+        self.n_updates += 1;
+        if self.n_updates < 2 { ReceiveAction::Continue } else { ReceiveAction::RequestTermination }
+    }
+}
+
+//--- a stand-in for a user who moves an ignition point
+
+struct User {
+    hstore: ActorHandle<SharedStoreActorMsg<SharedItem>>
+}
+
+#[derive(Debug)] struct MoveOrigin(f64,f64);
+
+define_actor_msg_set! { UserMsg = MoveOrigin }
+
+impl_actor! { match msg for Actor<User,UserMsg> as
+    _Start_ => cont! {
+        self.hself.send_msg( MoveOrigin( 37.137, -122.2854)).await;
+        self.hself.send_msg( MoveOrigin( 37.141, -122.2901)).await;
+    }
+    MoveOrigin => cont! {
+        sleep( millis(500)).await;
+        let value = SharedItem::Point2D( SharedItemValue::new( LatLon::from_degrees( msg.0, msg.1)));
+        let set = SetSharedStoreValue { key: "incidents/czu/origin".into(), value, expected_rev: None, requester: Requester::System };
+        self.hstore.send_msg( set).await;
+
+        // this one is not reported to the analyzer
+        let value = SharedItem::String( SharedItemValue::new( "dry lightning".to_string()));
+        let set = SetSharedStoreValue { key: "incidents/czu/cause".into(), value, expected_rev: None, requester: Requester::System };
+        self.hstore.send_msg( set).await;
+    }
+}
+
+//--- the system construction
+
+run_actor_system!( asys => {
+    let hstore = spawn_actor!( asys, "store", SharedStoreActor::new(
+        HashMap::<String,SharedItem>::new(),
+        no_shared_store_action(),
+        no_data_action()
+    ))?;
+
+    let analyzer = spawn_actor!( asys, "analyzer", IgnitionAnalyzer{ hstore: hstore.clone(), n_updates: 0 })?;
+    let user = spawn_actor!( asys, "user", User{ hstore })?;
+
+    Ok(())
+});
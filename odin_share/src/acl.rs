@@ -0,0 +1,146 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the acl module implements path-based access control for the key space of shared stores. Rules associate key
+//! glob patterns with the roles that are allowed to read or write matching items, e.g.
+//! ```ron
+//! KeyAclConfig(
+//!     rules: [
+//!         AclRule( pattern: "incidents/czu/**", read: ["public"], write: ["ops"] ),
+//!         AclRule( pattern: "internal/**", read: ["ops"], write: ["admin"] ),
+//!     ],
+//!     user_roles: { "jane": ["ops"], "joe": ["ops","admin"] }
+//! )
+//! ```
+//! The first rule with a matching pattern is used. Keys that don't match any rule are readable and writable by
+//! everybody. Each user (including anonymous ones) has the `public` role, users that can write a key can also read it.
+//!
+//! User names are the ones provided by the `SpaUser` of the (authenticated) connection. Store operations that are
+//! not initiated by clients (e.g. replication or imports) use `Requester::System` and are not subject to access control.
+
+use std::collections::HashMap;
+use globset::{Glob, GlobMatcher};
+use serde::{Serialize,Deserialize};
+
+use crate::errors::{OdinShareError, Result};
+
+pub const PUBLIC_ROLE: &str = "public";
+
+/// the identity on whose behalf a store operation is requested. There is intentionally no `Default` - since `System`
+/// requests bypass access control callers always have to name the requester explicitly
+#[derive(Debug,Clone,PartialEq)]
+pub enum Requester {
+    /// server-internal operations, which are not checked
+    System,
+
+    /// client operations of a (possibly anonymous) user
+    User(Option<String>)
+}
+
+impl Requester {
+    pub fn user (user: Option<&str>)->Self {
+        Requester::User( user.map( |s| s.to_string()))
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct AclRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub read: Vec<String>,
+    #[serde(default)]
+    pub write: Vec<String>
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+pub struct KeyAclConfig {
+    pub rules: Vec<AclRule>,
+    #[serde(default)]
+    pub user_roles: HashMap<String,Vec<String>>
+}
+
+/// the compiled `KeyAclConfig`
+#[derive(Debug,Clone,Default)]
+pub struct KeyAcl {
+    rules: Vec<(GlobMatcher,AclRule)>,
+    user_roles: HashMap<String,Vec<String>>
+}
+
+impl KeyAcl {
+    pub fn new (config: KeyAclConfig)->Result<Self> {
+        let mut rules = Vec::with_capacity( config.rules.len());
+        for rule in config.rules {
+            let glob = Glob::new( &rule.pattern)?.compile_matcher();
+            rules.push( (glob, rule));
+        }
+        Ok( KeyAcl { rules, user_roles: config.user_roles } )
+    }
+
+    /// an ACL without rules, i.e. everything can be read and written by everybody
+    pub fn open ()->Self {
+        KeyAcl::default()
+    }
+
+    pub fn is_open (&self)->bool {
+        self.rules.is_empty()
+    }
+
+    fn rule (&self, key: &str)->Option<&AclRule> {
+        self.rules.iter().find( |(glob,_)| glob.is_match( key)).map( |(_,rule)| rule)
+    }
+
+    fn has_role (&self, user: Option<&str>, roles: &Vec<String>)->bool {
+        roles.iter().any( |role| {
+            role == PUBLIC_ROLE || user.and_then( |u| self.user_roles.get(u)).map( |ur| ur.contains( role)).unwrap_or(false)
+        })
+    }
+
+    pub fn can_read (&self, user: Option<&str>, key: &str)->bool {
+        match self.rule( key) {
+            Some(rule) => self.has_role( user, &rule.read) || self.has_role( user, &rule.write),
+            None => true
+        }
+    }
+
+    pub fn can_write (&self, user: Option<&str>, key: &str)->bool {
+        match self.rule( key) {
+            Some(rule) => self.has_role( user, &rule.write),
+            None => true
+        }
+    }
+
+    /// can `key` be read by everybody (in which case we don't need to check connections before sending items)
+    pub fn is_public_read (&self, key: &str)->bool {
+        self.can_read( None, key)
+    }
+
+    pub fn check_read (&self, requester: &Requester, key: &str)->Result<()> {
+        match requester {
+            Requester::User(user) if !self.can_read( user.as_deref(), key) => Err( access_denied( key, user)),
+            _ => Ok(())
+        }
+    }
+
+    pub fn check_write (&self, requester: &Requester, key: &str)->Result<()> {
+        match requester {
+            Requester::User(user) if !self.can_write( user.as_deref(), key) => Err( access_denied( key, user)),
+            _ => Ok(())
+        }
+    }
+}
+
+fn access_denied (key: &str, user: &Option<String>)->OdinShareError {
+    OdinShareError::AccessDenied{ key: key.to_string(), user: user.clone() }
+}
@@ -17,7 +17,7 @@ use odin_action::{DataAction,DynDataRefAction};
 use odin_actor::prelude::*;
 use odin_actor::errors;
 
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 use std::{ collections::HashMap, path::Path, fs::File, io::BufReader, io, fmt::Debug };
 use serde::{Serialize,Deserialize};
 use serde_json;
@@ -26,6 +26,13 @@ use odin_common::fs;
 use crate::errors::op_failed;
 use crate::errors::OdinShareError;
 use crate::{SharedStore,SharedStoreAction,DynSharedStoreAction,SharedStoreValueConstraints};
+use crate::revisions::{RevisionedStore,SharedStoreRevision,ItemRevision,DEFAULT_MAX_HISTORY};
+use crate::acl::{KeyAcl,Requester};
+use crate::validity::Validity;
+use odin_job::JobHandle;
+use odin_actor::errors::OdinActorError;
+use globset::GlobMatcher;
+use chrono::{DateTime,Utc};
 
 /// message type to announce changes to clients of a SharedStore. Note this does not include the
 /// changed value, which might be expensive to clone
@@ -33,87 +40,443 @@ use crate::{SharedStore,SharedStoreAction,DynSharedStoreAction,SharedStoreValueC
 pub enum SharedStoreChange<T> where T: SharedStoreValueConstraints {
     Set { hstore: ActorHandle<SharedStoreActorMsg<T>>, key: String },
     Remove { hstore: ActorHandle<SharedStoreActorMsg<T>>, key: String },
-    // TODO - add more ops, like Rename
+    Rename { hstore: ActorHandle<SharedStoreActorMsg<T>>, old_key: String, new_key: String },
 }
 
-/// the state of an actor that encapsulates a SharedStore impl
-pub struct SharedStoreActor<T,S,I,C> where T: SharedStoreValueConstraints, S: SharedStore<T>, I: SharedStoreAction<T> + Send, C: DataAction<SharedStoreChange<T>> {
-    store: S,
+/// the message that is sent to subscribers of store changes (see `SubscribeSharedStoreChanges`) and the data of the
+/// change action. The value is only included if requested by the subscriber and is `None` for removals. The change
+/// action always gets the value and revision of set or renamed items since it can't query the store it runs in
+#[derive(Debug,Clone)]
+pub struct SharedStoreUpdate<T> where T: SharedStoreValueConstraints {
+    pub change: SharedStoreChange<T>,
+    pub value: Option<T>,
+    pub rev: Option<ItemRevision>
+}
+
+/// a runtime subscription for changes of keys that match a glob pattern
+struct Subscription<T> where T: SharedStoreValueConstraints {
+    glob: GlobMatcher,
+    glob_pattern: String,
+    with_value: bool,
+    subscriber: DynMsgReceiver<SharedStoreUpdate<T>>
+}
+
+impl<T> Subscription<T> where T: SharedStoreValueConstraints {
+    /// the key of the (current) value to send if the change matches. Renames match on both keys
+    fn matching_key<'a> (&self, change: &'a SharedStoreChange<T>)->Option<&'a String> {
+        match change {
+            SharedStoreChange::Set{key,..} | SharedStoreChange::Remove{key,..} => {
+                if self.glob.is_match( key) { Some(key) } else { None }
+            }
+            SharedStoreChange::Rename{old_key,new_key,..} => {
+                if self.glob.is_match( new_key) || self.glob.is_match( old_key) { Some(new_key) } else { None }
+            }
+        }
+    }
+}
+
+/// how long we wait for a subscriber to accept an update before we drop its subscription. Subscribers must not miss
+/// updates silently, and we can't block the store on a stuck subscriber
+pub const SUBSCRIBER_SEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// a scheduled removal of an expiring value
+struct Expiration {
+    expires: DateTime<Utc>,
+    job: JobHandle
+}
+
+/// the state of an actor that encapsulates a SharedStore impl. The store is wrapped into a `RevisionedStore`
+/// that keeps track of value revisions and a bounded change history for each key
+pub struct SharedStoreActor<T,S,I,C> where T: SharedStoreValueConstraints, S: SharedStore<T>, I: SharedStoreAction<T> + Send, C: DataAction<SharedStoreUpdate<T>> {
+    store: RevisionedStore<T,S>,
     init_action: I,
     change_action: C,
+    validator: Option<fn(&T)->Result<(),OdinShareError>>,
+    acl: Arc<KeyAcl>,
+    validity: Option<fn(&T)->Validity>,
+    set_validity: Option<fn(&mut T,Validity)>,
+    expirations: HashMap<String,Expiration>,
+    subscriptions: Vec<Subscription<T>>,
 
     phantom_t: PhantomData<T>
 }
 
 impl <T,S,I,C> SharedStoreActor<T,S,I,C> 
-    where T: SharedStoreValueConstraints, S: SharedStore<T>, I: SharedStoreAction<T> + Send, C: DataAction<SharedStoreChange<T>>
+    where T: SharedStoreValueConstraints, S: SharedStore<T>, I: SharedStoreAction<T> + Send, C: DataAction<SharedStoreUpdate<T>>
 {
     pub fn new (store: S, init_action: I, change_action: C)->Self {
-        SharedStoreActor { store, init_action, change_action, phantom_t: PhantomData }
+        let store = RevisionedStore::new( store, DEFAULT_MAX_HISTORY);
+        SharedStoreActor { store, init_action, change_action, validator: None, acl: Arc::new( KeyAcl::open()), 
+            validity: None, set_validity: None, expirations: HashMap::new(), subscriptions: Vec::new(), phantom_t: PhantomData }
+    }
+
+    /// set the number of revisions we keep per key (default is `revisions::DEFAULT_MAX_HISTORY`)
+    pub fn with_max_history (mut self, max_history: usize)->Self {
+        self.store.set_max_history( max_history);
+        self
+    }
+
+    /// set how many removed keys we keep the history for and for how long (defaults are
+    /// `revisions::DEFAULT_MAX_REMOVED_KEYS` and `revisions::DEFAULT_REMOVED_RETENTION`)
+    pub fn with_removed_retention (mut self, max_removed_keys: usize, removed_retention: Duration)->Self {
+        self.store.set_removed_retention( max_removed_keys, removed_retention);
+        self
+    }
+
+    /// set the key space access control rules for client requests (default is no restrictions)
+    pub fn with_acl (mut self, acl: Arc<KeyAcl>)->Self {
+        self.acl = acl;
+        self
+    }
+
+    /// set a function that checks values before they are stored. Invalid values are rejected with the validator error
+    pub fn with_validator (mut self, validator: fn(&T)->Result<(),OdinShareError>)->Self {
+        self.validator = Some(validator);
+        self
     }
 
-    async fn initialize (&mut self)->Result<(),OdinShareError> {
+    /// set functions to get and set the validity of values. If set, the actor removes values once they expire (using the
+    /// job scheduler of the actor system) and `ValidSharedStoreValuesQuery`s only return values valid at the given time.
+    /// Time-to-live validities of new values are replaced by absolute expiration times so that they are preserved if the
+    /// store is persisted and re-loaded
+    pub fn with_validity (mut self, validity: fn(&T)->Validity, set_validity: fn(&mut T,Validity))->Self {
+        self.validity = Some(validity);
+        self.set_validity = Some(set_validity);
+        self
+    }
+
+    async fn initialize (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>)->Result<(),OdinShareError> {
         self.store.initialize().await?;
+        if self.validity.is_some() {
+            let keys: Vec<String> = self.store.ref_iter().map( |(k,_)| k.clone()).collect();
+            for key in &keys { self.update_expiration( &hself, key) }
+        }
         self.init_action.execute( &self.store as &dyn SharedStore<T>).await.map_err(|e| op_failed("init action failed {e}"))
     }
 
-    async fn set (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>, key: String, value: T) {
-        if self.change_action.is_empty() {
-            self.store.insert( key, value);
-        } else {
-            let change = SharedStoreChange::Set{ hstore: hself, key: key.clone() };
-            self.store.insert( key, value);
-            self.change_action.execute(change).await;
+    /// set the value for `key` if there is no `expected_rev` or it matches the current revision, returning the new revision.
+    /// Note that an `expected_rev` of 0 means there must not be a current value for `key`
+    async fn set (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>, requester: &Requester, key: String, mut value: T, expected_rev: Option<u64>)->Result<u64,OdinShareError> {
+        self.acl.check_write( requester, &key)?;
+        if let Some(expected) = expected_rev {
+            let current = self.store.current_rev( &key);
+            if current != expected {
+                return Err( OdinShareError::RevisionConflict{ key, expected, current })
+            }
+        }
+        if let Some(validator) = &self.validator {
+            validator( &value)?;
+        }
+        if let (Some(validity_of), Some(set_validity)) = (self.validity, self.set_validity) {
+            let validity = validity_of( &value);
+            if validity.ttl.is_some() { set_validity( &mut value, validity.to_absolute( Utc::now())) }
         }
+
+        self.store.insert( key.clone(), value)?; // no revision, expiration or change notification if this failed
+        self.update_expiration( &hself, &key);
+
+        self.publish_change( SharedStoreChange::Set{ hstore: hself, key: key.clone() }).await;
+        Ok( self.store.current_rev( &key) )
     }
 
-    async fn remove (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>, key: String) {
-        if self.change_action.is_empty() {
-            self.store.remove( &key);
+    /// re-apply the value of an earlier revision, which creates a new revision
+    async fn revert (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>, requester: &Requester, key: String, rev: u64)->Result<u64,OdinShareError> {
+        match self.store.history( &key).into_iter().find( |r| r.rev == rev) {
+            Some(SharedStoreRevision{ value: Some(value), .. }) => self.set( hself, requester, key, value, None).await,
+            Some(SharedStoreRevision{ value: None, .. }) => {
+                self.remove( hself, requester, key.clone()).await?;
+                Ok( self.store.current_rev( &key))
+            }
+            None => Err( op_failed( format!("no revision {rev} for {key}")))
+        }
+    }
+
+    async fn remove (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>, requester: &Requester, key: String)->Result<(),OdinShareError> {
+        self.acl.check_write( requester, &key)?;
+
+        self.store.remove( &key)?;
+        self.update_expiration( &hself, &key);
+
+        self.publish_change( SharedStoreChange::Remove{ hstore: hself, key: key.clone() }).await;
+        Ok(())
+    }
+
+    /// called when an expiration job fires. The value might have been changed in the meantime, hence we have to
+    /// check if this is still the current expiration of `key`
+    async fn expire (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>, key: String, expires: DateTime<Utc>)->Result<(),OdinShareError> {
+        if self.expirations.get( &key).map_or( false, |e| e.expires == expires) {
+            self.remove( hself, &Requester::System, key).await
         } else {
-            let change = SharedStoreChange::Remove{ hstore: hself, key: key.clone() };
-            self.store.remove( &key);
-            self.change_action.execute(change).await;
+            Ok(())
+        }
+    }
+
+    /// notify matching subscribers and execute the change action. Subscribers that are gone or don't accept the update
+    /// within `SUBSCRIBER_SEND_TIMEOUT` are removed
+    async fn publish_change (&mut self, change: SharedStoreChange<T>) {
+        let value_key = match &change {
+            SharedStoreChange::Set{key,..} => Some(key),
+            SharedStoreChange::Rename{new_key,..} => Some(new_key),
+            SharedStoreChange::Remove{..} => None
+        };
+        let rev = value_key.and_then( |k| self.store.revision( k));
+
+        if !self.subscriptions.is_empty() {
+            let mut failed: Vec<usize> = Vec::new();
+            for (i,sub) in self.subscriptions.iter().enumerate() {
+                if let Some(key) = sub.matching_key( &change) {
+                    let value = if sub.with_value { self.store.get( key).map( |v| v.clone()) } else { None };
+                    let update = SharedStoreUpdate{ change: change.clone(), value, rev };
+                    if let Err(e) = sub.subscriber.timeout_send_msg( update, SUBSCRIBER_SEND_TIMEOUT).await {
+                        warn!("removing subscription {} of {}: {e}", sub.glob_pattern, sub.subscriber.id());
+                        failed.push( i);
+                    }
+                }
+            }
+            for i in failed.into_iter().rev() { self.subscriptions.remove( i); }
         }
+
+        if !self.change_action.is_empty() {
+            let value = value_key.and_then( |k| self.store.get( k).map( |v| v.clone()));
+            if let Err(e) = self.change_action.execute( SharedStoreUpdate{ change, value, rev }).await {
+                warn!("store change action failed: {e}");
+            }
+        }
+    }
+
+    fn subscribe (&mut self, glob_pattern: String, with_value: bool, subscriber: DynMsgReceiver<SharedStoreUpdate<T>>)->Result<(),OdinShareError> {
+        let glob = globset::Glob::new( &glob_pattern)?.compile_matcher();
+        self.subscriptions.push( Subscription{ glob, glob_pattern, with_value, subscriber });
+        Ok(())
+    }
+
+    fn unsubscribe (&mut self, subscriber_id: &str, glob_pattern: &Option<String>) {
+        self.subscriptions.retain( |sub| {
+            sub.subscriber.id() != subscriber_id || glob_pattern.as_ref().map_or( false, |p| *p != sub.glob_pattern)
+        });
+    }
+
+    /// (re-)schedule or cancel the expiration of `key` according to the validity of its current value
+    fn update_expiration (&mut self, hself: &ActorHandle<SharedStoreActorMsg<T>>, key: &str) {
+        let Some(validity_of) = self.validity else { return };
+
+        if let Some(Expiration{job,..}) = self.expirations.remove( key) {
+            if let Ok(mut scheduler) = hself.get_scheduler() { scheduler.abort_job( job); }
+        }
+
+        if let Some(value) = self.store.get( key) {
+            let modified = self.store.revision( key).and_then( |r| r.modified).unwrap_or_else( Utc::now);
+            if let Some(expires) = validity_of( value).expiration( modified) {
+                if let Ok(mut scheduler) = hself.get_scheduler() {
+                    let msg_hself = hself.clone();
+                    let msg_key = key.to_string();
+                    let action = move |_: &mut odin_job::JobContext| {
+                        let msg = ExpireSharedStoreValue{ key: msg_key.clone(), expires };
+                        if let Err(OdinActorError::ReceiverFull) = msg_hself.try_send_msg( msg.clone()) {
+                            // don't block the scheduler but make sure the expiration isn't lost
+                            let hself = msg_hself.clone();
+                            if let Err(e) = spawn( "expire-value", async move { hself.send_msg( msg).await }) {
+                                warn!("failed to send expiration of {}: {e}", msg_key);
+                            }
+                        }
+                    };
+                    match scheduler.schedule_at( &expires, action) {
+                        Ok(job) => { self.expirations.insert( key.to_string(), Expiration{ expires, job }); }
+                        Err(e) => warn!("failed to schedule expiration of {key}: {e:?}")
+                    }
+                }
+            }
+        }
+    }
+
+    /// the (key,value) pairs matching `glob_pattern` that are valid at `time`
+    fn valid_values (&self, requester: &Requester, glob_pattern: &str, time: DateTime<Utc>)->Result<Vec<(String,T)>,OdinShareError> {
+        let values = self.store.glob_ref_iter( glob_pattern)?
+            .filter( |(k,_)| self.acl.check_read( requester, k).is_ok())
+            .filter( |(k,v)| {
+                match self.validity {
+                    Some(validity_of) => {
+                        let modified = self.store.revision( k).and_then( |r| r.modified).unwrap_or( time);
+                        validity_of(v).is_valid_at( time, modified)
+                    }
+                    None => true
+                }
+            })
+            .map( |(k,v)| (k.clone(), v.clone()))
+            .collect();
+        Ok(values)
+    }
+
+    /// move the value of `old_key` to `new_key`, replacing any previous `new_key` value. This is a no-op if there is
+    /// no `old_key` value
+    async fn rename (&mut self, hself: ActorHandle<SharedStoreActorMsg<T>>, requester: &Requester, old_key: String, new_key: String)->Result<(),OdinShareError> {
+        if old_key == new_key { return Ok(()) }
+        self.acl.check_write( requester, &old_key)?;
+        self.acl.check_write( requester, &new_key)?;
+
+        if self.store.contains_key( &old_key) {
+            self.store.rename( &old_key, new_key.clone())?;
+            self.update_expiration( &hself, &old_key);
+            self.update_expiration( &hself, &new_key);
+            self.publish_change( SharedStoreChange::Rename{ hstore: hself, old_key, new_key }).await;
+        }
+        Ok(())
     }
 }
 
 //--- messages
 
+/// set a store value. If `expected_rev` is set the value is only stored if it matches the current revision of `key`
+/// (compare-and-set). Send this as a `SetSharedStoreValueQuery` to get the new revision or a `RevisionConflict` error.
+/// Mutating messages include the `requester`, which is checked against the ACL of the store (if any)
 #[derive(Debug)] 
 pub struct SetSharedStoreValue<T> {
     pub key: String,
-    pub value: T
+    pub value: T,
+    pub expected_rev: Option<u64>,
+    pub requester: Requester
 }
 
+pub type SetSharedStoreValueQuery<T> = Query<SetSharedStoreValue<T>,Result<u64,OdinShareError>>;
+
 #[derive(Debug)] 
 pub struct RemoveSharedStoreValue {
-    pub key: String
+    pub key: String,
+    pub requester: Requester
+}
+
+#[derive(Debug)] 
+pub struct RenameSharedStoreValue {
+    pub old_key: String,
+    pub new_key: String,
+    pub requester: Requester
+}
+
+/// revert `key` to the value it had in revision `rev` (which has to be in the history of `key`)
+#[derive(Debug)] 
+pub struct RevertSharedStoreValue {
+    pub key: String,
+    pub rev: u64,
+    pub requester: Requester
+}
+
+/// subscribe `subscriber` for changes of keys that match `glob_pattern` (e.g. "incidents/*/origin"). If `with_value`
+/// is set the updates include the new value. Subscribers are removed once their actor is terminated or if they don't
+/// accept an update within `SUBSCRIBER_SEND_TIMEOUT`
+#[derive(Debug)] 
+pub struct SubscribeSharedStoreChanges<T> where T: SharedStoreValueConstraints {
+    pub glob_pattern: String,
+    pub with_value: bool,
+    pub subscriber: DynMsgReceiver<SharedStoreUpdate<T>>
 }
 
+/// remove subscriptions of the actor with the given id, either all of them or only the one for `glob_pattern`
+#[derive(Debug)] 
+pub struct UnsubscribeSharedStoreChanges {
+    pub subscriber_id: String,
+    pub glob_pattern: Option<String>
+}
+
+/// sent by expiration jobs of the actor itself
+#[derive(Debug,Clone)] 
+pub struct ExpireSharedStoreValue {
+    pub key: String,
+    pub expires: DateTime<Utc>
+}
+
+/// query the (key,value) pairs that match `glob_pattern` and are valid at `time`, e.g. to show the state of temporary
+/// items at a given point in time. Without a configured validity function all matching values are returned
+#[derive(Debug)] 
+pub struct GetValidSharedStoreValues {
+    pub glob_pattern: String,
+    pub time: DateTime<Utc>,
+    pub requester: Requester
+}
+
+pub type ValidSharedStoreValuesQuery<T> = Query<GetValidSharedStoreValues,Result<Vec<(String,T)>,OdinShareError>>;
+
+/// query the change history of `key`. The response is empty if the requester is not allowed to read `key`
+#[derive(Debug)] 
+pub struct GetSharedStoreHistory {
+    pub key: String,
+    pub requester: Requester
+}
+
+pub type SharedStoreHistoryQuery<T> = Query<GetSharedStoreHistory,Vec<SharedStoreRevision<T>>>;
+
 #[derive(Debug)] 
 pub struct ExecSnapshotAction<T>( pub DynSharedStoreAction<T> );
 
 define_actor_msg_set! { pub SharedStoreActorMsg<T> where T: SharedStoreValueConstraints = 
-    SetSharedStoreValue<T> | RemoveSharedStoreValue | Query<String,Option<T>> | ExecSnapshotAction<T>
+    SetSharedStoreValue<T> | RemoveSharedStoreValue | RenameSharedStoreValue | RevertSharedStoreValue | ExpireSharedStoreValue |
+    SubscribeSharedStoreChanges<T> | UnsubscribeSharedStoreChanges |
+    Query<SetSharedStoreValue<T>,Result<u64,OdinShareError>> | Query<GetSharedStoreHistory,Vec<SharedStoreRevision<T>>> |
+    Query<GetValidSharedStoreValues,Result<Vec<(String,T)>,OdinShareError>> |
+    Query<String,Option<T>> | ExecSnapshotAction<T>
 }
 
 
 impl_actor! { match msg for Actor<SharedStoreActor<T,S,I,C>,SharedStoreActorMsg<T>> 
-        where T: SharedStoreValueConstraints, S: SharedStore<T>, I: SharedStoreAction<T> + Send, C: DataAction<SharedStoreChange<T>> as
+        where T: SharedStoreValueConstraints, S: SharedStore<T>, I: SharedStoreAction<T> + Send, C: DataAction<SharedStoreUpdate<T>> as
     _Start_ => cont! {
-        if let Err(e) = self.state.initialize().await {
+        let hself = self.hself.clone();
+        if let Err(e) = self.state.initialize( hself).await {
             error!("store failed to initialize {e}");
         }
     }
 
     SetSharedStoreValue<T> => cont! {
         let hself = self.hself.clone();
-        self.state.set( hself, msg.key, msg.value).await;
+        if let Err(e) = self.state.set( hself, &msg.requester, msg.key, msg.value, msg.expected_rev).await {
+            warn!("store value not set: {e}");
+        }
     }
     RemoveSharedStoreValue => cont! {
         let hself = self.hself.clone();
-        self.state.remove( hself, msg.key).await;
+        if let Err(e) = self.state.remove( hself, &msg.requester, msg.key).await {
+            warn!("store value not removed: {e}");
+        }
+    }
+    RenameSharedStoreValue => cont! {
+        let hself = self.hself.clone();
+        if let Err(e) = self.state.rename( hself, &msg.requester, msg.old_key, msg.new_key).await {
+            warn!("store value not renamed: {e}");
+        }
+    }
+    RevertSharedStoreValue => cont! {
+        let hself = self.hself.clone();
+        if let Err(e) = self.state.revert( hself, &msg.requester, msg.key, msg.rev).await {
+            warn!("store value not reverted: {e}");
+        }
+    }
+    SubscribeSharedStoreChanges<T> => cont! {
+        if let Err(e) = self.state.subscribe( msg.glob_pattern, msg.with_value, msg.subscriber) {
+            warn!("invalid subscription: {e}");
+        }
+    }
+    UnsubscribeSharedStoreChanges => cont! {
+        self.state.unsubscribe( &msg.subscriber_id, &msg.glob_pattern);
+    }
+    ExpireSharedStoreValue => cont! {
+        let hself = self.hself.clone();
+        if let Err(e) = self.state.expire( hself, msg.key, msg.expires).await {
+            warn!("store value not expired: {e}");
+        }
+    }
+    Query<SetSharedStoreValue<T>,Result<u64,OdinShareError>> => cont! {
+        let hself = self.hself.clone();
+        let SetSharedStoreValue{ key, value, expected_rev, requester } = &msg.question;
+        let result = self.state.set( hself, requester, key.clone(), value.clone(), *expected_rev).await;
+        msg.respond( result).await;
+    }
+    Query<GetSharedStoreHistory,Vec<SharedStoreRevision<T>>> => cont! {
+        let GetSharedStoreHistory{ key, requester } = &msg.question;
+        let history = if self.state.acl.check_read( requester, key).is_ok() { self.state.store.history( key) } else { Vec::new() };
+        msg.respond( history).await;
+    }
+    Query<GetValidSharedStoreValues,Result<Vec<(String,T)>,OdinShareError>> => cont! {
+        let GetValidSharedStoreValues{ glob_pattern, time, requester } = &msg.question;
+        msg.respond( self.state.valid_values( requester, glob_pattern, *time)).await;
     }
     Query<String,Option<T>> => cont! {
         msg.respond( self.state.store.get(&msg.question).map(|vr| vr.clone())).await;
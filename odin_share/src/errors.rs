@@ -29,11 +29,28 @@
     #[error("JSON error {0}")]
     JsonError( #[from] serde_json::Error),
 
+    #[cfg(feature="sqlite")]
+    #[error("SQLite error {0}")]
+    SqliteError( #[from] rusqlite::Error),
+
+    #[error("invalid value: {0}")]
+    InvalidValue( String ),
+
+    #[error("access to {key} denied for user {}", .user.as_deref().unwrap_or("<anonymous>"))]
+    AccessDenied{ key: String, user: Option<String> },
+
+    #[error("revision conflict for {key}: expected {expected}, current {current}")]
+    RevisionConflict{ key: String, expected: u64, current: u64 },
+
     // generic error
     #[error("operation failed: {0}")]
     OpFailed( String ),
 }
 
+pub fn invalid_value (msg: impl ToString)->OdinShareError {
+    OdinShareError::InvalidValue(msg.to_string())
+}
+
 pub fn op_failed (msg: impl ToString)->OdinShareError {
     OdinShareError::OpFailed(msg.to_string())
 }
\ No newline at end of file
@@ -0,0 +1,383 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the geo_items module defines the geospatial payload types of `SharedItem` variants that are not already in
+//! `odin_common::geo`, the validation of `SharedItem` values and their conversion from/to GeoJSON features.
+//!
+//! GeoJSON features use the usual \[lon,lat(,alt)\] position order. Exported features have a "key" property with the
+//! store key of the item and a "type" property with the `SharedItem` variant name, which is used to restore variants
+//! that don't have a native GeoJSON geometry (e.g. a `Circle` is exported as a Point with a "radius" property).
+
+use std::sync::Arc;
+use chrono::{DateTime,Utc};
+use serde::{Serialize,Deserialize};
+use serde_json::json;
+use geojson::{Feature, FeatureCollection, Geometry, JsonObject, JsonValue, Value};
+use odin_common::{geo::{DatedGeoPos, GeoBoundingBox, GeoPos, LatLon}, angle::{LatAngle,LonAngle}};
+
+use crate::{errors::{invalid_value, OdinShareError}, share_service::{SharedItem, SharedItemValue, is_valid_key}, validity::Validity};
+
+type Result<T> = std::result::Result<T,OdinShareError>;
+
+/* #region payload types *************************************************************************************/
+
+/// a polygon with an exterior ring and optional holes. Rings don't have to repeat the first position at the end
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct Polygon {
+    pub exterior: Vec<LatLon>,
+    #[serde(default)]
+    pub holes: Vec<Vec<LatLon>>
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct MultiPolygon {
+    pub polygons: Vec<Polygon>
+}
+
+/// a circle with radius in meters
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct Circle {
+    pub center: LatLon,
+    pub radius: f64
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct TimeRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>
+}
+
+/* #endregion payload types */
+
+/* #region validation ****************************************************************************************/
+
+fn check_lat_lon (lat: f64, lon: f64)->Result<()> {
+    if !lat.is_finite() || lat < -90.0 || lat > 90.0 {
+        Err( invalid_value( format!("latitude out of range: {lat}")))
+    } else if !lon.is_finite() || lon < -180.0 || lon > 180.0 {
+        Err( invalid_value( format!("longitude out of range: {lon}")))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_latlon (p: &LatLon)->Result<()> {
+    check_lat_lon( p.lat_deg, p.lon_deg)
+}
+
+fn check_geopos (p: &GeoPos)->Result<()> {
+    check_lat_lon( p.lat.degrees(), p.lon.degrees())?;
+    if p.alt.is_finite() { Ok(()) } else { Err( invalid_value( "altitude is not a number")) }
+}
+
+/// rings need at least 3 distinct positions (not counting an optional closing position)
+fn check_ring (ring: &Vec<LatLon>)->Result<()> {
+    let n = if ring.len() > 1 && ring.first() == ring.last() { ring.len() - 1 } else { ring.len() };
+    if n < 3 { return Err( invalid_value( "polygon ring needs at least 3 positions")) }
+    for p in ring { check_latlon(p)? }
+    Ok(())
+}
+
+fn check_polygon (poly: &Polygon)->Result<()> {
+    check_ring( &poly.exterior)?;
+    for hole in &poly.holes { check_ring( hole)? }
+    Ok(())
+}
+
+fn check_geojson_value (value: &Value)->Result<()> {
+    let check_pos = |p: &Vec<f64>| if p.len() < 2 { Err( invalid_value( "GeoJSON position needs at least 2 coordinates")) } else { check_lat_lon( p[1], p[0]) };
+
+    match value {
+        Value::Point(p) => check_pos(p),
+        Value::MultiPoint(ps) | Value::LineString(ps) => ps.iter().try_for_each( check_pos),
+        Value::MultiLineString(ls) | Value::Polygon(ls) => ls.iter().flatten().try_for_each( check_pos),
+        Value::MultiPolygon(pls) => pls.iter().flatten().flatten().try_for_each( check_pos),
+        Value::GeometryCollection(gs) => gs.iter().try_for_each( |g| check_geojson_value( &g.value))
+    }
+}
+
+impl SharedItem {
+    /// check if the payload of this item is well formed. This is used to validate values before they are stored
+    pub fn validate (&self)->Result<()> {
+        self.validity().check()?;
+
+        match self {
+            SharedItem::Point2D(v) => check_latlon( &v.data),
+            SharedItem::Point3D(v) => check_geopos( &v.data),
+            SharedItem::Polyline(v) => {
+                if v.data.len() < 2 { return Err( invalid_value( "polyline needs at least 2 positions")) }
+                v.data.iter().try_for_each( check_latlon)
+            }
+            SharedItem::Polygon(v) => check_polygon( &v.data),
+            SharedItem::MultiPolygon(v) => {
+                if v.data.polygons.is_empty() { return Err( invalid_value( "empty multi-polygon")) }
+                v.data.polygons.iter().try_for_each( check_polygon)
+            }
+            SharedItem::Circle(v) => {
+                check_latlon( &v.data.center)?;
+                if v.data.radius.is_finite() && v.data.radius > 0.0 { Ok(()) } else { Err( invalid_value( "circle radius has to be positive")) }
+            }
+            SharedItem::BoundingBox(v) => { // note that west > east is valid (crossing the antimeridian)
+                let bbox = &v.data;
+                check_lat_lon( bbox.south.degrees(), bbox.west.degrees())?;
+                check_lat_lon( bbox.north.degrees(), bbox.east.degrees())?;
+                if bbox.south.degrees() <= bbox.north.degrees() { Ok(()) } else { Err( invalid_value( "bounding box south > north")) }
+            }
+            SharedItem::TimeRange(v) => {
+                if v.data.start <= v.data.end { Ok(()) } else { Err( invalid_value( "time range start is after end")) }
+            }
+            SharedItem::GeoJson(v) => {
+                v.data.features.iter().filter_map( |f| f.geometry.as_ref()).try_for_each( |g| check_geojson_value( &g.value))
+            }
+            SharedItem::Track(v) => {
+                if v.data.is_empty() { return Err( invalid_value( "empty track")) }
+                v.data.iter().try_for_each( |p| check_geopos( &p.pos))?;
+                if v.data.windows(2).all( |w| w[0].date <= w[1].date) { Ok(()) } else { Err( invalid_value( "track positions are not in time order")) }
+            }
+            SharedItem::F64(v) => {
+                if v.data.is_finite() { Ok(()) } else { Err( invalid_value( "not a finite number")) }
+            }
+            SharedItem::Json(v) => {
+                serde_json::from_str::<JsonValue>( &v.data).map( |_| ()).map_err( |e| invalid_value( format!("malformed JSON: {e}")))
+            }
+            SharedItem::U64(_) | SharedItem::String(_) => Ok(())
+        }
+    }
+
+    /// the variant name, which is also the value of the serialized "type" tag
+    pub fn type_name (&self)->&'static str {
+        match self {
+            SharedItem::Point2D(_) => "Point2D",
+            SharedItem::Point3D(_) => "Point3D",
+            SharedItem::Polyline(_) => "Polyline",
+            SharedItem::Polygon(_) => "Polygon",
+            SharedItem::MultiPolygon(_) => "MultiPolygon",
+            SharedItem::Circle(_) => "Circle",
+            SharedItem::BoundingBox(_) => "BoundingBox",
+            SharedItem::TimeRange(_) => "TimeRange",
+            SharedItem::GeoJson(_) => "GeoJson",
+            SharedItem::Track(_) => "Track",
+            SharedItem::U64(_) => "U64",
+            SharedItem::F64(_) => "F64",
+            SharedItem::String(_) => "String",
+            SharedItem::Json(_) => "Json",
+        }
+    }
+
+    fn meta (&self)->(&Option<String>, &Option<String>) {
+        match self {
+            SharedItem::Point2D(v) => (&v.comment, &v.owner),
+            SharedItem::Point3D(v) => (&v.comment, &v.owner),
+            SharedItem::Polyline(v) => (&v.comment, &v.owner),
+            SharedItem::Polygon(v) => (&v.comment, &v.owner),
+            SharedItem::MultiPolygon(v) => (&v.comment, &v.owner),
+            SharedItem::Circle(v) => (&v.comment, &v.owner),
+            SharedItem::BoundingBox(v) => (&v.comment, &v.owner),
+            SharedItem::TimeRange(v) => (&v.comment, &v.owner),
+            SharedItem::GeoJson(v) => (&v.comment, &v.owner),
+            SharedItem::Track(v) => (&v.comment, &v.owner),
+            SharedItem::U64(v) => (&v.comment, &v.owner),
+            SharedItem::F64(v) => (&v.comment, &v.owner),
+            SharedItem::String(v) => (&v.comment, &v.owner),
+            SharedItem::Json(v) => (&v.comment, &v.owner),
+        }
+    }
+}
+
+/* #endregion validation */
+
+/* #region GeoJSON conversion ********************************************************************************/
+
+fn latlon_pos (p: &LatLon)->Vec<f64> { vec![p.lon_deg, p.lat_deg] }
+
+fn geopos_pos (p: &GeoPos)->Vec<f64> { vec![p.lon.degrees(), p.lat.degrees(), p.alt] }
+
+/// GeoJSON rings have to be closed
+fn closed_ring (ring: &Vec<LatLon>)->Vec<Vec<f64>> {
+    let mut positions: Vec<Vec<f64>> = ring.iter().map( latlon_pos).collect();
+    if ring.len() > 1 && ring.first() != ring.last() {
+        positions.push( latlon_pos( &ring[0]));
+    }
+    positions
+}
+
+fn polygon_rings (poly: &Polygon)->Vec<Vec<Vec<f64>>> {
+    let mut rings = vec![ closed_ring( &poly.exterior)];
+    for hole in &poly.holes { rings.push( closed_ring( hole)) }
+    rings
+}
+
+fn to_latlon (p: &Vec<f64>)->Result<LatLon> {
+    if p.len() < 2 { return Err( invalid_value( "GeoJSON position needs at least 2 coordinates")) }
+    Ok( LatLon::from_degrees( p[1], p[0]))
+}
+
+fn to_geopos (p: &Vec<f64>)->Result<GeoPos> {
+    if p.len() < 2 { return Err( invalid_value( "GeoJSON position needs at least 2 coordinates")) }
+    let alt = if p.len() > 2 { p[2] } else { 0.0 };
+    Ok( GeoPos::new( LatAngle::from_degrees( p[1]), LonAngle::from_degrees( p[0]), alt))
+}
+
+fn to_ring (positions: &Vec<Vec<f64>>)->Result<Vec<LatLon>> {
+    let mut ring: Vec<LatLon> = positions.iter().map( to_latlon).collect::<Result<_>>()?;
+    if ring.len() > 1 && ring.first() == ring.last() { ring.pop(); } // we don't store the closing position
+    Ok(ring)
+}
+
+fn to_polygon (rings: &Vec<Vec<Vec<f64>>>)->Result<Polygon> {
+    let mut it = rings.iter();
+    let exterior = to_ring( it.next().ok_or( invalid_value( "polygon without rings"))?)?;
+    let holes = it.map( to_ring).collect::<Result<_>>()?;
+    Ok( Polygon { exterior, holes })
+}
+
+fn get_f64 (props: &JsonObject, key: &str)->Option<f64> {
+    props.get( key).and_then( |v| v.as_f64())
+}
+
+fn get_string (props: &JsonObject, key: &str)->Option<String> {
+    props.get( key).and_then( |v| v.as_str()).map( |s| s.to_string())
+}
+
+impl SharedItem {
+    /// convert the item into a GeoJSON feature with "key", "type", "comment" and "owner" properties.
+    /// Returns `None` for non-geospatial items. `GeoJson` items are exported as a GeometryCollection of their features
+    pub fn to_geojson_feature (&self, key: &str)->Option<Feature> {
+        let mut props = JsonObject::new();
+
+        let value = match self {
+            SharedItem::Point2D(v) => Value::Point( latlon_pos( &v.data)),
+            SharedItem::Point3D(v) => Value::Point( geopos_pos( &v.data)),
+            SharedItem::Polyline(v) => Value::LineString( v.data.iter().map( latlon_pos).collect()),
+            SharedItem::Polygon(v) => Value::Polygon( polygon_rings( &v.data)),
+            SharedItem::MultiPolygon(v) => Value::MultiPolygon( v.data.polygons.iter().map( polygon_rings).collect()),
+            SharedItem::Circle(v) => {
+                props.insert( "radius".into(), json!(v.data.radius));
+                Value::Point( latlon_pos( &v.data.center))
+            }
+            SharedItem::BoundingBox(v) => {
+                let b = &v.data;
+                let (w,s,e,n) = (b.west.degrees(), b.south.degrees(), b.east.degrees(), b.north.degrees());
+                Value::Polygon( vec![ vec![ vec![w,s], vec![e,s], vec![e,n], vec![w,n], vec![w,s] ]])
+            }
+            SharedItem::Track(v) => {
+                props.insert( "times".into(), json!( v.data.iter().map( |p| p.date.to_rfc3339()).collect::<Vec<String>>()));
+                Value::LineString( v.data.iter().map( |p| geopos_pos( &p.pos)).collect())
+            }
+            SharedItem::GeoJson(v) => {
+                Value::GeometryCollection( v.data.features.iter().filter_map( |f| f.geometry.clone()).collect())
+            }
+            _ => return None
+        };
+
+        let (comment, owner) = self.meta();
+        props.insert( "key".into(), json!(key));
+        props.insert( "type".into(), json!(self.type_name()));
+        if let Some(comment) = comment { props.insert( "comment".into(), json!(comment)); }
+        if let Some(owner) = owner { props.insert( "owner".into(), json!(owner)); }
+        if let Ok(JsonValue::Object(validity)) = serde_json::to_value( self.validity()) { props.extend( validity); }
+
+        Some( Feature {
+            bbox: None,
+            geometry: Some( Geometry::new( value)),
+            id: None,
+            properties: Some(props),
+            foreign_members: None
+        })
+    }
+
+    /// create a (validated) item from a GeoJSON feature. The "type" property is used to restore variants that are
+    /// not GeoJSON geometries, otherwise we map Points with altitude to `Point3D`, LineStrings to `Polyline` etc.
+    pub fn from_geojson_feature (feature: &Feature)->Result<SharedItem> {
+        let empty = JsonObject::new();
+        let props = feature.properties.as_ref().unwrap_or( &empty);
+        let geometry = feature.geometry.as_ref().ok_or( invalid_value( "feature without geometry"))?;
+        let type_name = get_string( props, "type");
+        let comment = get_string( props, "comment");
+        let owner = get_string( props, "owner");
+        let validity: Validity = serde_json::from_value( JsonValue::Object( props.clone()))
+            .map_err( |e| invalid_value( format!("invalid feature validity: {e}")))?;
+
+        macro_rules! item {
+            ($variant:ident, $data:expr) => { SharedItem::$variant( SharedItemValue { comment, owner, validity, data: Arc::new($data) }) }
+        }
+
+        let item = match (&geometry.value, type_name.as_deref()) {
+            (Value::Point(p), Some("Circle")) => {
+                let radius = get_f64( props, "radius").ok_or( invalid_value( "circle feature without radius"))?;
+                item!( Circle, Circle { center: to_latlon(p)?, radius })
+            }
+            (Value::Point(p), _) if p.len() > 2 => item!( Point3D, to_geopos(p)?),
+            (Value::Point(p), _) => item!( Point2D, to_latlon(p)?),
+
+            (Value::LineString(ps), Some("Track")) => {
+                let times = props.get( "times").and_then( |v| v.as_array()).ok_or( invalid_value( "track feature without times"))?;
+                if times.len() != ps.len() { return Err( invalid_value( "track times don't match positions")) }
+                let track = ps.iter().zip( times.iter()).map( |(p,t)| {
+                    let date = t.as_str().and_then( |s| DateTime::parse_from_rfc3339(s).ok()).ok_or( invalid_value( "invalid track time"))?;
+                    Ok( DatedGeoPos { pos: to_geopos(p)?, date: date.with_timezone(&Utc) })
+                }).collect::<Result<Vec<DatedGeoPos>>>()?;
+                item!( Track, track)
+            }
+            (Value::LineString(ps), _) => item!( Polyline, ps.iter().map( to_latlon).collect::<Result<Vec<LatLon>>>()?),
+
+            (Value::Polygon(rings), Some("BoundingBox")) => {
+                // corners are exported as [w,s],[e,s],[e,n],.. - we can't use min/max since west > east is valid (antimeridian)
+                let ring = to_ring( rings.first().ok_or( invalid_value( "polygon without rings"))?)?;
+                if ring.len() < 4 { return Err( invalid_value( "bounding box needs 4 corners")) }
+                let (sw, ne) = (&ring[0], &ring[2]);
+                item!( BoundingBox, GeoBoundingBox::from_wsen_degrees( &[sw.lon_deg, sw.lat_deg, ne.lon_deg, ne.lat_deg]))
+            }
+            (Value::Polygon(rings), _) => item!( Polygon, to_polygon( rings)?),
+            (Value::MultiPolygon(polys), _) => {
+                item!( MultiPolygon, MultiPolygon { polygons: polys.iter().map( to_polygon).collect::<Result<_>>()? })
+            }
+
+            _ => { // everything else is kept as a GeoJSON feature collection
+                let mut f = feature.clone();
+                f.properties = None;
+                item!( GeoJson, FeatureCollection { bbox: None, features: vec![f], foreign_members: None })
+            }
+        };
+
+        item.validate()?;
+        Ok(item)
+    }
+}
+
+/// export all geospatial items of an iterator as a GeoJSON feature collection
+pub fn to_feature_collection<'a> (items: impl Iterator<Item=(&'a String,&'a SharedItem)>)->FeatureCollection {
+    let features = items.filter_map( |(key,item)| item.to_geojson_feature( key)).collect();
+    FeatureCollection { bbox: None, features, foreign_members: None }
+}
+
+/// import the features of a GeoJSON feature collection as (key,item) pairs. Keys are taken from the "key" property of
+/// features, or otherwise created from `key_prefix` and the feature index (or feature id if present).
+/// This fails if any of the features has an invalid key or item
+pub fn from_feature_collection (fc: &FeatureCollection, key_prefix: &str)->Result<Vec<(String,SharedItem)>> {
+    fc.features.iter().enumerate().map( |(i,f)| {
+        let key = f.properties.as_ref().and_then( |p| get_string( p, "key")).unwrap_or_else( || {
+            match &f.id {
+                Some(geojson::feature::Id::String(id)) => format!("{key_prefix}{id}"),
+                Some(geojson::feature::Id::Number(id)) => format!("{key_prefix}{id}"),
+                None => format!("{key_prefix}{i}")
+            }
+        });
+        if !is_valid_key( &key) { return Err( invalid_value( format!("invalid key {key:?} of feature {i}"))) }
+        SharedItem::from_geojson_feature( f).map( |item| (key,item))
+    }).collect()
+}
+
+/* #endregion GeoJSON conversion */
@@ -25,6 +25,15 @@ use async_trait::async_trait;
 pub mod prelude;
 pub mod actor;
 pub mod share_service;
+pub mod geo_items;
+pub mod revisions;
+pub mod acl;
+pub mod validity;
+pub mod replication;
+
+#[cfg(feature="sqlite")]
+pub mod sqlite_store;
+use revisions::{ItemRevision,SharedStoreRevision};
 
 pub mod errors;
 
@@ -53,10 +62,35 @@ pub trait SharedStore<T> : Send + Sync
 
     fn len(&self)->usize;
     fn contains_key (&self, k: &str)->bool;
-    fn insert(&mut self, k: String, v: T)->Option<T>;
-    fn remove (&mut self, k: &str)->Option<T>;
+
+    /// store `v` for `k`, returning the replaced value. Stores that persist values have to return an error if the
+    /// value could not be written, in which case the store content has to be unchanged
+    fn insert(&mut self, k: String, v: T)->Result<Option<T>,OdinShareError>;
+
+    /// remove the value for `k` (if any). As with `insert` an error means the store content is unchanged
+    fn remove (&mut self, k: &str)->Result<Option<T>,OdinShareError>;
+
     fn get (&self, k: &str)->Option<&T>;
 
+    /// move the value of `old_k` to `new_k`, returning the replaced `new_k` value. Override if the store can do this atomically
+    fn rename (&mut self, old_k: &str, new_k: String)->Result<Option<T>,OdinShareError> {
+        match self.remove( old_k)? {
+            Some(v) => self.insert( new_k, v),
+            None => Ok(None)
+        }
+    }
+
+    /// revision of the current value for `k` (if any). Revisions are tracked by the `RevisionedStore` wrapper that is
+    /// used by `SharedStoreActor` - plain stores only report the initial revision of existing values
+    fn revision (&self, k: &str)->Option<ItemRevision> {
+        if self.contains_key(k) { Some( ItemRevision{ rev: 1, modified: None }) } else { None }
+    }
+
+    /// the (bounded) change history of `k`, oldest revision first
+    fn history (&self, k: &str)->Vec<SharedStoreRevision<T>> {
+        Vec::new()
+    }
+
     fn to_json (&self)->Result<String,OdinShareError>;
     fn save (&self)->Result<(),OdinShareError>;
 
@@ -172,12 +206,12 @@ impl<T> SharedStore<T> for HashMap<String,T>
         HashMap::contains_key(self, k) 
     }
 
-    fn insert(&mut self, k: String, v: T)->Option<T> {
-        HashMap::insert(self, k, v)
+    fn insert(&mut self, k: String, v: T)->Result<Option<T>,OdinShareError> {
+        Ok( HashMap::insert(self, k, v) )
     }
 
-    fn remove (&mut self, k: &str)->Option<T> {
-        HashMap::remove(self, k)
+    fn remove (&mut self, k: &str)->Result<Option<T>,OdinShareError> {
+        Ok( HashMap::remove(self, k) )
     }
 
     fn get (&self, k: &str)->Option<&T> {
@@ -251,12 +285,12 @@ impl<T> SharedStore<T> for PersistentHashMapStore<T>
         self.map.contains_key(k) 
     }
 
-    fn insert(&mut self, k: String, v: T)->Option<T> {
-        self.map.insert( k, v)
+    fn insert(&mut self, k: String, v: T)->Result<Option<T>,OdinShareError> {
+        Ok( self.map.insert( k, v) )
     }
 
-    fn remove (&mut self, k: &str)->Option<T> {
-        self.map.remove(k)
+    fn remove (&mut self, k: &str)->Result<Option<T>,OdinShareError> {
+        Ok( self.map.remove(k) )
     }
 
     fn get (&self, k: &str)->Option<&T> {
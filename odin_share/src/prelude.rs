@@ -14,8 +14,15 @@
 
 pub use crate::{
     SharedStore, SharedStoreValueConstraints, SharedStoreAction, DynSharedStoreAction, PersistentHashMapStore,
-    actor::{SharedStoreActor,SharedStoreActorMsg,SharedStoreChange,SetSharedStoreValue,ExecSnapshotAction},
+    actor::{SharedStoreActor,SharedStoreActorMsg,SharedStoreChange,SetSharedStoreValue,RemoveSharedStoreValue,RenameSharedStoreValue,
+        RevertSharedStoreValue,GetSharedStoreHistory,SetSharedStoreValueQuery,SharedStoreHistoryQuery,ExecSnapshotAction,
+        GetValidSharedStoreValues,ValidSharedStoreValuesQuery,SharedStoreUpdate,SubscribeSharedStoreChanges,UnsubscribeSharedStoreChanges},
+    revisions::{ItemRevision,SharedStoreRevision},
+    acl::{KeyAcl,KeyAclConfig,AclRule,Requester},
+    validity::Validity,
+    replication::{ShareReplicator,ShareReplicatorMsg,ReplicationConfig,replicate_changes},
     shared_store_action, dyn_shared_store_action, no_shared_store_action,
-    share_service::{ShareService, SharedItem, SharedItemValue},
+    share_service::{ShareService, SharedItem, SharedItemValue, share_change_action, acl_share_change_action},
+    geo_items::{Polygon, MultiPolygon, Circle, TimeRange},
     errors::OdinShareError
 };
\ No newline at end of file
@@ -0,0 +1,714 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the replication module synchronizes `SharedStoreActor`s of different ODIN servers (e.g. an incident command post and
+//! a regional office) over websocket links. Each node runs a `ShareReplicator` actor that gets local store changes from
+//! the store change action (see [`replicate_changes`]) and exchanges them with its peers.
+//!
+//! Conflicts are resolved per key with last-writer-wins, using [hybrid logical clock](https://cse.buffalo.edu/tech-reports/2014-04.pdf)
+//! timestamps (wall clock millis, logical counter and node id as tie breaker), i.e. all nodes converge to the same value
+//! without having to rely on synchronized clocks. Removals are kept as tombstones so that they are not undone by
+//! outdated values from peers that were offline.
+//!
+//! Each node numbers the changes it applies with a local sequence number and keeps track of the last sequence number it
+//! received from each peer. Upon (re-)connection peers exchange `Hello` messages and then request the changes they missed
+//! with a `Sync{since}` message, which means nodes can operate offline for extended periods of time and catch up once
+//! the link is restored. Since sequence numbers are not persisted each replicator run has its own incarnation id that is
+//! sent with `Hello`. If a peer comes back with a new incarnation we start over with its sequence numbers, and a peer that
+//! syncs from scratch gets our complete state (including the changes it sent us before it was restarted). Changes received from one peer are forwarded to all other peers, i.e. nodes do not have to
+//! be fully connected.
+//!
+//! If the store actor cannot pass a local change on to the replicator (full mailbox) the replicator reconciles its state
+//! with a snapshot of the store once it has caught up, i.e. local changes are never lost.
+//!
+//! Note that replica state (timestamps and tombstones) is kept in memory.
+
+use std::{collections::HashMap, fmt::{self,Debug}, future::Future, net::SocketAddr, sync::{Arc, atomic::{AtomicBool,AtomicU64,Ordering}}, time::Duration};
+use chrono::Utc;
+use futures::{SinkExt,StreamExt};
+use serde::{Serialize,Deserialize};
+use tokio::{net::TcpListener, io::{AsyncRead,AsyncWrite}};
+use tokio_tungstenite::{accept_async, connect_async, WebSocketStream, tungstenite::protocol::Message};
+use async_trait::async_trait;
+use odin_action::{DataAction, OdinActionFailure};
+use odin_actor::prelude::*;
+
+use crate::{SharedStore, SharedStoreValueConstraints, DynSharedStoreActionTrait,
+    actor::{SharedStoreActorMsg, SharedStoreChange, SharedStoreUpdate, SetSharedStoreValue, RemoveSharedStoreValue, ExecSnapshotAction},
+    acl::Requester,
+    errors::{op_failed, OdinShareError}
+};
+
+const PEER_CHANNEL_BOUNDS: usize = 1024;
+const MAX_CHANGES_PER_MSG: usize = 256;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/* #region hybrid logical clock ******************************************************************************/
+
+/// a hybrid logical clock timestamp. Field order matters - we use the derived `Ord` for last-writer-wins
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub struct HlcTimestamp {
+    pub millis: u64,
+    pub counter: u32,
+    pub node: String
+}
+
+impl HlcTimestamp {
+    /// the timestamp of values that were in the store before replication started. Any change wins over these
+    pub fn initial (node: &str)->Self {
+        HlcTimestamp { millis: 0, counter: 0, node: node.to_string() }
+    }
+}
+
+pub struct HybridLogicalClock {
+    node: String,
+    millis: u64,
+    counter: u32
+}
+
+impl HybridLogicalClock {
+    pub fn new (node: impl ToString)->Self {
+        HybridLogicalClock { node: node.to_string(), millis: 0, counter: 0 }
+    }
+
+    fn wall_millis ()->u64 {
+        Utc::now().timestamp_millis() as u64
+    }
+
+    fn timestamp (&self)->HlcTimestamp {
+        HlcTimestamp { millis: self.millis, counter: self.counter, node: self.node.clone() }
+    }
+
+    /// timestamp for a local event
+    pub fn now (&mut self)->HlcTimestamp {
+        let pt = Self::wall_millis();
+        if pt > self.millis {
+            self.millis = pt;
+            self.counter = 0;
+        } else {
+            self.counter += 1;
+        }
+        self.timestamp()
+    }
+
+    /// merge a received timestamp so that subsequent local timestamps are ordered after it
+    pub fn update (&mut self, remote: &HlcTimestamp) {
+        let pt = Self::wall_millis();
+        let millis = pt.max( self.millis).max( remote.millis);
+
+        self.counter = if millis == self.millis && millis == remote.millis {
+            self.counter.max( remote.counter) + 1
+        } else if millis == self.millis {
+            self.counter + 1
+        } else if millis == remote.millis {
+            remote.counter + 1
+        } else {
+            0
+        };
+        self.millis = millis;
+    }
+}
+
+/* #endregion hybrid logical clock */
+
+/* #region replication protocol ******************************************************************************/
+
+/// configuration of a replication node
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct ReplicationConfig {
+    /// unique name of this node
+    pub node_id: String,
+
+    /// optional address to accept peer connections on
+    pub listen: Option<SocketAddr>,
+
+    /// websocket URIs of peers we connect to (e.g. "ws://regional-office:9100")
+    pub peers: Vec<String>,
+
+    /// shared secret that peers have to present in their `Hello` message
+    pub token: Option<String>,
+
+    /// delay before we try to re-connect to a peer
+    pub reconnect_interval: Duration,
+}
+
+/// a replicated store change. A `None` value is a removal (tombstone). `seq` is the sequence number of the sender
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct ReplicatedChange<T> {
+    pub key: String,
+    pub value: Option<T>,
+    pub ts: HlcTimestamp,
+    pub seq: u64
+}
+
+/// the JSON messages exchanged between peers
+#[derive(Serialize,Deserialize,Debug)]
+#[serde(tag="type")]
+pub enum ReplicationMsg<T> {
+    /// first message on each new connection (sent by both sides). The `incarnation` changes each time a node is started
+    Hello { node: String, token: Option<String>, #[serde(default)] incarnation: u64 },
+
+    /// request all changes with a sequence number > since
+    Sync { since: u64 },
+
+    Changes { changes: Vec<ReplicatedChange<T>> }
+}
+
+/// what we know about the current state of a key
+struct ReplicaEntry<T> {
+    ts: HlcTimestamp,
+    value: Option<T>,
+    seq: u64,
+    source: Option<String> // the peer we got this from, None if local change
+}
+
+struct PeerConnection {
+    remote: String,
+    node: Option<String>, // set once we got a valid Hello
+    tx: MpscSender<String>
+}
+
+/* #endregion replication protocol */
+
+/* #region actor *********************************************************************************************/
+
+/// the state of a replication actor for a `SharedStoreActor<T,..>`
+pub struct ShareReplicator<T> where T: SharedStoreValueConstraints {
+    config: Arc<ReplicationConfig>,
+    hstore: ActorHandle<SharedStoreActorMsg<T>>,
+
+    clock: HybridLogicalClock,
+    entries: HashMap<String,ReplicaEntry<T>>,
+    incarnation: u64, // id of this replicator run - our sequence numbers are only valid within it
+    seq: u64, // our last local sequence number
+    peer_seqs: HashMap<String,u64>, // last sequence number we received from each peer node (kept across disconnects)
+    peer_incarnations: HashMap<String,u64>, // the incarnation the respective peer_seqs entry refers to
+
+    connections: HashMap<u64,PeerConnection>,
+    conn_ids: Arc<AtomicU64>,
+    tasks: Vec<AbortHandle>
+}
+
+impl<T> ShareReplicator<T> where T: SharedStoreValueConstraints {
+    pub fn new (config: ReplicationConfig, hstore: ActorHandle<SharedStoreActorMsg<T>>)->Self {
+        let clock = HybridLogicalClock::new( &config.node_id);
+        ShareReplicator {
+            config: Arc::new(config),
+            hstore,
+            clock,
+            entries: HashMap::new(),
+            incarnation: Utc::now().timestamp_micros() as u64,
+            seq: 0,
+            peer_seqs: HashMap::new(),
+            peer_incarnations: HashMap::new(),
+            connections: HashMap::new(),
+            conn_ids: Arc::new( AtomicU64::new(0)),
+            tasks: Vec::new()
+        }
+    }
+
+    fn start_connections (&mut self, hself: &ActorHandle<ShareReplicatorMsg<T>>)->OdinActorResult<()> {
+        if let Some(addr) = self.config.listen {
+            let task = spawn( "replication-listener", listen_loop( hself.clone(), addr, self.conn_ids.clone()))?;
+            self.tasks.push( task.abort_handle());
+        }
+
+        for uri in &self.config.peers {
+            let task = spawn( "replication-peer", 
+                connect_loop( hself.clone(), uri.clone(), self.config.reconnect_interval, self.conn_ids.clone()))?;
+            self.tasks.push( task.abort_handle());
+        }
+        Ok(())
+    }
+
+    fn stop_connections (&mut self) {
+        for task in &self.tasks { task.abort() }
+        self.tasks.clear();
+        self.connections.clear(); // this closes the tx channels and hence terminates the connection tasks
+    }
+
+    fn next_seq (&mut self)->u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    /// values that are in the store before we start get an initial timestamp so that they lose against any change
+    fn init_replica (&mut self, items: Vec<(String,T)>) {
+        for (key,value) in items {
+            if !self.entries.contains_key( &key) {
+                let seq = self.next_seq();
+                let ts = HlcTimestamp::initial( &self.config.node_id);
+                self.entries.insert( key, ReplicaEntry { ts, value: Some(value), seq, source: None });
+            }
+        }
+    }
+
+    /// reconcile our entries with a store snapshot after we missed local changes. Entries that changed after we requested
+    /// the snapshot (seq > `since_seq`) are newer than the snapshot and hence skipped
+    async fn resync_replica (&mut self, items: Vec<(String,T)>, since_seq: u64) {
+        let mut store_keys = std::collections::HashSet::new();
+
+        for (key,value) in items {
+            store_keys.insert( key.clone());
+            let changed = match self.entries.get( &key) {
+                Some(e) => e.seq <= since_seq && !e.value.as_ref().map( |v| is_same_value( v, &value)).unwrap_or(false),
+                None => true
+            };
+            if changed { self.add_local_change( key, Some(value)).await }
+        }
+
+        let removed: Vec<String> = self.entries.iter()
+            .filter( |(k,e)| e.seq <= since_seq && e.value.is_some() && !store_keys.contains( *k))
+            .map( |(k,_)| k.clone())
+            .collect();
+        for key in removed {
+            self.add_local_change( key, None).await
+        }
+    }
+
+    //--- local changes
+
+    async fn local_change (&mut self, change: SharedStoreChange<T>) {
+        match change {
+            SharedStoreChange::Set { key, .. } => self.local_set( key).await,
+            SharedStoreChange::Remove { key, .. } => self.local_remove( key).await,
+            SharedStoreChange::Rename { old_key, new_key, .. } => {
+                self.local_remove( old_key).await;
+                self.local_set( new_key).await;
+            }
+        }
+    }
+
+    async fn local_set (&mut self, key: String) {
+        match timeout_query_ref( &self.hstore, key.clone(), QUERY_TIMEOUT).await {
+            Ok(Some(value)) => {
+                if let Some(e) = self.entries.get( &key) { // check if this is the echo of a change we applied
+                    if e.value.as_ref().map( |v| is_same_value( v, &value)).unwrap_or(false) { return }
+                }
+                self.add_local_change( key, Some(value)).await
+            }
+            Ok(None) => self.local_remove( key).await, // was removed in the meantime
+            Err(e) => warn!("failed to get store value for {key}: {e}")
+        }
+    }
+
+    async fn local_remove (&mut self, key: String) {
+        if let Some(e) = self.entries.get( &key) {
+            if e.value.is_none() { return } // already a tombstone
+        }
+        self.add_local_change( key, None).await
+    }
+
+    async fn add_local_change (&mut self, key: String, value: Option<T>) {
+        let ts = self.clock.now();
+        let seq = self.next_seq();
+        let change = ReplicatedChange { key: key.clone(), value: value.clone(), ts: ts.clone(), seq };
+        self.entries.insert( key, ReplicaEntry { ts, value, seq, source: None });
+
+        self.send_to_peers( None, vec![change]).await
+    }
+
+    //--- peer messages
+
+    async fn peer_connected (&mut self, conn_id: u64, remote: String, tx: MpscSender<String>) {
+        info!("replication peer {remote} connected");
+        let hello: ReplicationMsg<T> = ReplicationMsg::Hello { 
+            node: self.config.node_id.clone(), 
+            token: self.config.token.clone(), 
+            incarnation: self.incarnation 
+        };
+        if let Ok(json) = serde_json::to_string( &hello) {
+            send( &tx, json).await;
+        }
+        self.connections.insert( conn_id, PeerConnection { remote, node: None, tx });
+    }
+
+    fn peer_disconnected (&mut self, conn_id: u64) {
+        if let Some(conn) = self.connections.remove( &conn_id) {
+            info!("replication peer {} disconnected", conn.remote);
+        }
+    }
+
+    async fn peer_msg (&mut self, conn_id: u64, text: String) {
+        let msg = match serde_json::from_str::<ReplicationMsg<T>>( &text) {
+            Ok(msg) => msg,
+            Err(e) => { warn!("ignoring malformed replication message: {e}"); return }
+        };
+
+        let Some(conn) = self.connections.get_mut( &conn_id) else { return };
+
+        match (msg, conn.node.clone()) {
+            (ReplicationMsg::Hello { node, token, incarnation }, None) => {
+                if !is_same_token( &token, &self.config.token) || node == self.config.node_id {
+                    warn!("rejecting replication peer {} ({node})", conn.remote);
+                    self.connections.remove( &conn_id); // closes the connection
+                } else {
+                    conn.node = Some(node.clone());
+                    if self.peer_incarnations.insert( node.clone(), incarnation) != Some(incarnation) {
+                        self.peer_seqs.remove( &node); // peer was restarted, its old sequence numbers are meaningless
+                    }
+                    let since = self.peer_seqs.get( &node).copied().unwrap_or(0);
+                    let tx = conn.tx.clone();
+                    send_json( &tx, &ReplicationMsg::<T>::Sync { since }).await;
+                }
+            }
+            (ReplicationMsg::Sync { since }, Some(node)) => {
+                let tx = conn.tx.clone();
+                self.send_changes_since( &tx, &node, since).await;
+            }
+            (ReplicationMsg::Changes { changes }, Some(node)) => {
+                self.apply_peer_changes( &node, changes).await;
+            }
+            (_, None) => warn!("ignoring replication message before Hello from {}", conn.remote),
+            (ReplicationMsg::Hello{..}, Some(_)) => warn!("ignoring duplicated Hello from {}", conn.remote),
+        }
+    }
+
+    /// send our changes the peer has not seen yet. We normally skip the changes we got from this peer, but a peer that
+    /// syncs from scratch (e.g. after a restart) might have lost them
+    async fn send_changes_since (&self, tx: &MpscSender<String>, node: &str, since: u64) {
+        let mut changes: Vec<ReplicatedChange<T>> = self.entries.iter()
+            .filter( |(_,e)| e.seq > since && (since == 0 || e.source.as_deref() != Some(node)))
+            .map( |(k,e)| ReplicatedChange { key: k.clone(), value: e.value.clone(), ts: e.ts.clone(), seq: e.seq })
+            .collect();
+        changes.sort_by_key( |c| c.seq); // peer keeps track of the highest seq it got
+
+        for chunk in changes.chunks( MAX_CHANGES_PER_MSG) {
+            send_json( tx, &ReplicationMsg::Changes { changes: chunk.to_vec() }).await;
+        }
+    }
+
+    async fn apply_peer_changes (&mut self, node: &str, changes: Vec<ReplicatedChange<T>>) {
+        let mut forward = Vec::new();
+
+        for change in changes {
+            self.clock.update( &change.ts);
+            let peer_seq = self.peer_seqs.entry( node.to_string()).or_insert(0);
+            *peer_seq = (*peer_seq).max( change.seq);
+
+            let is_newer = self.entries.get( &change.key).map( |e| change.ts > e.ts).unwrap_or(true);
+            if is_newer {
+                let seq = self.next_seq();
+                let ReplicatedChange { key, value, ts, .. } = change;
+
+                let res = match &value {
+                    Some(v) => self.hstore.send_msg( SetSharedStoreValue{ key: key.clone(), value: v.clone(), expected_rev: None, requester: Requester::System }).await,
+                    None => self.hstore.send_msg( RemoveSharedStoreValue{ key: key.clone(), requester: Requester::System }).await
+                };
+                if let Err(e) = res { error!("failed to apply replicated change of {key}: {e}") }
+
+                forward.push( ReplicatedChange { key: key.clone(), value: value.clone(), ts: ts.clone(), seq });
+                self.entries.insert( key, ReplicaEntry { ts, value, seq, source: Some(node.to_string()) });
+            }
+        }
+
+        if !forward.is_empty() {
+            self.send_to_peers( Some(node), forward).await
+        }
+    }
+
+    /// send changes to all (validated) peer connections except the one of node `except`
+    async fn send_to_peers (&self, except: Option<&str>, changes: Vec<ReplicatedChange<T>>) {
+        if self.connections.is_empty() { return } // peers will catch up once they connect
+
+        for chunk in changes.chunks( MAX_CHANGES_PER_MSG) {
+            if let Ok(json) = serde_json::to_string( &ReplicationMsg::Changes { changes: chunk.to_vec() }) {
+                for conn in self.connections.values() {
+                    if let Some(node) = &conn.node {
+                        if except != Some(node.as_str()) {
+                            send( &conn.tx, json.clone()).await;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// compare tokens in time that only depends on their length (not on the position of the first difference)
+fn is_same_token (a: &Option<String>, b: &Option<String>)->bool {
+    match (a,b) {
+        (Some(a),Some(b)) => {
+            a.len() == b.len() && a.bytes().zip( b.bytes()).fold( 0u8, |acc, (x,y)| acc | (x ^ y)) == 0
+        }
+        (None,None) => true,
+        _ => false
+    }
+}
+
+fn is_same_value<T> (a: &T, b: &T)->bool where T: Serialize {
+    match (serde_json::to_string(a), serde_json::to_string(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false
+    }
+}
+
+async fn send_json<M> (tx: &MpscSender<String>, msg: &M) where M: Serialize {
+    match serde_json::to_string( msg) {
+        Ok(json) => { send( tx, json).await; }
+        Err(e) => error!("failed to serialize replication message: {e}")
+    }
+}
+
+//--- messages
+
+/// the initial store content
+#[derive(Debug)]
+pub struct InitReplica<T>( pub Vec<(String,T)> );
+
+pub struct PeerConnected {
+    pub conn_id: u64,
+    pub remote: String,
+    pub tx: MpscSender<String>
+}
+impl Debug for PeerConnected {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PeerConnected({},{})", self.conn_id, self.remote)
+    }
+}
+
+#[derive(Debug)]
+pub struct PeerText {
+    pub conn_id: u64,
+    pub text: String
+}
+
+#[derive(Debug)]
+pub struct PeerDisconnected {
+    pub conn_id: u64
+}
+
+/// sent by the `ReplicatingChangeAction` if it could not pass on a local change. The flag is reset once we got the request
+#[derive(Debug)]
+pub struct RequestResync( pub Arc<AtomicBool> );
+
+/// the store content we reconcile our entries with after we missed local changes
+#[derive(Debug)]
+pub struct ResyncReplica<T> {
+    pub items: Vec<(String,T)>,
+    pub since_seq: u64
+}
+
+define_actor_msg_set! { pub ShareReplicatorMsg<T> where T: SharedStoreValueConstraints = 
+    SharedStoreChange<T> | InitReplica<T> | RequestResync | ResyncReplica<T> | PeerConnected | PeerText | PeerDisconnected
+}
+
+/// the snapshot action we use to get the initial store content. This has to be an explicit type since it is generic
+#[derive(Debug)]
+struct InitReplicaAction<T> where T: SharedStoreValueConstraints {
+    hself: ActorHandle<ShareReplicatorMsg<T>>
+}
+
+#[async_trait]
+impl<T> DynSharedStoreActionTrait<T> for InitReplicaAction<T> where T: SharedStoreValueConstraints {
+    async fn execute (&self, store: &dyn SharedStore<T>) -> Result<(),OdinActionFailure> {
+        Ok( self.hself.try_send_msg( InitReplica( store.clone_iter().collect()))? )
+    }
+}
+
+/// the snapshot action we use to reconcile after missed local changes. Since the replicator mailbox might still be full
+/// (and we must not block the store actor) we send the snapshot from its own task
+#[derive(Debug)]
+struct ResyncReplicaAction<T> where T: SharedStoreValueConstraints {
+    hself: ActorHandle<ShareReplicatorMsg<T>>,
+    since_seq: u64
+}
+
+#[async_trait]
+impl<T> DynSharedStoreActionTrait<T> for ResyncReplicaAction<T> where T: SharedStoreValueConstraints {
+    async fn execute (&self, store: &dyn SharedStore<T>) -> Result<(),OdinActionFailure> {
+        let msg = ResyncReplica { items: store.clone_iter().collect(), since_seq: self.since_seq };
+        let hself = self.hself.clone();
+        spawn( "replica-resync", async move { hself.send_msg( msg).await })?;
+        Ok(())
+    }
+}
+
+impl_actor! { match msg for Actor<ShareReplicator<T>,ShareReplicatorMsg<T>> where T: SharedStoreValueConstraints as
+    _Start_ => cont! {
+        // we only connect once we have the initial store content, otherwise the first Sync could miss it
+        let hself = self.hself.clone();
+        if let Err(e) = self.hstore.send_msg( ExecSnapshotAction( Box::new( InitReplicaAction{ hself: hself.clone() }))).await {
+            error!("failed to get initial store content: {e}");
+            if let Err(e) = self.start_connections( &hself) {
+                error!("failed to start replication connections: {e}");
+            }
+        }
+    }
+    InitReplica<T> => cont! {
+        self.init_replica( msg.0);
+        if self.tasks.is_empty() {
+            let hself = self.hself.clone();
+            if let Err(e) = self.start_connections( &hself) {
+                error!("failed to start replication connections: {e}");
+            }
+        }
+    }
+    SharedStoreChange<T> => cont! {
+        self.local_change( msg).await
+    }
+    RequestResync => cont! {
+        msg.0.store( false, Ordering::Release); // changes after this point trigger a new resync
+        let action = ResyncReplicaAction{ hself: self.hself.clone(), since_seq: self.seq };
+        if let Err(e) = self.hstore.send_msg( ExecSnapshotAction( Box::new( action))).await {
+            error!("failed to request store snapshot for resync: {e}");
+        }
+    }
+    ResyncReplica<T> => cont! {
+        warn!("resyncing replica after missed local changes");
+        self.resync_replica( msg.items, msg.since_seq).await
+    }
+    PeerConnected => cont! {
+        self.peer_connected( msg.conn_id, msg.remote, msg.tx).await
+    }
+    PeerText => cont! {
+        self.peer_msg( msg.conn_id, msg.text).await
+    }
+    PeerDisconnected => cont! {
+        self.peer_disconnected( msg.conn_id)
+    }
+    _Terminate_ => stop! {
+        self.stop_connections()
+    }
+}
+
+/* #endregion actor */
+
+/* #region connection tasks **********************************************************************************/
+
+async fn listen_loop<T> (hself: ActorHandle<ShareReplicatorMsg<T>>, addr: SocketAddr, conn_ids: Arc<AtomicU64>) 
+    where T: SharedStoreValueConstraints
+{
+    let listener = match TcpListener::bind( addr).await {
+        Ok(listener) => listener,
+        Err(e) => { error!("failed to open replication listener on {addr}: {e}"); return }
+    };
+    info!("accepting replication peers on {addr}");
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, remote)) => {
+                let hself = hself.clone();
+                let conn_ids = conn_ids.clone();
+                let res = spawn( "replication-peer", async move {
+                    match accept_async( stream).await {
+                        Ok(ws) => run_peer( hself, conn_ids, ws, remote.to_string()).await,
+                        Err(e) => warn!("replication handshake with {remote} failed: {e}")
+                    }
+                });
+                if let Err(e) = res { error!("failed to spawn replication peer task: {e}") }
+            }
+            Err(e) => warn!("failed to accept replication peer: {e}")
+        }
+    }
+}
+
+async fn connect_loop<T> (hself: ActorHandle<ShareReplicatorMsg<T>>, uri: String, reconnect_interval: Duration, conn_ids: Arc<AtomicU64>)
+    where T: SharedStoreValueConstraints
+{
+    loop {
+        match connect_async( uri.as_str()).await {
+            Ok((ws,_)) => run_peer( hself.clone(), conn_ids.clone(), ws, uri.clone()).await,
+            Err(e) => warn!("failed to connect to replication peer {uri}: {e}")
+        }
+        if !hself.is_running() { break }
+        sleep( reconnect_interval).await;
+    }
+}
+
+/// the per-connection task that forwards incoming peer messages to the actor and sends outgoing ones
+async fn run_peer<T,S> (hself: ActorHandle<ShareReplicatorMsg<T>>, conn_ids: Arc<AtomicU64>, mut ws: WebSocketStream<S>, remote: String)
+    where T: SharedStoreValueConstraints, S: AsyncRead + AsyncWrite + Unpin
+{
+    let conn_id = conn_ids.fetch_add( 1, Ordering::Relaxed);
+    let (tx, rx) = create_mpsc_sender_receiver::<String>( PEER_CHANNEL_BOUNDS);
+    if hself.send_msg( PeerConnected{ conn_id, remote: remote.clone(), tx }).await.is_err() { return }
+
+    loop {
+        tokio::select! {
+            maybe_msg = ws.next() => {
+                match maybe_msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if hself.send_msg( PeerText{ conn_id, text }).await.is_err() { break }
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => {} // ping/pong is handled by tungstenite, we don't use binary messages
+                    Some(Err(e)) => { warn!("replication peer {remote} read failed: {e}"); break }
+                }
+            }
+            maybe_out = recv( &rx) => {
+                match maybe_out {
+                    Ok(text) => {
+                        if let Err(e) = ws.send( Message::Text(text)).await { warn!("replication peer {remote} write failed: {e}"); break }
+                    }
+                    Err(_) => break // actor dropped this connection
+                }
+            }
+        }
+    }
+
+    ws.close(None).await;
+    hself.send_msg( PeerDisconnected{ conn_id }).await;
+}
+
+/* #endregion connection tasks */
+
+/* #region change action *************************************************************************************/
+
+/// a change action for `SharedStoreActor` that sends each change to a `ShareReplicator` before it executes `action`.
+/// We can't block the store actor on a full replicator mailbox (the replicator sends to the store) so if a change cannot be
+/// sent we request a resync, which reconciles the replicator with a store snapshot once it has caught up
+pub struct ReplicatingChangeAction<T,A> where T: SharedStoreValueConstraints {
+    hreplicator: ActorHandle<ShareReplicatorMsg<T>>,
+    resync_pending: Arc<AtomicBool>,
+    action: A
+}
+
+impl<T,A> DataAction<SharedStoreUpdate<T>> for ReplicatingChangeAction<T,A> 
+    where T: SharedStoreValueConstraints, A: DataAction<SharedStoreUpdate<T>>
+{
+    fn execute (&self, update: SharedStoreUpdate<T>) -> impl Future<Output = Result<(),OdinActionFailure>> + Send {
+        if let Err(e) = self.hreplicator.try_send_msg( update.change.clone()) {
+            warn!("failed to send store change to replicator: {e}");
+            if !self.resync_pending.swap( true, Ordering::AcqRel) {
+                let hreplicator = self.hreplicator.clone();
+                let msg = RequestResync( self.resync_pending.clone());
+                if let Err(e) = spawn( "replica-resync-request", async move { hreplicator.send_msg( msg).await }) {
+                    error!("failed to request replica resync: {e}");
+                    self.resync_pending.store( false, Ordering::Release);
+                }
+            }
+        }
+        self.action.execute( update)
+    }
+}
+
+impl<T,A> Debug for ReplicatingChangeAction<T,A> where T: SharedStoreValueConstraints, A: Debug {
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ReplicatingChangeAction({:?})", self.action)
+    }
+}
+
+/// wrap the change action of a `SharedStoreActor` so that changes are also sent to a `ShareReplicator`, e.g.
+/// ```ignore
+/// SharedStoreActor::new( store, init_action, replicate_changes( hreplicator, share_change_action( hserver.clone())))
+/// ```
+pub fn replicate_changes<T,A> (hreplicator: ActorHandle<ShareReplicatorMsg<T>>, action: A)->ReplicatingChangeAction<T,A>
+    where T: SharedStoreValueConstraints, A: DataAction<SharedStoreUpdate<T>>
+{
+    ReplicatingChangeAction { hreplicator, resync_pending: Arc::new( AtomicBool::new(false)), action }
+}
+
+/* #endregion change action */
@@ -0,0 +1,263 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the revisions module implements per-key revision tracking for `SharedStore` implementations. Revisions are the basis
+//! for optimistic concurrency (compare-and-set with expected revisions) and for a bounded per-key change history that
+//! can be queried or used to revert items to earlier versions.
+//!
+//! Revision tracking is done by a `RevisionedStore` wrapper that is used by `SharedStoreActor`, i.e. `SharedStore`
+//! implementors don't have to care about it. Note that revisions and history are kept in memory and are not saved
+//! with the store content - values that are loaded when the store is initialized start at revision 1.
+//!
+//! The history of removed keys is only retained for a limited time and number of keys (see
+//! [`RevisionedStore::set_removed_retention`]), after which it is dropped. Re-created keys then start at revision 1 again.
+
+use std::{collections::{HashMap,VecDeque}, fmt::Debug, marker::PhantomData, time::Duration};
+use chrono::{DateTime,Utc};
+use serde::{Serialize,Deserialize};
+use async_trait::async_trait;
+
+use crate::{SharedStore, SharedStoreValueConstraints, errors::OdinShareError};
+
+pub const DEFAULT_MAX_HISTORY: usize = 16;
+pub const DEFAULT_MAX_REMOVED_KEYS: usize = 1024;
+pub const DEFAULT_REMOVED_RETENTION: Duration = Duration::from_secs( 60*60*24);
+
+/// the revision of a current store value. `modified` is `None` for values that have not been changed since the
+/// store was initialized
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub struct ItemRevision {
+    pub rev: u64,
+    pub modified: Option<DateTime<Utc>>
+}
+
+/// a history entry of a key. A `None` value means the key was removed in this revision
+#[derive(Serialize,Debug,Clone)]
+pub struct SharedStoreRevision<T> {
+    pub rev: u64,
+    pub modified: Option<DateTime<Utc>>,
+    pub value: Option<T>
+}
+
+struct KeyRevisions<T> {
+    last: ItemRevision,
+    history: VecDeque<SharedStoreRevision<T>> // oldest first, last entry is the current state
+}
+
+/// a SharedStore wrapper that keeps track of revisions and a bounded history of values for each key.
+/// Note that the history stores clones of values, i.e. values should be cheap to clone
+pub struct RevisionedStore<T,S> where T: SharedStoreValueConstraints, S: SharedStore<T> {
+    store: S,
+    max_history: usize,
+    revisions: HashMap<String,KeyRevisions<T>>,
+
+    max_removed_keys: usize,
+    removed_retention: Duration,
+    removed: VecDeque<(String,u64,DateTime<Utc>)> // (key,rev,removal time) in order of removal
+}
+
+impl<T,S> RevisionedStore<T,S> where T: SharedStoreValueConstraints, S: SharedStore<T> {
+    pub fn new (store: S, max_history: usize)->Self {
+        RevisionedStore { 
+            store, 
+            max_history: max_history.max(1), 
+            revisions: HashMap::new(),
+            max_removed_keys: DEFAULT_MAX_REMOVED_KEYS,
+            removed_retention: DEFAULT_REMOVED_RETENTION,
+            removed: VecDeque::new()
+        }
+    }
+
+    pub fn set_max_history (&mut self, max_history: usize) {
+        self.max_history = max_history.max(1);
+        for kr in self.revisions.values_mut() {
+            while kr.history.len() > self.max_history { kr.history.pop_front(); }
+        }
+    }
+
+    /// set how many removed keys we keep the history for, and for how long. Once either limit is exceeded the history
+    /// of the oldest removed keys is dropped
+    pub fn set_removed_retention (&mut self, max_removed_keys: usize, removed_retention: Duration) {
+        self.max_removed_keys = max_removed_keys;
+        self.removed_retention = removed_retention;
+        self.prune_removed( Utc::now());
+    }
+
+    /// the number of keys we keep revisions for, including removed keys that are still retained
+    pub fn num_tracked_keys (&self)->usize {
+        self.revisions.len()
+    }
+
+    /// the revision a compare-and-set has to specify. This is 0 if there is no value for `k`
+    pub fn current_rev (&self, k: &str)->u64 {
+        self.revision(k).map( |r| r.rev).unwrap_or(0)
+    }
+
+    /// the revision entry of `k`, lazily creating one (with the initial value as first history entry) if this is
+    /// the first change of `k`
+    fn key_revisions (&mut self, k: &str)->&mut KeyRevisions<T> {
+        if !self.revisions.contains_key(k) {
+            let mut history = VecDeque::new();
+            let last = if let Some(v) = self.store.get(k) {
+                let last = ItemRevision { rev: 1, modified: None };
+                history.push_back( SharedStoreRevision{ rev: last.rev, modified: last.modified, value: Some(v.clone()) });
+                last
+            } else {
+                ItemRevision { rev: 0, modified: None }
+            };
+            self.revisions.insert( k.to_string(), KeyRevisions { last, history });
+        }
+        self.revisions.get_mut(k).unwrap() // we just made sure it is there
+    }
+
+    fn add_revision (&mut self, k: &str, value: Option<T>)->u64 {
+        let max_history = self.max_history;
+        let is_removal = value.is_none();
+        let kr = self.key_revisions(k);
+
+        kr.last = ItemRevision { rev: kr.last.rev + 1, modified: Some(Utc::now()) };
+        kr.history.push_back( SharedStoreRevision{ rev: kr.last.rev, modified: kr.last.modified, value });
+        while kr.history.len() > max_history { kr.history.pop_front(); }
+
+        let ItemRevision{ rev, modified } = kr.last;
+        if is_removal {
+            self.removed.push_back( (k.to_string(), rev, modified.unwrap_or_else( Utc::now)));
+        }
+        self.prune_removed( Utc::now());
+
+        rev
+    }
+
+    /// drop a revision entry that was created for a change the store did not accept, if it has no history
+    fn drop_unchanged (&mut self, k: &str) {
+        if self.revisions.get(k).map( |kr| kr.last.rev == 0).unwrap_or(false) {
+            self.revisions.remove(k);
+        }
+    }
+
+    /// drop the revisions of removed keys that exceed our retention limits. Note that `removed` can contain keys that
+    /// have been re-created or removed again since, which are skipped when they reach the front
+    fn prune_removed (&mut self, now: DateTime<Utc>) {
+        while let Some((_,_,removed)) = self.removed.front() {
+            let age = now.signed_duration_since( *removed).to_std().unwrap_or_default();
+            if self.removed.len() <= self.max_removed_keys && age < self.removed_retention { break }
+
+            let (k,rev,_) = self.removed.pop_front().unwrap(); // we just checked
+            let is_last_removal = self.revisions.get( &k).map( |kr| kr.last.rev == rev).unwrap_or(false);
+            if is_last_removal && !self.store.contains_key( &k) {
+                self.revisions.remove( &k);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T,S> SharedStore<T> for RevisionedStore<T,S> where T: SharedStoreValueConstraints, S: SharedStore<T> {
+    fn ref_iter<'a>(&'a self)->Box<dyn Iterator<Item=(&'a String,&'a T)> + 'a> {
+        self.store.ref_iter()
+    }
+
+    fn glob_ref_iter<'a> (&'a self, glob_pattern: &str)->Result<Box<dyn Iterator<Item=(&'a String,&'a T)> + 'a>, OdinShareError> {
+        self.store.glob_ref_iter( glob_pattern)
+    }
+
+    fn glob_clone_iter(&self, glob_pattern: &str)->Result<Box<dyn Iterator<Item=(String,T)> + '_>, OdinShareError> {
+        self.store.glob_clone_iter( glob_pattern)
+    }
+
+    fn len(&self)->usize { 
+        self.store.len() 
+    }
+
+    fn contains_key (&self, k: &str)->bool { 
+        self.store.contains_key(k) 
+    }
+
+    /// note we only add a revision if the store accepted the change
+    fn insert(&mut self, k: String, v: T)->Result<Option<T>,OdinShareError> {
+        self.key_revisions( &k); // record the current state before we change the store
+        match self.store.insert( k.clone(), v.clone()) {
+            Ok(replaced) => {
+                self.add_revision( &k, Some(v));
+                Ok(replaced)
+            }
+            Err(e) => {
+                self.drop_unchanged( &k);
+                Err(e)
+            }
+        }
+    }
+
+    fn remove (&mut self, k: &str)->Result<Option<T>,OdinShareError> {
+        if !self.store.contains_key(k) { return self.store.remove(k) }
+
+        self.key_revisions( k);
+        let removed = self.store.remove(k)?;
+        self.add_revision( k, None);
+        Ok(removed)
+    }
+
+    fn get (&self, k: &str)->Option<&T> {
+        self.store.get(k)
+    }
+
+    fn rename (&mut self, old_k: &str, new_k: String)->Result<Option<T>,OdinShareError> {
+        let Some(v) = self.store.get( old_k).cloned() else { return self.store.rename( old_k, new_k) };
+
+        self.key_revisions( old_k);
+        self.key_revisions( &new_k);
+        match self.store.rename( old_k, new_k.clone()) {
+            Ok(replaced) => {
+                self.add_revision( old_k, None);
+                self.add_revision( &new_k, Some(v));
+                Ok(replaced)
+            }
+            Err(e) => {
+                self.drop_unchanged( &new_k);
+                Err(e)
+            }
+        }
+    }
+
+    fn revision (&self, k: &str)->Option<ItemRevision> {
+        if self.store.contains_key(k) {
+            Some( self.revisions.get(k).map( |kr| kr.last).unwrap_or( ItemRevision{ rev: 1, modified: None }))
+        } else {
+            None
+        }
+    }
+
+    fn history (&self, k: &str)->Vec<SharedStoreRevision<T>> {
+        if let Some(kr) = self.revisions.get(k) {
+            kr.history.iter().cloned().collect()
+        } else if let Some(v) = self.store.get(k) {
+            vec![ SharedStoreRevision{ rev: 1, modified: None, value: Some(v.clone()) } ]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn to_json (&self)->Result<String,OdinShareError> {
+        self.store.to_json()
+    }
+
+    fn save (&self)->Result<(),OdinShareError> {
+        self.store.save()
+    }
+
+    async fn initialize (&self)->Result<(),OdinShareError> { 
+        self.store.initialize().await
+    }
+}
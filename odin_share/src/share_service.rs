@@ -23,15 +23,24 @@
 
 use odin_server::{ prelude::*, errors::op_failed,};
 use async_trait::async_trait;
+use odin_action::{data_action, DataAction};
 use odin_actor::prelude::*;
 use odin_build::prelude::*;
-use odin_common::{define_serde_struct, geo::{GeoBoundingBox, GeoPos, LatLon}};
+use odin_common::{define_serde_struct, geo::{DatedGeoPos, GeoBoundingBox, GeoPos, LatLon}};
+use geojson::FeatureCollection;
 use core::str;
-use std::{sync::Arc,fmt::Debug, fs::File, io::BufReader, path::{Path, PathBuf},collections::HashMap, any::type_name, net::SocketAddr};
+use std::{sync::Arc,fmt::Debug, future::Future, time::Duration, fs::File, io::BufReader, path::{Path, PathBuf},collections::HashMap, any::type_name, net::SocketAddr};
 use serde::{Serialize,Deserialize};
 use bytes::Bytes;
-use crate::{dyn_shared_store_action, SharedStore, SharedStoreValueConstraints, load_asset,
-    actor::{ExecSnapshotAction, SharedStoreActorMsg}
+use chrono::{DateTime,Utc};
+use crate::{dyn_shared_store_action, SharedStore, SharedStoreValueConstraints, DynSharedStoreAction, load_asset,
+    actor::{ExecSnapshotAction, SharedStoreActorMsg, SharedStoreChange, SharedStoreUpdate, SetSharedStoreValue, RemoveSharedStoreValue, RenameSharedStoreValue,
+        RevertSharedStoreValue, GetSharedStoreHistory, GetValidSharedStoreValues},
+    revisions::{ItemRevision, SharedStoreRevision},
+    acl::{KeyAcl, Requester},
+    validity::Validity,
+    geo_items::{Polygon, MultiPolygon, Circle, TimeRange, to_feature_collection, from_feature_collection},
+    errors::OdinShareError
 };
 
 /// the generic wrapper type for shared items. This is what we keep in a SharedStore
@@ -43,6 +52,15 @@ pub enum SharedItem {
     Point2D ( SharedItemValue<LatLon> ),
     Point3D ( SharedItemValue<GeoPos> ),
     Polyline ( SharedItemValue<Vec<LatLon>> ),
+    Polygon ( SharedItemValue<Polygon> ),
+    MultiPolygon ( SharedItemValue<MultiPolygon> ),
+    Circle ( SharedItemValue<Circle> ),
+    BoundingBox ( SharedItemValue<GeoBoundingBox> ),
+    Track ( SharedItemValue<Vec<DatedGeoPos>> ),
+    GeoJson ( SharedItemValue<FeatureCollection> ),
+
+    // temporal types
+    TimeRange ( SharedItemValue<TimeRange> ),
 
     // primitive types
     U64 ( SharedItemValue<u64> ),
@@ -59,7 +77,8 @@ pub enum SharedItem {
 /// Note that we don't store the key - SharedItemValues are always accessed through their containing store, i.e.
 /// storing the key would be redundant.
 /// Note also that we keep the data in an Arc so that values can be efficiently cloned and we don't suffer from potential
-/// enum variant size disparity
+/// enum variant size disparity.
+/// The (optional) validity fields are serialized inline, e.g. `{"type": "Polygon", "validUntil": "2024-08-16T18:00:00Z", "ttl": "2h", ..}`
 #[derive(Serialize,Deserialize,Clone,Debug,PartialEq)]
 #[serde(bound = "T: for<'a> serde::Deserialize<'a>")]
 pub struct SharedItemValue <T> 
@@ -67,13 +86,239 @@ pub struct SharedItemValue <T>
 {
     pub comment: Option<String>,
     pub owner: Option<String>,
+    #[serde(flatten)]
+    pub validity: Validity,
     pub data: Arc<T>
 }
 
+impl <T> SharedItemValue<T> where T: SharedStoreValueConstraints {
+    pub fn new (data: T)->Self {
+        SharedItemValue { comment: None, owner: None, validity: Validity::default(), data: Arc::new(data) }
+    }
+}
+
+impl SharedItem {
+    /// the validity of the item, which is used by `SharedStoreActor::with_validity(..)` to expire items
+    pub fn validity (&self)->Validity {
+        match self {
+            SharedItem::Point2D(v) => v.validity,
+            SharedItem::Point3D(v) => v.validity,
+            SharedItem::Polyline(v) => v.validity,
+            SharedItem::Polygon(v) => v.validity,
+            SharedItem::MultiPolygon(v) => v.validity,
+            SharedItem::Circle(v) => v.validity,
+            SharedItem::BoundingBox(v) => v.validity,
+            SharedItem::Track(v) => v.validity,
+            SharedItem::GeoJson(v) => v.validity,
+            SharedItem::TimeRange(v) => v.validity,
+            SharedItem::U64(v) => v.validity,
+            SharedItem::F64(v) => v.validity,
+            SharedItem::String(v) => v.validity,
+            SharedItem::Json(v) => v.validity,
+        }
+    }
+
+    /// set the validity of the item. This is used by `SharedStoreActor::with_validity(..)` to turn TTLs into absolute times
+    pub fn set_validity (&mut self, validity: Validity) {
+        match self {
+            SharedItem::Point2D(v) => v.validity = validity,
+            SharedItem::Point3D(v) => v.validity = validity,
+            SharedItem::Polyline(v) => v.validity = validity,
+            SharedItem::Polygon(v) => v.validity = validity,
+            SharedItem::MultiPolygon(v) => v.validity = validity,
+            SharedItem::Circle(v) => v.validity = validity,
+            SharedItem::BoundingBox(v) => v.validity = validity,
+            SharedItem::Track(v) => v.validity = validity,
+            SharedItem::GeoJson(v) => v.validity = validity,
+            SharedItem::TimeRange(v) => v.validity = validity,
+            SharedItem::U64(v) => v.validity = validity,
+            SharedItem::F64(v) => v.validity = validity,
+            SharedItem::String(v) => v.validity = validity,
+            SharedItem::Json(v) => v.validity = validity,
+        }
+    }
+}
+
+//--- websocket message payloads (used in both directions)
+
+/// "setSharedItem" payload, e.g. `{"key": "incident/czu/origin", "item": {"type": "Point2D", "comment": null, "owner": null, "data": {"lat_deg": 37.1, "lon_deg": -122.2}}, "expectedRev": 3}`.
+/// Clients can set `expectedRev` to the last revision they know of (or 0 for new items) to avoid overwriting changes
+/// of other users. Messages sent to clients include the new `rev`
+#[derive(Serialize,Deserialize,Debug)]
+#[serde(rename_all="camelCase")]
+pub struct SetSharedItem {
+    pub key: String,
+    pub item: SharedItem,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub expected_rev: Option<u64>,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub rev: Option<ItemRevision>
+}
+
+/// "removeSharedItem" payload, e.g. `{"key": "incident/czu/origin"}`
+#[derive(Serialize,Deserialize,Debug)]
+pub struct RemoveSharedItem {
+    pub key: String
+}
+
+/// "renameSharedItem" payload, e.g. `{"oldKey": "incident/czu/origin", "newKey": "incident/czu/ignition"}`.
+/// When sent to clients this includes the (current) item so that the message is self-contained
+#[derive(Serialize,Deserialize,Debug)]
+#[serde(rename_all="camelCase")]
+pub struct RenameSharedItem {
+    pub old_key: String,
+    pub new_key: String,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub item: Option<SharedItem>,
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub rev: Option<ItemRevision>
+}
+
+/// "revertSharedItem" payload, e.g. `{"key": "incident/czu/origin", "rev": 2}`
+#[derive(Serialize,Deserialize,Debug)]
+pub struct RevertSharedItem {
+    pub key: String,
+    pub rev: u64
+}
+
+/// "getSharedItemHistory" payload, e.g. `{"key": "incident/czu/origin"}`
+#[derive(Serialize,Deserialize,Debug)]
+pub struct GetSharedItemHistory {
+    pub key: String
+}
+
+/// "sharedItemHistory" response payload (oldest revision first)
+#[derive(Serialize,Debug)]
+pub struct SharedItemHistory {
+    pub key: String,
+    pub revisions: Vec<SharedStoreRevision<SharedItem>>
+}
+
+/// "sharedItemConflict" response payload, sent if a "setSharedItem" had an outdated `expectedRev`
+#[derive(Serialize,Debug)]
+pub struct SharedItemConflict {
+    pub key: String,
+    pub expected: u64,
+    pub current: u64
+}
+
+/// "sharedItemRejected" response payload, sent if a "setSharedItem" or "importGeoJson" had an invalid item
+#[derive(Serialize,Debug)]
+pub struct SharedItemRejected {
+    pub key: String,
+    pub reason: String
+}
+
+/// "importGeoJson" payload. Features are stored under their "key" property or, if they don't have one, under
+/// `prefix` + feature id/index, e.g. `{"prefix": "incident/czu/import/", "collection": {"type": "FeatureCollection", "features": [...]}}`
+#[derive(Serialize,Deserialize,Debug)]
+pub struct ImportGeoJson {
+    pub prefix: String,
+    pub collection: FeatureCollection
+}
+
+/// "exportGeoJson" payload. The response is a "geoJson" message with a FeatureCollection of all geospatial items
+/// that have keys starting with `prefix` (all geospatial items if not set)
+#[derive(Serialize,Deserialize,Debug)]
+pub struct ExportGeoJson {
+    #[serde(default)]
+    pub prefix: Option<String>
+}
+
+/// "getSharedItemsAt" payload, e.g. `{"pattern": "incident/czu/**", "time": "2020-08-16T12:00:00Z"}`
+#[derive(Serialize,Deserialize,Debug)]
+pub struct GetSharedItemsAt {
+    #[serde(default="all_keys")]
+    pub pattern: String,
+    pub time: DateTime<Utc>
+}
+
+fn all_keys()->String { "**".to_string() }
+
+/// "sharedItemsAt" response payload with the items that were valid at the requested time
+#[derive(Serialize,Debug)]
+pub struct SharedItemsAt {
+    pub time: DateTime<Utc>,
+    pub items: HashMap<String,SharedItem>
+}
+
+/// the value type of "initSharedItems" payload maps
+#[derive(Serialize,Debug)]
+struct SharedItemEntry<'a> {
+    item: &'a SharedItem,
+    rev: Option<ItemRevision>
+}
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub(crate) fn is_valid_key (key: &str)->bool {
+    !key.is_empty() && key.trim() == key
+}
+
+/// broadcast `data` to all connections that can read `key`. We can't block the store actor indefinitely on a full
+/// server mailbox since the server also sends to the store, hence the send is bounded by `QUERY_TIMEOUT`
+async fn broadcast_for_key (hserver: &ActorHandle<SpaServerMsg>, acl: &Arc<KeyAcl>, key: &str, data: String)->odin_actor::errors::Result<()> {
+    if acl.is_public_read( key) {
+        hserver.timeout_send_msg( BroadcastWsMsg{data}, QUERY_TIMEOUT).await
+    } else {
+        let acl = acl.clone();
+        let key = key.to_string();
+        let user_filter: UserFilter = Arc::new( move |user| acl.can_read( user, &key));
+        hserver.timeout_send_msg( BroadcastUserWsMsg{data, user_filter}, QUERY_TIMEOUT).await
+    }
+}
+
+/// the change action to use for a `SharedStoreActor` that is connected to a `ShareService`. This broadcasts all
+/// store changes to the connected clients, including the client that originated a change (clients only update
+/// their view from these broadcasts, i.e. the store is the single source of truth).
+///
+/// The `SharedStoreUpdate` passed into change actions includes the value and revision of set or renamed items so
+/// we can broadcast directly from within the store actor, which also makes sure broadcasts are sent in the order in
+/// which changes were made
+pub fn share_change_action (hserver: ActorHandle<SpaServerMsg>)->impl DataAction<SharedStoreUpdate<SharedItem>> {
+    acl_share_change_action( hserver, Arc::new( KeyAcl::open()))
+}
+
+/// a `share_change_action` that only sends changes to connections of users which are allowed to read the
+/// respective keys. Use the same `KeyAcl` as for the `SharedStoreActor` and the `ShareService`
+pub fn acl_share_change_action (hserver: ActorHandle<SpaServerMsg>, acl: Arc<KeyAcl>)->impl DataAction<SharedStoreUpdate<SharedItem>> {
+    data_action!( let hserver: ActorHandle<SpaServerMsg> = hserver, let acl: Arc<KeyAcl> = acl => |update: SharedStoreUpdate<SharedItem>| {
+        let SharedStoreUpdate{ change, value, rev } = update;
+        match change {
+            SharedStoreChange::Set { key, .. } => {
+                if let Some(item) = value {
+                    let msg = SetSharedItem{ key: key.clone(), item, expected_rev: None, rev };
+                    let data = WsMsg::json( ShareService::mod_path(), "setSharedItem", msg)?;
+                    broadcast_for_key( hserver, acl, &key, data).await?;
+                }
+                Ok(())
+            }
+            SharedStoreChange::Remove { key, .. } => {
+                let data = WsMsg::json( ShareService::mod_path(), "removeSharedItem", RemoveSharedItem{ key: key.clone() })?;
+                Ok( broadcast_for_key( hserver, acl, &key, data).await? )
+            }
+            SharedStoreChange::Rename { old_key, new_key, .. } => {
+                let data = WsMsg::json( ShareService::mod_path(), "renameSharedItem", 
+                    RenameSharedItem{ old_key: old_key.clone(), new_key: new_key.clone(), item: value, rev })?;
+                broadcast_for_key( hserver, acl, &new_key, data).await?;
+
+                // users that could see the old item but can't read the new key just see a removal
+                if !acl.is_public_read( &new_key) {
+                    let data = WsMsg::json( ShareService::mod_path(), "removeSharedItem", RemoveSharedItem{ key: old_key.clone() })?;
+                    let acl = acl.clone();
+                    let user_filter: UserFilter = Arc::new( move |user| acl.can_read( user, &old_key) && !acl.can_read( user, &new_key));
+                    hserver.timeout_send_msg( BroadcastUserWsMsg{data, user_filter}, QUERY_TIMEOUT).await?;
+                }
+                Ok(())
+            }
+        }
+    })
+}
 
 /// micro service to share data between users and other micro-services. This is UI-less
 pub struct ShareService {
-    hstore: ActorHandle<SharedStoreActorMsg<SharedItem>>
+    hstore: ActorHandle<SharedStoreActorMsg<SharedItem>>,
+    acl: Arc<KeyAcl>
 }
 
 impl ShareService 
@@ -82,7 +327,42 @@ impl ShareService
 
     pub fn new (hstore: ActorHandle<SharedStoreActorMsg<SharedItem>>) -> Self {
         //let data_dir = odin_build::data_dir().join("odin_server");
-        ShareService { hstore }
+        ShareService { hstore, acl: Arc::new( KeyAcl::open()) }
+    }
+
+    /// set the access control rules for the key space. Clients only get items they are allowed to read and
+    /// requests for keys they are not allowed to write are rejected
+    pub fn with_acl (mut self, acl: Arc<KeyAcl>)->Self {
+        self.acl = acl;
+        self
+    }
+
+    fn rejection (key: impl ToString, reason: impl ToString)->OdinServerResult<WsMsgReaction> {
+        let data = WsMsg::json( ShareService::mod_path(), "sharedItemRejected", SharedItemRejected{ key: key.to_string(), reason: reason.to_string() })?;
+        Ok( WsMsgReaction::Send(data) )
+    }
+
+    fn access_denied (key: impl ToString)->OdinServerResult<WsMsgReaction> {
+        Self::rejection( key, "access denied")
+    }
+
+    /// run a store query in a background task so that a slow store does not block the SpaServer (and hence all other
+    /// websocket traffic). If the query produces a response it is sent directly to the requesting connection
+    fn reply_async<F> (hself: &ActorHandle<SpaServerMsg>, conn_id: &ConnectionId, task_name: &str, response: F)
+        where F: Future<Output=OdinServerResult<Option<String>>> + Send + 'static
+    {
+        let hself = hself.clone();
+        let conn_id = *conn_id;
+        let res = spawn( task_name, async move {
+            match response.await {
+                Ok(Some(data)) => if let Err(e) = hself.send_msg( SendWsMsg{ conn_id, data}).await {
+                    warn!("failed to send store response to {conn_id}: {e}")
+                }
+                Ok(None) => {}
+                Err(e) => warn!("store query for {conn_id} failed: {e}")
+            }
+        });
+        if let Err(e) = res { warn!("failed to spawn {task_name}: {e}") }
     }
 }
 
@@ -107,11 +387,16 @@ impl SpaService for ShareService {
         if is_data_available {
             let action = dyn_shared_store_action!( 
                 let hself: ActorHandle<SpaServerMsg> = hself.clone(),
-                let remote_addr: SocketAddr = conn.remote_addr => 
+                let acl: Arc<KeyAcl> = self.acl.clone(),
+                let user: Option<String> = conn.user.clone(),
+                let conn_id: ConnectionId = conn.id => 
                 |store as &dyn SharedStore<SharedItem>| {
-                    let json = store.to_json()?;
-                    let msg = ws_msg_from_json(ShareService::mod_path(), "initSharedItems", &json);
-                    hself.try_send_msg( SendWsMsg{ remote_addr: *remote_addr, data: msg});
+                    let items: HashMap<&String,SharedItemEntry> = store.ref_iter()
+                        .filter( |(key,_)| acl.can_read( user.as_deref(), key))
+                        .map( |(key,item)| (key, SharedItemEntry{ item, rev: store.revision(key) }))
+                        .collect();
+                    let msg = WsMsg::json( ShareService::mod_path(), "initSharedItems", items)?;
+                    hself.try_send_msg( SendWsMsg{ conn_id: *conn_id, data: msg});
                     Ok(())
                 }
             );
@@ -129,16 +414,138 @@ impl SpaService for ShareService {
         Ok(true)
     }
 
-    // "setLatLon": { "key": "/incidents/czu/origin", "comment": "blah", "data": {"lat": 37.123, "lon": -122.12} }
-
-    /// this is how we get data from clients. Called from ws input task of respective connection
+    /// this is how we get data from clients. Called from ws input task of respective connection.
+    /// Note that we don't respond directly - clients get updated by the store change action (see [`share_change_action`]).
+    /// Requests that are not permitted by the ACL or contain invalid items are answered with a "sharedItemRejected" message.
+    /// Requests that need a store response (compare-and-set, history and time queries) are answered asynchronously
+    /// since we must not wait for the store inside of the SpaServer actor
     async fn handle_ws_msg (&mut self, 
-        hself: &ActorHandle<SpaServerMsg>, remote_addr: &SocketAddr, ws_msg_parts: &WsMsgParts) -> OdinServerResult<WsMsgReaction> 
+        hself: &ActorHandle<SpaServerMsg>, conn_id: &ConnectionId, user: Option<&str>, ws_msg_parts: &WsMsgParts) -> OdinServerResult<WsMsgReaction> 
     {
         if ws_msg_parts.mod_path == ShareService::mod_path() {
+            let requester = Requester::user( user);
+
             match ws_msg_parts.msg_type {
-                "setLatLon" => {
-                    if let Ok(shared_item) = serde_json::from_str::<SharedItem>(ws_msg_parts.payload) {
+                "setSharedItem" => {
+                    match serde_json::from_str::<SetSharedItem>(ws_msg_parts.payload) {
+                        Ok(SetSharedItem{key,..}) if !self.acl.can_write( user, &key) => return Self::access_denied( key),
+                        Ok(SetSharedItem{key,item,..}) if item.validate().is_err() => { // don't even bother the store
+                            let reason = item.validate().err().map( |e| e.to_string()).unwrap_or_default();
+                            return Self::rejection( key, reason)
+                        }
+                        Ok(SetSharedItem{key,item,expected_rev: None,..}) if is_valid_key(&key) => {
+                            self.hstore.send_msg( SetSharedStoreValue{ key, value: item, expected_rev: None, requester }).await?
+                        }
+                        Ok(SetSharedItem{key,item,expected_rev,..}) if is_valid_key(&key) => { // compare-and-set, report conflicts to sender
+                            let set = SetSharedStoreValue{ key, value: item, expected_rev, requester };
+                            let hstore = self.hstore.clone();
+                            Self::reply_async( hself, conn_id, "share-set", async move {
+                                match timeout_query( hstore, set, QUERY_TIMEOUT).await? {
+                                    Ok(_) => Ok(None), // we get the broadcast from the change action
+                                    Err(OdinShareError::RevisionConflict{key,expected,current}) => {
+                                        Ok( Some( WsMsg::json( ShareService::mod_path(), "sharedItemConflict", SharedItemConflict{key,expected,current})?))
+                                    }
+                                    Err(e) => { warn!("failed to set shared item: {e}"); Ok(None) }
+                                }
+                            });
+                        }
+                        Ok(SetSharedItem{key,..}) => warn!("ignoring invalid shared item key {key:?} from {conn_id}"),
+                        Err(e) => warn!("ignoring malformed setSharedItem message from {conn_id}: {e}")
+                    }
+                }
+                "removeSharedItem" => {
+                    match serde_json::from_str::<RemoveSharedItem>(ws_msg_parts.payload) {
+                        Ok(RemoveSharedItem{key}) if !self.acl.can_write( user, &key) => return Self::access_denied( key),
+                        Ok(RemoveSharedItem{key}) => self.hstore.send_msg( RemoveSharedStoreValue{ key, requester }).await?,
+                        Err(e) => warn!("ignoring malformed removeSharedItem message from {conn_id}: {e}")
+                    }
+                }
+                "renameSharedItem" => {
+                    match serde_json::from_str::<RenameSharedItem>(ws_msg_parts.payload) {
+                        Ok(RenameSharedItem{old_key,..}) if !self.acl.can_write( user, &old_key) => return Self::access_denied( old_key),
+                        Ok(RenameSharedItem{new_key,..}) if !self.acl.can_write( user, &new_key) => return Self::access_denied( new_key),
+                        Ok(RenameSharedItem{old_key,new_key,..}) if is_valid_key(&new_key) => {
+                            self.hstore.send_msg( RenameSharedStoreValue{ old_key, new_key, requester }).await?
+                        }
+                        Ok(RenameSharedItem{new_key,..}) => warn!("ignoring invalid shared item key {new_key:?} from {conn_id}"),
+                        Err(e) => warn!("ignoring malformed renameSharedItem message from {conn_id}: {e}")
+                    }
+                }
+                "revertSharedItem" => {
+                    match serde_json::from_str::<RevertSharedItem>(ws_msg_parts.payload) {
+                        Ok(RevertSharedItem{key,..}) if !self.acl.can_write( user, &key) => return Self::access_denied( key),
+                        Ok(RevertSharedItem{key,rev}) => self.hstore.send_msg( RevertSharedStoreValue{ key, rev, requester }).await?,
+                        Err(e) => warn!("ignoring malformed revertSharedItem message from {conn_id}: {e}")
+                    }
+                }
+                "getSharedItemHistory" => {
+                    match serde_json::from_str::<GetSharedItemHistory>(ws_msg_parts.payload) {
+                        Ok(GetSharedItemHistory{key}) => {
+                            let hstore = self.hstore.clone();
+                            Self::reply_async( hself, conn_id, "share-history", async move {
+                                let revisions = timeout_query( hstore, GetSharedStoreHistory{ key: key.clone(), requester }, QUERY_TIMEOUT).await?;
+                                Ok( Some( WsMsg::json( ShareService::mod_path(), "sharedItemHistory", SharedItemHistory{key,revisions})?))
+                            });
+                        }
+                        Err(e) => warn!("ignoring malformed getSharedItemHistory message from {conn_id}: {e}")
+                    }
+                }
+                "getSharedItemsAt" => {
+                    match serde_json::from_str::<GetSharedItemsAt>(ws_msg_parts.payload) {
+                        Ok(GetSharedItemsAt{pattern,time}) => {
+                            let query = GetValidSharedStoreValues{ glob_pattern: pattern, time, requester };
+                            let hstore = self.hstore.clone();
+                            Self::reply_async( hself, conn_id, "share-items-at", async move {
+                                match timeout_query( hstore, query, QUERY_TIMEOUT).await? {
+                                    Ok(items) => {
+                                        Ok( Some( WsMsg::json( ShareService::mod_path(), "sharedItemsAt", SharedItemsAt{ time, items: items.into_iter().collect() })?))
+                                    }
+                                    Err(e) => { warn!("failed to get shared items at {time}: {e}"); Ok(None) }
+                                }
+                            });
+                        }
+                        Err(e) => warn!("ignoring malformed getSharedItemsAt message from {conn_id}: {e}")
+                    }
+                }
+                "importGeoJson" => {
+                    match serde_json::from_str::<ImportGeoJson>(ws_msg_parts.payload) {
+                        Ok(ImportGeoJson{prefix,collection}) => {
+                            // this is all-or-nothing - we don't want to end up with partial imports
+                            match from_feature_collection( &collection, &prefix) {
+                                Ok(items) => {
+                                    if let Some((key,_)) = items.iter().find( |(key,_)| !self.acl.can_write( user, key)) {
+                                        return Self::access_denied( key)
+                                    }
+                                    for (key,item) in items {
+                                        self.hstore.send_msg( SetSharedStoreValue{ key, value: item, expected_rev: None, requester: requester.clone() }).await?
+                                    }
+                                }
+                                Err(e) => return Self::rejection( prefix, e)
+                            }
+                        }
+                        Err(e) => warn!("ignoring malformed importGeoJson message from {conn_id}: {e}")
+                    }
+                }
+                "exportGeoJson" => {
+                    match serde_json::from_str::<ExportGeoJson>(ws_msg_parts.payload) {
+                        Ok(ExportGeoJson{prefix}) => {
+                            let action = dyn_shared_store_action!(
+                                let hself: ActorHandle<SpaServerMsg> = hself.clone(),
+                                let acl: Arc<KeyAcl> = self.acl.clone(),
+                                let user: Option<String> = user.map( |u| u.to_string()),
+                                let conn_id: ConnectionId = *conn_id,
+                                let prefix: Option<String> = prefix =>
+                                |store as &dyn SharedStore<SharedItem>| {
+                                    let items = store.ref_iter().filter( |(key,_)| {
+                                        prefix.as_ref().map_or( true, |p| key.starts_with(p.as_str())) && acl.can_read( user.as_deref(), key)
+                                    });
+                                    let data = WsMsg::json( ShareService::mod_path(), "geoJson", to_feature_collection( items))?;
+                                    Ok( hself.try_send_msg( SendWsMsg{ conn_id: *conn_id, data})? )
+                                }
+                            );
+                            self.hstore.send_msg( ExecSnapshotAction(action)).await?
+                        }
+                        Err(e) => warn!("ignoring malformed exportGeoJson message from {conn_id}: {e}")
                     }
                 }
                 _ => {
@@ -0,0 +1,223 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! a `SharedStore` implementation that is backed by an embedded SQLite database. Values are stored as JSON text, each
+//! mutation is written (and committed) immediately, i.e. there is no whole-store `save()` step that could lose changes
+//! if the process is terminated. The database uses write-ahead logging with full synchronization for crash safety.
+//!
+//! Since `SharedStore` accessors return references we keep a write-through cache of all values in memory. The database
+//! is the persistent source of truth and is used for indexed key prefix queries, which are the basis of `glob_..iter()`
+//! queries with patterns that start with a literal prefix (e.g. "incident/czu/**").
+
+use std::{collections::HashMap, path::{Path,PathBuf}, sync::{Mutex,MutexGuard}};
+use rusqlite::{params, Connection, OptionalExtension};
+use async_trait::async_trait;
+use odin_actor::{warn,error};
+
+use crate::{SharedStore, SharedStoreValueConstraints, errors::{op_failed, OdinShareError}};
+
+const INIT_SQL: &str = "
+    PRAGMA synchronous=FULL;
+    CREATE TABLE IF NOT EXISTS shared_items (
+        key   TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    ) WITHOUT ROWID;
+";
+
+pub struct SqliteStore<T> where T: SharedStoreValueConstraints {
+    path: Option<PathBuf>, // None for in-memory databases
+    conn: Mutex<Connection>, // Connection is not Sync
+    cache: HashMap<String,T>
+}
+
+impl<T> SqliteStore<T> where T: SharedStoreValueConstraints {
+
+    /// open or create a SQLite store at the given path
+    pub fn open<P> (path: &P)->Result<Self,OdinShareError> where P: AsRef<Path> {
+        let conn = Connection::open( path)?;
+        Self::from_connection( conn, Some(path.as_ref().to_path_buf()))
+    }
+
+    /// create a non-persistent SQLite store (mostly for testing purposes)
+    pub fn open_in_memory ()->Result<Self,OdinShareError> {
+        let conn = Connection::open_in_memory()?;
+        Self::from_connection( conn, None)
+    }
+
+    fn from_connection (conn: Connection, path: Option<PathBuf>)->Result<Self,OdinShareError> {
+        // journal_mode returns the new mode (which is "memory" for in-memory databases)
+        conn.pragma_update_and_check( None, "journal_mode", "WAL", |row| row.get::<_,String>(0))?;
+        conn.execute_batch( INIT_SQL)?;
+        let cache = load_items( &conn)?;
+        Ok( SqliteStore { path, conn: Mutex::new(conn), cache } )
+    }
+
+    pub fn path (&self)->Option<&Path> {
+        self.path.as_ref().map( |p| p.as_path())
+    }
+
+    fn conn (&self)->MutexGuard<Connection> {
+        self.conn.lock().unwrap_or_else( |poisoned| poisoned.into_inner())
+    }
+
+    /// insert or replace a batch of items within a single transaction, i.e. either all or none of them get stored
+    pub fn insert_all<I> (&mut self, items: I)->Result<(),OdinShareError> where I: IntoIterator<Item=(String,T)> {
+        let items: Vec<(String,T)> = items.into_iter().collect();
+        {
+            let mut conn = self.conn();
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached( "INSERT OR REPLACE INTO shared_items (key,value) VALUES (?1,?2)")?;
+                for (k,v) in &items {
+                    stmt.execute( params![k, serde_json::to_string(v)?])?;
+                }
+            }
+            tx.commit()?;
+        }
+        self.cache.extend( items);
+        Ok(())
+    }
+
+    /// get the (sorted) keys that start with `prefix`. This uses the primary key index of the database
+    pub fn prefix_keys (&self, prefix: &str)->Result<Vec<String>,OdinShareError> {
+        let conn = self.conn();
+        let mut keys = Vec::new();
+
+        if prefix.is_empty() {
+            let mut stmt = conn.prepare_cached( "SELECT key FROM shared_items ORDER BY key")?;
+            for key in stmt.query_map( [], |row| row.get::<_,String>(0))? {
+                keys.push( key?);
+            }
+        } else {
+            // all keys with prefix sort between the prefix and the prefix followed by the largest unicode char
+            let upper = format!("{prefix}\u{10FFFF}");
+            let mut stmt = conn.prepare_cached( "SELECT key FROM shared_items WHERE key >= ?1 AND key < ?2 ORDER BY key")?;
+            for key in stmt.query_map( params![prefix, upper], |row| row.get::<_,String>(0))? {
+                keys.push( key?);
+            }
+        }
+        Ok(keys)
+    }
+
+    fn glob_keys (&self, glob_pattern: &str)->Result<Vec<String>,OdinShareError> {
+        let glob = globset::Glob::new(glob_pattern)?.compile_matcher();
+        let keys = self.prefix_keys( literal_prefix( glob_pattern))?;
+        Ok( keys.into_iter().filter( |k| glob.is_match(k)).collect() )
+    }
+
+    fn write_item (&self, k: &str, v: &T)->Result<(),OdinShareError> {
+        let value = serde_json::to_string(v)?;
+        self.conn().prepare_cached( "INSERT OR REPLACE INTO shared_items (key,value) VALUES (?1,?2)")?.execute( params![k, value])?;
+        Ok(())
+    }
+
+    fn delete_item (&self, k: &str)->Result<(),OdinShareError> {
+        self.conn().prepare_cached( "DELETE FROM shared_items WHERE key = ?1")?.execute( params![k])?;
+        Ok(())
+    }
+
+    fn rename_item (&self, old_k: &str, new_k: &str)->Result<(),OdinShareError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute( "DELETE FROM shared_items WHERE key = ?1", params![new_k])?;
+        tx.execute( "UPDATE shared_items SET key = ?2 WHERE key = ?1", params![old_k, new_k])?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// the part of a glob pattern before the first meta char
+fn literal_prefix (glob_pattern: &str)->&str {
+    match glob_pattern.find( |c| matches!(c, '*' | '?' | '[' | '{' | '\\')) {
+        Some(idx) => &glob_pattern[..idx],
+        None => glob_pattern
+    }
+}
+
+fn load_items<T> (conn: &Connection)->Result<HashMap<String,T>,OdinShareError> where T: SharedStoreValueConstraints {
+    let mut map = HashMap::new();
+    let mut stmt = conn.prepare( "SELECT key,value FROM shared_items")?;
+    let mut rows = stmt.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let value: String = row.get(1)?;
+        match serde_json::from_str::<T>( &value) {
+            Ok(v) => { map.insert( key, v); }
+            Err(e) => warn!("ignoring unreadable store item {key}: {e}")
+        }
+    }
+    Ok(map)
+}
+
+#[async_trait]
+impl<T> SharedStore<T> for SqliteStore<T> where T: SharedStoreValueConstraints {
+    fn ref_iter<'a>(&'a self)->Box<dyn Iterator<Item=(&'a String,&'a T)> + 'a> {
+        Box::new( self.cache.iter())
+    }
+
+    fn glob_ref_iter<'a> (&'a self, glob_pattern: &str)->Result<Box<dyn Iterator<Item=(&'a String,&'a T)> + 'a>, OdinShareError> {
+        let keys = self.glob_keys( glob_pattern)?;
+        Ok( Box::new( keys.into_iter().filter_map( |k| self.cache.get_key_value( &k))) )
+    }
+
+    fn glob_clone_iter(&self, glob_pattern: &str)->Result<Box<dyn Iterator<Item=(String,T)> + '_>, OdinShareError> {
+        let keys = self.glob_keys( glob_pattern)?;
+        Ok( Box::new( keys.into_iter().filter_map( |k| self.cache.get( &k).map( |v| (k, v.clone())))) )
+    }
+
+    fn len(&self)->usize { 
+        self.cache.len()
+    }
+
+    fn contains_key (&self, k: &str)->bool { 
+        self.cache.contains_key(k)
+    }
+
+    /// note that the cache is only updated if the item was successfully written to the database
+    fn insert(&mut self, k: String, v: T)->Result<Option<T>,OdinShareError> {
+        self.write_item( &k, &v)?;
+        Ok( self.cache.insert( k, v) )
+    }
+
+    fn remove (&mut self, k: &str)->Result<Option<T>,OdinShareError> {
+        self.delete_item( k)?;
+        Ok( self.cache.remove( k) )
+    }
+
+    fn rename (&mut self, old_k: &str, new_k: String)->Result<Option<T>,OdinShareError> {
+        if !self.cache.contains_key( old_k) { return Ok(None) }
+
+        self.rename_item( old_k, &new_k)?;
+        match self.cache.remove( old_k) {
+            Some(v) => Ok( self.cache.insert( new_k, v) ),
+            None => Ok(None)
+        }
+    }
+
+    fn get (&self, k: &str)->Option<&T> {
+        self.cache.get(k)
+    }
+
+    fn to_json (&self)->Result<String,OdinShareError> {
+        Ok( serde_json::to_string( &self.cache)? )
+    }
+
+    /// all changes are already committed - we just move the write-ahead log into the database file
+    fn save (&self)->Result<(),OdinShareError> {
+        self.conn().execute_batch( "PRAGMA wal_checkpoint(TRUNCATE);")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+//! the validity module supports time-bounded store values. Values can have a `valid_from` and `valid_until` time
+//! and/or a time-to-live (TTL) that is counted from the last modification of the value. Since modification times are
+//! not persisted `SharedStoreActor` turns TTLs into absolute `valid_until` times when values are stored.
+//! `SharedStoreActor` uses this (if configured with `with_validity(..)`) to remove values once they expire, and to
+//! filter values that are valid at a given point in time.
+
+use std::time::Duration;
+use chrono::{DateTime,Utc};
+use serde::{Serialize,Deserialize};
+use odin_common::datetime::{deserialize_optional_duration, serialize_optional_duration};
+
+use crate::errors::{invalid_value, OdinShareError};
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq,Default)]
+#[serde(rename_all="camelCase")]
+pub struct Validity {
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub valid_from: Option<DateTime<Utc>>,
+
+    #[serde(default, skip_serializing_if="Option::is_none")]
+    pub valid_until: Option<DateTime<Utc>>,
+
+    /// time-to-live after the last modification, e.g. "2h"
+    #[serde(default, skip_serializing_if="Option::is_none",
+            deserialize_with="deserialize_optional_duration", serialize_with="serialize_optional_duration")]
+    pub ttl: Option<Duration>
+}
+
+impl Validity {
+    pub fn is_unbounded (&self)->bool {
+        self.valid_from.is_none() && self.valid_until.is_none() && self.ttl.is_none()
+    }
+
+    /// the time at which a value with this validity that was last modified at `modified` expires (if any)
+    pub fn expiration (&self, modified: DateTime<Utc>)->Option<DateTime<Utc>> {
+        let ttl_exp = self.ttl.and_then( |ttl| chrono::Duration::from_std(ttl).ok()).map( |ttl| modified + ttl);
+        match (self.valid_until, ttl_exp) {
+            (Some(a), Some(b)) => Some( a.min(b)),
+            (a, b) => a.or(b)
+        }
+    }
+
+    /// the equivalent validity without TTL for a value that was modified at `modified`
+    pub fn to_absolute (&self, modified: DateTime<Utc>)->Validity {
+        Validity { valid_from: self.valid_from, valid_until: self.expiration( modified), ttl: None }
+    }
+
+    pub fn is_valid_at (&self, time: DateTime<Utc>, modified: DateTime<Utc>)->bool {
+        self.valid_from.map_or( true, |from| from <= time) && self.expiration( modified).map_or( true, |exp| time < exp)
+    }
+
+    pub fn check (&self)->Result<(),OdinShareError> {
+        if let (Some(from), Some(until)) = (self.valid_from, self.valid_until) {
+            if from > until { return Err( invalid_value( "validFrom is after validUntil")) }
+        }
+        if self.ttl.map_or( false, |ttl| ttl.is_zero()) {
+            return Err( invalid_value( "ttl has to be positive"))
+        }
+        Ok(())
+    }
+}
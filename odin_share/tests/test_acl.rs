@@ -0,0 +1,67 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::collections::HashMap;
+use odin_share::prelude::*;
+
+fn create_acl()->Result<KeyAcl,OdinShareError> {
+    KeyAcl::new( KeyAclConfig {
+        rules: vec![
+            AclRule{ pattern: "/incidents/czu/**".into(), read: vec!["public".into()], write: vec!["ops".into()] },
+            AclRule{ pattern: "/internal/**".into(), read: vec!["ops".into()], write: vec!["admin".into()] },
+        ],
+        user_roles: HashMap::from([
+            ("jane".into(), vec!["ops".into()]),
+            ("joe".into(), vec!["ops".into(), "admin".into()]),
+        ])
+    })
+}
+
+#[test]
+fn test_key_acl()->Result<(),OdinShareError> {
+    let acl = create_acl()?;
+
+    assert!( acl.can_read( None, "/incidents/czu/origin"));
+    assert!( !acl.can_write( None, "/incidents/czu/origin"));
+    assert!( acl.can_write( Some("jane"), "/incidents/czu/origin"));
+
+    assert!( !acl.can_read( None, "/internal/notes"));
+    assert!( acl.can_read( Some("jane"), "/internal/notes"));
+    assert!( !acl.can_write( Some("jane"), "/internal/notes"));
+    assert!( acl.can_write( Some("joe"), "/internal/notes"));
+
+    // keys without rules are not restricted
+    assert!( acl.can_write( None, "/views/bay_area"));
+    assert!( KeyAcl::open().can_write( None, "/internal/notes"));
+
+    // system requests are not checked
+    assert!( acl.check_write( &Requester::System, "/internal/notes").is_ok());
+    assert!( matches!( acl.check_write( &Requester::user( Some("jane")), "/internal/notes"), Err(OdinShareError::AccessDenied{..})));
+    Ok(())
+}
+
+#[test]
+fn test_acl_config_ron() {
+    let src = r#"
+        KeyAclConfig(
+            rules: [
+                AclRule( pattern: "/incidents/czu/**", read: ["public"], write: ["ops"] ),
+            ],
+            user_roles: { "jane": ["ops"] }
+        )
+    "#;
+    let config: KeyAclConfig = ron::from_str( src).unwrap();
+    assert_eq!( config.rules.len(), 1);
+    assert_eq!( config.rules[0].write, vec!["ops".to_string()]);
+}
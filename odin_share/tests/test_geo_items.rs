@@ -0,0 +1,123 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::sync::Arc;
+use chrono::{DateTime,Utc,Duration};
+use odin_common::{geo::{DatedGeoPos,GeoBoundingBox,LatLon},angle::{LatAngle,LonAngle}};
+use odin_share::{prelude::*, geo_items::{to_feature_collection,from_feature_collection}};
+use serde_json;
+
+fn item<T> (data: T)->SharedItemValue<T> where T: SharedStoreValueConstraints {
+    SharedItemValue{ comment: None, owner: None, validity: Validity::default(), data: Arc::new(data) }
+}
+
+fn perimeter()->Polygon {
+    Polygon {
+        exterior: vec![ LatLon::from_degrees( 37.0, -122.3), LatLon::from_degrees( 37.0, -122.1), LatLon::from_degrees( 37.2, -122.1), LatLon::from_degrees( 37.2, -122.3)],
+        holes: vec![ vec![ LatLon::from_degrees( 37.05, -122.25), LatLon::from_degrees( 37.05, -122.2), LatLon::from_degrees( 37.1, -122.2)]]
+    }
+}
+
+#[test]
+fn test_validation() {
+    assert!( SharedItem::Polygon( item( perimeter())).validate().is_ok());
+    assert!( SharedItem::Polygon( item( Polygon{ exterior: vec![ LatLon::from_degrees( 37.0, -122.3), LatLon::from_degrees( 37.0, -122.1)], holes: vec![] })).validate().is_err());
+    assert!( SharedItem::Point2D( item( LatLon::from_degrees( 91.0, 0.0))).validate().is_err());
+    assert!( SharedItem::Circle( item( Circle{ center: LatLon::from_degrees( 37.0, -122.0), radius: 0.0 })).validate().is_err());
+    assert!( SharedItem::BoundingBox( item( GeoBoundingBox::from_wsen_degrees( &[-122.0, 38.0, -121.0, 37.0]))).validate().is_err());
+
+    let now = Utc::now();
+    assert!( SharedItem::TimeRange( item( TimeRange{ start: now, end: now - Duration::hours(1) })).validate().is_err());
+    assert!( SharedItem::Json( item( "{\"a\": ".to_string())).validate().is_err());
+}
+
+#[test]
+fn test_item_serde()->Result<(),OdinShareError> {
+    let items = vec![
+        SharedItem::Polygon( item( perimeter())),
+        SharedItem::Circle( item( Circle{ center: LatLon::from_degrees( 37.0, -122.0), radius: 500.0 })),
+        SharedItem::BoundingBox( item( GeoBoundingBox::from_wsen_degrees( &[-122.3, 37.0, -122.1, 37.2]))),
+    ];
+
+    for it in items {
+        let json = serde_json::to_string( &it)?;
+        println!("{json}");
+        let it1: SharedItem = serde_json::from_str( &json)?;
+        assert_eq!( it, it1);
+    }
+    Ok(())
+}
+
+// run with "cargo test test_geojson_roundtrip -- --nocapture"
+#[test]
+fn test_geojson_roundtrip()->Result<(),OdinShareError> {
+    let t0: DateTime<Utc> = DateTime::parse_from_rfc3339("2020-08-16T10:00:00Z").unwrap().with_timezone(&Utc);
+    let track = vec![
+        DatedGeoPos::new( LatAngle::from_degrees( 37.0), LonAngle::from_degrees( -122.0), 100.0, t0),
+        DatedGeoPos::new( LatAngle::from_degrees( 37.1), LonAngle::from_degrees( -122.1), 150.0, t0 + Duration::minutes(5)),
+    ];
+
+    let items = vec![
+        ("incident/czu/perimeter".to_string(), SharedItem::Polygon( item( perimeter()))),
+        ("incident/czu/circle".to_string(), SharedItem::Circle( item( Circle{ center: LatLon::from_degrees( 37.0, -122.0), radius: 500.0 }))),
+        ("incident/czu/bbox".to_string(), SharedItem::BoundingBox( item( GeoBoundingBox::from_wsen_degrees( &[-122.3, 37.0, -122.1, 37.2])))),
+        ("incident/czu/track".to_string(), SharedItem::Track( item( track))),
+        ("incident/czu/cause".to_string(), SharedItem::String( item( "dry lightning".to_string()))),
+    ];
+
+    let fc = to_feature_collection( items.iter().map( |(k,v)| (k,v)));
+    println!("{}", serde_json::to_string_pretty( &fc)?);
+    assert_eq!( fc.features.len(), 4); // String items are not exported
+
+    let imported = from_feature_collection( &fc, "import/")?;
+    assert_eq!( imported.len(), 4);
+    for (key,it) in &imported {
+        let (_,orig) = items.iter().find( |(k,_)| k == key).expect("imported key not found");
+        assert_eq!( it, orig);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_antimeridian_bbox_roundtrip()->Result<(),OdinShareError> {
+    let bbox = SharedItem::BoundingBox( item( GeoBoundingBox::from_wsen_degrees( &[179.0, -18.0, -179.0, -16.0]))); // Fiji
+    assert!( bbox.validate().is_ok());
+    let items = vec![ ("incident/fiji/bbox".to_string(), bbox) ];
+
+    let fc = to_feature_collection( items.iter().map( |(k,v)| (k,v)));
+    let imported = from_feature_collection( &fc, "import/")?;
+    assert_eq!( imported.len(), 1);
+    assert_eq!( imported[0].1, items[0].1); // not its complement
+    Ok(())
+}
+
+#[test]
+fn test_invalid_import() {
+    let feature = |props: serde_json::Value| serde_json::json!({
+        "type": "Feature",
+        "geometry": { "type": "Point", "coordinates": [-122.2854, 37.137] },
+        "properties": props
+    });
+    let collection = |f: serde_json::Value| -> geojson::FeatureCollection {
+        serde_json::from_value( serde_json::json!({ "type": "FeatureCollection", "features": [f] })).unwrap()
+    };
+
+    assert!( from_feature_collection( &collection( feature( serde_json::json!({ "key": "incident/czu/origin" }))), "import/").is_ok());
+    assert!( from_feature_collection( &collection( feature( serde_json::json!({ "key": "" }))), "import/").is_err());
+    assert!( from_feature_collection( &collection( feature( serde_json::json!({ "key": " incident/czu/origin" }))), "import/").is_err());
+
+    // validity values are not silently dropped
+    assert!( from_feature_collection( &collection( feature( serde_json::json!({ "validUntil": "yesterday" }))), "import/").is_err());
+    assert!( from_feature_collection( &collection( feature( serde_json::json!({ "ttl": "2h" }))), "import/").is_ok());
+}
@@ -0,0 +1,162 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::{collections::HashMap, net::{SocketAddr,TcpListener}, time::Duration};
+use odin_action::no_data_action;
+use odin_actor::prelude::*;
+use odin_share::{prelude::*, replication::{HybridLogicalClock, HlcTimestamp, ReplicationMsg, ReplicatedChange}};
+
+// run with "cargo test test_hlc -- --nocapture"
+#[test]
+fn test_hlc() {
+    let mut clock_a = HybridLogicalClock::new("a");
+    let mut clock_b = HybridLogicalClock::new("b");
+
+    let t1 = clock_a.now();
+    let t2 = clock_a.now();
+    assert!( t2 > t1);
+
+    // a remote timestamp from the future (skewed clock) has to move our clock forward
+    let remote = HlcTimestamp { millis: t2.millis + 60_000, counter: 3, node: "b".into() };
+    clock_a.update( &remote);
+    let t3 = clock_a.now();
+    println!("t1={t1:?}\nt2={t2:?}\nremote={remote:?}\nt3={t3:?}");
+    assert!( t3 > remote);
+
+    // same millis and counter - node id breaks ties
+    let ta = HlcTimestamp { millis: 1, counter: 0, node: "a".into() };
+    let tb = HlcTimestamp { millis: 1, counter: 0, node: "b".into() };
+    assert!( tb > ta);
+    assert!( HlcTimestamp::initial("z") < clock_b.now());
+}
+
+#[test]
+fn test_replication_msg_serde() {
+    let msg: ReplicationMsg<String> = ReplicationMsg::Changes { changes: vec![
+        ReplicatedChange { key: "incident/czu/origin".into(), value: Some("37.1,-122.2".into()), ts: HlcTimestamp { millis: 42, counter: 0, node: "a".into() }, seq: 1 },
+        ReplicatedChange { key: "incident/czu/cause".into(), value: None, ts: HlcTimestamp { millis: 43, counter: 0, node: "a".into() }, seq: 2 },
+    ]};
+    let json = serde_json::to_string( &msg).unwrap();
+    println!("{json}");
+
+    match serde_json::from_str::<ReplicationMsg<String>>( &json).unwrap() {
+        ReplicationMsg::Changes { changes } => {
+            assert_eq!( changes.len(), 2);
+            assert!( changes[1].value.is_none());
+        }
+        _ => panic!("wrong message type")
+    }
+}
+
+type Item = String;
+
+fn free_addr ()->SocketAddr {
+    TcpListener::bind( "127.0.0.1:0").and_then( |l| l.local_addr()).unwrap()
+}
+
+fn config (node_id: &str, listen: Option<SocketAddr>, peers: Vec<String>)->ReplicationConfig {
+    ReplicationConfig { node_id: node_id.into(), listen, peers, token: Some("secret".into()), reconnect_interval: millis(200) }
+}
+
+/// a node with its own actor system, store and replicator
+fn start_node (name: &str, config: ReplicationConfig, items: HashMap<String,Item>)->(ActorSystem, ActorHandle<SharedStoreActorMsg<Item>>) {
+    start_node_with_mailbox( name, config, items, 64)
+}
+
+fn start_node_with_mailbox (name: &str, config: ReplicationConfig, items: HashMap<String,Item>, bound: usize)
+    ->(ActorSystem, ActorHandle<SharedStoreActorMsg<Item>>)
+{
+    let mut asys = ActorSystem::new( name);
+    let pre_repl = PreActorHandle::<ShareReplicatorMsg<Item>>::new( &asys, "replicator", bound);
+    let hstore = spawn_actor!( asys, "store", SharedStoreActor::new(
+        items,
+        no_shared_store_action(),
+        replicate_changes( pre_repl.to_actor_handle(), no_data_action())
+    )).unwrap();
+    spawn_pre_actor!( asys, pre_repl, ShareReplicator::new( config, hstore.clone())).unwrap();
+    (asys, hstore)
+}
+
+async fn set (hstore: &ActorHandle<SharedStoreActorMsg<Item>>, key: &str, value: &str) {
+    let set = SetSharedStoreValue { key: key.into(), value: value.into(), expected_rev: None, requester: Requester::System };
+    hstore.send_msg( set).await.unwrap();
+}
+
+/// poll the store until it has the expected value for `key` (or we time out)
+async fn has_value (hstore: &ActorHandle<SharedStoreActorMsg<Item>>, key: &str, value: &str)->bool {
+    for _ in 0..50 {
+        if let Ok(Some(v)) = timeout_query_ref( hstore, key.to_string(), secs(1)).await {
+            if v == value { return true }
+        }
+        sleep( millis(100)).await;
+    }
+    false
+}
+
+// run with "cargo test test_replicated_stores -- --nocapture"
+#[tokio::test]
+async fn test_replicated_stores() {
+    let addr = free_addr();
+    let peer_uri = format!("ws://{addr}");
+
+    let (mut sys_a, hstore_a) = start_node( "a", config( "a", Some(addr), vec![]), HashMap::new());
+    sys_a.start_all().await.unwrap();
+    let (mut sys_b, hstore_b) = start_node( "b", config( "b", None, vec![peer_uri.clone()]), HashMap::new());
+    sys_b.start_all().await.unwrap();
+
+    //--- both nodes converge
+    set( &hstore_a, "incident/czu/origin", "37.137,-122.2854").await;
+    set( &hstore_b, "incident/czu/cause", "lightning").await;
+    set( &hstore_b, "incident/czu/status", "active").await;
+    assert!( has_value( &hstore_b, "incident/czu/origin", "37.137,-122.2854").await);
+    assert!( has_value( &hstore_a, "incident/czu/cause", "lightning").await);
+    assert!( has_value( &hstore_a, "incident/czu/status", "active").await);
+
+    //--- restart node B with a store that got a new value while it was down. This has a lower sequence number
+    // than the ones node A already got from node B
+    sys_b.terminate_and_wait( secs(2)).await.unwrap();
+    let items = HashMap::from([ ("incident/lnu/origin".to_string(), "38.5,-122.4".to_string()) ]);
+    let (mut sys_b, hstore_b) = start_node( "b", config( "b", None, vec![peer_uri]), items);
+    sys_b.start_all().await.unwrap();
+
+    println!("checking catch up after restart of node b");
+    assert!( has_value( &hstore_a, "incident/lnu/origin", "38.5,-122.4").await);
+    assert!( has_value( &hstore_b, "incident/czu/origin", "37.137,-122.2854").await);
+    assert!( has_value( &hstore_b, "incident/czu/cause", "lightning").await); // our own change from before the restart
+
+    sys_b.terminate_and_wait( secs(2)).await.unwrap();
+    sys_a.terminate_and_wait( secs(2)).await.unwrap();
+}
+
+// run with "cargo test test_full_replicator_mailbox -- --nocapture"
+#[tokio::test]
+async fn test_full_replicator_mailbox() {
+    let addr = free_addr();
+
+    let (mut sys_a, hstore_a) = start_node( "a", config( "a", Some(addr), vec![]), HashMap::new());
+    sys_a.start_all().await.unwrap();
+    let (mut sys_b, hstore_b) = start_node_with_mailbox( "b", config( "b", None, vec![format!("ws://{addr}")]), HashMap::new(), 1);
+    sys_b.start_all().await.unwrap();
+
+    // more changes than the replicator mailbox of node b can hold - dropped ones have to be picked up by a resync
+    for i in 0..32 {
+        set( &hstore_b, &format!("incident/czu/unit-{i}"), &format!("pos-{i}")).await;
+    }
+    for i in 0..32 {
+        assert!( has_value( &hstore_a, &format!("incident/czu/unit-{i}"), &format!("pos-{i}")).await);
+    }
+
+    sys_b.terminate_and_wait( secs(2)).await.unwrap();
+    sys_a.terminate_and_wait( secs(2)).await.unwrap();
+}
@@ -0,0 +1,101 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::collections::HashMap;
+use odin_share::{prelude::*, revisions::RevisionedStore};
+
+// run with "cargo test test_revisions -- --nocapture"
+#[test]
+fn test_revisions() {
+    let map = HashMap::from([ ("a".to_string(), 1u64) ]);
+    let mut store = RevisionedStore::new( map, 3);
+
+    assert_eq!( store.current_rev("a"), 1);  // loaded values start with revision 1
+    assert_eq!( store.current_rev("b"), 0);  // no value
+
+    store.insert( "a".to_string(), 2).unwrap();
+    store.insert( "a".to_string(), 3).unwrap();
+    assert_eq!( store.current_rev("a"), 3);
+    assert!( store.revision("a").unwrap().modified.is_some());
+
+    store.remove("a").unwrap();
+    assert_eq!( store.current_rev("a"), 0);
+    assert!( store.revision("a").is_none());
+
+    store.insert( "a".to_string(), 5).unwrap();
+    assert_eq!( store.current_rev("a"), 5); // revisions continue after re-creation
+
+    let history = store.history("a");
+    println!("history of 'a': {history:?}");
+    let revs: Vec<u64> = history.iter().map( |r| r.rev).collect();
+    assert_eq!( revs, vec![3,4,5]); // bounded, oldest first
+    assert_eq!( history[0].value, Some(3));
+    assert_eq!( history[1].value, None);
+}
+
+#[test]
+fn test_removed_retention() {
+    let map: HashMap<String,u64> = HashMap::new();
+    let mut store = RevisionedStore::new( map, 3);
+    store.set_removed_retention( 2, std::time::Duration::from_secs(3600));
+
+    for k in ["a","b","c"] {
+        store.insert( k.to_string(), 1).unwrap();
+        store.remove( k).unwrap();
+    }
+    assert!( store.history("a").is_empty()); // oldest removed key exceeded the count limit
+    assert_eq!( store.history("b").len(), 2);
+    assert_eq!( store.history("c").len(), 2);
+
+    store.insert( "a".to_string(), 2).unwrap();
+    assert_eq!( store.current_rev("a"), 1); // dropped keys start over
+
+    store.set_removed_retention( 2, std::time::Duration::ZERO);
+    assert!( store.history("b").is_empty());
+    assert!( store.history("c").is_empty());
+    assert_eq!( store.num_tracked_keys(), 1); // only "a" is left
+}
+
+/// a store that rejects all changes, to check that failed writes don't create revisions
+struct ReadOnlyStore( HashMap<String,u64> );
+
+impl SharedStore<u64> for ReadOnlyStore {
+    fn ref_iter<'a>(&'a self)->Box<dyn Iterator<Item=(&'a String,&'a u64)> + 'a> { self.0.ref_iter() }
+    fn glob_ref_iter<'a> (&'a self, glob_pattern: &str)->Result<Box<dyn Iterator<Item=(&'a String,&'a u64)> + 'a>, OdinShareError> {
+        self.0.glob_ref_iter( glob_pattern)
+    }
+    fn glob_clone_iter(&self, glob_pattern: &str)->Result<Box<dyn Iterator<Item=(String,u64)> + '_>, OdinShareError> {
+        self.0.glob_clone_iter( glob_pattern)
+    }
+    fn len(&self)->usize { self.0.len() }
+    fn contains_key (&self, k: &str)->bool { self.0.contains_key(k) }
+    fn insert(&mut self, _k: String, _v: u64)->Result<Option<u64>,OdinShareError> { Err( OdinShareError::OpFailed("read only".into())) }
+    fn remove (&mut self, _k: &str)->Result<Option<u64>,OdinShareError> { Err( OdinShareError::OpFailed("read only".into())) }
+    fn get (&self, k: &str)->Option<&u64> { self.0.get(k) }
+    fn to_json (&self)->Result<String,OdinShareError> { self.0.to_json() }
+    fn save (&self)->Result<(),OdinShareError> { Ok(()) }
+}
+
+#[test]
+fn test_failed_write() {
+    let mut store = RevisionedStore::new( ReadOnlyStore( HashMap::from([ ("a".to_string(), 1u64) ])), 3);
+
+    assert!( store.insert( "a".to_string(), 2).is_err());
+    assert!( store.remove( "a").is_err());
+    assert!( store.insert( "b".to_string(), 1).is_err());
+
+    assert_eq!( store.current_rev("a"), 1); // no revision for rejected changes
+    assert_eq!( store.history("a").len(), 1);
+    assert_eq!( store.num_tracked_keys(), 1); // "b" is not tracked
+}
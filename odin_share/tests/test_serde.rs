@@ -24,6 +24,7 @@ fn create_store()->HashMap<String,SharedItem> {
             SharedItemValue {
                 comment: None,
                 owner: None,
+                validity: Validity::default(),
                 data: Arc::new(GeoPos::new( LatAngle::from_degrees(38.15910), LonAngle::from_degrees(-122.67800), 800000.0))
             }
         )),
@@ -31,6 +32,7 @@ fn create_store()->HashMap<String,SharedItem> {
             SharedItemValue {
                 comment: Some("origin of fire at blabla".to_string()),
                 owner: None,
+                validity: Validity::default(),
                 data: Arc::new(LatLon::from_degrees( 37.137, -122.2854))
             }
         )),
@@ -38,6 +40,7 @@ fn create_store()->HashMap<String,SharedItem> {
             SharedItemValue {
                 comment: Some("preliminary".to_string()),
                 owner: None,
+                validity: Validity::default(),
                 data: Arc::new("dry lightning".to_string())
             }
         )),
@@ -69,3 +72,25 @@ fn test_init_msg()->Result<(),OdinShareError> {
     Ok(())
 }
 
+
+#[test]
+fn test_client_msgs()->Result<(),OdinShareError> {
+    use odin_share::share_service::{SetSharedItem,RenameSharedItem};
+
+    let json = r#"{"key": "incident/czu/origin", "item": {"type": "Point2D", "comment": "blah", "owner": null, "data": {"lat_deg": 37.137, "lon_deg": -122.2854}}}"#;
+    let msg: SetSharedItem = serde_json::from_str( json)?;
+    println!("### parsed setSharedItem: {msg:?}");
+    assert_eq!( msg.key, "incident/czu/origin");
+    assert_eq!( msg.item, SharedItem::Point2D( SharedItemValue {
+        comment: Some("blah".to_string()),
+        owner: None,
+        validity: Validity::default(),
+        data: Arc::new( LatLon::from_degrees( 37.137, -122.2854))
+    }));
+
+    let json = r#"{"oldKey": "incident/czu/origin", "newKey": "incident/czu/ignition"}"#;
+    let msg: RenameSharedItem = serde_json::from_str( json)?;
+    assert_eq!( msg.new_key, "incident/czu/ignition");
+    assert!( msg.item.is_none());
+    Ok(())
+}
@@ -0,0 +1,114 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::collections::HashMap;
+use serde_json::Value;
+use odin_actor::prelude::*;
+use odin_common::geo::LatLon;
+use odin_server::prelude::*;
+use odin_share::prelude::*;
+
+type Store = ActorHandle<SharedStoreActorMsg<SharedItem>>;
+
+/// a store that broadcasts its changes to a server handle whose mailbox we read directly
+fn start_store (asys: &mut ActorSystem)->(Store, MpscReceiver<SpaServerMsg>) {
+    let (_, hserver, rx) = asys.new_actor::<(),SpaServerMsg>( "server", (), 16);
+    let hstore = spawn_actor!( asys, "store", SharedStoreActor::new(
+        HashMap::<String,SharedItem>::new(),
+        no_shared_store_action(),
+        share_change_action( hserver)
+    )).unwrap();
+    (hstore, rx)
+}
+
+fn client()->Requester { Requester::User( Some("alice".into())) }
+
+async fn set_point (hstore: &Store, key: &str, lat: f64, lon: f64) {
+    let value = SharedItem::Point2D( SharedItemValue::new( LatLon::from_degrees( lat, lon)));
+    hstore.send_msg( SetSharedStoreValue { key: key.into(), value, expected_rev: None, requester: client() }).await.unwrap();
+}
+
+/// the next broadcast as (msg_type, payload)
+async fn next_broadcast (rx: &MpscReceiver<SpaServerMsg>)->(String,Value) {
+    match timeout( millis(500), recv( rx)).await {
+        Ok( SpaServerMsg::BroadcastWsMsg( BroadcastWsMsg{data})) => {
+            let Value::Object(mut o) = serde_json::from_str::<Value>( &data).unwrap() else { panic!("not a JSON object: {data}") };
+            assert_eq!( o.remove("mod").and_then( |m| m.as_str().map( |s| s.to_string())).as_deref(), Some( ShareService::mod_path()));
+            o.into_iter().next().expect("no payload")
+        }
+        Ok(other) => panic!("unexpected server message: {other:?}"),
+        Err(e) => panic!("no broadcast: {e}")
+    }
+}
+
+// run with "cargo test test_client_set -- --nocapture"
+#[tokio::test]
+async fn test_client_set() {
+    let mut asys = ActorSystem::new( "test");
+    let (hstore, rx) = start_store( &mut asys);
+    asys.start_all().await.unwrap();
+
+    set_point( &hstore, "incidents/czu/origin", 37.17, -122.22).await;
+    let (msg_type, payload) = next_broadcast( &rx).await;
+    assert_eq!( msg_type, "setSharedItem");
+    assert_eq!( payload["key"], "incidents/czu/origin");
+    assert_eq!( payload["rev"]["rev"], 1);
+    assert_eq!( payload["item"]["type"], "Point2D");
+
+    set_point( &hstore, "incidents/czu/origin", 37.18, -122.23).await;
+    let (msg_type, payload) = next_broadcast( &rx).await;
+    assert_eq!( msg_type, "setSharedItem");
+    assert_eq!( payload["rev"]["rev"], 2);
+
+    asys.terminate_and_wait( secs(2)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_remove() {
+    let mut asys = ActorSystem::new( "test");
+    let (hstore, rx) = start_store( &mut asys);
+    asys.start_all().await.unwrap();
+
+    set_point( &hstore, "incidents/czu/origin", 37.17, -122.22).await;
+    next_broadcast( &rx).await;
+
+    hstore.send_msg( RemoveSharedStoreValue{ key: "incidents/czu/origin".into(), requester: client() }).await.unwrap();
+    let (msg_type, payload) = next_broadcast( &rx).await;
+    assert_eq!( msg_type, "removeSharedItem");
+    assert_eq!( payload["key"], "incidents/czu/origin");
+
+    asys.terminate_and_wait( secs(2)).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_rename() {
+    let mut asys = ActorSystem::new( "test");
+    let (hstore, rx) = start_store( &mut asys);
+    asys.start_all().await.unwrap();
+
+    set_point( &hstore, "incidents/czu/origin", 37.17, -122.22).await;
+    next_broadcast( &rx).await;
+
+    let rename = RenameSharedStoreValue{ old_key: "incidents/czu/origin".into(), new_key: "incidents/czu/ignition".into(), requester: client() };
+    hstore.send_msg( rename).await.unwrap();
+    let (msg_type, payload) = next_broadcast( &rx).await;
+    assert_eq!( msg_type, "renameSharedItem");
+    assert_eq!( payload["oldKey"], "incidents/czu/origin");
+    assert_eq!( payload["newKey"], "incidents/czu/ignition");
+    assert_eq!( payload["item"]["type"], "Point2D");
+    assert!( payload["rev"].is_object());
+
+    asys.terminate_and_wait( secs(2)).await.unwrap();
+}
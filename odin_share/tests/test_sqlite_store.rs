@@ -0,0 +1,60 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![cfg(feature="sqlite")]
+
+use std::{fs, sync::Arc};
+use odin_common::geo::LatLon;
+use odin_share::{prelude::*, sqlite_store::SqliteStore};
+
+fn point (lat: f64, lon: f64)->SharedItem {
+    SharedItem::Point2D( SharedItemValue{ comment: None, owner: None, validity: Validity::default(), data: Arc::new( LatLon::from_degrees( lat, lon)) })
+}
+
+// run with "cargo test --features sqlite test_sqlite_store -- --nocapture"
+#[test]
+fn test_sqlite_store()->Result<(),OdinShareError> {
+    let path = std::env::temp_dir().join( format!("odin_share_test_{}.db", std::process::id()));
+    let _ = fs::remove_file( &path);
+
+    {
+        let mut store = SqliteStore::<SharedItem>::open( &path)?;
+        store.insert( "incident/czu/origin".to_string(), point( 37.137, -122.2854))?;
+        store.insert_all( vec![
+            ("incident/czu/evac/1".to_string(), point( 37.1, -122.2)),
+            ("incident/czu/evac/2".to_string(), point( 37.2, -122.3)),
+            ("incident/lnu/origin".to_string(), point( 38.5, -122.4)),
+        ])?;
+        store.remove( "incident/czu/evac/2")?;
+        store.rename( "incident/lnu/origin", "incident/lnu/ignition".to_string())?;
+    } // no save() - everything should already be committed
+
+    let store = SqliteStore::<SharedItem>::open( &path)?;
+    assert_eq!( store.len(), 3);
+    assert!( store.contains_key( "incident/lnu/ignition"));
+    assert!( !store.contains_key( "incident/lnu/origin"));
+
+    let keys = store.prefix_keys( "incident/czu/")?;
+    assert_eq!( keys, vec!["incident/czu/evac/1".to_string(), "incident/czu/origin".to_string()]);
+
+    let mut keys: Vec<String> = store.glob_clone_iter( "incident/*/origin")?.map( |(k,_)| k).collect();
+    keys.sort();
+    println!("glob matches: {keys:?}");
+    assert_eq!( keys, vec!["incident/czu/origin".to_string()]);
+
+    assert_eq!( store.get( "incident/czu/evac/1"), Some( &point( 37.1, -122.2)));
+
+    drop(store);
+    let _ = fs::remove_file( &path);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License. You may obtain a copy
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+#![allow(unused)]
+
+use std::collections::HashMap;
+use odin_actor::prelude::*;
+use odin_action::no_data_action;
+use odin_common::geo::LatLon;
+use odin_share::prelude::*;
+
+type Store = ActorHandle<SharedStoreActorMsg<SharedItem>>;
+
+/// a subscriber that forwards all updates it gets to the test
+struct Subscriber {
+    tx: MpscSender<SharedStoreUpdate<SharedItem>>
+}
+
+define_actor_msg_set! { SubscriberMsg = SharedStoreUpdate<SharedItem> }
+
+impl_actor! { match msg for Actor<Subscriber,SubscriberMsg> as
+    SharedStoreUpdate<SharedItem> => cont! {
+        send( &self.tx, msg).await;
+    }
+}
+
+fn start_store (asys: &mut ActorSystem)->Store {
+    spawn_actor!( asys, "store", SharedStoreActor::new(
+        HashMap::<String,SharedItem>::new(),
+        no_shared_store_action(),
+        no_data_action()
+    )).unwrap()
+}
+
+async fn set_point (hstore: &Store, key: &str, lat: f64, lon: f64) {
+    let value = SharedItem::Point2D( SharedItemValue::new( LatLon::from_degrees( lat, lon)));
+    hstore.send_msg( SetSharedStoreValue { key: key.into(), value, expected_rev: None, requester: Requester::System }).await.unwrap();
+}
+
+async fn next_update (rx: &MpscReceiver<SharedStoreUpdate<SharedItem>>)->Option<SharedStoreUpdate<SharedItem>> {
+    timeout( millis(500), recv( rx)).await.ok()
+}
+
+// run with "cargo test test_subscriptions -- --nocapture"
+#[tokio::test]
+async fn test_subscriptions() {
+    let mut asys = ActorSystem::new( "test");
+    let hstore = start_store( &mut asys);
+    let (tx, rx) = create_mpsc_sender_receiver( 16);
+    let hsub = spawn_actor!( asys, "subscriber", Subscriber{ tx }).unwrap();
+    asys.start_all().await.unwrap();
+
+    let subscribe = SubscribeSharedStoreChanges { glob_pattern: "incidents/*/origin".into(), with_value: true, subscriber: hsub.clone().into() };
+    hstore.send_msg( subscribe).await.unwrap();
+
+    //--- subscribe
+    set_point( &hstore, "incidents/czu/origin", 37.137, -122.2854).await;
+    match next_update( &rx).await {
+        Some( SharedStoreUpdate{ change: SharedStoreChange::Set{key,..}, value: Some(SharedItem::Point2D(p)), .. }) => {
+            assert_eq!( key, "incidents/czu/origin");
+            assert_eq!( *p.data, LatLon::from_degrees( 37.137, -122.2854));
+        }
+        other => panic!("unexpected update {other:?}")
+    }
+
+    //--- key filter
+    set_point( &hstore, "incidents/czu/perimeter/0", 37.14, -122.29).await;
+    set_point( &hstore, "incidents/lnu/origin", 38.5, -122.4).await;
+    match next_update( &rx).await { // the perimeter point is not reported
+        Some( SharedStoreUpdate{ change: SharedStoreChange::Set{key,..}, .. }) => assert_eq!( key, "incidents/lnu/origin"),
+        other => panic!("unexpected update {other:?}")
+    }
+
+    //--- unsubscribe
+    hstore.send_msg( UnsubscribeSharedStoreChanges{ subscriber_id: hsub.id().to_string(), glob_pattern: None }).await.unwrap();
+    set_point( &hstore, "incidents/czu/origin", 37.141, -122.2901).await;
+    assert!( next_update( &rx).await.is_none());
+
+    asys.terminate_and_wait( secs(2)).await.unwrap();
+}
@@ -0,0 +1,64 @@
+/*
+ * Copyright © 2024, United States Government, as represented by the Administrator of 
+ * the National Aeronautics and Space Administration. All rights reserved.
+ *
+ * The “ODIN” software is licensed under the Apache License, Version 2.0 (the "License"); 
+ * you may not use this file except in compliance with the License. You may obtain a copy 
+ * of the License at http://www.apache.org/licenses/LICENSE-2.0.
+ *
+ * Unless required by applicable law or agreed to in writing, software distributed under
+ * the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND,
+ * either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+
+use std::time::Duration;
+use chrono::{DateTime,Utc};
+use odin_share::prelude::*;
+use serde_json;
+
+fn utc (s: &str)->DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+#[test]
+fn test_validity() {
+    let modified = utc("2020-08-16T10:00:00Z");
+
+    let v = Validity { valid_from: Some(utc("2020-08-16T11:00:00Z")), valid_until: Some(utc("2020-08-16T14:00:00Z")), ttl: Some( Duration::from_secs(7200)) };
+    assert_eq!( v.expiration( modified), Some(utc("2020-08-16T12:00:00Z"))); // ttl expires before valid_until
+    assert!( !v.is_valid_at( utc("2020-08-16T10:30:00Z"), modified));
+    assert!( v.is_valid_at( utc("2020-08-16T11:30:00Z"), modified));
+    assert!( !v.is_valid_at( utc("2020-08-16T12:00:00Z"), modified));
+
+    assert!( Validity::default().is_unbounded());
+    assert_eq!( Validity::default().expiration( modified), None);
+
+    let v = Validity { valid_from: Some(utc("2020-08-16T11:00:00Z")), valid_until: Some(utc("2020-08-16T10:00:00Z")), ttl: None };
+    assert!( v.check().is_err());
+
+    // absolute validities don't depend on the modification time anymore
+    let v = Validity { valid_from: None, valid_until: None, ttl: Some( Duration::from_secs(7200)) }.to_absolute( modified);
+    assert_eq!( v, Validity { valid_from: None, valid_until: Some(utc("2020-08-16T12:00:00Z")), ttl: None });
+    assert_eq!( v.expiration( utc("2020-08-17T10:00:00Z")), Some(utc("2020-08-16T12:00:00Z")));
+}
+
+#[test]
+fn test_validity_serde()->Result<(),OdinShareError> {
+    let json = r#"{"type": "Point2D", "comment": "road closure", "owner": null, "validUntil": "2020-08-16T14:00:00Z", "ttl": "2h", "data": {"lat_deg": 37.1, "lon_deg": -122.2}}"#;
+    let item: SharedItem = serde_json::from_str( json)?;
+    let validity = item.validity();
+    assert_eq!( validity.valid_until, Some(utc("2020-08-16T14:00:00Z")));
+    assert_eq!( validity.ttl, Some( Duration::from_secs(7200)));
+
+    let json1 = serde_json::to_string( &item)?;
+    println!("{json1}");
+    let item1: SharedItem = serde_json::from_str( &json1)?;
+    assert_eq!( item, item1);
+
+    let mut item2 = item1.clone();
+    item2.set_validity( validity.to_absolute( utc("2020-08-16T10:00:00Z")));
+    assert_eq!( item2.validity().valid_until, Some(utc("2020-08-16T12:00:00Z")));
+    assert_eq!( item2.validity().ttl, None);
+    Ok(())
+}